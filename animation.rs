@@ -0,0 +1,276 @@
+// Keyframe-based animations: the user lists views at given times and every frame in between is
+// interpolated, which allows fly-throughs instead of a single straight zoom.
+//
+// The keyframes are described in a file read with config::load_document, for example:
+//
+//     fps = 30
+//     size = "1280x720"
+//
+//     [[keyframe]]
+//     time = 0
+//     center = "-0.5,0"
+//     zoom = 1
+//     iterations = 100
+//
+//     [[keyframe]]
+//     time = 8
+//     center = "-0.7436,0.1318"
+//     zoom = 500
+//     iterations = 600
+//     palette = "inferno"
+//
+// A keyframe with `julia = "RE,IM"` shows the Julia set of that constant instead of the Mandelbrot set.
+use crate::checkpoint::Checkpoint;
+use crate::cli;
+use crate::config::{self, Document};
+use crate::mandelbrot::{self, ColoredColorMap};
+use crate::naming;
+use crate::palette::Palette;
+use crate::video::{self, VideoEncoder};
+use image::RgbImage;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// Everything needed to render one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32, // In seconds from the start of the animation
+    pub center: (f32, f32),
+    pub zoom: f32,
+    pub iterations: u32,
+    pub palette: String,
+    pub julia: Option<(f32, f32)>,
+}
+
+impl Keyframe {
+    // Read a keyframe from its table; missing values are taken from the previous keyframe.
+    fn from_options(options: &HashMap<String, String>, previous: Option<&Keyframe>) -> Result<Self, String> {
+        let parse = |name: &str| options.get(name).map(|v| v.parse::<f32>().map_err(|_| format!("Invalid {} '{}'", name, v)));
+        let time = parse("time").ok_or("Every keyframe needs a time")??;
+        let center = match options.get("center") {
+            Some(center) => cli::parse_point(center)?,
+            None => previous.map_or((-0.5, 0.0), |p| p.center),
+        };
+        let zoom = parse("zoom").transpose()?.or(previous.map(|p| p.zoom)).unwrap_or(1.0);
+        if zoom <= 0.0 {
+            return Err(format!("The zoom of the keyframe at {}s must be positive", time));
+        }
+        let iterations = match options.get("iterations") {
+            Some(v) => v.parse::<u32>().map_err(|_| format!("Invalid iterations '{}'", v))?,
+            None => previous.map_or(100, |p| p.iterations),
+        };
+        let palette = match options.get("palette") {
+            Some(palette) => palette.clone(),
+            None => previous.map_or("turbo".to_string(), |p| p.palette.clone()),
+        };
+        // The Julia constant is not inherited: leaving it out switches back to the Mandelbrot set.
+        let julia = options.get("julia").map(|c| cli::parse_point(c)).transpose()?;
+        Ok(Self { time, center, zoom, iterations, palette, julia })
+    }
+}
+
+#[derive(Debug)]
+pub struct Animation {
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    keyframes: Vec<Keyframe>,
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+impl Animation {
+    // Build the animation from a parsed keyframe file.
+    pub fn from_document(document: &Document) -> Result<Self, String> {
+        let fps = match document.options.get("fps") {
+            Some(fps) => fps.parse::<u32>().map_err(|_| format!("Invalid fps '{}'", fps))?,
+            None => 30,
+        };
+        let (width, height) = match document.options.get("size") {
+            Some(size) => cli::parse_size(size)?,
+            None => (800, 600),
+        };
+
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+        for table in document.tables.iter().filter(|table| table.name == "keyframe") {
+            let keyframe = Keyframe::from_options(&table.options, keyframes.last())?;
+            if let Some(previous) = keyframes.last() {
+                if keyframe.time <= previous.time {
+                    return Err(format!("Keyframe times must increase, found {}s after {}s", keyframe.time, previous.time));
+                }
+            }
+            Palette::by_name(&keyframe.palette)?;
+            keyframes.push(keyframe);
+        }
+        if keyframes.len() < 2 {
+            return Err("An animation needs at least two [[keyframe]] tables".to_string());
+        }
+        if fps == 0 {
+            return Err("The fps must be positive".to_string());
+        }
+        Ok(Self { fps, width, height, keyframes })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_document(&config::load_document(path)?)
+    }
+
+    // Total duration in seconds, from the first to the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time - self.keyframes[0].time
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        (self.duration() * self.fps as f32).round() as u32 + 1
+    }
+
+    // Interpolate the view at a time (in seconds) between the surrounding keyframes.
+    pub fn state_at(&self, time: f32) -> Keyframe {
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time >= last.time {
+            return last.clone();
+        }
+        let index = self.keyframes.iter().rposition(|k| k.time <= time).unwrap_or(0);
+        let (from, to) = (&self.keyframes[index], &self.keyframes[index + 1]);
+        let t = ((time - from.time) / (to.time - from.time)).clamp(0.0, 1.0);
+
+        // Zooming by a constant factor per second looks steady, so the zoom is interpolated on a log scale.
+        let zoom = (lerp(from.zoom.ln(), to.zoom.ln(), t)).exp();
+        let julia = match (from.julia, to.julia) {
+            (Some(a), Some(b)) => Some((lerp(a.0, b.0, t), lerp(a.1, b.1, t))),
+            (julia, _) => julia, // Switching between Mandelbrot and Julia happens at the next keyframe
+        };
+        Keyframe {
+            time,
+            center: (lerp(from.center.0, to.center.0, t), lerp(from.center.1, to.center.1, t)),
+            zoom,
+            iterations: lerp(from.iterations as f32, to.iterations as f32, t).round() as u32,
+            palette: from.palette.clone(), // Palettes cannot be blended, they change at the next keyframe
+            julia,
+        }
+    }
+}
+
+// Render the image described by an interpolated keyframe.
+pub fn render_frame(state: &Keyframe, width: u32, height: u32) -> Result<RgbImage, String> {
+    let bounds = mandelbrot::view_bounds(state.center, state.zoom, width, height);
+    let iterations = match state.julia {
+        Some(c) => mandelbrot::compute_julia_iterations(width, height, state.iterations, bounds, c),
+        None => mandelbrot::compute_iterations(width, height, state.iterations, bounds),
+    };
+    let color_map = ColoredColorMap::with_palette(state.iterations, &state.palette)?;
+    Ok(mandelbrot::colorize(width, height, &iterations, &color_map))
+}
+
+// Where the frames of an animation go: a video (.mp4/.webm output), numbered PNGs in a directory, or
+// PNGs named by a template holding {frame}, like shots/zoom_{frame:04}.png.
+pub enum FrameOutput {
+    Directory(PathBuf),
+    Pattern(String),
+    Video(VideoEncoder),
+}
+
+impl FrameOutput {
+    pub fn open(output: &str, width: u32, height: u32, fps: u32) -> Result<Self, Box<dyn Error>> {
+        let path = Path::new(output);
+        if naming::has_frame(output) {
+            if video::is_video_path(path) {
+                return Err("A video is written as one file, {frame} only names the frames written as PNGs".into());
+            }
+            fs::create_dir_all(path.parent().unwrap_or(Path::new("")))?;
+            Ok(FrameOutput::Pattern(output.to_string()))
+        } else if video::is_video_path(path) {
+            Ok(FrameOutput::Video(VideoEncoder::start(path, width, height, fps, "8M")?))
+        } else {
+            fs::create_dir_all(path)?;
+            Ok(FrameOutput::Directory(path.to_path_buf()))
+        }
+    }
+
+    pub fn write(&mut self, frame: u32, image: &RgbImage) -> Result<(), Box<dyn Error>> {
+        match self {
+            FrameOutput::Directory(directory) => image.save(directory.join(format!("frame_{:05}.png", frame)))?,
+            FrameOutput::Pattern(template) => image.save(naming::frame_name(template, frame)?)?,
+            FrameOutput::Video(encoder) => encoder.write_frame(image)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            FrameOutput::Directory(_) | FrameOutput::Pattern(_) => Ok(()),
+            FrameOutput::Video(encoder) => encoder.finish(),
+        }
+    }
+}
+
+// Render every frame of a keyframe animation. With a checkpoint, the frames it records are skipped
+// and every frame written is added to it; only frames written as PNGs can be resumed.
+pub fn render_animation(animation: &Animation, output: &str, mut checkpoint: Option<Checkpoint>) -> Result<(), Box<dyn Error>> {
+    let mut frame_output = FrameOutput::open(output, animation.width, animation.height, animation.fps)?;
+    if checkpoint.is_some() && matches!(frame_output, FrameOutput::Video(_)) {
+        return Err("A video cannot be resumed, write the frames into a directory to use a checkpoint".into());
+    }
+    let start = animation.keyframes[0].time;
+    let frames = animation.frame_count();
+    for frame in 0..frames {
+        if checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.is_done(frame)) {
+            continue;
+        }
+        let state = animation.state_at(start + frame as f32 / animation.fps as f32);
+        let image = render_frame(&state, animation.width, animation.height)?;
+        frame_output.write(frame, &image)?;
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.complete(frame)?;
+        }
+        info!("Frame {}/{} rendered", frame + 1, frames);
+    }
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.remove()?;
+    }
+    frame_output.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation() -> Animation {
+        let document = config::parse_document(
+            "fps = 10\n[[keyframe]]\ntime = 0\ncenter = \"0,0\"\nzoom = 1\niterations = 100\n\
+             [[keyframe]]\ntime = 2\ncenter = \"1,-1\"\nzoom = 100\niterations = 300\njulia = \"0.3,0.5\"\n",
+        )
+        .unwrap();
+        Animation::from_document(&document).unwrap()
+    }
+
+    /// Tests the interpolation halfway between two keyframes.
+    #[test]
+    fn test_state_at_midpoint() {
+        let state = animation().state_at(1.0);
+        assert_eq!(state.center, (0.5, -0.5));
+        assert!((state.zoom - 10.0).abs() < 1e-3); // Halfway on a log scale between 1 and 100
+        assert_eq!(state.iterations, 200);
+        assert_eq!(state.julia, None);
+    }
+
+    /// Tests the frame count and the clamping after the last keyframe.
+    #[test]
+    fn test_frame_count_and_end() {
+        let animation = animation();
+        assert_eq!(animation.frame_count(), 21);
+        assert_eq!(animation.state_at(5.0).julia, Some((0.3, 0.5)));
+    }
+
+    /// Tests that keyframes must be in chronological order.
+    #[test]
+    fn test_keyframes_out_of_order() {
+        let document = config::parse_document("[[keyframe]]\ntime = 3\n[[keyframe]]\ntime = 1\n").unwrap();
+        assert!(Animation::from_document(&document).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// The Apollonian gasket: three circles touching each other inside a fourth one that touches them all,
+// then in every curved triangle left between three touching circles the one circle touching all three,
+// again and again. By the Descartes circle theorem, the curvatures k of four touching circles and the
+// products k·z of their curvatures and centers satisfy
+//
+//     (k₁ + k₂ + k₃ + k₄)² = 2(k₁² + k₂² + k₃² + k₄²)
+//
+// and the same for k·z, so the two circles touching three given ones are the two roots, and one is
+// found from the other without any square root: k₄' = 2(k₁ + k₂ + k₃) − k₄. The outer circle has a
+// negative curvature, its inside being outside of it. The circles are filled with the color of their
+// generation, the first three being generation 0.
+use crate::complex::Complex;
+use crate::curves::MARGIN;
+use final_exercice::palette::Palette;
+use image::{Rgb, RgbImage};
+
+pub const DEFAULT_DEPTH: u32 = 6;
+// Every generation triples the circles, though those smaller than a pixel are left out anyway.
+pub const MAX_DEPTH: u32 = 20;
+// The smallest circle drawn, in pixels.
+const MIN_RADIUS: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    curvature: f64,
+    scaled_center: Complex, // The center times the curvature
+}
+
+impl Circle {
+    fn new(center: Complex, curvature: f64) -> Self {
+        Self { curvature, scaled_center: center * curvature }
+    }
+
+    pub fn center(&self) -> Complex {
+        self.scaled_center / self.curvature
+    }
+
+    pub fn radius(&self) -> f64 {
+        1.0 / self.curvature.abs()
+    }
+
+    // The other circle touching the three circles this one touches.
+    fn flip(&self, a: &Circle, b: &Circle, c: &Circle) -> Circle {
+        Circle {
+            curvature: 2.0 * (a.curvature + b.curvature + c.curvature) - self.curvature,
+            scaled_center: (a.scaled_center + b.scaled_center + c.scaled_center) * 2.0 - self.scaled_center,
+        }
+    }
+}
+
+// The circles of the gasket inside the unit circle with their generation, down to a depth, leaving out
+// those smaller than a radius and the circles inside them.
+pub fn gasket(depth: u32, min_radius: f64) -> Result<Vec<(Circle, u32)>, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("The Apollonian gasket goes at most {} generations deep, not {}", MAX_DEPTH, depth));
+    }
+    // Three equal circles of radius r touching the unit circle have centers (1 − r)√3 apart, which is 2r
+    // when they also touch each other
+    let radius = 2.0 * 3f64.sqrt() - 3.0;
+    let first: Vec<Circle> =
+        [90.0f64, 210.0, 330.0].iter().map(|angle| Circle::new(Complex::from_polar(1.0 - radius, angle.to_radians()), 1.0 / radius)).collect();
+    let outer = Circle::new(Complex::ZERO, -1.0);
+    let mut gasket = Gasket { depth, min_radius, circles: first.iter().map(|&circle| (circle, 0)).collect() };
+    let (a, b, c) = (&first[0], &first[1], &first[2]);
+    for (x, y, z, opposite) in [(a, b, c, &outer), (&outer, a, b, c), (&outer, b, c, a), (&outer, a, c, b)] {
+        gasket.fill(x, y, z, opposite, 1);
+    }
+    Ok(gasket.circles)
+}
+
+struct Gasket {
+    depth: u32,
+    min_radius: f64,
+    circles: Vec<(Circle, u32)>,
+}
+
+impl Gasket {
+    // Add the circle in the gap between three touching circles, on the other side from the fourth one,
+    // and the circles in the three gaps it leaves.
+    fn fill(&mut self, a: &Circle, b: &Circle, c: &Circle, opposite: &Circle, generation: u32) {
+        if generation > self.depth {
+            return;
+        }
+        let circle = opposite.flip(a, b, c);
+        if circle.radius() < self.min_radius {
+            return;
+        }
+        self.circles.push((circle, generation));
+        self.fill(a, b, &circle, c, generation + 1);
+        self.fill(a, c, &circle, b, generation + 1);
+        self.fill(b, c, &circle, a, generation + 1);
+    }
+}
+
+// Render the gasket fitted into the image, every circle filled through the palette by its generation,
+// the first near its start and the deepest at its end, and a line of the background left between them.
+pub fn render(width: u32, height: u32, depth: u32, palette: &Palette, background: Rgb<u8>) -> Result<RgbImage, String> {
+    let scale = width.min(height) as f64 * (1.0 - 2.0 * MARGIN) / 2.0;
+    let mut image = RgbImage::from_pixel(width, height, background);
+    for (circle, generation) in gasket(depth, MIN_RADIUS / scale)? {
+        let color = palette.at((generation + 1) as f64 / (depth + 1) as f64);
+        let center = circle.center();
+        let (cx, cy) = (width as f64 / 2.0 + center.re * scale, height as f64 / 2.0 - center.im * scale);
+        let radius = circle.radius() * scale - 0.5;
+        let column = |x: f64| (x.floor().max(0.0) as u32).min(width);
+        let row = |y: f64| (y.floor().max(0.0) as u32).min(height);
+        for py in row(cy - radius)..row(cy + radius + 1.0) {
+            for px in column(cx - radius)..column(cx + radius + 1.0) {
+                let (dx, dy) = (px as f64 + 0.5 - cx, py as f64 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    image.put_pixel(px, py, color);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that every generation fills the gaps of the previous one with circles touching their
+    /// neighbours, inside the outer circle.
+    #[test]
+    fn test_gasket() {
+        assert_eq!(gasket(0, 0.0).unwrap().len(), 3);
+        assert_eq!(gasket(1, 0.0).unwrap().len(), 7);
+        assert_eq!(gasket(2, 0.0).unwrap().len(), 19);
+        let circles = gasket(1, 0.0).unwrap();
+        let touch = |a: &Circle, b: &Circle| ((a.center() - b.center()).norm() - a.radius() - b.radius()).abs() < 1e-9;
+        // The central circle touches the first three, the others the outer circle
+        let central = &circles[3].0;
+        assert!(central.center().norm() < 1e-12);
+        assert!(circles[..3].iter().all(|(circle, _)| touch(circle, central)));
+        for (circle, generation) in &circles[4..] {
+            assert_eq!(*generation, 1);
+            assert!((circle.center().norm() + circle.radius() - 1.0).abs() < 1e-9);
+        }
+        // Small circles are left out
+        assert!(gasket(8, 0.01).unwrap().iter().all(|(circle, _)| circle.radius() >= 0.01));
+        assert!(gasket(MAX_DEPTH + 1, 0.0).is_err());
+    }
+}
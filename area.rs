@@ -0,0 +1,171 @@
+// Monte Carlo estimation of the area of a fractal: points are drawn uniformly in a region and the area
+// is the fraction that did not escape times the area of the region. The fraction of a sample follows a
+// binomial distribution, so its standard error is sqrt(p(1 - p) / n), from which the confidence interval
+// is taken with the normal approximation.
+//
+// The estimate is of the points staying bounded for the iteration limit, which is slightly larger than
+// the fractal itself; the area of the Mandelbrot set is about 1.50659, which needs a high limit.
+use crate::fractal::Fractal;
+use crate::complex::Complex;
+
+// A region containing the whole Mandelbrot set.
+pub const AREA_BOUNDS: (f32, f32, f32, f32) = (-2.0, 0.5, -1.25, 1.25);
+pub const DEFAULT_SAMPLES: u64 = 1_000_000;
+pub const DEFAULT_ITERATIONS: u32 = 2000;
+// Points sampled between two checks of the precision.
+pub const BATCH_SAMPLES: u64 = 100_000;
+
+// The SplitMix64 generator: small, fast and good enough for sampling, with a seed so that an estimate
+// can be reproduced.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number in [0, 1), from the 53 high bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The z-score of a two-sided confidence level, for the usual levels.
+pub fn z_score(confidence: f64) -> Result<f64, String> {
+    match (confidence * 100.0).round() as u32 {
+        80 => Ok(1.2816),
+        90 => Ok(1.6449),
+        95 => Ok(1.9600),
+        98 => Ok(2.3263),
+        99 => Ok(2.5758),
+        _ => Err(format!("Unsupported confidence level {}, expected one of 0.8, 0.9, 0.95, 0.98 or 0.99", confidence)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AreaEstimate {
+    pub samples: u64,
+    pub inside: u64,
+    pub region_area: f64,
+}
+
+impl AreaEstimate {
+    pub fn area(&self) -> f64 {
+        self.inside as f64 / self.samples.max(1) as f64 * self.region_area
+    }
+
+    pub fn standard_error(&self) -> f64 {
+        let n = self.samples.max(1) as f64;
+        let p = self.inside as f64 / n;
+        (p * (1.0 - p) / n).sqrt() * self.region_area
+    }
+
+    // Half the width of the confidence interval of a z-score.
+    pub fn margin(&self, z: f64) -> f64 {
+        z * self.standard_error()
+    }
+}
+
+// Draws the points of an estimate, which can be continued until it is precise enough.
+pub struct AreaSampler<'a> {
+    fractal: &'a dyn Fractal,
+    bounds: (f32, f32, f32, f32),
+    max_iterations: u32,
+    random: Random,
+    estimate: AreaEstimate,
+}
+
+impl<'a> AreaSampler<'a> {
+    pub fn new(fractal: &'a dyn Fractal, bounds: (f32, f32, f32, f32), max_iterations: u32, seed: u64) -> Result<Self, String> {
+        let (xmin, xmax, ymin, ymax) = bounds;
+        if !(xmax > xmin && ymax > ymin) {
+            return Err(format!("The region {:?} is empty", bounds));
+        }
+        let region_area = (xmax as f64 - xmin as f64) * (ymax as f64 - ymin as f64);
+        let estimate = AreaEstimate { samples: 0, inside: 0, region_area };
+        Ok(Self { fractal, bounds, max_iterations, random: Random::new(seed), estimate })
+    }
+
+    // Test `samples` more random points.
+    pub fn sample(&mut self, samples: u64) {
+        let (xmin, xmax, ymin, ymax) = self.bounds;
+        for _ in 0..samples {
+            let re = xmin as f64 + self.random.next_f64() * (xmax as f64 - xmin as f64);
+            let im = ymin as f64 + self.random.next_f64() * (ymax as f64 - ymin as f64);
+            if self.fractal.iterate(Complex::new(re, im), self.max_iterations).iterations >= self.max_iterations {
+                self.estimate.inside += 1;
+            }
+        }
+        self.estimate.samples += samples;
+    }
+
+    pub fn estimate(&self) -> AreaEstimate {
+        self.estimate
+    }
+}
+
+// Sample up to max_samples points, stopping early once the margin of the z-score is below the precision.
+pub fn estimate_area(sampler: &mut AreaSampler, max_samples: u64, z: f64, precision: Option<f64>) -> AreaEstimate {
+    while sampler.estimate().samples < max_samples {
+        sampler.sample(BATCH_SAMPLES.min(max_samples - sampler.estimate().samples));
+        if precision.is_some_and(|precision| sampler.estimate().margin(z) <= precision) {
+            break;
+        }
+    }
+    sampler.estimate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{Julia, Mandelbrot};
+
+    /// Tests that the generator is reproducible and stays in [0, 1).
+    #[test]
+    fn test_random() {
+        let (mut a, mut b) = (Random::new(7), Random::new(7));
+        let values: Vec<f64> = (0..1000).map(|_| a.next_f64()).collect();
+        assert!(values.iter().all(|&x| (0.0..1.0).contains(&x)));
+        assert_eq!(values[999], (0..1000).map(|_| b.next_f64()).last().unwrap());
+        let mean = values.iter().sum::<f64>() / 1000.0;
+        assert!((mean - 0.5).abs() < 0.05);
+    }
+
+    /// Tests that the estimated area of the Mandelbrot set is close to the known 1.50659, and that
+    /// the filled Julia set of c = 0, the unit disk, gets an area close to π.
+    #[test]
+    fn test_estimate_area() {
+        let mut sampler = AreaSampler::new(&Mandelbrot, AREA_BOUNDS, 500, 1).unwrap();
+        let estimate = estimate_area(&mut sampler, 40_000, 1.96, None);
+        assert_eq!(estimate.samples, 40_000);
+        // The iteration limit of 500 adds about 0.02 of points close to the boundary
+        assert!((estimate.area() - 1.5066).abs() < estimate.margin(3.0) + 0.03, "{:?}", estimate.area());
+
+        let disk = Julia::new(Complex::new(0.0, 0.0));
+        let mut sampler = AreaSampler::new(&disk, (-1.0, 1.0, -1.0, 1.0), 100, 2).unwrap();
+        let estimate = estimate_area(&mut sampler, 1_000_000, 1.96, Some(0.02));
+        assert!(estimate.samples < 1_000_000);
+        assert!((estimate.area() - std::f64::consts::PI).abs() < 0.04);
+    }
+
+    /// Tests the normal approximation of the confidence interval.
+    #[test]
+    fn test_confidence_interval() {
+        let estimate = AreaEstimate { samples: 10_000, inside: 2_500, region_area: 4.0 };
+        assert_eq!(estimate.area(), 1.0);
+        assert!((estimate.standard_error() - 4.0 * (0.25f64 * 0.75 / 10_000.0).sqrt()).abs() < 1e-12);
+        assert_eq!(z_score(0.95), Ok(1.96));
+        assert!(z_score(0.5).is_err());
+        assert!(AreaSampler::new(&Mandelbrot, (1.0, 1.0, 0.0, 1.0), 10, 0).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// Strange attractors: a map of the plane iterated millions of times from one point, or a flow followed
+// in small steps, never settles nor escapes but keeps coming back near where it has already been, and
+// counting the points visited in every pixel shades the attractor by how often each part is visited.
+//
+//     Clifford      x' = sin(a·y) + c·cos(a·x)     y' = sin(b·x) + d·cos(b·y)
+//     Peter de Jong x' = sin(a·y) − cos(b·x)       y' = sin(c·x) − cos(d·y)
+//     Lorenz        dx/dt = σ(y − x)   dy/dt = x(ρ − z) − y   dz/dt = xy − βz, seen from the side (x, z)
+//
+// Small changes of the parameters give very different shapes, which makes them worth exploring; a few
+// parameters instead bring the points onto a closed curve or a handful of points.
+use crate::density::{Density, Extent};
+
+pub const ATTRACTOR_NAMES: [&str; 3] = ["clifford", "de-jong", "lorenz"];
+pub const DEFAULT_POINTS: u64 = 5_000_000;
+
+// The first points only get closer to the attractor, they are not counted.
+const SKIPPED_POINTS: u32 = 1000;
+// The points run to find the bounds of an attractor, and the space left around them.
+const BOUNDS_POINTS: u64 = 100_000;
+const BOUNDS_MARGIN: f64 = 0.05;
+// The time step of the Lorenz flow, small enough for its points to draw continuous lines.
+const LORENZ_STEP: f64 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attractor {
+    Clifford { a: f64, b: f64, c: f64, d: f64 },
+    DeJong { a: f64, b: f64, c: f64, d: f64 },
+    Lorenz { sigma: f64, rho: f64, beta: f64 },
+}
+
+impl Attractor {
+    // One of ATTRACTOR_NAMES, with parameters showing a well-known shape.
+    pub fn by_name(name: &str) -> Result<Self, String> {
+        match name {
+            "clifford" => Ok(Attractor::Clifford { a: -1.4, b: 1.6, c: 1.0, d: 0.7 }),
+            "de-jong" => Ok(Attractor::DeJong { a: 1.4, b: -2.3, c: 2.4, d: -2.1 }),
+            "lorenz" => Ok(Attractor::Lorenz { sigma: 10.0, rho: 28.0, beta: 8.0 / 3.0 }),
+            _ => Err(format!("Unknown attractor '{}', expected one of {}", name, ATTRACTOR_NAMES.join(", "))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attractor::Clifford { .. } => "clifford",
+            Attractor::DeJong { .. } => "de-jong",
+            Attractor::Lorenz { .. } => "lorenz",
+        }
+    }
+
+    // The parameters by name, to be changed from the command line.
+    pub fn parameters_mut(&mut self) -> Vec<(&'static str, &mut f64)> {
+        match self {
+            Attractor::Clifford { a, b, c, d } | Attractor::DeJong { a, b, c, d } => vec![("a", a), ("b", b), ("c", c), ("d", d)],
+            Attractor::Lorenz { sigma, rho, beta } => vec![("sigma", sigma), ("rho", rho), ("beta", beta)],
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        let mut attractor = *self;
+        for (name, value) in attractor.parameters_mut() {
+            if !value.is_finite() {
+                return Err(format!("The parameter {} of the attractor must be a finite number, not {}", name, value));
+            }
+        }
+        Ok(())
+    }
+
+    // Visit the points of the attractor, in the plane they are drawn in.
+    fn run(&self, points: u64, mut visit: impl FnMut(f64, f64)) {
+        match *self {
+            Attractor::Clifford { a, b, c, d } => {
+                let step = |(x, y): (f64, f64)| ((a * y).sin() + c * (a * x).cos(), (b * x).sin() + d * (b * y).cos());
+                Self::iterate((0.1, 0.1), step, points, |(x, y)| visit(x, y));
+            }
+            Attractor::DeJong { a, b, c, d } => {
+                let step = |(x, y): (f64, f64)| ((a * y).sin() - (b * x).cos(), (c * x).sin() - (d * y).cos());
+                Self::iterate((0.1, 0.1), step, points, |(x, y)| visit(x, y));
+            }
+            Attractor::Lorenz { sigma, rho, beta } => {
+                let flow = |[x, y, z]: [f64; 3]| [sigma * (y - x), x * (rho - z) - y, x * y - beta * z];
+                Self::iterate([0.1, 0.0, 0.0], |p| runge_kutta(p, LORENZ_STEP, flow), points, |[x, _, z]| visit(x, z));
+            }
+        }
+    }
+
+    fn iterate<P: Copy>(mut point: P, step: impl Fn(P) -> P, points: u64, mut visit: impl FnMut(P)) {
+        for _ in 0..SKIPPED_POINTS {
+            point = step(point);
+        }
+        for _ in 0..points {
+            point = step(point);
+            visit(point);
+        }
+    }
+
+    // The bounds of the points of a first run, with a margin.
+    pub fn bounds(&self) -> Result<(f32, f32, f32, f32), String> {
+        let mut extent = Extent::default();
+        self.run(BOUNDS_POINTS, |x, y| extent.add(x, y));
+        extent.bounds(BOUNDS_MARGIN).ok_or_else(|| format!("The points of the {} attractor go to infinity with these parameters", self.name()))
+    }
+
+    // Count a number of points of the attractor in the density.
+    pub fn render(&self, points: u64, density: &mut Density) {
+        self.run(points, |x, y| density.add(x, y));
+    }
+}
+
+// One step of the classic fourth-order Runge-Kutta method along a flow.
+fn runge_kutta(p: [f64; 3], h: f64, flow: impl Fn([f64; 3]) -> [f64; 3]) -> [f64; 3] {
+    let along = |p: [f64; 3], d: [f64; 3], t: f64| [p[0] + d[0] * t, p[1] + d[1] * t, p[2] + d[2] * t];
+    let k1 = flow(p);
+    let k2 = flow(along(p, k1, h / 2.0));
+    let k3 = flow(along(p, k2, h / 2.0));
+    let k4 = flow(along(p, k3, h));
+    [0, 1, 2].map(|i| p[i] + h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the attractors stay in the regions their formulas bound them to, and their parameters.
+    #[test]
+    fn test_attractor_bounds() {
+        let (xmin, xmax, ymin, ymax) = Attractor::by_name("clifford").unwrap().bounds().unwrap();
+        assert!(xmin >= -2.25 && xmax <= 2.25 && ymin >= -1.95 && ymax <= 1.95 && xmax - xmin > 1.0);
+        let (xmin, xmax, ymin, ymax) = Attractor::by_name("de-jong").unwrap().bounds().unwrap();
+        assert!(xmin >= -2.25 && xmax <= 2.25 && ymin >= -2.25 && ymax <= 2.25);
+        // The two wings of the Lorenz butterfly, above the plane z = 0
+        let (xmin, xmax, zmin, zmax) = Attractor::by_name("lorenz").unwrap().bounds().unwrap();
+        assert!(xmin < -15.0 && xmax > 15.0 && zmin > -5.0 && zmax > 40.0 && zmax < 55.0, "{:?}", (xmin, xmax, zmin, zmax));
+        assert!(Attractor::by_name("henon").is_err());
+
+        let mut lorenz = Attractor::by_name("lorenz").unwrap();
+        for (name, value) in lorenz.parameters_mut() {
+            if name == "rho" {
+                *value = f64::NAN;
+            }
+        }
+        assert!(lorenz.check().is_err());
+        let mut density = Density::new(10, 10, (-2.0, 2.0, -2.0, 2.0));
+        Attractor::by_name("de-jong").unwrap().render(1000, &mut density);
+        assert_eq!(density.counts.iter().sum::<u32>(), 1000);
+    }
+}
@@ -0,0 +1,115 @@
+// A grid over an image of a region of the complex plane: faint lines at round values of the real and
+// imaginary parts, 1, 2 or 5 times a power of ten apart so that they stay at least MIN_SPACING pixels
+// apart at any zoom, brighter lines along the axes when they cross the region, tick marks and the
+// values of the lines along the bottom and left edges. The image maps the region like the renderer,
+// pixel (x, y) showing the point xmin + x·scale + i(ymin + y·scale).
+use crate::text;
+use image::{Rgb, RgbImage};
+
+const MIN_SPACING: f64 = 80.0;
+// How much of the white of the lines is blended into the image, for the grid and the axes.
+const GRID_OPACITY: f32 = 0.25;
+const AXIS_OPACITY: f32 = 0.8;
+const TICK_LENGTH: u32 = 6;
+const LABEL_PADDING: u32 = 2;
+
+// The distance between two lines of the grid over an extent of the plane shown by a number of pixels.
+pub fn tick_step(extent: f64, pixels: u32) -> f64 {
+    let smallest = extent * MIN_SPACING / pixels.max(1) as f64;
+    let power = 10f64.powf(smallest.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter().map(|m| m * power).find(|&step| step >= smallest).unwrap_or(10.0 * power)
+}
+
+// The values of the lines of the grid between two bounds, and the decimals needed to tell them apart.
+pub fn ticks(min: f64, max: f64, step: f64) -> (Vec<f64>, usize) {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    ((first..=last).map(|k| k as f64 * step).collect(), decimals)
+}
+
+// Draw the grid, the axes and their labels over an image of the bounds.
+pub fn draw_axes(image: &mut RgbImage, bounds: (f32, f32, f32, f32)) {
+    let (width, height) = image.dimensions();
+    let (xmin, xmax, ymin, ymax) = (bounds.0 as f64, bounds.1 as f64, bounds.2 as f64, bounds.3 as f64);
+    let step = tick_step(xmax - xmin, width).max(tick_step(ymax - ymin, height)); // Squares on square pixels
+    let column = |re: f64| ((re - xmin) / (xmax - xmin) * width as f64).floor() as u32;
+    let row = |im: f64| ((im - ymin) / (ymax - ymin) * height as f64).floor() as u32;
+    let (columns, decimals) = ticks(xmin, xmax, step);
+    let (rows, _) = ticks(ymin, ymax, step);
+    let is_axis = |value: f64| value.abs() < step / 2.0;
+    for &re in &columns {
+        let opacity = if is_axis(re) { AXIS_OPACITY } else { GRID_OPACITY };
+        whiten(image, (column(re), 0), (1, height), opacity);
+        whiten(image, (column(re), height.saturating_sub(TICK_LENGTH)), (1, TICK_LENGTH), 1.0);
+    }
+    for &im in &rows {
+        let opacity = if is_axis(im) { AXIS_OPACITY } else { GRID_OPACITY };
+        whiten(image, (0, row(im)), (width, 1), opacity);
+        whiten(image, (0, row(im)), (TICK_LENGTH, 1), 1.0);
+    }
+    let label_height = text::text_size("0", 1).1 + 2 * LABEL_PADDING;
+    for &re in &columns {
+        let label = format!("{:.*}", decimals, re);
+        draw_label(image, &label, (column(re) + LABEL_PADDING, height.saturating_sub(TICK_LENGTH + label_height)));
+    }
+    for &im in &rows {
+        // The labels of the rows stop above those of the columns
+        let top = row(im) + LABEL_PADDING;
+        if top + 2 * label_height + TICK_LENGTH <= height {
+            draw_label(image, &format!("{:.*}i", decimals, im), (TICK_LENGTH + LABEL_PADDING, top));
+        }
+    }
+}
+
+// Blend white into a rectangle of the image.
+fn whiten(image: &mut RgbImage, (left, top): (u32, u32), (width, height): (u32, u32), opacity: f32) {
+    for y in top..(top + height).min(image.height()) {
+        for x in left..(left + width).min(image.width()) {
+            let Rgb(channels) = *image.get_pixel(x, y);
+            image.put_pixel(x, y, Rgb(channels.map(|v| (v as f32 + (255.0 - v as f32) * opacity).round() as u8)));
+        }
+    }
+}
+
+// A value on a darkened box with its top left corner at a pixel.
+fn draw_label(image: &mut RgbImage, label: &str, (left, top): (u32, u32)) {
+    let (width, height) = text::text_size(label, 1);
+    text::darken_rect(image, (left, top), (width + 2 * LABEL_PADDING, height + 2 * LABEL_PADDING), 0.35);
+    text::draw_text(image, label, ((left + LABEL_PADDING) as i32, (top + LABEL_PADDING) as i32), 1, Rgb([255, 255, 255]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the round steps of the grid and the lines between the bounds.
+    #[test]
+    fn test_ticks() {
+        assert_eq!(tick_step(4.0, 800), 0.5);
+        assert_eq!(tick_step(4.0, 400), 1.0);
+        assert_eq!(tick_step(0.003, 600), 0.0005);
+        let (values, decimals) = ticks(-2.0, 2.0, 0.5);
+        assert_eq!(values, vec![-2.0, -1.5, -1.0, -0.5, 0.0, 0.5, 1.0, 1.5, 2.0]);
+        assert_eq!(decimals, 1);
+        let (values, decimals) = ticks(0.12, 0.35, 0.1);
+        assert_eq!((values.len(), decimals), (2, 1));
+        assert!((values[0] - 0.2).abs() < 1e-12 && (values[1] - 0.3).abs() < 1e-12);
+    }
+
+    /// Tests that the axes are brighter than the grid, at the columns and rows of zero.
+    #[test]
+    fn test_draw_axes() {
+        let mut image = RgbImage::new(400, 300);
+        draw_axes(&mut image, (-2.0, 2.0, -1.5, 1.5));
+        // The imaginary axis at column 200, the lines of ±1 at columns 100 and 300, away from the labels
+        let brightness = |x: u32, y: u32| image.get_pixel(x, y)[0];
+        assert_eq!(brightness(200, 40), 204);
+        assert_eq!(brightness(100, 40), 64);
+        assert_eq!(brightness(150, 40), 0);
+        // The real axis at row 150
+        assert_eq!(brightness(250, 150), 204);
+        assert_eq!(brightness(250, 299), 0);
+        assert_eq!(brightness(200, 299), 255); // A tick mark
+    }
+}
@@ -0,0 +1,149 @@
+// Batch renders: a file lists many commands to run, each in a `[[job]]` table holding its options like
+// a session (`render` unless the table names another `command`, positional `arguments`), while the
+// options before the first table are shared by every job:
+//
+//     # jobs.conf
+//     size = "1920x1080"
+//     palette = "inferno"
+//
+//     [[job]]
+//     center = "-0.75,0.1"
+//     zoom = "40"
+//     output = "seahorse.png"
+//
+//     [[job]]
+//     fractal = "julia"
+//     c = "-0.8,0.156"
+//     output = "julia.png"
+//
+// The jobs run one after the other or on several threads. A job that fails, even by panicking, is
+// reported and the others go on.
+use crate::cli::Args;
+use crate::config::{self, Document};
+use crate::session::{self, Session};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const JOB_TABLE: &str = "job";
+const DEFAULT_COMMAND: &str = "render";
+
+pub struct Job {
+    pub name: String, // Its output, or its number in the file
+    pub session: Session,
+}
+
+pub struct Outcome {
+    pub name: String,
+    pub result: Result<(), String>,
+    pub elapsed: Duration,
+}
+
+// The jobs of a batch file, the options of `overrides` (given on the command line) winning over those
+// of the file.
+pub fn from_document(document: &Document, overrides: &Args) -> Result<Vec<Job>, String> {
+    let mut jobs = Vec::new();
+    for (index, table) in document.tables.iter().enumerate() {
+        if table.name != JOB_TABLE {
+            return Err(format!("Unexpected table [{}], the jobs are [[{}]] tables", table.name, JOB_TABLE));
+        }
+        let mut options = document.options.clone();
+        options.extend(table.options.clone());
+        let command = options.entry("command".to_string()).or_insert_with(|| DEFAULT_COMMAND.to_string());
+        if command == "batch" {
+            return Err(format!("Job {} runs a batch, which batches cannot hold", index + 1));
+        }
+        let name = options.get("output").cloned().unwrap_or_else(|| format!("job {}", index + 1));
+        let session = session::restore(Document { options, tables: Vec::new() }, overrides)?;
+        jobs.push(Job { name, session });
+    }
+    if jobs.is_empty() {
+        return Err(format!("The batch holds no [[{}]] table", JOB_TABLE));
+    }
+    Ok(jobs)
+}
+
+pub fn load(path: &str, overrides: &Args) -> Result<Vec<Job>, String> {
+    from_document(&config::load_document(path)?, overrides)
+}
+
+// Run the jobs on a number of threads, each taking the next job left, and log them as they finish.
+// The outcomes are in the order of the jobs.
+pub fn run(jobs: &[Job], threads: usize, run_job: impl Fn(&Session) -> Result<(), String> + Sync) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut outcomes: Vec<(usize, Outcome)> = thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            let sender = sender.clone();
+            let (next, run_job) = (&next, &run_job);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(job) = jobs.get(index) else { break };
+                let start = Instant::now();
+                let result = panic::catch_unwind(AssertUnwindSafe(|| run_job(&job.session)))
+                    .unwrap_or_else(|_| Err("The job panicked".to_string()));
+                let outcome = Outcome { name: job.name.clone(), result, elapsed: start.elapsed() };
+                if sender.send((index, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        // The receiver ends once every thread has dropped its sender
+        receiver
+            .iter()
+            .enumerate()
+            .map(|(done, (index, outcome))| {
+                let progress = format!("[{}/{}] {}", done + 1, jobs.len(), outcome.name);
+                match &outcome.result {
+                    Ok(()) => info!("{} done in {:.1}s", progress, outcome.elapsed.as_secs_f64()),
+                    Err(e) => warn!("{} failed: {}", progress, e),
+                }
+                (index, outcome)
+            })
+            .collect()
+    });
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(text: &str) -> Result<Vec<Job>, String> {
+        from_document(&config::parse_document(text).unwrap(), &Args::parse(&[]))
+    }
+
+    /// Tests that the jobs take the shared options, their own winning, and render by default.
+    #[test]
+    fn test_jobs() {
+        let batch = jobs("palette = inferno\nzoom = 2\n[[job]]\nzoom = 40\noutput = a.png\n[[job]]\ncommand = koch\n").unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!((batch[0].name.as_str(), batch[0].session.command.as_str()), ("a.png", "render"));
+        assert_eq!(batch[0].session.args.value("zoom"), Some("40"));
+        assert_eq!(batch[0].session.args.value("palette"), Some("inferno"));
+        assert_eq!((batch[1].name.as_str(), batch[1].session.command.as_str()), ("job 2", "koch"));
+        assert!(jobs("size = 10x10\n").is_err());
+        assert!(jobs("[[render]]\nzoom = 2\n").is_err());
+        assert!(jobs("[[job]]\ncommand = batch\narguments = jobs.conf\n").is_err());
+    }
+
+    /// Tests that failing and panicking jobs leave the others to finish, the outcomes in order.
+    #[test]
+    fn test_run() {
+        let batch = jobs("[[job]]\noutput = a\n[[job]]\noutput = fail\n[[job]]\noutput = panic\n[[job]]\noutput = b\n").unwrap();
+        let outcomes = run(&batch, 2, |session| match session.args.value("output") {
+            Some("fail") => Err("failed".to_string()),
+            Some("panic") => panic!("job panicked"),
+            _ => Ok(()),
+        });
+        let names: Vec<&str> = outcomes.iter().map(|outcome| outcome.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "fail", "panic", "b"]);
+        let failed: Vec<bool> = outcomes.iter().map(|outcome| outcome.result.is_err()).collect();
+        assert_eq!(failed, vec![false, true, true, false]);
+    }
+}
@@ -0,0 +1,153 @@
+// Built-in benchmark: a fixed set of views is rendered with fixed settings and every stage of the
+// pipeline is timed, so that a change making the renderer slower shows up as a lower pixel rate.
+//
+// The renderer only has one backend for now, the scalar single-threaded loop of fractal::escape, so
+// every view is reported for it; faster backends get their own rows once they exist.
+use crate::fractal::{BurningShip, Fractal, Julia, Mandelbrot, DEFAULT_BAILOUT};
+use crate::mandelbrot::{self, ColoredColorMap};
+use image::ImageFormat;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+// Image size and number of runs of each view unless other ones are asked for; the fastest run is kept.
+pub const BENCH_SIZE: (u32, u32) = (800, 600);
+pub const BENCH_RUNS: u32 = 3;
+
+// Name of the only rendering backend.
+pub const BACKEND: &str = "scalar";
+
+// A view rendered by the benchmark.
+pub struct BenchView {
+    pub name: &'static str,
+    pub fractal: Box<dyn Fractal>,
+    pub bounds: (f32, f32, f32, f32),
+    pub max_iterations: u32,
+}
+
+// The standard views: the whole set (mostly interior, where periodicity checking helps), a deep
+// boundary view (mostly long escaping orbits), a fractal without symmetry and a Julia set.
+pub fn standard_views() -> Vec<BenchView> {
+    let seahorse = mandelbrot::view_bounds((-0.743_643_9, 0.131_825_9), 1000.0, BENCH_SIZE.0, BENCH_SIZE.1);
+    vec![
+        BenchView { name: "mandelbrot", fractal: Box::new(Mandelbrot), bounds: (-2.5, 1.5, -1.5, 1.5), max_iterations: 500 },
+        BenchView { name: "seahorse-valley", fractal: Box::new(Mandelbrot), bounds: seahorse, max_iterations: 1000 },
+        BenchView { name: "burning-ship", fractal: Box::new(BurningShip), bounds: BurningShip.default_bounds(), max_iterations: 500 },
+        BenchView { name: "julia", fractal: Box::new(Julia::new(Julia::DEFAULT_C)), bounds: (-1.6, 1.6, -1.2, 1.2), max_iterations: 500 },
+    ]
+}
+
+// Time spent in each stage of one render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StageTimings {
+    pub iterate: Duration,
+    pub colorize: Duration,
+    pub encode: Duration, // Encoding the image as a PNG in memory
+}
+
+impl StageTimings {
+    pub fn total(&self) -> Duration {
+        self.iterate + self.colorize + self.encode
+    }
+
+    // Pixels rendered per second over the whole pipeline.
+    pub fn pixels_per_second(&self, width: u32, height: u32) -> f64 {
+        (width as f64 * height as f64) / self.total().as_secs_f64().max(f64::EPSILON)
+    }
+
+    // The fastest of two runs, stage by stage.
+    fn fastest(self, other: StageTimings) -> StageTimings {
+        StageTimings {
+            iterate: self.iterate.min(other.iterate),
+            colorize: self.colorize.min(other.colorize),
+            encode: self.encode.min(other.encode),
+        }
+    }
+}
+
+// Render a view once, timing every stage.
+pub fn time_view(view: &BenchView, width: u32, height: u32) -> Result<StageTimings, String> {
+    let start = Instant::now();
+    let escapes = mandelbrot::compute_escape_info(&*view.fractal, width, height, view.max_iterations, view.bounds, DEFAULT_BAILOUT, None);
+    let iterate = start.elapsed();
+
+    let start = Instant::now();
+    let image = mandelbrot::colorize_escapes(width, height, &escapes, &ColoredColorMap::new(view.max_iterations));
+    let colorize = start.elapsed();
+
+    let start = Instant::now();
+    image.write_to(&mut Cursor::new(Vec::new()), ImageFormat::Png).map_err(|e| format!("Could not encode the {} view: {}", view.name, e))?;
+    let encode = start.elapsed();
+
+    Ok(StageTimings { iterate, colorize, encode })
+}
+
+// Render a view `runs` times and keep the fastest time of every stage, which is the least disturbed
+// by whatever else the machine is doing.
+pub fn bench_view(view: &BenchView, width: u32, height: u32, runs: u32) -> Result<StageTimings, String> {
+    let mut best = time_view(view, width, height)?;
+    for _ in 1..runs {
+        best = best.fastest(time_view(view, width, height)?);
+    }
+    Ok(best)
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// One line of the report.
+pub fn report_line(name: &str, timings: &StageTimings, width: u32, height: u32) -> String {
+    format!(
+        "{:<16} {:<8} {:>10.2} {:>10.2} {:>10.2} {:>12.0}",
+        name,
+        BACKEND,
+        milliseconds(timings.iterate),
+        milliseconds(timings.colorize),
+        milliseconds(timings.encode),
+        timings.pixels_per_second(width, height)
+    )
+}
+
+// Run the benchmark on every standard view and print one line per view, then the overall rate.
+pub fn run(width: u32, height: u32, runs: u32) -> Result<(), String> {
+    if runs == 0 {
+        return Err("The benchmark needs at least one run".to_string());
+    }
+    println!("Benchmark at {}x{}, fastest of {} runs", width, height, runs);
+    println!("{:<16} {:<8} {:>10} {:>10} {:>10} {:>12}", "view", "backend", "iterate ms", "color ms", "encode ms", "pixels/s");
+    let mut total = Duration::ZERO;
+    let views = standard_views();
+    for view in &views {
+        let timings = bench_view(view, width, height, runs)?;
+        println!("{}", report_line(view.name, &timings, width, height));
+        total += timings.total();
+    }
+    let pixels = width as f64 * height as f64 * views.len() as f64;
+    println!("Overall: {:.0} pixels/s", pixels / total.as_secs_f64().max(f64::EPSILON));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the pixel rate and that the fastest time of every stage is kept across runs.
+    #[test]
+    fn test_stage_timings() {
+        let timings = StageTimings { iterate: Duration::from_millis(300), colorize: Duration::from_millis(150), encode: Duration::from_millis(50) };
+        assert_eq!(timings.pixels_per_second(1000, 500), 1_000_000.0);
+
+        let other = StageTimings { iterate: Duration::from_millis(200), colorize: Duration::from_millis(200), encode: Duration::from_millis(50) };
+        assert_eq!(timings.fastest(other).total(), Duration::from_millis(400));
+    }
+
+    /// Tests that every standard view can be rendered, here at a tiny size.
+    #[test]
+    fn test_standard_views_render() {
+        let views = standard_views();
+        assert!(!views.is_empty());
+        for view in &views {
+            assert!(bench_view(view, 8, 6, 1).is_ok());
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// Bifurcation diagrams of the logistic map x ← r·x·(1 − x): every column is a rate r, for which the
+// orbit of x = 0.5 is iterated past its transient, and the values it then keeps returning to are
+// counted in the rows, x going up. A stable cycle leaves as many points as its period, and the period
+// doubles again and again until chaos fills whole bands, with windows of order in between: the rates
+// with a negative Lyapunov exponent in the lyapunov fractal are the columns showing cycles here.
+use crate::density::Density;
+use final_exercice::palette::Palette;
+use image::RgbImage;
+
+pub const BIFURCATION_BOUNDS: (f32, f32, f32, f32) = (2.5, 4.0, 0.0, 1.0);
+pub const DEFAULT_TRANSIENT: u32 = 500;
+pub const DEFAULT_SAMPLES: u32 = 2000;
+
+pub struct Bifurcation {
+    pub transient: u32, // The iterations skipped before the orbit is counted
+    pub samples: u32,   // The points counted in every column
+}
+
+impl Bifurcation {
+    pub fn new(transient: u32, samples: u32) -> Result<Self, String> {
+        if samples == 0 {
+            return Err("A bifurcation diagram needs at least one sample per column".to_string());
+        }
+        Ok(Self { transient, samples })
+    }
+
+    // Count the orbits of the rates at the centers of the columns of the bounds.
+    pub fn accumulate(&self, density: &mut Density, width: u32, (rmin, rmax): (f32, f32)) {
+        let step = (rmax - rmin) as f64 / width as f64;
+        for column in 0..width {
+            let r = rmin as f64 + (column as f64 + 0.5) * step;
+            let mut x = 0.5;
+            for _ in 0..self.transient {
+                x = r * x * (1.0 - x);
+            }
+            for _ in 0..self.samples {
+                x = r * x * (1.0 - x);
+                density.add(r, x);
+            }
+        }
+    }
+}
+
+// Render the diagram with r on the horizontal axis and x on the vertical one, shaded through the palette
+// by how often the orbits visit every pixel.
+pub fn generate_bifurcation_diagram(width: u32, height: u32, bifurcation: &Bifurcation, bounds: (f32, f32, f32, f32), palette: &Palette) -> RgbImage {
+    let mut density = Density::new(width, height, bounds);
+    bifurcation.accumulate(&mut density, width, (bounds.0, bounds.1));
+    density.image(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the columns of a fixed point, a 2-cycle and chaos fill one, two and many rows.
+    #[test]
+    fn test_bifurcation_columns() {
+        // The columns are the rates 2.7, 2.9, ..., 3.9
+        let mut density = Density::new(7, 100, (2.6, 4.0, 0.0, 1.0));
+        Bifurcation::new(500, 200).unwrap().accumulate(&mut density, 7, (2.6, 4.0));
+        let rows = |column: usize| (0..100).filter(|row| density.counts[row * 7 + column] > 0).count();
+        assert_eq!(rows(0), 1);
+        assert_eq!(rows(3), 2);
+        assert!(rows(6) > 20);
+        assert_eq!(density.counts.iter().sum::<u32>(), 7 * 200);
+        assert!(Bifurcation::new(100, 0).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+// Bookmarks of favorite locations, saved from the viewer with B and opened again by name.
+//
+// They are kept in `bookmarks.conf` in the configuration directory of the user (for example
+// `~/.config/mandelbrot/bookmarks.conf`), one `[[bookmark]]` table per location, written in the
+// format read by config::load_document:
+//
+//     [[bookmark]]
+//     name = "seahorse valley"
+//     center = "-0.743,0.131"
+//     zoom = "150"
+//     iterations = "500"
+//     palette = "inferno"
+use crate::cli;
+use crate::config::{self, Document};
+use crate::viewer::View;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub center: (f32, f32),
+    pub zoom: f32,
+    pub iterations: u32,
+    pub palette: String,
+}
+
+impl Bookmark {
+    pub fn from_view(name: String, view: &View, palette: &str) -> Self {
+        Self { name, center: view.center, zoom: view.zoom, iterations: view.max_iterations, palette: palette.to_string() }
+    }
+
+    // The bookmarked view rendered at the given size.
+    pub fn view(&self, width: u32, height: u32) -> View {
+        View { center: self.center, zoom: self.zoom, max_iterations: self.iterations, width, height }
+    }
+}
+
+// The bookmarks file in the configuration directory of the platform.
+pub fn default_path() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("bookmarks.conf"))
+}
+
+// Read the bookmarks of a parsed file.
+pub fn from_document(document: &Document) -> Result<Vec<Bookmark>, String> {
+    let mut bookmarks = Vec::new();
+    for table in document.tables.iter().filter(|table| table.name == "bookmark") {
+        let get = |key: &str| table.options.get(key).ok_or_else(|| format!("A bookmark is missing its {}", key));
+        let name = get("name")?.clone();
+        let invalid = |key: &str| format!("Bookmark '{}' has an invalid {}", name, key);
+        bookmarks.push(Bookmark {
+            center: cli::parse_point(get("center")?)?,
+            zoom: get("zoom")?.parse().map_err(|_| invalid("zoom"))?,
+            iterations: get("iterations")?.parse().map_err(|_| invalid("iterations"))?,
+            palette: get("palette")?.clone(),
+            name,
+        });
+    }
+    Ok(bookmarks)
+}
+
+// Write bookmarks in the format read by from_document.
+pub fn to_text(bookmarks: &[Bookmark]) -> String {
+    let mut text = String::new();
+    for bookmark in bookmarks {
+        text.push_str(&format!(
+            "[[bookmark]]\nname = {}\ncenter = \"{},{}\"\nzoom = \"{}\"\niterations = \"{}\"\npalette = {}\n\n",
+            config::quote(&bookmark.name), bookmark.center.0, bookmark.center.1, bookmark.zoom, bookmark.iterations, config::quote(&bookmark.palette)
+        ));
+    }
+    text
+}
+
+// Read the bookmarks file, a missing file meaning no bookmarks yet.
+pub fn load(path: &Path) -> Result<Vec<Bookmark>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    from_document(&config::load_document(&path.to_string_lossy())?)
+}
+
+pub fn save(path: &Path, bookmarks: &[Bookmark]) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Could not write '{}': {}", path.display(), e);
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(error)?;
+    }
+    fs::write(path, to_text(bookmarks)).map_err(error)
+}
+
+// Add a bookmark, replacing the one with the same name if there is one.
+pub fn insert(bookmarks: &mut Vec<Bookmark>, bookmark: Bookmark) {
+    match bookmarks.iter_mut().find(|b| b.name == bookmark.name) {
+        Some(existing) => *existing = bookmark,
+        None => bookmarks.push(bookmark),
+    }
+}
+
+pub fn find<'a>(bookmarks: &'a [Bookmark], name: &str) -> Result<&'a Bookmark, String> {
+    bookmarks.iter().find(|b| b.name == name).ok_or_else(|| format!("No bookmark named '{}'", name))
+}
+
+// The first name of the form `bookmark-N` not taken yet.
+pub fn unused_name(bookmarks: &[Bookmark]) -> String {
+    (1..)
+        .map(|n| format!("bookmark-{}", n))
+        .find(|name| bookmarks.iter().all(|b| &b.name != name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(name: &str) -> Bookmark {
+        Bookmark { name: name.to_string(), center: (-0.743, 0.131), zoom: 150.0, iterations: 500, palette: "inferno".to_string() }
+    }
+
+    /// Tests that bookmarks written to text are read back identically.
+    #[test]
+    fn test_round_trip() {
+        let bookmarks = vec![bookmark("seahorse valley"), bookmark("second")];
+        let document = config::parse_document(&to_text(&bookmarks)).unwrap();
+        assert_eq!(from_document(&document).unwrap(), bookmarks);
+    }
+
+    /// Tests replacing a bookmark by name and generating names.
+    #[test]
+    fn test_insert_and_names() {
+        let mut bookmarks = vec![bookmark("bookmark-1")];
+        assert_eq!(unused_name(&bookmarks), "bookmark-2");
+        insert(&mut bookmarks, Bookmark { zoom: 2.0, ..bookmark("bookmark-1") });
+        insert(&mut bookmarks, bookmark("other"));
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(find(&bookmarks, "bookmark-1").unwrap().zoom, 2.0);
+        assert!(find(&bookmarks, "missing").is_err());
+    }
+}
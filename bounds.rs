@@ -0,0 +1,235 @@
+// Validation of the bounds xmin;xmax;ymin;ymax of a region of the plane, typed in the menu, given to
+// --bounds or read back from the metadata of an image. Bounds that cannot select a region (numbers
+// missing, infinite, or a range of zero width) are refused; reversed ranges are swapped back into
+// order; regions that render but will not show what was probably meant are kept with a warning:
+//
+//   - a range narrower than MIN_STEPS steps of f32 around its values, where neighbouring pixels
+//     compute the same point and the image turns into blocks
+//   - a region entirely outside the circle of radius 2, where every point escapes at once
+//
+// Bounds of another aspect ratio than the image would stretch it, so they are fitted to the image
+// first, the pixels staying square, by one of the policies of AspectPolicy.
+use crate::error::Error;
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// The fewest distinct f32 values a range should span for the image not to show blocks.
+pub const MIN_STEPS: f32 = 1000.0;
+
+// Bounds made valid, with what was changed or looks wrong about them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedBounds {
+    pub bounds: (f32, f32, f32, f32),
+    pub warnings: Vec<String>,
+}
+
+// Read bounds written as xmin;xmax;ymin;ymax, without checking them.
+pub fn parse_bounds(input: &str) -> Result<(f32, f32, f32, f32), Error> {
+    let invalid = || Error::parse("bounds", input.trim(), "four numbers xmin;xmax;ymin;ymax");
+    let numbers: Vec<f32> = input.split(';').map(|n| n.trim().parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+    match numbers[..] {
+        [xmin, xmax, ymin, ymax] => Ok((xmin, xmax, ymin, ymax)),
+        _ => Err(invalid()),
+    }
+}
+
+// Read bounds and check them.
+pub fn parse_checked(input: &str) -> Result<CheckedBounds, Error> {
+    check(parse_bounds(input)?)
+}
+
+// Refuse the bounds selecting no region, and put the others in order.
+pub fn check(bounds: (f32, f32, f32, f32)) -> Result<CheckedBounds, Error> {
+    let (mut xmin, mut xmax, mut ymin, mut ymax) = bounds;
+    if ![xmin, xmax, ymin, ymax].iter().all(|value| value.is_finite()) {
+        return Err(Error::InvalidView(format!("The bounds {};{};{};{} must be finite numbers", xmin, xmax, ymin, ymax)));
+    }
+    if xmin == xmax || ymin == ymax {
+        return Err(Error::InvalidView(format!("The bounds {};{};{};{} select no region, xmin must differ from xmax and ymin from ymax", xmin, xmax, ymin, ymax)));
+    }
+    let mut warnings = Vec::new();
+    if xmin > xmax {
+        (xmin, xmax) = (xmax, xmin);
+        warnings.push(format!("xmin was greater than xmax, using {};{}", xmin, xmax));
+    }
+    if ymin > ymax {
+        (ymin, ymax) = (ymax, ymin);
+        warnings.push(format!("ymin was greater than ymax, using {};{}", ymin, ymax));
+    }
+    for (axis, min, max) in [("x", xmin, xmax), ("y", ymin, ymax)] {
+        if steps(min, max) < MIN_STEPS {
+            warnings.push(format!("The {} range {} to {} is too narrow for f32: the image will show blocks", axis, min, max));
+        }
+    }
+    let nearest = (xmin.max(0.0).min(xmax).powi(2) + ymin.max(0.0).min(ymax).powi(2)).sqrt();
+    if nearest > 2.0 {
+        warnings.push("The region is outside the circle of radius 2, where every point escapes at once".to_string());
+    }
+    Ok(CheckedBounds { bounds: (xmin, xmax, ymin, ymax), warnings })
+}
+
+// About how many distinct f32 values lie between min and max.
+fn steps(min: f32, max: f32) -> f32 {
+    let spacing = min.abs().max(max.abs()) * f32::EPSILON;
+    if spacing == 0.0 {
+        f32::INFINITY
+    } else {
+        (max - min) / spacing
+    }
+}
+
+// The bounds of a width × height image centered on a point, `zoom` times narrower than `extent`: views
+// given by center and magnification, which stay exact at zooms where their bounds would round to the
+// same numbers.
+pub fn centered(center: (f32, f32), zoom: f32, extent: f32, width: u32, height: u32) -> Result<(f32, f32, f32, f32), Error> {
+    if !(zoom.is_finite() && zoom > 0.0) {
+        return Err(Error::InvalidView(format!("The zoom must be a positive number, not {}", zoom)));
+    }
+    if !(center.0.is_finite() && center.1.is_finite()) {
+        return Err(Error::InvalidView("The center must be a finite point".to_string()));
+    }
+    let half_width = extent / zoom / 2.0;
+    let half_height = half_width * height as f32 / width as f32;
+    Ok((center.0 - half_width, center.0 + half_width, center.1 - half_height, center.1 + half_height))
+}
+
+// How bounds of another aspect ratio than the image are fitted to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AspectPolicy {
+    Letterbox, // The bounds exactly, black bars filling the image along their short side
+    #[default]
+    Expand, // The bounds and more of the plane along their short side
+    Crop,   // The image filled, the bounds cut along their long side
+}
+
+impl FromStr for AspectPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "letterbox" => Ok(AspectPolicy::Letterbox),
+            "expand" => Ok(AspectPolicy::Expand),
+            "crop" => Ok(AspectPolicy::Crop),
+            _ => Err(Error::parse("aspect policy", s, "letterbox, expand or crop")),
+        }
+    }
+}
+
+impl AspectPolicy {
+    // The zoom on a box, which fills the width of the image at zoom_x and its height at zoom_y.
+    pub fn box_zoom(self, zoom_x: f32, zoom_y: f32) -> f32 {
+        match self {
+            AspectPolicy::Crop => zoom_x.max(zoom_y),
+            AspectPolicy::Letterbox | AspectPolicy::Expand => zoom_x.min(zoom_y),
+        }
+    }
+}
+
+// Bounds fitted to an image: the part of the plane it shows, and with a letterbox the region asked
+// for, the image being black outside of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Framing {
+    pub bounds: (f32, f32, f32, f32),
+    pub region: Option<(f32, f32, f32, f32)>,
+}
+
+impl Framing {
+    pub fn new(bounds: (f32, f32, f32, f32), width: u32, height: u32, policy: AspectPolicy) -> Self {
+        let (xmin, xmax, ymin, ymax) = bounds;
+        // Plane units per pixel along each axis, the same once fitted
+        let (scale_x, scale_y) = ((xmax - xmin) / width as f32, (ymax - ymin) / height as f32);
+        if (scale_x - scale_y).abs() <= scale_x.max(scale_y) * 1e-5 {
+            return Self { bounds, region: None };
+        }
+        let keep_x = match policy {
+            AspectPolicy::Crop => scale_x < scale_y,
+            AspectPolicy::Letterbox | AspectPolicy::Expand => scale_x > scale_y,
+        };
+        let fitted = if keep_x {
+            let (center, half) = ((ymin + ymax) / 2.0, scale_x * height as f32 / 2.0);
+            (xmin, xmax, center - half, center + half)
+        } else {
+            let (center, half) = ((xmin + xmax) / 2.0, scale_y * width as f32 / 2.0);
+            (center - half, center + half, ymin, ymax)
+        };
+        let region = (policy == AspectPolicy::Letterbox).then_some(bounds);
+        Self { bounds: fitted, region }
+    }
+
+    // Whether each pixel of a width × height image of the bounds is on the bars of the letterbox, its
+    // center being outside the region, row by row. None without a letterbox.
+    pub fn bars(&self, width: u32, height: u32) -> Option<Vec<bool>> {
+        let (xmin, xmax, ymin, ymax) = self.region?;
+        let (left, right, top, bottom) = self.bounds;
+        let (scale_x, scale_y) = ((right - left) / width as f32, (bottom - top) / height as f32);
+        let outside = |px: u32, py: u32| {
+            let (x, y) = (left + (px as f32 + 0.5) * scale_x, top + (py as f32 + 0.5) * scale_y);
+            x < xmin || x > xmax || y < ymin || y > ymax
+        };
+        Some((0..height).flat_map(|py| (0..width).map(move |px| outside(px, py))).collect())
+    }
+
+    // Paint the bars of the letterbox, if any, black.
+    pub fn apply(&self, image: &mut RgbImage) {
+        if let Some(bars) = self.bars(image.width(), image.height()) {
+            for (pixel, _) in image.pixels_mut().zip(bars).filter(|(_, bar)| *bar) {
+                *pixel = Rgb([0, 0, 0]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that bounds selecting no region are refused and reversed ones put in order.
+    #[test]
+    fn test_check_bounds() {
+        let checked = parse_checked("-2;2;-1.5;1.5").unwrap();
+        assert_eq!(checked, CheckedBounds { bounds: (-2.0, 2.0, -1.5, 1.5), warnings: Vec::new() });
+        let checked = parse_checked("2;-2;-1.5;1.5").unwrap();
+        assert_eq!(checked.bounds, (-2.0, 2.0, -1.5, 1.5));
+        assert_eq!(checked.warnings.len(), 1);
+        assert!(matches!(parse_checked("-1;-1;0;1"), Err(Error::InvalidView(_))));
+        assert!(matches!(parse_checked("-2;inf;0;1"), Err(Error::InvalidView(_))));
+        assert!(matches!(parse_checked("-2;2;0"), Err(Error::Parse { .. })));
+        assert!(matches!(parse_checked("-2;2;0;x"), Err(Error::Parse { .. })));
+    }
+
+    /// Tests the warnings of regions that render but will not show the set as expected.
+    #[test]
+    fn test_degenerate_bounds() {
+        let narrow = check((-0.75, -0.75 + 1e-6, 0.1, 0.2)).unwrap();
+        assert!(narrow.warnings[0].contains("too narrow"));
+        let outside = check((3.0, 4.0, -1.0, 1.0)).unwrap();
+        assert!(outside.warnings[0].contains("radius 2"));
+        assert!(check((-0.1, 0.1, -0.1, 0.1)).unwrap().warnings.is_empty());
+    }
+
+    /// Tests that each policy fits square bounds to a 2:1 image without stretching them.
+    #[test]
+    fn test_aspect_policies() {
+        let square = (-1.0, 1.0, -1.0, 1.0);
+        assert_eq!(Framing::new(square, 200, 100, AspectPolicy::Expand), Framing { bounds: (-2.0, 2.0, -1.0, 1.0), region: None });
+        assert_eq!(Framing::new(square, 200, 100, AspectPolicy::Crop), Framing { bounds: (-1.0, 1.0, -0.5, 0.5), region: None });
+        let letterbox = Framing::new(square, 200, 100, AspectPolicy::Letterbox);
+        assert_eq!(letterbox, Framing { bounds: (-2.0, 2.0, -1.0, 1.0), region: Some(square) });
+        let bars = letterbox.bars(8, 4).unwrap();
+        assert_eq!(bars[..8], [true, true, false, false, false, false, true, true]);
+        // Bounds already of the aspect ratio of the image are kept as they are
+        let view = (-2.0, 2.0, -1.5, 1.5);
+        assert_eq!(Framing::new(view, 800, 600, AspectPolicy::Letterbox), Framing { bounds: view, region: None });
+        assert_eq!(AspectPolicy::Crop.box_zoom(2.0, 4.0), 4.0);
+        assert!("stretch".parse::<AspectPolicy>().is_err());
+    }
+
+    /// Tests the bounds of a view given by its center and magnification.
+    #[test]
+    fn test_centered_bounds() {
+        assert_eq!(centered((-0.5, 0.0), 2.0, 4.0, 800, 600), Ok((-1.5, 0.5, -0.75, 0.75)));
+        assert!(centered((-0.5, 0.0), 0.0, 4.0, 800, 600).is_err());
+        assert!(centered((f32::NAN, 0.0), 1.0, 4.0, 800, 600).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+// Density images of the orbits of the Mandelbrot set. Instead of coloring each point c by how fast it
+// escapes, random points c are iterated and every point z their orbits visit is counted in the pixel
+// it falls in:
+//
+//   - the Buddhabrot counts the orbits escaping within the iteration limit, and at least
+//     `min_iterations` long
+//   - the Anti-Buddhabrot counts the orbits that never escape, showing the cycles they settle on
+//     inside the set
+//   - the Nebulabrot counts escaping orbits three times, with different iteration limits and their
+//     own numbers of samples, as the red, green and blue of the image (5000, 500 and 50 iterations by
+//     default, the limits of the classic pictures)
+//
+// A count becomes a brightness through the exposure, full brightness being reached at the largest
+// count divided by it, and a gamma, which brightens the faint orbits.
+use crate::area::{Random, AREA_BOUNDS};
+use crate::complex::Complex;
+use image::{Rgb, RgbImage};
+use std::str::FromStr;
+
+// The default view: the whole set and the orbits around it.
+pub const BUDDHABROT_BOUNDS: (f32, f32, f32, f32) = (-2.0, 1.0, -1.125, 1.125);
+pub const DEFAULT_SAMPLES: u64 = 1_000_000;
+pub const DEFAULT_ITERATIONS: u32 = 1000;
+pub const NEBULA_ITERATIONS: [u32; 3] = [5000, 500, 50];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Buddhabrot,
+    Anti,
+    Nebula,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "buddhabrot" => Ok(Mode::Buddhabrot),
+            "anti" => Ok(Mode::Anti),
+            "nebula" => Ok(Mode::Nebula),
+            _ => Err(format!("Invalid mode '{}', expected buddhabrot, anti or nebula", s)),
+        }
+    }
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Buddhabrot => "buddhabrot",
+            Mode::Anti => "anti-buddhabrot",
+            Mode::Nebula => "nebulabrot",
+        }
+    }
+
+    // The channels of the mode from the iteration limits and sample counts given: one value each, or
+    // for the Nebulabrot either one value or one per channel, red first.
+    pub fn channels(self, iterations: &[u32], samples: &[u64], min_iterations: u32) -> Result<Vec<Channel>, String> {
+        let count = if self == Mode::Nebula { 3 } else { 1 };
+        let invalid = |name: &str| match count {
+            1 => format!("The {} takes one number of {}", self.name(), name),
+            _ => format!("The {} takes one or three numbers of {}, red first", self.name(), name),
+        };
+        let iterations = spread(iterations, count).ok_or_else(|| invalid("iterations"))?;
+        let samples = spread(samples, count).ok_or_else(|| invalid("samples"))?;
+        let escaping = self != Mode::Anti;
+        Ok(iterations.into_iter().zip(samples).map(|(max_iterations, samples)| Channel { max_iterations, min_iterations, samples, escaping }).collect())
+    }
+}
+
+// One value repeated for every channel, or one value per channel.
+fn spread<T: Copy>(values: &[T], count: usize) -> Option<Vec<T>> {
+    match values.len() {
+        1 => Some(vec![values[0]; count]),
+        n if n == count => Some(values.to_vec()),
+        _ => None,
+    }
+}
+
+// How counts become brightness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub exposure: f64,
+    pub gamma: f64,
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self { exposure: 1.0, gamma: 2.0 }
+    }
+}
+
+impl Exposure {
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.exposure.is_finite() && self.exposure > 0.0 && self.gamma.is_finite() && self.gamma > 0.0) {
+            return Err(format!("The exposure and the gamma must be positive, not {} and {}", self.exposure, self.gamma));
+        }
+        Ok(())
+    }
+
+    // The brightness in [0, 1] of a count, given the largest count of its channel.
+    pub fn brightness(&self, count: u32, max: u32) -> f64 {
+        if max == 0 {
+            return 0.0;
+        }
+        (count as f64 / max as f64 * self.exposure).min(1.0).powf(1.0 / self.gamma)
+    }
+}
+
+// The orbits counted in one channel of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    pub max_iterations: u32,
+    pub min_iterations: u32, // Shortest escaping orbit counted
+    pub samples: u64,        // Points c drawn
+    pub escaping: bool,      // The escaping orbits, or else those which never escape
+}
+
+// The main cardioid and the disk of period 2, whose points never escape.
+fn in_main_components(c: Complex) -> bool {
+    let x = c.re - 0.25;
+    let q = x * x + c.im * c.im;
+    q * (q + x) <= c.im * c.im / 4.0 || (c.re + 1.0).powi(2) + c.im * c.im <= 1.0 / 16.0
+}
+
+// Count the visits of the orbits of a channel in the pixels of a width × height image of the bounds,
+// row by row, the points c being drawn in area::AREA_BOUNDS which holds the whole set.
+pub fn accumulate(channel: &Channel, width: u32, height: u32, bounds: (f32, f32, f32, f32), random: &mut Random) -> Vec<u32> {
+    let mut counts = vec![0; (width * height) as usize];
+    let (xmin, xmax, ymin, ymax) = (bounds.0 as f64, bounds.1 as f64, bounds.2 as f64, bounds.3 as f64);
+    let (scale_x, scale_y) = (width as f64 / (xmax - xmin), height as f64 / (ymax - ymin));
+    let (sample_x, sample_width) = (AREA_BOUNDS.0 as f64, (AREA_BOUNDS.1 - AREA_BOUNDS.0) as f64);
+    let (sample_y, sample_height) = (AREA_BOUNDS.2 as f64, (AREA_BOUNDS.3 - AREA_BOUNDS.2) as f64);
+    let mut orbit = Vec::with_capacity(channel.max_iterations as usize);
+    for _ in 0..channel.samples {
+        let c = Complex::new(sample_x + random.next_f64() * sample_width, sample_y + random.next_f64() * sample_height);
+        // Skip the longest orbits of all when they would not be counted
+        if channel.escaping && in_main_components(c) {
+            continue;
+        }
+        orbit.clear();
+        let (mut z, mut escaped) = (Complex::new(0.0, 0.0), false);
+        for _ in 0..channel.max_iterations {
+            z = z * z + c;
+            if z.norm_sqr() > 4.0 {
+                escaped = true;
+                break;
+            }
+            orbit.push(z);
+        }
+        if escaped != channel.escaping || (escaped && (orbit.len() as u32) < channel.min_iterations) {
+            continue;
+        }
+        for z in &orbit {
+            let (px, py) = ((z.re - xmin) * scale_x, (z.im - ymin) * scale_y);
+            if px >= 0.0 && py >= 0.0 && px < width as f64 && py < height as f64 {
+                counts[py as usize * width as usize + px as usize] += 1;
+            }
+        }
+    }
+    counts
+}
+
+// Render the channels: one gives a grayscale image, three the red, green and blue of the image, each
+// exposed from its own largest count. Channel i draws its samples from the seed + i.
+pub fn render(channels: &[Channel], width: u32, height: u32, bounds: (f32, f32, f32, f32), exposure: Exposure, seed: u64) -> Result<RgbImage, String> {
+    exposure.check()?;
+    if !(channels.len() == 1 || channels.len() == 3) {
+        return Err(format!("An image has one or three channels, not {}", channels.len()));
+    }
+    let brightness: Vec<Vec<u8>> = channels
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| {
+            let counts = accumulate(channel, width, height, bounds, &mut Random::new(seed.wrapping_add(index as u64)));
+            let max = counts.iter().copied().max().unwrap_or(0);
+            counts.iter().map(|&count| (exposure.brightness(count, max) * 255.0).round() as u8).collect()
+        })
+        .collect();
+    Ok(RgbImage::from_fn(width, height, |x, y| {
+        let index = (y * width + x) as usize;
+        let channel = |i: usize| brightness[i.min(brightness.len() - 1)][index];
+        Rgb([channel(0), channel(1), channel(2)])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the channels of each mode and the number of values they accept.
+    #[test]
+    fn test_mode_channels() {
+        let nebula = Mode::Nebula.channels(&NEBULA_ITERATIONS, &[1000], 0).unwrap();
+        assert_eq!(nebula.iter().map(|c| c.max_iterations).collect::<Vec<_>>(), NEBULA_ITERATIONS);
+        assert!(nebula.iter().all(|c| c.samples == 1000 && c.escaping));
+        let anti = Mode::Anti.channels(&[200], &[500], 0).unwrap();
+        assert_eq!(anti, vec![Channel { max_iterations: 200, min_iterations: 0, samples: 500, escaping: false }]);
+        assert!(Mode::Buddhabrot.channels(&NEBULA_ITERATIONS, &[1000], 0).is_err());
+        assert!(Mode::Nebula.channels(&[10, 20], &[1000], 0).is_err());
+        assert!("nebulabrot".parse::<Mode>().is_err());
+    }
+
+    /// Tests that the points of the cardioid and the period 2 disk are recognized.
+    #[test]
+    fn test_main_components() {
+        assert!(in_main_components(Complex::new(0.0, 0.0)));
+        assert!(in_main_components(Complex::new(-1.0, 0.1)));
+        assert!(!in_main_components(Complex::new(0.3, 0.0)));
+        assert!(!in_main_components(Complex::new(-0.75, 0.3)));
+    }
+
+    /// Tests that escaping and bounded orbits land in different places: escaping ones spread outside
+    /// the set, the bounded ones stay within the circle of radius 2.
+    #[test]
+    fn test_accumulate_orbits() {
+        let bounds = (-2.0, 2.0, -2.0, 2.0);
+        let channel = Channel { max_iterations: 100, min_iterations: 0, samples: 20_000, escaping: false };
+        let anti = accumulate(&channel, 40, 40, bounds, &mut Random::new(3));
+        let buddha = accumulate(&Channel { escaping: true, ..channel }, 40, 40, bounds, &mut Random::new(3));
+        assert!(anti.iter().sum::<u32>() > 0 && buddha.iter().sum::<u32>() > 0);
+        // The fixed point 0 of c = 0 and its neighbours, inside the cardioid, only get bounded orbits
+        let center = 20 * 40 + 20;
+        assert!(anti[center] > buddha[center]);
+    }
+
+    /// Tests the mapping of counts to brightness and the images of one and three channels.
+    #[test]
+    fn test_exposure() {
+        let default = Exposure::default();
+        assert_eq!(default.brightness(0, 10), 0.0);
+        assert_eq!(default.brightness(10, 10), 1.0);
+        assert_eq!(default.brightness(5, 0), 0.0);
+        assert_eq!(Exposure { exposure: 2.0, gamma: 1.0 }.brightness(5, 10), 1.0);
+        assert!(Exposure { exposure: 0.0, gamma: 1.0 }.check().is_err());
+        let channels = Mode::Nebula.channels(&[50, 20, 10], &[2000], 0).unwrap();
+        let image = render(&channels, 16, 12, BUDDHABROT_BOUNDS, default, 1).unwrap();
+        assert_eq!(image.dimensions(), (16, 12));
+        let gray = render(&channels[..1], 16, 12, BUDDHABROT_BOUNDS, default, 1).unwrap();
+        assert!(gray.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(render(&channels[..2], 16, 12, BUDDHABROT_BOUNDS, default, 1).is_err());
+    }
+}
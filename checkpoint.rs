@@ -0,0 +1,133 @@
+// Checkpoints of long renders, so that a render stopped by a crash or Ctrl+C resumes where it was
+// instead of starting over. A render made of independent items (the tiles of an image, the frames of
+// an animation) writes each item to disk as soon as it is done, and the checkpoint file records which
+// items are complete along with a fingerprint of the render, in the format of config::load_document:
+//
+//     state = "9c1f0e33a5d07b42"
+//     done = "0-127,130"
+//
+// The file is written every SAVE_INTERVAL, through a temporary file renamed over it so that it is never
+// left half written: an item finished after the last save is simply rendered again. A checkpoint whose
+// fingerprint differs from the one of the render is refused rather than mixed with it.
+use crate::config;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct Checkpoint {
+    path: PathBuf,
+    state: String,
+    done: BTreeSet<u32>,
+    last_save: Instant,
+}
+
+// A short fingerprint of the description of a render, FNV-1a so that it stays the same across builds.
+pub fn fingerprint(description: &str) -> String {
+    let hash = description.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+// Write a set of items as ranges, like "0-127,130".
+pub fn format_ranges(items: &BTreeSet<u32>) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &item in items {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == item => *end = item,
+            _ => ranges.push((item, item)),
+        }
+    }
+    let ranges: Vec<String> = ranges.iter().map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) }).collect();
+    ranges.join(",")
+}
+
+pub fn parse_ranges(text: &str) -> Result<BTreeSet<u32>, String> {
+    let mut items = BTreeSet::new();
+    for range in text.split(',').map(str::trim).filter(|range| !range.is_empty()) {
+        let invalid = || format!("Invalid range '{}' in the checkpoint", range);
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let (start, end): (u32, u32) = (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?);
+        if start > end {
+            return Err(invalid());
+        }
+        items.extend(start..=end);
+    }
+    Ok(items)
+}
+
+impl Checkpoint {
+    // Resume from the checkpoint file of a render if there is one, start an empty one otherwise.
+    pub fn open(path: &Path, description: &str) -> Result<Self, String> {
+        let state = fingerprint(description);
+        let mut done = BTreeSet::new();
+        if path.exists() {
+            let options = config::load_config(&path.to_string_lossy())?;
+            if options.get("state") != Some(&state) {
+                return Err(format!("The checkpoint {} belongs to another render, delete it to start this one over", path.display()));
+            }
+            done = parse_ranges(options.get("done").map_or("", String::as_str))?;
+        }
+        Ok(Self { path: path.to_path_buf(), state, done, last_save: Instant::now() })
+    }
+
+    pub fn is_done(&self, item: u32) -> bool {
+        self.done.contains(&item)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.done.len()
+    }
+
+    // Record an item as written to disk, saving the checkpoint if the last save is old enough.
+    pub fn complete(&mut self, item: u32) -> Result<(), String> {
+        self.done.insert(item);
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        let options = HashMap::from([("state".to_string(), self.state.clone()), ("done".to_string(), format_ranges(&self.done))]);
+        let text = config::to_text(&config::Document { options, tables: Vec::new() });
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, text).map_err(|e| format!("Could not write the checkpoint {}: {}", temporary.display(), e))?;
+        fs::rename(&temporary, &self.path).map_err(|e| format!("Could not write the checkpoint {}: {}", self.path.display(), e))?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    // Delete the checkpoint once the render is complete.
+    pub fn remove(self) -> Result<(), String> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Could not delete the checkpoint {}: {}", self.path.display(), e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the items done are written as ranges and read back.
+    #[test]
+    fn test_ranges() {
+        let items: BTreeSet<u32> = [0, 1, 2, 3, 7, 9, 10].into_iter().collect();
+        assert_eq!(format_ranges(&items), "0-3,7,9-10");
+        assert_eq!(parse_ranges("0-3,7,9-10"), Ok(items));
+        assert_eq!(parse_ranges(""), Ok(BTreeSet::new()));
+        assert!(parse_ranges("4-2").is_err());
+        assert!(parse_ranges("1-x").is_err());
+    }
+
+    /// Tests that the fingerprint tells renders apart and stays the same for the same render.
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(""), "cbf29ce484222325");
+        assert_eq!(fingerprint("zoom 2"), fingerprint("zoom 2"));
+        assert_ne!(fingerprint("zoom 2"), fingerprint("zoom 3"));
+    }
+}
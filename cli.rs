@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+// A tiny command-line argument holder: positional arguments followed by `--name value` options.
+//
+// Positional arguments have to come before the options, so `reproduce image.png --iterations 500`
+// works as expected. An option that is not followed by a value (or is followed by another option)
+// is stored as a switch.
+#[derive(Clone)]
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    // Split raw command-line words into positional arguments and options.
+    pub fn parse(raw: &[String]) -> Self {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut words = raw.iter().peekable();
+
+        while let Some(word) = words.next() {
+            if let Some(name) = word.strip_prefix("--") {
+                // Both `--name value` and `--name=value` are accepted.
+                if let Some((name, value)) = name.split_once('=') {
+                    options.insert(name.to_string(), Some(value.to_string()));
+                } else {
+                    let value = match words.peek() {
+                        Some(next) if !next.starts_with("--") => words.next().cloned(),
+                        _ => None,
+                    };
+                    options.insert(name.to_string(), value);
+                }
+            } else {
+                positional.push(word.clone());
+            }
+        }
+
+        Self { positional, options }
+    }
+
+    // Use the given values (e.g. read from a config file) for the options missing on the command line.
+    pub fn merge_defaults(&mut self, defaults: HashMap<String, String>) {
+        for (name, value) in defaults {
+            self.options.entry(name).or_insert(Some(value));
+        }
+    }
+
+    // Replace the positional arguments, keeping the options.
+    pub fn set_positional(&mut self, positional: Vec<String>) {
+        self.positional = positional;
+    }
+
+    pub fn positionals(&self) -> &[String] {
+        &self.positional
+    }
+
+    // Every option given with a value, sorted by name.
+    pub fn values(&self) -> BTreeMap<String, String> {
+        self.options
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name.clone(), value.clone())))
+            .collect()
+    }
+
+    // Get the positional argument at `index`, if it was given.
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    // Get the raw value of an option, if it was given with a value.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|value| value.as_deref())
+    }
+
+    // True if an option was given, with or without a value, like the switch `--auto-iter`.
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    // Parse the value of an option, reporting which option was malformed on failure.
+    pub fn parse_value<T: FromStr>(&self, name: &str) -> Result<Option<T>, UsageError> {
+        match self.value(name) {
+            Some(value) => value
+                .parse::<T>()
+                .map(Some)
+                .map_err(|_| UsageError(format!("Invalid value '{}' for --{}", value, name))),
+            None => Ok(None),
+        }
+    }
+
+    // Parse the comma-separated values of an option, like `--iterations 5000,500,50`.
+    pub fn parse_list<T: FromStr>(&self, name: &str) -> Result<Option<Vec<T>>, UsageError> {
+        match self.value(name) {
+            Some(value) => value
+                .split(',')
+                .map(|item| item.trim().parse::<T>().map_err(|_| UsageError(format!("Invalid value '{}' in --{} {}", item, name, value))))
+                .collect::<Result<_, _>>()
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+// A command line that cannot be read: an unknown command, an option whose value is malformed. It shows as
+// its message like the other errors, but the program exits with another status for it.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{0}")]
+pub struct UsageError(pub String);
+
+impl From<UsageError> for String {
+    fn from(error: UsageError) -> Self {
+        error.0
+    }
+}
+
+// Parse a size given as `WIDTHxHEIGHT`, e.g. `1920x1080`.
+pub fn parse_size(input: &str) -> Result<(u32, u32), UsageError> {
+    let (width, height) = input
+        .split_once('x')
+        .ok_or_else(|| UsageError(format!("Size '{}' must be in the format WIDTHxHEIGHT", input)))?;
+    let width = width.trim().parse::<u32>().map_err(|_| UsageError(format!("Invalid width in '{}'", input)))?;
+    let height = height.trim().parse::<u32>().map_err(|_| UsageError(format!("Invalid height in '{}'", input)))?;
+    if width == 0 || height == 0 {
+        return Err(UsageError(format!("Size '{}' must not be zero", input)));
+    }
+    Ok((width, height))
+}
+
+// Parse a point of the complex plane given as `RE,IM`, e.g. `-0.75,0.1`.
+pub fn parse_point(input: &str) -> Result<(f32, f32), UsageError> {
+    let (re, im) = input
+        .split_once(',')
+        .ok_or_else(|| UsageError(format!("Point '{}' must be in the format RE,IM", input)))?;
+    let re = re.trim().parse::<f32>().map_err(|_| UsageError(format!("Invalid real part in '{}'", input)))?;
+    let im = im.trim().parse::<f32>().map_err(|_| UsageError(format!("Invalid imaginary part in '{}'", input)))?;
+    Ok((re, im))
+}
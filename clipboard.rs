@@ -0,0 +1,33 @@
+// The system clipboard, reached through the command line tool of each platform rather than a
+// dependency: pbcopy on macOS, clip on Windows, and wl-copy (Wayland), xclip or xsel (X11) on the
+// other systems, the first one installed being used.
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const TOOLS: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+    &[("pbcopy", &[])]
+} else if cfg!(windows) {
+    &[("clip", &[])]
+} else {
+    &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+};
+
+// Put a text into the clipboard.
+pub fn copy(text: &str) -> Result<(), String> {
+    for (program, args) in TOOLS {
+        // A tool that cannot be started is not installed, the next one is tried
+        let Ok(mut child) = Command::new(program).args(*args).stdin(Stdio::piped()).spawn() else {
+            continue;
+        };
+        // The input is closed when dropped, which lets the tool finish
+        let mut stdin = child.stdin.take().ok_or_else(|| format!("Could not open the input of {}", program))?;
+        stdin.write_all(text.as_bytes()).map_err(|e| format!("Could not write to {}: {}", program, e))?;
+        drop(stdin);
+        let status = child.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            return Ok(());
+        }
+    }
+    let names: Vec<&str> = TOOLS.iter().map(|(program, _)| *program).collect();
+    Err(format!("Could not copy to the clipboard, it needs one of {} in PATH", names.join(", ")))
+}
@@ -0,0 +1,52 @@
+// The Collatz map extended to the complex plane:
+//
+//     f(z) = (2 + 7z − (2 + 5z)·cos(πz)) / 4
+//
+// which is n / 2 at even integers and 3n + 1 at odd ones, so the orbits of the integers are those
+// of the Collatz conjecture. Every pixel is a starting point. cos(πz) grows like e^(π|Im z|), so the
+// orbits leaving the real axis explode within a few steps, and a large bailout leaves the escaping
+// ones enough steps to be told apart.
+use crate::complex::Complex;
+use crate::fractal::{self, EscapeResult, Fractal, OrbitObserver};
+use std::f64::consts::PI;
+
+pub const COLLATZ_BAILOUT: f64 = 1e6;
+
+pub struct Collatz;
+
+impl Collatz {
+    fn step(z: Complex) -> Complex {
+        (z * 7.0 + 2.0 - (z * 5.0 + 2.0) * (z * PI).cos()) / 4.0
+    }
+}
+
+impl Fractal for Collatz {
+    fn iterate_with(&self, z0: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        fractal::escape(z0, max_iterations, bailout.max(COLLATZ_BAILOUT), observer, Collatz::step)
+    }
+
+    // cos(πz̄) is the conjugate of cos(πz).
+    fn conjugate_symmetric(&self) -> bool {
+        true
+    }
+
+    // The real axis from -2 to 6, with the thin bounded regions around the small integers.
+    fn default_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.0, 6.0, -3.0, 3.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the map is the Collatz map at the integers, whose orbit of 1 is the cycle 1 → 4 → 2 → 1.
+    #[test]
+    fn test_integers() {
+        for (n, next) in [(1.0, 4.0), (4.0, 2.0), (3.0, 10.0), (10.0, 5.0), (7.0, 22.0)] {
+            assert!((Collatz::step(Complex::new(n, 0.0)) - Complex::new(next, 0.0)).norm() < 1e-9);
+        }
+        assert_eq!(Collatz.iterate(Complex::ONE, 100).iterations, 100);
+        assert!(Collatz.iterate(Complex::new(0.5, 1.5), 100).iterations < 10);
+    }
+}
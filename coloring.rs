@@ -0,0 +1,487 @@
+// Coloring algorithms going beyond the iteration count, selected by name with `--coloring`.
+//
+// Some of them only look at the end of the orbit (its smooth count or its closest approach to a
+// trap), others average a statistic along the whole orbit. The latter tell the compute pass which
+// statistic to gather through ColorMap::statistic, so orbits never have to be stored.
+use crate::complex::Complex;
+use crate::error::Error;
+use crate::fractal::OrbitObserver;
+use crate::mandelbrot::{self, ColorMap, ColoredColorMap, EscapeInfo};
+use crate::palette::{Palette, PaletteTransform};
+use crate::plugins::{self, ColoringRequest};
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Names of the coloring algorithms, the first one being the classic coloring by iteration count.
+pub const COLORINGS: [&str; 8] = ["iterations", "smooth", "trap", "stripe", "tia", "binary", "angle", "hsv"];
+
+// Number of stripes per turn around the origin drawn by the stripe coloring.
+pub const STRIPE_DENSITY: f64 = 5.0;
+
+// Build the color map of a coloring algorithm with a palette. Only the iteration coloring accepts
+// mandelbrot::GRAYSCALE.
+pub fn coloring_by_name(coloring: &str, palette: &str, max_iterations: u32) -> Result<Box<dyn ColorMap>, Error> {
+    coloring_with_options(coloring, palette, max_iterations, &ColoringOptions::default())
+}
+
+// The settings of the colorings beyond their palette.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ColoringOptions {
+    pub palette_transform: PaletteTransform, // Not supported by grayscale
+    pub hsv: HsvOptions,                      // Used by the hsv coloring, which has no palette
+}
+
+// Build the color map of a coloring algorithm with a palette and its options, the colorings added to
+// plugins::register_coloring included.
+pub fn coloring_with_options(coloring: &str, palette: &str, max_iterations: u32, options: &ColoringOptions) -> Result<Box<dyn ColorMap>, Error> {
+    let transform = &options.palette_transform;
+    if palette == mandelbrot::GRAYSCALE && !transform.is_identity() {
+        return Err(Error::InvalidPalette("The palette transforms need a gradient, not grayscale".to_string()));
+    }
+    let name = palette;
+    let palette = || Palette::by_name(name)?.with_transform(*transform);
+    match coloring {
+        "iterations" if name == mandelbrot::GRAYSCALE => mandelbrot::color_map_by_name(name, max_iterations),
+        "iterations" => Ok(Box::new(ColoredColorMap::from_palette(max_iterations, palette()?))),
+        "smooth" => Ok(Box::new(SmoothColorMap { max_iterations, palette: palette()? })),
+        "trap" => Ok(Box::new(OrbitTrapColorMap { max_iterations, palette: palette()? })),
+        "stripe" => {
+            let statistic = OrbitStatistic::Stripe { density: STRIPE_DENSITY };
+            Ok(Box::new(OrbitAverageColorMap { max_iterations, palette: palette()?, statistic }))
+        }
+        "tia" => {
+            let statistic = OrbitStatistic::TriangleInequality;
+            Ok(Box::new(OrbitAverageColorMap { max_iterations, palette: palette()?, statistic }))
+        }
+        "binary" => Ok(Box::new(DecompositionColorMap { max_iterations, palette: palette()?, binary: true })),
+        "angle" => Ok(Box::new(DecompositionColorMap { max_iterations, palette: palette()?, binary: false })),
+        "hsv" => {
+            options.hsv.check()?;
+            Ok(Box::new(HsvColorMap { max_iterations, options: options.hsv }))
+        }
+        other => match plugins::registered_coloring(other) {
+            Some(factory) => factory(&ColoringRequest { palette: name, max_iterations, options }),
+            None => Err(Error::UnknownColoring(other.to_string())),
+        },
+    }
+}
+
+// A colored map of the continuous iteration count, without the bands of ColoredColorMap.
+pub struct SmoothColorMap {
+    max_iterations: u32,
+    palette: Palette,
+}
+
+impl ColorMap for SmoothColorMap {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        mandelbrot::quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        if !escape.escaped {
+            return [0.0; 3];
+        }
+        self.palette.at_float((escape.smooth / self.max_iterations as f64).clamp(0.0, 1.0))
+    }
+
+    fn get_max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+}
+
+// Color every point, inside the set too, by how close its orbit comes to the origin (a point orbit trap).
+pub struct OrbitTrapColorMap {
+    max_iterations: u32,
+    palette: Palette,
+}
+
+impl ColorMap for OrbitTrapColorMap {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        mandelbrot::quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        // Distances are scaled by the usual escape radius 2, which orbits stay within for most steps.
+        self.palette.at_float((escape.min_trap_distance / 2.0).min(1.0))
+    }
+
+    fn get_max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+}
+
+// Color escaping points by the argument of z at escape, which approximates the external angle of the
+// point and shows the external rays. In binary mode only the sign of Im(z) is kept, picking one end
+// of the gradient or the other (binary decomposition).
+pub struct DecompositionColorMap {
+    max_iterations: u32,
+    palette: Palette,
+    binary: bool,
+}
+
+impl ColorMap for DecompositionColorMap {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        mandelbrot::quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        if !escape.escaped {
+            return [0.0; 3];
+        }
+        let z = escape.final_z;
+        let t = if self.binary {
+            if z.im >= 0.0 { 0.0 } else { 1.0 }
+        } else {
+            (z.arg() / std::f64::consts::TAU).rem_euclid(1.0)
+        };
+        self.palette.at_float(t)
+    }
+
+    fn get_max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+}
+
+// A value going from start to end as t goes from 0 to 1, along t^exponent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    pub start: f64,
+    pub end: f64,
+    pub exponent: f64,
+}
+
+impl Curve {
+    pub const fn constant(value: f64) -> Self {
+        Self { start: value, end: value, exponent: 1.0 }
+    }
+
+    pub fn at(&self, t: f64) -> f64 {
+        self.start + (self.end - self.start) * t.powf(self.exponent)
+    }
+}
+
+impl FromStr for Curve {
+    type Err = Error;
+
+    // Read "VALUE", "START,END" or "START,END,EXPONENT", the values being in [0, 1].
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::parse("curve", s, "VALUE, START,END or START,END,EXPONENT");
+        let numbers: Vec<f64> = s.split(',').map(|n| n.trim().parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+        let curve = match numbers[..] {
+            [value] => Curve::constant(value),
+            [start, end] => Curve { start, end, exponent: 1.0 },
+            [start, end, exponent] => Curve { start, end, exponent },
+            _ => return Err(invalid()),
+        };
+        let unit = 0.0..=1.0;
+        if !(unit.contains(&curve.start) && unit.contains(&curve.end) && curve.exponent.is_finite() && curve.exponent > 0.0) {
+            return Err(Error::InvalidColoring(format!("The curve '{}' must stay in [0, 1] with a positive exponent", s)));
+        }
+        Ok(curve)
+    }
+}
+
+// Where the hsv coloring takes its hue from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HueSource {
+    Iterations, // The smooth iteration count, one turn of the color wheel every `hue_period` iterations
+    Angle,      // The argument of z at escape
+}
+
+impl FromStr for HueSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "iterations" => Ok(HueSource::Iterations),
+            "angle" => Ok(HueSource::Angle),
+            _ => Err(Error::parse("hue source", s, "iterations or angle")),
+        }
+    }
+}
+
+// The knobs of the hsv coloring. Saturation and value follow their curve along the normalized smooth
+// iteration count, so that for example the colors can darken towards the set.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HsvOptions {
+    pub hue: HueSource,
+    pub hue_period: f64, // Iterations per turn of the hue
+    pub hue_offset: f64, // In turns
+    pub saturation: Curve,
+    pub value: Curve,
+}
+
+impl Default for HsvOptions {
+    fn default() -> Self {
+        Self { hue: HueSource::Iterations, hue_period: 64.0, hue_offset: 0.0, saturation: Curve::constant(0.85), value: Curve::constant(1.0) }
+    }
+}
+
+impl HsvOptions {
+    pub fn check(&self) -> Result<(), Error> {
+        if !(self.hue_period.is_finite() && self.hue_period > 0.0) {
+            return Err(Error::InvalidColoring(format!("The hue period must be a positive number of iterations, not {}", self.hue_period)));
+        }
+        if !self.hue_offset.is_finite() {
+            return Err(Error::InvalidColoring("The hue offset must be a finite number of turns".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// Convert a color given as hue (in turns), saturation and value, all in [0, 1].
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb<u8> {
+    mandelbrot::quantize(hsv_to_float(hue, saturation, value))
+}
+
+// The same conversion to RGB channels in [0, 1].
+pub fn hsv_to_float(hue: f64, saturation: f64, value: f64) -> [f64; 3] {
+    let sector = hue.rem_euclid(1.0) * 6.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r + m, g + m, b + m]
+}
+
+// A procedural coloring without a gradient: a hue wheel driven by the smooth iteration count or the
+// angle of z at escape, with saturation and value curves.
+pub struct HsvColorMap {
+    max_iterations: u32,
+    options: HsvOptions,
+}
+
+impl ColorMap for HsvColorMap {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        mandelbrot::quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        if !escape.escaped {
+            return [0.0; 3];
+        }
+        let options = &self.options;
+        let t = (escape.smooth / self.max_iterations as f64).clamp(0.0, 1.0);
+        let hue = match options.hue {
+            HueSource::Iterations => escape.smooth / options.hue_period,
+            HueSource::Angle => escape.final_z.arg() / std::f64::consts::TAU,
+        };
+        hsv_to_float(hue + options.hue_offset, options.saturation.at(t), options.value.at(t))
+    }
+
+    fn get_max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+}
+
+// A value computed at every step of an orbit, in [0, 1], whose average colors the pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitStatistic {
+    // 0.5 + 0.5·sin(density·arg z): its average draws stripes following the flow lines around the set.
+    Stripe { density: f64 },
+    // Where |z| falls between the bounds ||z'²| − |c|| and |z'²| + |c| given by the triangle
+    // inequality for z = z'² + c, z' being the previous value.
+    TriangleInequality,
+}
+
+impl OrbitStatistic {
+    // The value of a step, or None when it is not defined for this step.
+    pub fn value(&self, previous: Complex, z: Complex) -> Option<f64> {
+        match self {
+            OrbitStatistic::Stripe { density } => Some(0.5 + 0.5 * (density * z.arg()).sin()),
+            OrbitStatistic::TriangleInequality => {
+                // c is recovered from the step, so this works for the Julia sets (whose c is not the pixel) too.
+                let squared = previous * previous;
+                let (squared_norm, c_norm) = (squared.norm(), (z - squared).norm());
+                let (low, high) = ((squared_norm - c_norm).abs(), squared_norm + c_norm);
+                // Both bounds are |c| on the first step of the Mandelbrot set, where z' = 0.
+                (high > low).then(|| (z.norm() - low) / (high - low))
+            }
+        }
+    }
+
+    // The average along the conjugate orbit, given the average along an orbit.
+    pub fn mirrored_average(&self, average: f64) -> f64 {
+        match self {
+            // sin(density·arg z̄) = -sin(density·arg z)
+            OrbitStatistic::Stripe { .. } => 1.0 - average,
+            // Conjugating keeps every norm.
+            OrbitStatistic::TriangleInequality => average,
+        }
+    }
+}
+
+// Running average of a statistic along an orbit.
+pub struct OrbitAverage {
+    statistic: OrbitStatistic,
+    sum: f64,
+    last: f64, // Value of the last step
+    count: u32,
+}
+
+impl OrbitAverage {
+    pub fn new(statistic: OrbitStatistic) -> Self {
+        Self { statistic, sum: 0.0, last: 0.0, count: 0 }
+    }
+
+    // The averages without and with the last step.
+    pub fn averages(&self) -> (f64, f64) {
+        match self.count {
+            0 => (0.0, 0.0),
+            1 => (self.sum, self.sum),
+            count => ((self.sum - self.last) / (count - 1) as f64, self.sum / count as f64),
+        }
+    }
+}
+
+impl OrbitObserver for OrbitAverage {
+    fn observe(&mut self, previous: Complex, z: Complex) {
+        if let Some(value) = self.statistic.value(previous, z) {
+            self.last = value;
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+}
+
+// Color escaping points by the average of a statistic along their orbit.
+pub struct OrbitAverageColorMap {
+    max_iterations: u32,
+    palette: Palette,
+    statistic: OrbitStatistic,
+}
+
+impl ColorMap for OrbitAverageColorMap {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        mandelbrot::quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        if !escape.escaped {
+            return [0.0; 3];
+        }
+        self.palette.at_float(escape.orbit_average.clamp(0.0, 1.0))
+    }
+
+    fn get_max_iterations(&self) -> u32 {
+        self.max_iterations
+    }
+
+    fn statistic(&self) -> Option<OrbitStatistic> {
+        Some(self.statistic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{Fractal, Mandelbrot, DEFAULT_BAILOUT};
+
+    /// Tests that the stripe statistic is averaged over every step of the orbit.
+    #[test]
+    fn test_stripe_average() {
+        let mut average = OrbitAverage::new(OrbitStatistic::Stripe { density: 1.0 });
+        // 0 → -1 → 0 → -1: arg(-1) = π and arg(0) = 0 give 0.5 each time.
+        let escape = Mandelbrot.iterate_with(Complex::new(-1.0, 0.0), 4, DEFAULT_BAILOUT, &mut average);
+        assert_eq!(escape.iterations, 4);
+        let (previous, last) = average.averages();
+        assert!((previous - 0.5).abs() < 1e-9 && (last - 0.5).abs() < 1e-9);
+
+        let mut average = OrbitAverage::new(OrbitStatistic::Stripe { density: 1.0 });
+        average.observe(Complex::ZERO, Complex::new(0.0, 1.0)); // sin(π/2) gives 1
+        average.observe(Complex::ZERO, Complex::new(0.0, -1.0)); // sin(-π/2) gives 0
+        assert_eq!(average.averages(), (1.0, 0.5));
+    }
+
+    /// Tests the triangle inequality statistic on steps where |z| reaches each bound.
+    #[test]
+    fn test_triangle_inequality_value() {
+        let tia = OrbitStatistic::TriangleInequality;
+        // z' = 1 and c = 1: z = 2 = |z'²| + |c| is the upper bound, z' = 1 and c = -1 give z = 0, the lower one.
+        assert_eq!(tia.value(Complex::ONE, Complex::new(2.0, 0.0)), Some(1.0));
+        assert_eq!(tia.value(Complex::ONE, Complex::ZERO), Some(0.0));
+        // With c = i, |z| = |1 + i| lies strictly between 0 and 2.
+        let value = tia.value(Complex::ONE, Complex::new(1.0, 1.0)).unwrap();
+        assert!((value - 2f64.sqrt() / 2.0).abs() < 1e-9);
+        // The first step of the Mandelbrot set is skipped.
+        assert_eq!(tia.value(Complex::ZERO, Complex::new(0.3, 0.1)), None);
+    }
+
+    /// Tests that the binary decomposition follows the sign of Im(z) at escape and the angle its argument.
+    #[test]
+    fn test_decomposition_colors() {
+        let palette = || Palette::by_name("viridis").unwrap();
+        let binary = DecompositionColorMap { max_iterations: 10, palette: palette(), binary: true };
+        let angle = DecompositionColorMap { max_iterations: 10, palette: palette(), binary: false };
+        let escape = |z| EscapeInfo { final_z: z, ..EscapeInfo::from_iterations(3, 10) };
+
+        let (upper, lower) = (escape(Complex::new(1.0, 2.0)), escape(Complex::new(1.0, -2.0)));
+        assert_eq!(binary.color(&upper), palette().at(0.0));
+        assert_eq!(binary.color(&lower), palette().at(1.0));
+        // -3i is three quarters of a turn around the origin.
+        assert_eq!(angle.color(&escape(Complex::new(0.0, -3.0))), palette().at(0.75));
+        assert_eq!(binary.color(&EscapeInfo::from_iterations(10, 10)), Rgb([0, 0, 0]));
+    }
+
+    /// Tests that the mirrored half of a symmetric view gets the stripe average of the conjugate orbits.
+    #[test]
+    fn test_stripe_average_mirrored() {
+        let statistic = Some(OrbitStatistic::Stripe { density: STRIPE_DENSITY });
+        let escapes = mandelbrot::compute_escape_info(&Mandelbrot, 12, 8, 50, (-2.0, 1.0, -1.0, 1.0), DEFAULT_BAILOUT, statistic);
+        let direct = crate::fractal::map_plane(12, 8, (-2.0, 1.0, -1.0, 1.0), |c| {
+            let mut average = OrbitAverage::new(OrbitStatistic::Stripe { density: STRIPE_DENSITY });
+            let escape = Mandelbrot.iterate_with(c, 50, DEFAULT_BAILOUT, &mut average);
+            EscapeInfo::new(&escape, 50, DEFAULT_BAILOUT).with_orbit_average(&average)
+        });
+        for (mirrored, computed) in escapes.iter().zip(&direct) {
+            assert_eq!(mirrored.iterations, computed.iterations);
+            assert!((mirrored.orbit_average - computed.orbit_average).abs() < 1e-9);
+            assert!((mirrored.final_z - computed.final_z).norm() < 1e-9);
+        }
+    }
+
+    /// Tests the conversion from HSV, the curves and the hue of the hsv coloring.
+    #[test]
+    fn test_hsv_coloring() {
+        assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), Rgb([255, 0, 0]));
+        assert_eq!(hsv_to_rgb(1.0 / 3.0, 1.0, 1.0), Rgb([0, 255, 0]));
+        assert_eq!(hsv_to_rgb(-1.0 / 3.0, 1.0, 0.5), Rgb([0, 0, 128]));
+        assert_eq!(hsv_to_rgb(0.7, 0.0, 1.0), Rgb([255, 255, 255]));
+
+        let curve: Curve = "1,0.5,2".parse().unwrap();
+        assert_eq!((curve.at(0.0), curve.at(0.5), curve.at(1.0)), (1.0, 0.875, 0.5));
+        assert_eq!("0.3".parse(), Ok(Curve::constant(0.3)));
+        assert!("0,2".parse::<Curve>().is_err());
+        assert!("1,2,3,4".parse::<Curve>().is_err());
+
+        let options = HsvOptions { hue_period: 8.0, saturation: Curve::constant(1.0), ..Default::default() };
+        let color_map = HsvColorMap { max_iterations: 100, options };
+        // 4 iterations are half a turn, cyan
+        assert_eq!(color_map.color(&EscapeInfo::from_iterations(4, 100)), Rgb([0, 255, 255]));
+        assert_eq!(color_map.color(&EscapeInfo::from_iterations(100, 100)), Rgb([0, 0, 0]));
+        let angle = HsvColorMap { max_iterations: 100, options: HsvOptions { hue: HueSource::Angle, ..options } };
+        let escape = EscapeInfo { final_z: Complex::new(-1.0, 1e-12), ..EscapeInfo::from_iterations(4, 100) };
+        assert_eq!(angle.color(&escape), Rgb([0, 255, 255]));
+        let invalid = ColoringOptions { hsv: HsvOptions { hue_period: 0.0, ..options }, ..Default::default() };
+        assert!(coloring_with_options("hsv", "turbo", 100, &invalid).is_err());
+    }
+
+    /// Tests that the compute pass gathers the statistic of the color map for the stripe coloring.
+    #[test]
+    fn test_stripe_coloring_gathers_statistic() {
+        let color_map = coloring_by_name("stripe", "turbo", 50).unwrap();
+        assert!(color_map.statistic().is_some());
+        let escapes = mandelbrot::compute_escape_info(&Mandelbrot, 8, 6, 50, (-2.0, 2.0, -1.5, 1.5), DEFAULT_BAILOUT, color_map.statistic());
+        assert!(escapes.iter().any(|escape| escape.escaped && escape.orbit_average > 0.0));
+        assert!(coloring_by_name("iterations", "turbo", 50).unwrap().statistic().is_none());
+        assert!(coloring_by_name("stripe", mandelbrot::GRAYSCALE, 50).is_err());
+    }
+}
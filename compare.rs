@@ -0,0 +1,136 @@
+// Palettes side by side: the escapes of a view are computed once, then colored with every palette
+// compared, the images being laid out in a grid with the name of their palette in a corner. Coloring
+// is the cheap pass of a render, so even a long list of palettes costs little more than one render.
+//
+// The previews show the palettes themselves, one row each: the strip of its gradient from start to end,
+// and next to it, when asked, a thumbnail of the Mandelbrot set colored with it.
+use crate::mandelbrot::{self, EscapeInfo};
+use crate::palette::{Palette, PaletteTransform};
+use crate::render::RenderOptions;
+use crate::text;
+use image::{Rgb, RgbImage};
+
+// The space between two images of the grid, and its color.
+const GAP: u32 = 4;
+const GAP_COLOR: Rgb<u8> = Rgb([32, 32, 32]);
+const LABEL_PADDING: u32 = 3;
+// The default sizes of the strips of the previews and of their thumbnails.
+pub const STRIP_SIZE: (u32, u32) = (512, 32);
+pub const THUMBNAIL_SIZE: (u32, u32) = (96, 72);
+
+// The columns and rows of a grid as close to a square as possible holding a number of images.
+pub fn grid_shape(count: usize) -> (u32, u32) {
+    let columns = (count as f64).sqrt().ceil().max(1.0) as u32;
+    (columns, (count as u32).div_ceil(columns).max(1))
+}
+
+// The escapes of the options colored with each palette, in a grid read row by row.
+pub fn palette_grid(options: &RenderOptions, escapes: &[EscapeInfo], palettes: &[&str]) -> Result<RgbImage, String> {
+    if palettes.is_empty() {
+        return Err("No palettes to compare".to_string());
+    }
+    let (columns, rows) = grid_shape(palettes.len());
+    let (width, height) = (options.width, options.height);
+    let mut grid = RgbImage::from_pixel(columns * width + (columns - 1) * GAP, rows * height + (rows - 1) * GAP, GAP_COLOR);
+    for (index, &palette) in palettes.iter().enumerate() {
+        let mut image = RenderOptions { palette: palette.to_string(), ..options.clone() }.colorize(escapes)?;
+        draw_label(&mut image, palette);
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        image::imageops::replace(&mut grid, &image, (column * (width + GAP)) as i64, (row * (height + GAP)) as i64);
+    }
+    Ok(grid)
+}
+
+// A palette from its start on the left to its end on the right, with a transform of its index.
+pub fn gradient_strip(palette: &str, transform: PaletteTransform, (width, height): (u32, u32)) -> Result<RgbImage, String> {
+    let color: Box<dyn Fn(f64) -> Rgb<u8>> = if palette == mandelbrot::GRAYSCALE {
+        Box::new(move |t| Rgb([(transform.apply(t) * 255.0).round() as u8; 3]))
+    } else {
+        let palette = Palette::by_name(palette)?.with_transform(transform)?;
+        Box::new(move |t| palette.at(t))
+    };
+    let colors: Vec<Rgb<u8>> = (0..width).map(|x| color(x as f64 / (width.max(2) - 1) as f64)).collect();
+    Ok(RgbImage::from_fn(width, height, |x, _| colors[x as usize]))
+}
+
+// The previews of palettes stacked in rows, each strip of `strip_width` labeled with its palette. With
+// the escapes of a render, its image colored with the palette follows the strip, which gets its height.
+pub fn palette_strips(
+    palettes: &[&str],
+    (strip_width, strip_height): (u32, u32),
+    transform: PaletteTransform,
+    thumbnail: Option<(&RenderOptions, &[EscapeInfo])>,
+) -> Result<RgbImage, String> {
+    if palettes.is_empty() {
+        return Err("No palettes to preview".to_string());
+    }
+    let row_height = thumbnail.map_or(strip_height, |(options, _)| options.height);
+    let width = strip_width + thumbnail.map_or(0, |(options, _)| GAP + options.width);
+    let rows = palettes.len() as u32;
+    let mut image = RgbImage::from_pixel(width, rows * row_height + (rows - 1) * GAP, GAP_COLOR);
+    for (index, &palette) in palettes.iter().enumerate() {
+        let top = index as u32 * (row_height + GAP);
+        let mut strip = gradient_strip(palette, transform, (strip_width, row_height))?;
+        draw_label(&mut strip, palette);
+        image::imageops::replace(&mut image, &strip, 0, top as i64);
+        if let Some((options, escapes)) = thumbnail {
+            let colored = RenderOptions { palette: palette.to_string(), ..options.clone() }.colorize(escapes)?;
+            image::imageops::replace(&mut image, &colored, (strip_width + GAP) as i64, top as i64);
+        }
+    }
+    Ok(image)
+}
+
+// The name of a palette on a darkened box in the bottom left corner of its image.
+fn draw_label(image: &mut RgbImage, label: &str) {
+    let (width, height) = text::text_size(label, 1);
+    let size = (width + 2 * LABEL_PADDING, height + 2 * LABEL_PADDING);
+    let top = image.height().saturating_sub(size.1);
+    text::darken_rect(image, (0, top), size, 0.35);
+    text::draw_text(image, label, (LABEL_PADDING as i32, (top + LABEL_PADDING) as i32), 1, Rgb([255, 255, 255]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the shape of the grid and that every palette gets its own image from the same escapes.
+    #[test]
+    fn test_palette_grid() {
+        assert_eq!(grid_shape(1), (1, 1));
+        assert_eq!(grid_shape(4), (2, 2));
+        assert_eq!(grid_shape(5), (3, 2));
+        assert_eq!(grid_shape(8), (3, 3));
+        let options = RenderOptions::builder().size(40, 30).iterations(50).build().unwrap();
+        let escapes = options.compute().unwrap();
+        let grid = palette_grid(&options, &escapes, &["turbo", "grayscale", "viridis"]).unwrap();
+        assert_eq!(grid.dimensions(), (2 * 40 + GAP, 2 * 30 + GAP));
+        // The grayscale image, right of the first one, as rendered on its own away from its label
+        let grayscale = RenderOptions { palette: "grayscale".to_string(), ..options.clone() }.render().unwrap();
+        assert_eq!(grid.get_pixel(40 + GAP + 39, 0), grayscale.get_pixel(39, 0));
+        // The cell left empty by three images
+        assert_eq!(*grid.get_pixel(40 + GAP + 20, 30 + GAP + 15), GAP_COLOR);
+        assert!(palette_grid(&options, &escapes, &[]).is_err());
+        assert!(palette_grid(&options, &escapes, &["no-such-palette"]).is_err());
+    }
+
+    /// Tests that the rows of the previews hold the strip of every palette, and their thumbnails.
+    #[test]
+    fn test_palette_strips() {
+        let strip = gradient_strip("grayscale", PaletteTransform::default(), (256, 4)).unwrap();
+        assert_eq!((*strip.get_pixel(0, 0), *strip.get_pixel(255, 3)), (Rgb([0, 0, 0]), Rgb([255, 255, 255])));
+        let inverted = gradient_strip("grayscale", PaletteTransform { invert: true, ..Default::default() }, (256, 4)).unwrap();
+        assert_eq!(*inverted.get_pixel(0, 0), Rgb([255, 255, 255]));
+        assert!(gradient_strip("no-such-palette", PaletteTransform::default(), (16, 4)).is_err());
+
+        let previews = palette_strips(&["turbo", "grayscale"], (100, 20), PaletteTransform::default(), None).unwrap();
+        assert_eq!(previews.dimensions(), (100, 2 * 20 + GAP));
+        assert_eq!(*previews.get_pixel(99, 20 + GAP), Rgb([255, 255, 255])); // The end of the grayscale strip
+        let options = RenderOptions::builder().size(40, 30).iterations(50).palette("grayscale").build().unwrap();
+        let escapes = options.compute().unwrap();
+        let previews = palette_strips(&["grayscale"], (100, 20), PaletteTransform::default(), Some((&options, &escapes))).unwrap();
+        assert_eq!(previews.dimensions(), (100 + GAP + 40, 30));
+        assert_eq!(previews.get_pixel(100 + GAP + 39, 0), options.render().unwrap().get_pixel(39, 0));
+        assert!(palette_strips(&[], (100, 20), PaletteTransform::default(), None).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+// A small complex number type used by the fractal formulas that go beyond z² + c.
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+
+    pub const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    // Squared modulus, cheaper than norm() when only comparing distances.
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    // Build a complex number from its modulus and argument.
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    // Argument in (-π, π].
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn inv(self) -> Self {
+        let d = self.norm_sqr();
+        Self::new(self.re / d, -self.im / d)
+    }
+
+    // Integer power by repeated squaring.
+    pub fn powi(self, exponent: i32) -> Self {
+        if exponent < 0 {
+            return self.inv().powi(-exponent);
+        }
+        let (mut base, mut e, mut result) = (self, exponent as u32, Complex::ONE);
+        while e > 0 {
+            if e & 1 == 1 {
+                result *= base;
+            }
+            base *= base;
+            e >>= 1;
+        }
+        result
+    }
+
+    // Principal value of a complex power.
+    pub fn powc(self, exponent: Complex) -> Self {
+        if self == Complex::ZERO {
+            return Complex::ZERO;
+        }
+        (self.ln() * exponent).exp()
+    }
+
+    // Real power using the polar form.
+    pub fn powf(self, exponent: f64) -> Self {
+        if self == Complex::ZERO {
+            return Complex::ZERO;
+        }
+        Self::from_polar(self.norm().powf(exponent), self.arg() * exponent)
+    }
+
+    pub fn sqrt(self) -> Self {
+        self.powf(0.5)
+    }
+
+    pub fn exp(self) -> Self {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    // Principal natural logarithm.
+    pub fn ln(self) -> Self {
+        Self::new(self.norm().ln(), self.arg())
+    }
+
+    pub fn sin(self) -> Self {
+        Self::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Self {
+        Self::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(self) -> Self {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(self) -> Self {
+        Self::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin())
+    }
+
+    pub fn cosh(self) -> Self {
+        Self::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin())
+    }
+
+    // True if both parts are finite numbers (the orbit has not overflowed).
+    pub fn is_finite(self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+// Parse either a real number (`0.5`) or a complex number written as `RE,IM` (`0.5,-0.25`).
+impl FromStr for Complex {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::parse("complex number", input, "RE or RE,IM");
+        match input.split_once(',') {
+            Some((re, im)) => Ok(Complex::new(
+                re.trim().parse().map_err(|_| invalid())?,
+                im.trim().parse().map_err(|_| invalid())?,
+            )),
+            None => Ok(Complex::from(input.trim().parse::<f64>().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let d = other.norm_sqr();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Add<f64> for Complex {
+    type Output = Complex;
+    fn add(self, other: f64) -> Complex {
+        Complex::new(self.re + other, self.im)
+    }
+}
+
+impl Sub<f64> for Complex {
+    type Output = Complex;
+    fn sub(self, other: f64) -> Complex {
+        Complex::new(self.re - other, self.im)
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Complex;
+    fn mul(self, other: f64) -> Complex {
+        Complex::new(self.re * other, self.im * other)
+    }
+}
+
+impl Div<f64> for Complex {
+    type Output = Complex;
+    fn div(self, other: f64) -> Complex {
+        Complex::new(self.re / other, self.im / other)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, other: Complex) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Complex {
+    fn sub_assign(&mut self, other: Complex) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Complex {
+    fn mul_assign(&mut self, other: Complex) {
+        *self = *self * other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a - b).norm() < 1e-9
+    }
+
+    /// Tests the basic arithmetic against values computed by hand.
+    #[test]
+    fn test_arithmetic() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+        assert_eq!(a * b, Complex::new(5.0, 5.0));
+        assert!(close(a / b * b, a));
+        assert_eq!(Complex::new(0.0, 1.0) * Complex::new(0.0, 1.0), -Complex::ONE);
+    }
+
+    /// Tests that the integer and real powers agree with repeated multiplication.
+    #[test]
+    fn test_powers() {
+        let z = Complex::new(0.5, -1.5);
+        assert!(close(z.powi(3), z * z * z));
+        assert!(close(z.powi(-2), (z * z).inv()));
+        assert!(close(z.powf(3.0), z * z * z));
+        assert!(close(z.powc(Complex::new(2.0, 0.0)), z * z));
+        assert!(close(z.sqrt() * z.sqrt(), z));
+    }
+
+    /// Tests parsing real and complex numbers from the command line.
+    #[test]
+    fn test_from_str() {
+        assert_eq!("0.5".parse::<Complex>(), Ok(Complex::new(0.5, 0.0)));
+        assert_eq!("-0.5, 0.25".parse::<Complex>(), Ok(Complex::new(-0.5, 0.25)));
+        assert!("0.5;1".parse::<Complex>().is_err());
+    }
+
+    /// Tests the transcendental functions on identities that must hold for any z.
+    #[test]
+    fn test_transcendental_identities() {
+        let z = Complex::new(0.3, 0.7);
+        assert!(close(z.sin() * z.sin() + z.cos() * z.cos(), Complex::ONE));
+        assert!(close(z.cosh() * z.cosh() - z.sinh() * z.sinh(), Complex::ONE));
+        assert!(close(z.ln().exp(), z));
+        assert!(close(z.tan() * z.cos(), z.sin()));
+    }
+}
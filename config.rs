@@ -0,0 +1,237 @@
+// Configuration files holding default values for the command-line options.
+//
+// A config file is a list of `name = value` lines using the same names as the `--name` options,
+// for example:
+//
+//     # phoenix.conf
+//     fractal = "phoenix"
+//     p = "0.56667"   # the real part
+//     q = "-0.5"
+//     iterations = 200
+//
+// Blank lines and lines starting with `#` are ignored, and so is the rest of a line from a `#` after a
+// space. Values may be wrapped in double quotes, to hold a " #" or spaces at their ends: inside them \"
+// is a quote, \\ a backslash and \n and \r the line breaks, other backslashes being kept as they are.
+// Options given on the command line always win over the ones read from the file.
+//
+// Files describing several items (like animation keyframes) group them in `[[name]]` tables, each
+// table holding its own `name = value` lines until the next table header.
+//
+// The format is only this, of the program's own: it looks like TOML but has no arrays, numbers or nested
+// tables, so its files are named .conf rather than .toml.
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A named group of options, started by a `[[name]]` (or `[name]`) header.
+pub struct Table {
+    pub name: String,
+    pub options: HashMap<String, String>,
+}
+
+// The content of a file: the options before the first table, then every table in order.
+pub struct Document {
+    pub options: HashMap<String, String>,
+    pub tables: Vec<Table>,
+}
+
+// Parse a file that may contain tables.
+pub fn parse_document(content: &str) -> Result<Document, String> {
+    let mut document = Document { options: HashMap::new(), tables: Vec::new() };
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            let name = header.trim_start_matches('[').trim_end_matches(']').trim();
+            if name.is_empty() {
+                return Err(format!("Line {}: missing table name", number + 1));
+            }
+            document.tables.push(Table { name: name.to_string(), options: HashMap::new() });
+            continue;
+        }
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Line {}: expected 'name = value', found '{}'", number + 1, line))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("Line {}: missing option name", number + 1));
+        }
+        let value = parse_value(value.trim()).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+        // Options belong to the last table seen, or to the top of the file before any table.
+        let options = match document.tables.last_mut() {
+            Some(table) => &mut table.options,
+            None => &mut document.options,
+        };
+        options.insert(name.to_string(), value);
+    }
+    Ok(document)
+}
+
+// The value of a line: a quoted value with its escapes read, or the text before a comment.
+fn parse_value(value: &str) -> Result<String, String> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        let comment = value.char_indices().find(|&(i, c)| c == '#' && value[..i].ends_with(char::is_whitespace));
+        return Ok(comment.map_or(value, |(i, _)| value[..i].trim_end()).to_string());
+    };
+    let mut text = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let rest = chars.as_str().trim_start();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(format!("unexpected '{}' after the closing quote", rest));
+                }
+                return Ok(text);
+            }
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some(c @ ('"' | '\\')) => text.push(c),
+                Some(c) => text.extend(['\\', c]),
+                None => break,
+            },
+            c => text.push(c),
+        }
+    }
+    Err(format!("missing the closing quote of {}", value))
+}
+
+// A value quoted for a file, escaped so that parse_document reads it back unchanged.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Parse the content of a config file into option names and values.
+pub fn parse_config(content: &str) -> Result<HashMap<String, String>, String> {
+    let document = parse_document(content)?;
+    if let Some(table) = document.tables.first() {
+        return Err(format!("Unexpected table [{}] in a config file", table.name));
+    }
+    Ok(document.options)
+}
+
+// Write a document back in the format read by parse_document, options sorted by name.
+pub fn to_text(document: &Document) -> String {
+    fn write_options(text: &mut String, options: &HashMap<String, String>) {
+        let mut names: Vec<&String> = options.keys().collect();
+        names.sort();
+        for name in names {
+            text.push_str(&format!("{} = {}\n", name, quote(&options[name])));
+        }
+    }
+
+    let mut text = String::new();
+    write_options(&mut text, &document.options);
+    for table in &document.tables {
+        text.push_str(&format!("\n[[{}]]\n", table.name));
+        write_options(&mut text, &table.options);
+    }
+    text
+}
+
+// Read and parse a config file.
+pub fn load_config(path: &str) -> Result<HashMap<String, String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Could not read config file '{}': {}", path, e))?;
+    parse_config(&content).map_err(|e| format!("{}: {}", path, e))
+}
+
+// The directory of the files of the user (bookmarks, palettes) in the configuration directory of the
+// platform, like `~/.config/mandelbrot`.
+pub fn config_dir() -> Result<PathBuf, String> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from)) // Windows
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .ok_or("Could not find a configuration directory, set XDG_CONFIG_HOME")?;
+    Ok(base.join("mandelbrot"))
+}
+
+// Read and parse a file that may contain tables.
+pub fn load_document(path: &str) -> Result<Document, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Could not read '{}': {}", path, e))?;
+    parse_document(&content).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests comments, quoting and spacing in a config file.
+    #[test]
+    fn test_parse_config() {
+        let options = parse_config("# comment\nfractal = \"phoenix\"\n\n  iterations=200  \nbounds = -2;2;-1.5;1.5\n").unwrap();
+        assert_eq!(options.len(), 3);
+        assert_eq!(options["fractal"], "phoenix");
+        assert_eq!(options["iterations"], "200");
+        assert_eq!(options["bounds"], "-2;2;-1.5;1.5");
+    }
+
+    /// Tests that malformed lines are reported with their line number.
+    #[test]
+    fn test_parse_config_errors() {
+        assert_eq!(parse_config("fractal = phoenix\noops").unwrap_err(), "Line 2: expected 'name = value', found 'oops'");
+        assert!(parse_config("= 3").is_err());
+        assert!(parse_config("[[keyframe]]\ntime = 0").is_err());
+    }
+
+    /// Tests that options are attached to the table they follow.
+    #[test]
+    fn test_parse_document_tables() {
+        let document = parse_document("fps = 30\n[[keyframe]]\ntime = 0\n\n[[keyframe]]\ntime = 5\nzoom = 10\n").unwrap();
+        assert_eq!(document.options["fps"], "30");
+        assert_eq!(document.tables.len(), 2);
+        assert_eq!(document.tables[0].name, "keyframe");
+        assert_eq!(document.tables[0].options["time"], "0");
+        assert_eq!(document.tables[1].options.len(), 2);
+    }
+
+    /// Tests that a written document is parsed back to the same options and tables.
+    #[test]
+    fn test_to_text_round_trip() {
+        let document = parse_document("fps = 30\nsize = 640x480\n[[keyframe]]\ntime = 0\n[[keyframe]]\ntime = 5\nzoom = 10\n").unwrap();
+        let text = to_text(&document);
+        assert!(text.starts_with("fps = \"30\"\nsize = \"640x480\"\n"));
+        let parsed = parse_document(&text).unwrap();
+        assert_eq!(parsed.options, document.options);
+        assert_eq!(parsed.tables.len(), 2);
+        assert_eq!(parsed.tables[1].options, document.tables[1].options);
+    }
+
+    /// Tests that comments end unquoted values, and that quoted values keep them and their escapes.
+    #[test]
+    fn test_comments_and_escapes() {
+        let options = parse_config("zoom = 40 # deep\ncolor = #ff0000\ntitle = \"a # b\"  # c\nsay = \"\\\"hi\\\"\\n\\\\ C:\\temp\"\n").unwrap();
+        assert_eq!(options["zoom"], "40");
+        assert_eq!(options["color"], "#ff0000");
+        assert_eq!(options["title"], "a # b");
+        assert_eq!(options["say"], "\"hi\"\n\\ C:\\temp");
+        assert_eq!(parse_config("title = \"open").unwrap_err(), "Line 1: missing the closing quote of \"open");
+        assert!(parse_config("title = \"a\" b").is_err());
+    }
+
+    /// Tests that values holding quotes, backslashes, line breaks and comments are written back unchanged.
+    #[test]
+    fn test_to_text_escapes_round_trip() {
+        let values = ["say \"hi\"", "C:\\renders\\a.png", "two\nlines\r", "a # b", "#ff0000", "  padded  ", "", "tail\\"];
+        let options: HashMap<String, String> = values.iter().enumerate().map(|(i, v)| (format!("v{}", i), v.to_string())).collect();
+        let document = Document { options, tables: vec![Table { name: "t".to_string(), options: HashMap::from([("x".to_string(), "\\\"".to_string())]) }] };
+        let parsed = parse_document(&to_text(&document)).unwrap();
+        assert_eq!(parsed.options, document.options);
+        assert_eq!(parsed.tables[0].options, document.tables[0].options);
+    }
+}
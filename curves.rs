@@ -0,0 +1,265 @@
+// Fractals drawn as lines: a curve is made of polylines in its own coordinates (y pointing up),
+// fitted into the image with a margin, keeping its proportions, and stroked with round ends and joins,
+// in one color or along a palette from its first segment to its last. The same curve can be written
+// as an SVG file instead, which stays sharp at any size.
+//
+// The Koch curve replaces every segment by four segments a third as long, the middle two rising into
+// a peak; three Koch curves on the sides of a triangle make the snowflake. The Heighway dragon is a
+// strip of paper folded in half again and again and unfolded at right angles: every level appends the
+// curve to itself turned by 90° around its end.
+use crate::patterns::format_hex_color;
+use final_exercice::palette::Palette;
+use image::{Rgb, RgbImage};
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::str::FromStr;
+
+// Each level multiplies the segments by 4, 4^8 being already finer than any image.
+pub const MAX_KOCH_DEPTH: u32 = 8;
+// Each level doubles the segments, 2^20 of them being already finer than any image.
+pub const MAX_DRAGON_DEPTH: u32 = 20;
+// The pieces of one color a curve along a palette is cut into in an SVG file.
+const SVG_GRADIENT_PIECES: usize = 256;
+// The space left around a curve, as a fraction of the smaller side of the image.
+pub const MARGIN: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KochShape {
+    Curve,
+    Snowflake,
+}
+
+impl FromStr for KochShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "curve" => Ok(KochShape::Curve),
+            "snowflake" => Ok(KochShape::Snowflake),
+            _ => Err(format!("Invalid Koch shape '{}', expected curve or snowflake", s)),
+        }
+    }
+}
+
+// How a curve is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub width: f64, // In pixels
+    pub color: Rgb<u8>,
+    pub background: Rgb<u8>,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self { width: 1.0, color: Rgb([0, 0, 0]), background: Rgb([255, 255, 255]) }
+    }
+}
+
+impl Stroke {
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.width.is_finite() && self.width > 0.0) {
+            return Err(format!("The stroke width must be a positive number of pixels, not {}", self.width));
+        }
+        Ok(())
+    }
+}
+
+// The points of the Koch curve from (0, 0) to (1, 0) or of the snowflake on the triangle of side 1,
+// the last point of the snowflake closing it.
+pub fn koch(shape: KochShape, depth: u32) -> Result<Vec<(f64, f64)>, String> {
+    if depth > MAX_KOCH_DEPTH {
+        return Err(format!("The Koch curves go at most {} levels deep, not {}", MAX_KOCH_DEPTH, depth));
+    }
+    // Clockwise, so that the peaks raised on the left of every side point outwards
+    let mut points = match shape {
+        KochShape::Curve => vec![(0.0, 0.0), (1.0, 0.0)],
+        KochShape::Snowflake => vec![(0.0, 0.0), (0.5, 3f64.sqrt() / 2.0), (1.0, 0.0), (0.0, 0.0)],
+    };
+    let (cos, sin) = ((PI / 3.0).cos(), (PI / 3.0).sin());
+    for _ in 0..depth {
+        let mut next = Vec::with_capacity(points.len() * 4);
+        for segment in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+            let (dx, dy) = ((x1 - x0) / 3.0, (y1 - y0) / 3.0);
+            let a = (x0 + dx, y0 + dy);
+            let peak = (a.0 + dx * cos - dy * sin, a.1 + dx * sin + dy * cos);
+            next.extend([(x0, y0), a, peak, (x0 + 2.0 * dx, y0 + 2.0 * dy)]);
+        }
+        next.push(points[points.len() - 1]);
+        points = next;
+    }
+    Ok(points)
+}
+
+// The points of the Heighway dragon folded depth times, from (0, 0) and (1, 0).
+pub fn dragon(depth: u32) -> Result<Vec<(f64, f64)>, String> {
+    if depth > MAX_DRAGON_DEPTH {
+        return Err(format!("The dragon curve goes at most {} levels deep, not {}", MAX_DRAGON_DEPTH, depth));
+    }
+    let mut points = vec![(0.0, 0.0), (1.0, 0.0)];
+    for _ in 0..depth {
+        let (px, py) = points[points.len() - 1];
+        // Back along the curve, turned by a quarter turn around its end
+        let unfolded: Vec<(f64, f64)> = points.iter().rev().skip(1).map(|&(x, y)| (px + py - y, py - px + x)).collect();
+        points.extend(unfolded);
+    }
+    Ok(points)
+}
+
+// The pixel coordinates of the points of curves fitted together into a width × height image,
+// centered and with the margin around them.
+pub fn fit(paths: &[Vec<(f64, f64)>], width: u32, height: u32) -> Vec<Vec<(f64, f64)>> {
+    let (mut xmin, mut xmax, mut ymin, mut ymax) = (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY);
+    for &(x, y) in paths.iter().flatten() {
+        (xmin, xmax, ymin, ymax) = (xmin.min(x), xmax.max(x), ymin.min(y), ymax.max(y));
+    }
+    let margin = MARGIN * width.min(height) as f64;
+    let (room_x, room_y) = (width as f64 - 2.0 * margin, height as f64 - 2.0 * margin);
+    // A curve along a line has no extent in one direction, only the other one sets the scale
+    let scale = (room_x / (xmax - xmin)).min(room_y / (ymax - ymin));
+    let scale = if scale.is_finite() { scale } else { 1.0 };
+    let (offset_x, offset_y) = ((width as f64 - (xmax - xmin) * scale) / 2.0, (height as f64 - (ymax - ymin) * scale) / 2.0);
+    let to_pixel = |&(x, y): &(f64, f64)| (offset_x + (x - xmin) * scale, height as f64 - offset_y - (y - ymin) * scale);
+    paths.iter().map(|path| path.iter().map(to_pixel).collect()).collect()
+}
+
+// Draw a polyline of pixel coordinates, every pixel within half the stroke width of a segment taking
+// the color of that segment.
+pub fn draw_polyline(image: &mut RgbImage, points: &[(f64, f64)], width: f64, color: impl Fn(usize) -> Rgb<u8>) {
+    let (image_width, image_height) = image.dimensions();
+    let radius = width.max(1.0) / 2.0;
+    for (index, segment) in points.windows(2).enumerate() {
+        let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+        let color = color(index);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let length_sqr = dx * dx + dy * dy;
+        let column = |x: f64| (x.floor().max(0.0) as u32).min(image_width);
+        let row = |y: f64| (y.floor().max(0.0) as u32).min(image_height);
+        for py in row(y0.min(y1) - radius)..row(y0.max(y1) + radius + 1.0) {
+            for px in column(x0.min(x1) - radius)..column(x0.max(x1) + radius + 1.0) {
+                // The distance from the center of the pixel to the nearest point of the segment
+                let (cx, cy) = (px as f64 + 0.5, py as f64 + 0.5);
+                let t = if length_sqr > 0.0 { (((cx - x0) * dx + (cy - y0) * dy) / length_sqr).clamp(0.0, 1.0) } else { 0.0 };
+                let (ex, ey) = (cx - x0 - t * dx, cy - y0 - t * dy);
+                if ex * ex + ey * ey <= radius * radius {
+                    image.put_pixel(px, py, color);
+                }
+            }
+        }
+    }
+}
+
+// The color of every segment of curves, counted along all of them: the stroke color, or the palette from
+// the first segment to the last.
+fn segment_color<'a>(paths: &[Vec<(f64, f64)>], stroke: &'a Stroke, palette: Option<&'a Palette>) -> impl Fn(usize) -> Rgb<u8> + 'a {
+    let segments: usize = paths.iter().map(|path| path.len().saturating_sub(1)).sum();
+    let last = segments.saturating_sub(1).max(1) as f64;
+    move |index| palette.map_or(stroke.color, |palette| palette.at(index as f64 / last))
+}
+
+// Draw curves fitted into a new image.
+pub fn render(paths: &[Vec<(f64, f64)>], width: u32, height: u32, stroke: &Stroke, palette: Option<&Palette>) -> Result<RgbImage, String> {
+    stroke.check()?;
+    let mut image = RgbImage::from_pixel(width, height, stroke.background);
+    let color = segment_color(paths, stroke, palette);
+    let mut first = 0;
+    for path in fit(paths, width, height) {
+        draw_polyline(&mut image, &path, stroke.width, |index| color(first + index));
+        first += path.len().saturating_sub(1);
+    }
+    Ok(image)
+}
+
+// The SVG document of curves fitted into a width × height picture, one polyline each. Along a palette,
+// the curves are cut into polylines of one color instead.
+pub fn svg(paths: &[Vec<(f64, f64)>], width: u32, height: u32, stroke: &Stroke, palette: Option<&Palette>) -> Result<String, String> {
+    stroke.check()?;
+    let mut document = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n", width, height, width, height);
+    let _ = writeln!(document, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", format_hex_color(stroke.background));
+    let _ = writeln!(
+        document,
+        "<g fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\">",
+        format_hex_color(stroke.color),
+        stroke.width
+    );
+    let color = segment_color(paths, stroke, palette);
+    let segments: usize = paths.iter().map(|path| path.len().saturating_sub(1)).sum();
+    let piece = if palette.is_some() { segments.div_ceil(SVG_GRADIENT_PIECES).max(1) } else { usize::MAX };
+    let mut first = 0;
+    for path in fit(paths, width, height) {
+        let mut start = 0;
+        while start + 1 < path.len() {
+            let end = start.saturating_add(piece).min(path.len() - 1);
+            let points: Vec<String> = path[start..=end].iter().map(|(x, y)| format!("{:.3},{:.3}", x, y)).collect();
+            match palette {
+                Some(_) => {
+                    let middle = first + (start + end) / 2;
+                    let _ = writeln!(document, "<polyline stroke=\"{}\" points=\"{}\"/>", format_hex_color(color(middle)), points.join(" "));
+                }
+                None => {
+                    let _ = writeln!(document, "<polyline points=\"{}\"/>", points.join(" "));
+                }
+            }
+            start = end;
+        }
+        first += path.len().saturating_sub(1);
+    }
+    document.push_str("</g>\n</svg>\n");
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the points of the Koch curves: the peak of the first level, and 4 times more segments at
+    /// every level.
+    #[test]
+    fn test_koch() {
+        let curve = koch(KochShape::Curve, 1).unwrap();
+        assert_eq!(curve.len(), 5);
+        assert!((curve[2].0 - 0.5).abs() < 1e-12 && (curve[2].1 - 3f64.sqrt() / 6.0).abs() < 1e-12);
+        let snowflake = koch(KochShape::Snowflake, 3).unwrap();
+        assert_eq!(snowflake.len(), 3 * 64 + 1);
+        assert_eq!(snowflake[0], snowflake[snowflake.len() - 1]);
+        // The peaks point outwards, the lowest point being the peak raised below the bottom side
+        let lowest = snowflake.iter().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
+        assert!((lowest + 3f64.sqrt() / 6.0).abs() < 1e-12);
+        assert!(koch(KochShape::Curve, MAX_KOCH_DEPTH + 1).is_err());
+        assert!("square".parse::<KochShape>().is_err());
+    }
+
+    /// Tests that a curve is fitted into the image with its margin and that its line is drawn.
+    #[test]
+    fn test_render_curve() {
+        let points = [vec![(0.0, 0.0), (1.0, 0.0)]];
+        assert_eq!(fit(&points, 100, 50), vec![vec![(2.5, 25.0), (97.5, 25.0)]]);
+        let stroke = Stroke { width: 3.0, ..Stroke::default() };
+        let image = render(&points, 100, 50, &stroke, None).unwrap();
+        assert_eq!(*image.get_pixel(50, 25), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(50, 26), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(50, 28), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(0, 25), Rgb([255, 255, 255]));
+        let document = svg(&points, 100, 50, &stroke, None).unwrap();
+        assert!(document.contains("points=\"2.500,25.000 97.500,25.000\""));
+        assert!(render(&points, 100, 50, &Stroke { width: 0.0, ..stroke }, None).is_err());
+    }
+
+    /// Tests that the dragon curve doubles its segments at every level without ever drawing one twice.
+    #[test]
+    fn test_dragon() {
+        let points = dragon(10).unwrap();
+        assert_eq!(points.len(), 1025);
+        assert_eq!(dragon(1).unwrap(), vec![(0.0, 0.0), (1.0, 0.0), (1.0, -1.0)]);
+        let mut segments = std::collections::HashSet::new();
+        for segment in points.windows(2) {
+            let (a, b) = ((segment[0].0 as i64, segment[0].1 as i64), (segment[1].0 as i64, segment[1].1 as i64));
+            assert_eq!((a.0 - b.0).abs() + (a.1 - b.1).abs(), 1);
+            assert!(segments.insert(if a < b { (a, b) } else { (b, a) }));
+        }
+        assert!(dragon(MAX_DRAGON_DEPTH + 1).is_err());
+        // Along a palette, an SVG file cuts the curve into pieces of one color
+        let document = svg(&[points], 200, 200, &Stroke::default(), Some(&Palette::by_name("turbo").unwrap())).unwrap();
+        assert_eq!(document.matches("<polyline stroke=").count(), 256);
+    }
+}
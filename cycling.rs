@@ -0,0 +1,137 @@
+// Animated GIF output where the palette rotates from frame to frame (classic color cycling).
+//
+// The iteration counts are computed only once: every frame shares the same index buffer and only
+// the local color table of the frame changes, which also keeps the file small.
+use crate::bounds::Framing;
+use crate::mandelbrot;
+use crate::palette::Palette;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// A GIF color table holds at most 256 colors, the last one is kept for the points inside the set.
+const MAX_GRADIENT_COLORS: u32 = 255;
+
+// Parameters of a palette cycling animation.
+pub struct CycleOptions {
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u32,
+    pub bounds: (f32, f32, f32, f32),
+    pub palette: Palette, // With its transform, applied after the rotation
+    pub letterbox: Option<(f32, f32, f32, f32)>, // Region outside of which the frames are black
+    pub frames: u32,
+    // Number of full palette rotations over the whole animation; whole numbers loop seamlessly.
+    pub speed: f64,
+    // Delay between two frames in milliseconds.
+    pub delay_ms: u32,
+}
+
+// Map every iteration count to a color-table index, quantizing when there are more than 255 counts.
+fn quantize(iterations: &[u32], max_iterations: u32) -> (Vec<u8>, u32) {
+    let colors = max_iterations.clamp(1, MAX_GRADIENT_COLORS);
+    let indices = iterations
+        .iter()
+        .map(|&i| {
+            if i >= max_iterations {
+                colors as u8 // Points inside the set use the entry after the gradient
+            } else {
+                (i as u64 * colors as u64 / max_iterations as u64) as u8
+            }
+        })
+        .collect();
+    (indices, colors)
+}
+
+// Build the RGB color table of one frame, with the palette shifted by `offset` (in 0..1). The colors
+// are spread over [0, 1) rather than [0, 1] since the rotation wraps the end of the palette to its start,
+// which would give the first color twice.
+fn color_table(palette: &Palette, colors: u32, offset: f64) -> Vec<u8> {
+    let mut table = Vec::with_capacity((colors as usize + 1) * 3);
+    for index in 0..colors {
+        let t = index as f64 / colors as f64;
+        table.extend_from_slice(&palette.at((t + offset).rem_euclid(1.0)).0);
+    }
+    table.extend_from_slice(&[0, 0, 0]); // Points inside the set stay black
+    table
+}
+
+// Render the Mandelbrot set once and write an animated GIF cycling its palette.
+pub fn render_cycling_gif(options: &CycleOptions, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    if options.width > u16::MAX as u32 || options.height > u16::MAX as u32 {
+        return Err("GIF images are limited to 65535x65535 pixels".into());
+    }
+    if options.frames == 0 {
+        return Err("The animation needs at least one frame".into());
+    }
+
+    let mut iterations = mandelbrot::compute_iterations(options.width, options.height, options.max_iterations, options.bounds);
+    // The bars of a letterbox take the black of the points inside the set
+    if let Some(bars) = (Framing { bounds: options.bounds, region: options.letterbox }).bars(options.width, options.height) {
+        for (count, _) in iterations.iter_mut().zip(bars).filter(|(_, bar)| *bar) {
+            *count = options.max_iterations;
+        }
+    }
+    let (indices, colors) = quantize(&iterations, options.max_iterations);
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(writer, options.width as u16, options.height as u16, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    // GIF delays are expressed in hundredths of a second.
+    let delay = (options.delay_ms / 10).clamp(1, u16::MAX as u32) as u16;
+    for frame_index in 0..options.frames {
+        let offset = (options.speed * frame_index as f64 / options.frames as f64).rem_euclid(1.0);
+        let frame = gif::Frame {
+            width: options.width as u16,
+            height: options.height as u16,
+            delay,
+            palette: Some(color_table(&options.palette, colors, offset)),
+            buffer: Cow::Borrowed(&indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that interior points get the reserved last entry and exterior points stay in the gradient.
+    #[test]
+    fn test_quantize_small_iteration_count() {
+        let (indices, colors) = quantize(&[0, 50, 99, 100], 100);
+        assert_eq!(colors, 100);
+        assert_eq!(indices, vec![0, 50, 99, 100]);
+    }
+
+    /// Tests that large iteration counts are squeezed into the 256 entries of a GIF color table.
+    #[test]
+    fn test_quantize_large_iteration_count() {
+        let (indices, colors) = quantize(&[0, 500, 999, 1000], 1000);
+        assert_eq!(colors, 255);
+        assert_eq!(indices, vec![0, 127, 254, 255]);
+    }
+
+    /// Tests that the table at offset 0 spreads the palette without reaching its end, which is its start
+    /// again once rotated, and that rotating by one entry shifts the table by one color.
+    #[test]
+    fn test_color_table() {
+        let palette = Palette::by_name("viridis").unwrap();
+        let table = color_table(&palette, 4, 0.0);
+        assert_eq!(table.len(), 5 * 3);
+        for index in 0..4 {
+            assert_eq!(table[index * 3..][..3], palette.at(index as f64 / 4.0).0);
+        }
+        assert_ne!(table[3 * 3..][..3], table[..3]);
+        assert_eq!(table[12..], [0, 0, 0]);
+
+        let rotated = color_table(&palette, 4, 0.25);
+        assert_eq!(rotated[..9], table[3..12]);
+        assert_eq!(rotated[9..12], table[..3]);
+    }
+}
@@ -0,0 +1,100 @@
+// Images of where many points fall rather than of what happens at every pixel: the points are counted
+// in the pixel they land in, and the counts become colors of a palette, the empty pixels taking its
+// first color and the fullest ones its last. Counts spread over orders of magnitude, so they are
+// mapped through their logarithm, which keeps the sparse regions visible next to the dense ones.
+//
+// Unlike the complex plane of the escape-time renders, y points up: the top row holds ymax.
+use final_exercice::palette::Palette;
+use image::RgbImage;
+
+pub struct Density {
+    width: u32,
+    height: u32,
+    bounds: (f64, f64, f64, f64),
+    pub counts: Vec<u32>, // Row by row
+}
+
+impl Density {
+    pub fn new(width: u32, height: u32, bounds: (f32, f32, f32, f32)) -> Self {
+        let bounds = (bounds.0 as f64, bounds.1 as f64, bounds.2 as f64, bounds.3 as f64);
+        Self { width, height, bounds, counts: vec![0; (width * height) as usize] }
+    }
+
+    // Count a point, if it falls inside the bounds.
+    pub fn add(&mut self, x: f64, y: f64) {
+        let (xmin, xmax, ymin, ymax) = self.bounds;
+        let px = (x - xmin) / (xmax - xmin) * self.width as f64;
+        let py = (ymax - y) / (ymax - ymin) * self.height as f64;
+        if px >= 0.0 && py >= 0.0 && px < self.width as f64 && py < self.height as f64 {
+            let index = py as usize * self.width as usize + px as usize;
+            self.counts[index] = self.counts[index].saturating_add(1);
+        }
+    }
+
+    // Where a count falls in [0, 1], on the logarithmic scale up to the largest count.
+    pub fn level(count: u32, max: u32) -> f64 {
+        if max == 0 {
+            return 0.0;
+        }
+        (count as f64).ln_1p() / (max as f64).ln_1p()
+    }
+
+    // The image of the counts colored through the palette.
+    pub fn image(&self, palette: &Palette) -> RgbImage {
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        RgbImage::from_fn(self.width, self.height, |x, y| palette.at(Self::level(self.counts[(y * self.width + x) as usize], max)))
+    }
+}
+
+// The smallest rectangle holding the points added to it, to find where an attractor lies.
+pub struct Extent {
+    xmin: f64,
+    xmax: f64,
+    ymin: f64,
+    ymax: f64,
+}
+
+impl Default for Extent {
+    fn default() -> Self {
+        Self { xmin: f64::INFINITY, xmax: f64::NEG_INFINITY, ymin: f64::INFINITY, ymax: f64::NEG_INFINITY }
+    }
+}
+
+impl Extent {
+    pub fn add(&mut self, x: f64, y: f64) {
+        (self.xmin, self.xmax, self.ymin, self.ymax) = (self.xmin.min(x), self.xmax.max(x), self.ymin.min(y), self.ymax.max(y));
+    }
+
+    // The rectangle with a margin around it, a fraction of its larger side, or None if no point or
+    // points at infinity were added.
+    pub fn bounds(&self, margin: f64) -> Option<(f32, f32, f32, f32)> {
+        let (xmin, xmax, ymin, ymax) = (self.xmin, self.xmax, self.ymin, self.ymax);
+        if ![xmin, xmax, ymin, ymax].iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        // Points on a line or at a point still need a region to be drawn in
+        let margin = ((xmax - xmin).max(ymax - ymin) * margin).max(1e-6);
+        Some(((xmin - margin) as f32, (xmax + margin) as f32, (ymin - margin) as f32, (ymax + margin) as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that points land in their pixel with y pointing up, and the logarithmic levels.
+    #[test]
+    fn test_density() {
+        let mut density = Density::new(4, 2, (0.0, 4.0, 0.0, 2.0));
+        density.add(0.5, 1.5);
+        density.add(0.5, 1.5);
+        density.add(3.5, 0.5);
+        density.add(5.0, 0.5); // Outside
+        assert_eq!(density.counts, vec![2, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(Density::level(0, 10), 0.0);
+        assert_eq!(Density::level(10, 10), 1.0);
+        assert!(Density::level(1, 100) > 0.1);
+        assert_eq!(Density::level(3, 0), 0.0);
+        assert_eq!(density.image(&Palette::by_name("viridis").unwrap()).dimensions(), (4, 2));
+    }
+}
@@ -0,0 +1,137 @@
+// Dithering of the quantization from the float colors of the color maps to 8 bits per channel. Smooth
+// gradients through dark colors only have a few levels to step through, which shows as bands; adding a
+// threshold in [-0.5, 0.5) of a level that changes from pixel to pixel before rounding trades the bands
+// for a fine, regular noise.
+//
+// The ordered dither takes its thresholds from the 8x8 Bayer matrix; the blue-noise one from a 32x32
+// map ranked by filling the largest void first, whose noise has no low frequencies and looks finer.
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Dither {
+    #[default]
+    None,
+    Bayer,
+    BlueNoise,
+}
+
+impl FromStr for Dither {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "none" => Ok(Dither::None),
+            "bayer" => Ok(Dither::Bayer),
+            "blue-noise" => Ok(Dither::BlueNoise),
+            _ => Err(Error::parse("dithering", s, "none, bayer or blue-noise")),
+        }
+    }
+}
+
+impl Dither {
+    // Threshold added to a pixel before rounding, in levels.
+    pub fn threshold(&self, x: u32, y: u32) -> f64 {
+        match self {
+            Dither::None => 0.0,
+            Dither::Bayer => (bayer(x % 8, y % 8) as f64 + 0.5) / 64.0 - 0.5,
+            Dither::BlueNoise => {
+                let size = BLUE_NOISE_SIZE as u32;
+                let rank = blue_noise_ranks()[((y % size) * size + x % size) as usize];
+                (rank as f64 + 0.5) / (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as f64 - 0.5
+            }
+        }
+    }
+
+    // Quantize a color with channels in [0, 1] for the pixel at (x, y).
+    pub fn quantize(&self, color: [f64; 3], x: u32, y: u32) -> [u8; 3] {
+        let threshold = self.threshold(x, y);
+        color.map(|channel| (channel * 255.0 + threshold).round().clamp(0.0, 255.0) as u8)
+    }
+}
+
+// The entry of the 8x8 Bayer matrix: the bits of x ^ y and y interleaved, lowest bits first.
+fn bayer(x: u32, y: u32) -> u32 {
+    let mut value = 0;
+    for bit in 0..3 {
+        value = (value << 2) | ((((x ^ y) >> bit) & 1) << 1) | ((y >> bit) & 1);
+    }
+    value
+}
+
+const BLUE_NOISE_SIZE: usize = 32;
+// Standard deviation in pixels of the Gaussian measuring how crowded the neighbourhood of a pixel is.
+const BLUE_NOISE_SIGMA: f64 = 1.5;
+
+// The rank of every pixel of the blue-noise map, computed once: each pixel in turn is the one farthest
+// from the pixels already ranked, which is where the sum of their Gaussians (wrapping around the map)
+// is lowest.
+fn blue_noise_ranks() -> &'static [u32] {
+    static RANKS: OnceLock<Vec<u32>> = OnceLock::new();
+    RANKS.get_or_init(|| {
+        let n = BLUE_NOISE_SIZE;
+        // Gaussian of every offset on the torus, and a small fixed jitter breaking the ties between
+        // equally empty pixels, which would otherwise grow a regular grid.
+        let kernel: Vec<f64> = (0..n * n)
+            .map(|i| {
+                let wrap = |d: usize| d.min(n - d) as f64;
+                let (dx, dy) = (wrap(i % n), wrap(i / n));
+                (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+            })
+            .collect();
+        let mut energy: Vec<f64> = (0..n * n).map(|i| ((i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 40) as f64 * 1e-12).collect();
+        let mut ranks = vec![u32::MAX; n * n];
+        for rank in 0..(n * n) as u32 {
+            let void = (0..n * n)
+                .filter(|&i| ranks[i] == u32::MAX)
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap_or(0);
+            ranks[void] = rank;
+            let (vx, vy) = (void % n, void / n);
+            for (i, e) in energy.iter_mut().enumerate() {
+                let (dx, dy) = ((i % n + n - vx) % n, (i / n + n - vy) % n);
+                *e += kernel[dy * n + dx];
+            }
+        }
+        ranks
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that both threshold maps hold every level once, so that they average to no shift.
+    #[test]
+    fn test_threshold_maps() {
+        let mut bayer_values: Vec<u32> = (0..64).map(|i| bayer(i % 8, i / 8)).collect();
+        assert_eq!(&bayer_values[..4], &[0, 32, 8, 40]);
+        bayer_values.sort_unstable();
+        assert_eq!(bayer_values, (0..64).collect::<Vec<u32>>());
+
+        let mut ranks = blue_noise_ranks().to_vec();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..(BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as u32).collect::<Vec<u32>>());
+        for dither in [Dither::Bayer, Dither::BlueNoise] {
+            let mean: f64 = (0..64).flat_map(|y| (0..64).map(move |x| dither.threshold(x, y))).sum::<f64>() / 4096.0;
+            assert!(mean.abs() < 1e-9);
+        }
+    }
+
+    /// Tests that a color between two levels is dithered into a mix of both in proportion.
+    #[test]
+    fn test_quantize() {
+        assert_eq!(Dither::None.quantize([0.0, 0.5, 1.0], 3, 4), [0, 128, 255]);
+        let between = 100.25 / 255.0;
+        for dither in [Dither::Bayer, Dither::BlueNoise] {
+            let pixels: Vec<u8> = (0..32).flat_map(|y| (0..32).map(move |x| dither.quantize([between; 3], x, y)[0])).collect();
+            assert!(pixels.iter().all(|&level| level == 100 || level == 101));
+            let high = pixels.iter().filter(|&&level| level == 101).count();
+            assert_eq!(high, 256, "{:?}", dither);
+        }
+        assert_eq!("blue-noise".parse(), Ok(Dither::BlueNoise));
+        assert!("floyd".parse::<Dither>().is_err());
+    }
+}
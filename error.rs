@@ -0,0 +1,80 @@
+// The errors of the renderer, one variant per cause so that a program using the library can match
+// on what went wrong instead of reading the message. Every error still displays as the sentence the
+// command line prints, and hint() suggests how to get past it.
+//
+// The bin modules report their own errors as strings: the From impl below lets them propagate the
+// errors of the library with `?` unchanged.
+use crate::mandelbrot::GRAYSCALE;
+use crate::palette::palette_names;
+use crate::plugins::coloring_names;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    // A value that could not be read, like a complex number or a dithering name
+    #[error("Invalid {what} '{value}', expected {expected}")]
+    Parse { what: &'static str, value: String, expected: &'static str },
+    #[error("Unknown palette '{0}'. Available palettes: {}", palette_names().join(", "))]
+    UnknownPalette(String),
+    // A palette that exists but cannot be built as asked, like a composite with a negative weight
+    #[error("{0}")]
+    InvalidPalette(String),
+    #[error("Unknown coloring '{0}'. Available colorings: {}", coloring_names().join(", "))]
+    UnknownColoring(String),
+    // Settings of a coloring out of their range
+    #[error("{0}")]
+    InvalidColoring(String),
+    // The region or the size of the image: zero sizes, bounds or a zoom that select no region
+    #[error("{0}")]
+    InvalidView(String),
+    // Settings of the computation itself: iterations, bailout, antialiasing, power, external angles
+    #[error("{0}")]
+    InvalidParameter(String),
+    // A coloring registered under a name already taken, or a plugin library that cannot be loaded
+    #[error("{0}")]
+    Plugin(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn parse(what: &'static str, value: &str, expected: &'static str) -> Self {
+        Error::Parse { what, value: value.to_string(), expected }
+    }
+
+    // How to get past the error, for the command line to print under it.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Error::Parse { .. } => None,
+            Error::UnknownPalette(_) => Some(format!("palettes are chosen with --palette, {} included", GRAYSCALE)),
+            Error::InvalidPalette(_) => Some("weights are written like '70%inferno+30%viridis', and the palette transforms need finite numbers".to_string()),
+            Error::UnknownColoring(_) => Some("colorings are chosen with --coloring".to_string()),
+            Error::InvalidColoring(_) => Some("leave the option out to use the default of the coloring".to_string()),
+            Error::InvalidView(_) => Some("the view is given by --bounds, or by --center and --zoom, with a non-zero --size".to_string()),
+            Error::InvalidParameter(_) => None,
+            Error::Plugin(_) => Some("plugins must be built by the same compiler and against the same version of the crate".to_string()),
+        }
+    }
+}
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the errors display as the messages of the command line and only some have a hint.
+    #[test]
+    fn test_error_messages() {
+        let error = Error::parse("dithering", "floyd", "none, bayer or blue-noise");
+        assert_eq!(error.to_string(), "Invalid dithering 'floyd', expected none, bayer or blue-noise");
+        assert_eq!(error.hint(), None);
+        let error = Error::UnknownPalette("sunset".to_string());
+        assert!(error.to_string().starts_with("Unknown palette 'sunset'. Available palettes: turbo, viridis"));
+        assert!(error.hint().is_some());
+        assert_eq!(String::from(Error::InvalidView("zero".to_string())), "zero");
+    }
+}
@@ -0,0 +1,119 @@
+// Unattended exploration: a random point close to the boundary of the Mandelbrot set is picked and
+// zoomed into a random number of steps, every step checking that the view still holds some structure
+// so that the zoom never ends inside the set or in the empty space around it. Each view is rendered
+// with a random palette into a gallery, and the same seed gives the same gallery again.
+use crate::area::{Random, AREA_BOUNDS};
+use crate::complex::Complex;
+use crate::fractal::{Fractal, Mandelbrot};
+use crate::interest::{self, SCAN_SIZE};
+use crate::mandelbrot::{self, PALETTE_NAMES};
+use crate::metadata;
+use crate::naming::NameTokens;
+use crate::postprocess::PostProcess;
+use crate::render::RenderOptions;
+use std::fs;
+use std::path::Path;
+
+// Magnification of one zoom step, and the most steps of a view, which 4^8 keeps within the precision
+// of the f32 bounds.
+pub const ZOOM_FACTOR: f32 = 4.0;
+pub const MAX_STEPS: u32 = 8;
+// A point is taken for close to the boundary when it escapes after at least this many of the
+// BOUNDARY_LIMIT iterations.
+pub const BOUNDARY_ITERATIONS: u32 = 50;
+const BOUNDARY_LIMIT: u32 = 1000;
+const MAX_TRIES: u32 = 100_000;
+// Entropy in bits of the iteration counts of a view below which it is taken for empty.
+pub const MIN_ENTROPY: f64 = 2.0;
+
+// A random point escaping slowly, hence close to the boundary.
+pub fn random_boundary_point(random: &mut Random) -> Result<(f32, f32), String> {
+    let (xmin, xmax, ymin, ymax) = AREA_BOUNDS;
+    for _ in 0..MAX_TRIES {
+        let re = xmin as f64 + random.next_f64() * (xmax - xmin) as f64;
+        let im = ymin as f64 + random.next_f64() * (ymax - ymin) as f64;
+        let iterations = Mandelbrot.iterate(Complex::new(re, im), BOUNDARY_LIMIT).iterations;
+        if (BOUNDARY_ITERATIONS..BOUNDARY_LIMIT).contains(&iterations) {
+            return Ok((re as f32, im as f32));
+        }
+    }
+    Err(format!("No point close to the boundary found in {} tries", MAX_TRIES))
+}
+
+// Entropy of the iteration counts of a view scanned at a low resolution.
+pub fn view_entropy(center: (f32, f32), zoom: f32) -> f64 {
+    let (width, height) = SCAN_SIZE;
+    let bounds = mandelbrot::view_bounds(center, zoom, width, height);
+    interest::entropy(&mandelbrot::compute_iterations(width, height, mandelbrot::auto_iterations(zoom), bounds))
+}
+
+// Zoom on a point up to `steps` times, stopping at the last zoom whose view is not empty.
+pub fn zoom_on(center: (f32, f32), steps: u32) -> f32 {
+    let mut zoom = 1.0;
+    for _ in 0..steps {
+        if view_entropy(center, zoom * ZOOM_FACTOR) < MIN_ENTROPY {
+            break;
+        }
+        zoom *= ZOOM_FACTOR;
+    }
+    zoom
+}
+
+// One view of the gallery.
+pub fn random_view(random: &mut Random, width: u32, height: u32, palette: Option<&str>) -> Result<RenderOptions, String> {
+    let center = random_boundary_point(random)?;
+    let zoom = zoom_on(center, 1 + (random.next_u64() % MAX_STEPS as u64) as u32);
+    let palette = palette.unwrap_or(PALETTE_NAMES[(random.next_u64() % PALETTE_NAMES.len() as u64) as usize]);
+    RenderOptions::builder()
+        .size(width, height)
+        .center(center.0, center.1)
+        .zoom(zoom)
+        .iterations(mandelbrot::auto_iterations(zoom))
+        .palette(palette)
+        .build()
+        .map_err(String::from)
+}
+
+// Render `count` random views into a directory as explore_001.png, explore_002.png..., each drawn over by
+// `post` with the tokens of its own view.
+pub fn explore_random(directory: &str, count: u32, seed: u64, width: u32, height: u32, palette: Option<&str>, post: &PostProcess) -> Result<(), String> {
+    fs::create_dir_all(directory).map_err(|e| format!("Could not create {}: {}", directory, e))?;
+    let mut random = Random::new(seed);
+    for index in 1..=count {
+        let options = random_view(&mut random, width, height, palette)?;
+        let path = Path::new(directory).join(format!("explore_{:03}.png", index));
+        let tokens = NameTokens::new().with("fractal", "mandelbrot").view(options.center, options.zoom).with("iter", options.max_iterations);
+        let mut image = options.render()?;
+        post.apply(&mut image, Some(options.bounds()), &tokens.size(width, height).with("palette", &options.palette))?;
+        metadata::save_render(&image, &path, &options).map_err(|e| format!("Could not save {}: {}", path.display(), e))?;
+        println!(
+            "{}: --center {},{} --zoom {} --iterations {} --palette {}",
+            path.display(),
+            options.center.0,
+            options.center.1,
+            options.zoom,
+            options.max_iterations,
+            options.palette
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the random points escape slowly and that the zoom stays out of empty views.
+    #[test]
+    fn test_random_boundary_zoom() {
+        let mut random = Random::new(3);
+        let (re, im) = random_boundary_point(&mut random).unwrap();
+        let iterations = Mandelbrot.iterate(Complex::new(re as f64, im as f64), BOUNDARY_LIMIT).iterations;
+        assert!(iterations >= BOUNDARY_ITERATIONS);
+
+        // Far from the set every point escapes at once, so there is nothing to zoom on
+        assert_eq!(zoom_on((3.0, 3.0), 3), 1.0);
+        let zoom = zoom_on((re, im), 2);
+        assert!(zoom == 1.0 || zoom == ZOOM_FACTOR || zoom == ZOOM_FACTOR * ZOOM_FACTOR);
+    }
+}
@@ -0,0 +1,343 @@
+// User-defined iteration formulas such as `z^2 + c*sin(z)`.
+//
+// The formula is parsed once into a small expression tree which is then compiled into nested
+// closures, so the hot loop only calls closures instead of walking the tree for every iteration.
+use crate::complex::Complex;
+use crate::fractal::{self, EscapeResult, Fractal, OrbitObserver};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+// A parse error, with the character position where it was detected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormulaError {
+    pub message: String,
+    pub position: usize,
+    source: String,
+}
+
+impl fmt::Display for FormulaError {
+    // Show the message followed by the formula with a caret under the faulty position.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} (at position {})", self.message, self.position + 1)?;
+        writeln!(f, "  {}", self.source)?;
+        write!(f, "  {}^", " ".repeat(self.position))
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(usize, usize), // Start and end of the identifier in the source
+    Op(char),
+    Open,
+    Close,
+}
+
+// The expression tree produced by the parser.
+#[derive(Debug, Clone)]
+enum Expr {
+    Constant(Complex),
+    Z,
+    C,
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(fn(Complex) -> Complex, Box<Expr>),
+}
+
+// The functions that can be called from a formula.
+fn function(name: &str) -> Option<fn(Complex) -> Complex> {
+    let f: fn(Complex) -> Complex = match name {
+        "sin" => Complex::sin,
+        "cos" => Complex::cos,
+        "tan" => Complex::tan,
+        "sinh" => Complex::sinh,
+        "cosh" => Complex::cosh,
+        "exp" => Complex::exp,
+        "ln" | "log" => Complex::ln,
+        "sqrt" => Complex::sqrt,
+        "conj" => Complex::conj,
+        "abs" => |z| Complex::from(z.norm()),
+        "arg" => |z| Complex::from(z.arg()),
+        "re" => |z| Complex::from(z.re),
+        "im" => |z| Complex::from(z.im),
+        _ => return None,
+    };
+    Some(f)
+}
+
+// Split the formula into tokens, remembering the position of each one.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, FormulaError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse::<f64>().map_err(|_| error(source, start, format!("Invalid number '{}'", text)))?;
+            Token::Number(value)
+        } else if c.is_ascii_alphabetic() {
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            Token::Ident(start, i)
+        } else {
+            i += 1;
+            match c {
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => return Err(error(source, start, format!("Unexpected character '{}'", c))),
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+fn error(source: &str, position: usize, message: String) -> FormulaError {
+    FormulaError { message, position, source: source.to_string() }
+}
+
+// Recursive descent parser over the token list.
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.next).map(|(token, _)| *token)
+    }
+
+    // Position of the current token, or the end of the formula when all tokens were consumed.
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map(|(_, position)| *position).unwrap_or(self.chars.len())
+    }
+
+    fn fail<T>(&self, message: &str) -> Result<T, FormulaError> {
+        Err(error(self.source, self.position(), message.to_string()))
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expr, FormulaError> {
+        let mut left = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.next += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, FormulaError> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek() {
+            self.next += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<Expr, FormulaError> {
+        if let Some(Token::Op('-')) = self.peek() {
+            self.next += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    // power := primary ('^' unary)?, right associative so z^2^3 is z^(2^3)
+    fn power(&mut self) -> Result<Expr, FormulaError> {
+        let base = self.primary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.next += 1;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    // primary := number | variable | constant | function '(' expression ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<Expr, FormulaError> {
+        match self.peek() {
+            Some(Token::Number(value)) => {
+                self.next += 1;
+                Ok(Expr::Constant(Complex::from(value)))
+            }
+            Some(Token::Ident(start, end)) => {
+                let name: String = self.chars[start..end].iter().collect();
+                let position = self.position();
+                self.next += 1;
+                match name.as_str() {
+                    "z" => Ok(Expr::Z),
+                    "c" => Ok(Expr::C),
+                    "i" => Ok(Expr::Constant(Complex::new(0.0, 1.0))),
+                    "pi" => Ok(Expr::Constant(Complex::from(std::f64::consts::PI))),
+                    "e" => Ok(Expr::Constant(Complex::from(std::f64::consts::E))),
+                    _ => match function(&name) {
+                        Some(f) => {
+                            if self.peek() != Some(Token::Open) {
+                                return self.fail(&format!("Expected '(' after function '{}'", name));
+                            }
+                            let argument = self.primary()?;
+                            Ok(Expr::Call(f, Box::new(argument)))
+                        }
+                        None => Err(error(self.source, position, format!("Unknown name '{}', use z, c, i, pi, e or a function", name))),
+                    },
+                }
+            }
+            Some(Token::Open) => {
+                self.next += 1;
+                let inner = self.expression()?;
+                if self.peek() != Some(Token::Close) {
+                    return self.fail("Expected ')'");
+                }
+                self.next += 1;
+                Ok(inner)
+            }
+            Some(Token::Close) => self.fail("Unexpected ')'"),
+            Some(Token::Op(op)) => self.fail(&format!("Unexpected operator '{}'", op)),
+            None => self.fail("Unexpected end of formula"),
+        }
+    }
+}
+
+type Compiled = Box<dyn Fn(Complex, Complex) -> Complex + Send + Sync>;
+
+// Turn the expression tree into nested closures taking (z, c).
+fn compile(expr: Expr) -> Compiled {
+    match expr {
+        Expr::Constant(value) => Box::new(move |_, _| value),
+        Expr::Z => Box::new(|z, _| z),
+        Expr::C => Box::new(|_, c| c),
+        Expr::Neg(inner) => {
+            let inner = compile(*inner);
+            Box::new(move |z, c| -inner(z, c))
+        }
+        // Integer powers are by far the most common, so they get the fast repeated squaring.
+        Expr::Binary('^', base, exponent) if is_integer_constant(&exponent) => {
+            let Expr::Constant(value) = *exponent else { unreachable!() };
+            let n = value.re as i32;
+            let base = compile(*base);
+            Box::new(move |z, c| base(z, c).powi(n))
+        }
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (compile(*left), compile(*right));
+            match op {
+                '+' => Box::new(move |z, c| left(z, c) + right(z, c)),
+                '-' => Box::new(move |z, c| left(z, c) - right(z, c)),
+                '*' => Box::new(move |z, c| left(z, c) * right(z, c)),
+                '/' => Box::new(move |z, c| left(z, c) / right(z, c)),
+                _ => Box::new(move |z, c| left(z, c).powc(right(z, c))),
+            }
+        }
+        Expr::Call(f, argument) => {
+            let argument = compile(*argument);
+            Box::new(move |z, c| f(argument(z, c)))
+        }
+    }
+}
+
+fn is_integer_constant(expr: &Expr) -> bool {
+    matches!(expr, Expr::Constant(v) if v.im == 0.0 && v.re.fract() == 0.0 && v.re.abs() <= 64.0)
+}
+
+// A parsed and compiled iteration formula z ← f(z, c).
+pub struct Formula {
+    source: String,
+    compiled: Compiled,
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Self, FormulaError> {
+        let mut parser = Parser { source, chars: source.chars().collect(), tokens: tokenize(source)?, next: 0 };
+        let expr = parser.expression()?;
+        if parser.next < parser.tokens.len() {
+            return parser.fail("Unexpected input after the end of the formula");
+        }
+        Ok(Self { source: source.to_string(), compiled: compile(expr) })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn eval(&self, z: Complex, c: Complex) -> Complex {
+        (self.compiled)(z, c)
+    }
+}
+
+impl Fractal for Formula {
+    // Iterate from z = c. Starting from c (rather than 0) keeps formulas like z^2 + c*sin(z) from being stuck at 0.
+    fn iterate_with(&self, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        fractal::escape(c, max_iterations, bailout, observer, |z| self.eval(z, c))
+    }
+}
+
+// A formula is serialized as its source text and compiled again when deserialized.
+impl Serialize for Formula {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Formula {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Formula::parse(&source).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests operator precedence and the variables on a formula with a known value.
+    #[test]
+    fn test_eval_precedence() {
+        let formula = Formula::parse("z^2 + c*2 - -1").unwrap();
+        let value = formula.eval(Complex::new(3.0, 0.0), Complex::new(1.0, 1.0));
+        assert_eq!(value, Complex::new(12.0, 2.0));
+    }
+
+    /// Tests that functions, constants and complex powers are evaluated.
+    #[test]
+    fn test_eval_functions() {
+        let formula = Formula::parse("sin(z) * exp(i*pi) + z^(1/2)").unwrap();
+        let z = Complex::new(0.5, 0.25);
+        let expected = -z.sin() + z.sqrt();
+        assert!((formula.eval(z, Complex::ZERO) - expected).norm() < 1e-9);
+    }
+
+    /// Tests that the classic formula escapes like the built-in Mandelbrot loop.
+    #[test]
+    fn test_escape_iterations() {
+        let formula = Formula::parse("z^2 + c").unwrap();
+        assert_eq!(formula.iterate(Complex::ZERO, 100).iterations, 100);
+        assert!(formula.iterate(Complex::new(1.0, 1.0), 100).iterations < 5);
+    }
+
+    /// Tests that bad formulas are reported with the position of the problem.
+    #[test]
+    fn test_errors() {
+        assert_eq!(Formula::parse("z^2 + )").err().unwrap().position, 6);
+        assert_eq!(Formula::parse("z^2 + w").err().unwrap().position, 6);
+        assert_eq!(Formula::parse("sin z").err().unwrap().position, 4);
+        assert_eq!(Formula::parse("(z + c").err().unwrap().position, 6);
+        assert!(Formula::parse("z $ c").is_err());
+    }
+}
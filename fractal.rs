@@ -0,0 +1,908 @@
+// Escape-time fractals: a formula is iterated from every point of the plane until the orbit leaves
+// the circle of the bailout radius or the iteration limit is reached, or for some formulas until it
+// converges to an attractor (escape_or_converge).
+//
+// The renderer only knows the Fractal trait, so a new formula only has to implement `iterate` to be
+// rendered and colored like the built-in ones. Formulas with a parameter c implement Dynamics
+// instead, iterating from any starting point, and get both their parameter plane and their Julia
+// sets, for example:
+//
+//     struct Cubic;
+//
+//     impl Dynamics for Cubic {
+//         fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+//             fractal::escape(z0, max_iterations, bailout, observer, |z| z * z * z + c)
+//         }
+//     }
+//
+// Formulas keeping more state than z, like a previous value or a parameter moving along the orbit,
+// capture it in their step and iterate with escape_without_periodicity or escape_with_parameter.
+//
+// Colorings that need more than the end of the orbit gather their statistics with an OrbitObserver
+// called at every step, so the orbit does not have to be stored.
+use crate::complex::Complex;
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Escape radius used unless another one is given: once |z| > 2 the orbit of z² + c is known to diverge.
+// Larger radii (like 1e6) leave the orbit more steps to settle, which the smooth colorings need.
+pub const DEFAULT_BAILOUT: f64 = 2.0;
+
+// Refuse escape radii that would stop orbits still bounded by the usual radius 2.
+pub fn check_bailout(bailout: f64) -> Result<(), Error> {
+    if bailout.is_finite() && bailout >= DEFAULT_BAILOUT {
+        Ok(())
+    } else {
+        Err(Error::InvalidParameter(format!("The bailout radius must be a finite number of at least {}, not {}", DEFAULT_BAILOUT, bailout)))
+    }
+}
+
+// What iterating one point gives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscapeResult {
+    pub iterations: u32,   // Equal to the limit for points that did not escape
+    pub z: Complex,        // Last value of the orbit
+    pub min_distance: f64, // Smallest |z| reached after the first step, the distance to a point trap at 0
+    pub converged: bool,   // Stopped by reaching the attractor of escape_or_converge rather than by escaping
+}
+
+// Something following an orbit while it is iterated.
+pub trait OrbitObserver {
+    // Called after every step with the values before and after it.
+    fn observe(&mut self, previous: Complex, z: Complex);
+}
+
+// The observer of the renders that only need the end of the orbit.
+impl OrbitObserver for () {
+    fn observe(&mut self, _previous: Complex, _z: Complex) {}
+}
+
+// Observer keeping every value of the orbit, starting with its first point.
+#[derive(Default)]
+pub struct OrbitRecorder {
+    pub points: Vec<Complex>,
+}
+
+impl OrbitObserver for OrbitRecorder {
+    fn observe(&mut self, previous: Complex, z: Complex) {
+        if self.points.is_empty() {
+            self.points.push(previous);
+        }
+        self.points.push(z);
+    }
+}
+
+pub trait Fractal {
+    // Iterate the formula for a point of the plane, which is c for the Mandelbrot-like sets and the
+    // starting point of the orbit for the Julia-like ones, until |z| exceeds the bailout radius.
+    // Every step is shown to the observer.
+    fn iterate_with(&self, point: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult;
+
+    fn iterate(&self, point: Complex, max_iterations: u32) -> EscapeResult {
+        self.iterate_with(point, max_iterations, DEFAULT_BAILOUT, &mut ())
+    }
+
+    // True if the pixels mirrored across the real axis have conjugate orbits, so the renderer only has
+    // to compute one half of the views straddling the axis.
+    fn conjugate_symmetric(&self) -> bool {
+        false
+    }
+
+    // The bounds showing the whole fractal.
+    fn default_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.0, 2.0, -1.5, 1.5)
+    }
+}
+
+// Distance under which an orbit coming back to an earlier value is taken for a cycle.
+pub const PERIODICITY_EPSILON: f64 = 1e-10;
+
+// Apply step to z until |z| > bailout or max_iterations steps were made, the loop shared by every formula.
+//
+// Orbits of interior points end up in a cycle, which is detected by comparing z with a snapshot
+// taken at every power of two steps (Brent's method) so that they stop as soon as the cycle closed
+// instead of running up to the limit. They are then reported as reaching the limit, like before.
+// This assumes that every value only depends on the previous one; steps keeping more of the orbit
+// must use escape_without_periodicity.
+pub fn escape(
+    z: Complex,
+    max_iterations: u32,
+    bailout: f64,
+    observer: &mut dyn OrbitObserver,
+    step: impl FnMut(Complex) -> Complex,
+) -> EscapeResult {
+    iterate_orbit(z, max_iterations, bailout, observer, step, true, None)
+}
+
+// The escape loop without the cycle detection.
+pub fn escape_without_periodicity(
+    z: Complex,
+    max_iterations: u32,
+    bailout: f64,
+    observer: &mut dyn OrbitObserver,
+    step: impl FnMut(Complex) -> Complex,
+) -> EscapeResult {
+    iterate_orbit(z, max_iterations, bailout, observer, step, false, None)
+}
+
+// The escape loop of the formulas whose parameter moves along the orbit too, like the Spider
+// fractal: step maps z and the current parameter c to their next values. z alone coming back to an
+// earlier value no longer repeats the orbit, so cycles are not detected.
+pub fn escape_with_parameter(
+    z: Complex,
+    c: Complex,
+    max_iterations: u32,
+    bailout: f64,
+    observer: &mut dyn OrbitObserver,
+    mut step: impl FnMut(Complex, Complex) -> (Complex, Complex),
+) -> EscapeResult {
+    let mut parameter = c;
+    escape_without_periodicity(z, max_iterations, bailout, observer, |z| {
+        let next;
+        (next, parameter) = step(z, parameter);
+        next
+    })
+}
+
+// A point that the orbits of some formulas converge to instead of escaping, like 1 for the Magnet
+// fractals, and how close they have to come to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attractor {
+    pub point: Complex,
+    pub tolerance: f64,
+}
+
+// The escape loop which also stops once the orbit comes within the tolerance of the attractor,
+// reporting the steps made with `converged` set, so that the basin of the attractor is colored by
+// how fast it is reached like the outside by how fast it is left.
+pub fn escape_or_converge(
+    z: Complex,
+    max_iterations: u32,
+    bailout: f64,
+    attractor: Attractor,
+    observer: &mut dyn OrbitObserver,
+    step: impl FnMut(Complex) -> Complex,
+) -> EscapeResult {
+    iterate_orbit(z, max_iterations, bailout, observer, step, true, Some(attractor))
+}
+
+fn iterate_orbit(
+    mut z: Complex,
+    max_iterations: u32,
+    bailout: f64,
+    observer: &mut dyn OrbitObserver,
+    mut step: impl FnMut(Complex) -> Complex,
+    check_periodicity: bool,
+    attractor: Option<Attractor>,
+) -> EscapeResult {
+    let (mut iterations, mut min_norm_sqr, bailout_sqr) = (0, z.norm_sqr(), bailout * bailout);
+    let mut converged = false;
+    let (mut snapshot, mut next_snapshot) = (z, 1);
+    while z.norm_sqr() <= bailout_sqr && iterations < max_iterations {
+        let previous = z;
+        z = step(z);
+        observer.observe(previous, z);
+        // The starting point only counts when the orbit escapes at once, as it is 0 for the Mandelbrot set.
+        min_norm_sqr = if iterations == 0 { z.norm_sqr() } else { min_norm_sqr.min(z.norm_sqr()) };
+        iterations += 1;
+
+        // Checked before the cycles, as an orbit settling on the attractor would also look periodic
+        if let Some(attractor) = attractor {
+            if (z - attractor.point).norm_sqr() < attractor.tolerance * attractor.tolerance {
+                converged = iterations < max_iterations;
+                break;
+            }
+        }
+        if check_periodicity && z.norm_sqr() <= bailout_sqr {
+            if (z - snapshot).norm_sqr() < PERIODICITY_EPSILON * PERIODICITY_EPSILON {
+                iterations = max_iterations;
+            } else if iterations == next_snapshot {
+                snapshot = z;
+                next_snapshot *= 2;
+            }
+        }
+    }
+    EscapeResult { iterations, z, min_distance: min_norm_sqr.sqrt(), converged }
+}
+
+// A formula z ← f(z, c) apart from the plane it is drawn in. Its escape-time pictures are of two
+// kinds: the parameter plane, whose pixels are parameters c iterated from the critical point, like the
+// Mandelbrot set, and the dynamical plane of one c, whose pixels are starting points, its Julia set.
+// Every Dynamics is a Fractal drawing its parameter plane, and julia(c) gives its Julia sets:
+//
+//     Multibrot::new(3)?.julia(Complex::new(0.4, 0.1))
+pub trait Dynamics {
+    // Iterate the formula for the parameter c from z0, until |z| exceeds the bailout radius.
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult;
+
+    // Where the orbits of the parameter plane start: a critical point of the formula, whose orbit
+    // decides whether the Julia set of c is connected.
+    fn critical_point(&self) -> Complex {
+        Complex::ZERO
+    }
+
+    // True if f(z̄, c̄) is the conjugate of f(z, c), which makes the parameter plane and the Julia sets
+    // of real parameters symmetric about the real axis.
+    fn commutes_with_conjugation(&self) -> bool {
+        false
+    }
+
+    fn parameter_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.0, 2.0, -1.5, 1.5)
+    }
+
+    fn julia_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.0, 2.0, -1.5, 1.5)
+    }
+
+    // The dynamical plane of the parameter c.
+    fn julia(self, c: Complex) -> JuliaSet<Self>
+    where
+        Self: Sized,
+    {
+        JuliaSet { dynamics: self, c }
+    }
+}
+
+// The formulas chosen at run time.
+impl<D: Dynamics + ?Sized> Dynamics for Box<D> {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        (**self).iterate_from(z0, c, max_iterations, bailout, observer)
+    }
+
+    fn critical_point(&self) -> Complex {
+        (**self).critical_point()
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        (**self).commutes_with_conjugation()
+    }
+
+    fn parameter_bounds(&self) -> (f32, f32, f32, f32) {
+        (**self).parameter_bounds()
+    }
+
+    fn julia_bounds(&self) -> (f32, f32, f32, f32) {
+        (**self).julia_bounds()
+    }
+}
+
+// The parameter plane.
+impl<D: Dynamics> Fractal for D {
+    fn iterate_with(&self, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        self.iterate_from(self.critical_point(), c, max_iterations, bailout, observer)
+    }
+
+    fn conjugate_symmetric(&self) -> bool {
+        self.commutes_with_conjugation()
+    }
+
+    fn default_bounds(&self) -> (f32, f32, f32, f32) {
+        self.parameter_bounds()
+    }
+}
+
+// The dynamical plane of a formula for a fixed c, the pixel being the starting point of the orbit.
+pub struct JuliaSet<D> {
+    pub dynamics: D,
+    pub c: Complex,
+}
+
+impl<D: Dynamics> Fractal for JuliaSet<D> {
+    fn iterate_with(&self, z0: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        self.dynamics.iterate_from(z0, self.c, max_iterations, bailout, observer)
+    }
+
+    // Only a real parameter keeps the conjugate of an orbit an orbit.
+    fn conjugate_symmetric(&self) -> bool {
+        self.dynamics.commutes_with_conjugation() && self.c.im == 0.0
+    }
+
+    fn default_bounds(&self) -> (f32, f32, f32, f32) {
+        self.dynamics.julia_bounds()
+    }
+}
+
+// z² + c with the orbit starting at 0.
+pub struct Mandelbrot;
+
+impl Dynamics for Mandelbrot {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape(z0, max_iterations, bailout, observer, |z| z * z + c)
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        true
+    }
+}
+
+// The Julia sets of z² + c.
+pub type Julia = JuliaSet<Mandelbrot>;
+
+impl Julia {
+    // A constant giving a connected, dendrite-like set.
+    pub const DEFAULT_C: Complex = Complex::new(-0.8, 0.156);
+
+    pub fn new(c: Complex) -> Self {
+        Mandelbrot.julia(c)
+    }
+}
+
+// (|Re z| + i|Im z|)² + c: folding z into the first quadrant before squaring.
+pub struct BurningShip;
+
+impl Dynamics for BurningShip {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape(z0, max_iterations, bailout, observer, |z| {
+            let folded = Complex::new(z.re.abs(), z.im.abs());
+            folded * folded + c
+        })
+    }
+
+    // The ship lies mostly below the real axis, which is the top of the image.
+    fn parameter_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.5, 1.5, -2.0, 1.0)
+    }
+}
+
+// conj(z)² + c, also called the Mandelbar set.
+pub struct Tricorn;
+
+impl Dynamics for Tricorn {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape(z0, max_iterations, bailout, observer, |z| z.conj() * z.conj() + c)
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        true
+    }
+}
+
+// z^d + c with the orbit starting at 0, the Mandelbrot set being the power 2. The set has d − 1 bulbs
+// and (d − 1)-fold rotational symmetry. It is deserialized through Multibrot::new.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MultibrotFields")]
+pub struct Multibrot {
+    pub power: i32,
+}
+
+#[derive(Deserialize)]
+struct MultibrotFields {
+    power: i32,
+}
+
+impl TryFrom<MultibrotFields> for Multibrot {
+    type Error = Error;
+
+    fn try_from(fields: MultibrotFields) -> Result<Self, Error> {
+        Self::new(fields.power)
+    }
+}
+
+impl Multibrot {
+    pub const DEFAULT_POWER: i32 = 3;
+
+    // Powers below 2 have no bounded orbits worth looking at.
+    pub fn new(power: i32) -> Result<Self, Error> {
+        if power < 2 {
+            return Err(Error::InvalidParameter(format!("The power of the Multibrot set must be at least 2, not {}", power)));
+        }
+        Ok(Self { power })
+    }
+}
+
+impl Dynamics for Multibrot {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape(z0, max_iterations, bailout, observer, |z| z.powi(self.power) + c)
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        true
+    }
+}
+
+// z² + c starting from z = c, the parameter then moving to c/2 + z after every step. With c part of
+// the orbit there is no dynamical plane, so the Spider is only a Fractal.
+pub struct Spider;
+
+impl Fractal for Spider {
+    fn iterate_with(&self, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape_with_parameter(c, c, max_iterations, bailout, observer, |z, c| {
+            let next = z * z + c;
+            (next, c / 2.0 + next)
+        })
+    }
+
+    fn conjugate_symmetric(&self) -> bool {
+        true
+    }
+}
+
+// λz(1 − z), the logistic map, on the plane of its parameter λ, the orbit starting from the critical
+// point 1/2. Orbits leaving the circle of radius 1 + 1/|λ| diverge, which is more than 2 for small λ.
+pub struct Lambda;
+
+impl Dynamics for Lambda {
+    fn iterate_from(&self, z0: Complex, lambda: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        let bailout = bailout.max(1.0 + 1.0 / lambda.norm());
+        escape(z0, max_iterations, bailout, observer, |z| lambda * z * (Complex::ONE - z))
+    }
+
+    fn critical_point(&self) -> Complex {
+        Complex::new(0.5, 0.0)
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        true
+    }
+
+    // The set is symmetric about λ = 1 as well, λ and 2 − λ giving conjugate maps.
+    fn parameter_bounds(&self) -> (f32, f32, f32, f32) {
+        (-2.0, 4.0, -2.25, 2.25)
+    }
+
+    // The Julia sets are symmetric about 1/2.
+    fn julia_bounds(&self) -> (f32, f32, f32, f32) {
+        (-0.5, 1.5, -0.75, 0.75)
+    }
+}
+
+// The Julia sets of the logistic map.
+pub type LambdaJulia = JuliaSet<Lambda>;
+
+impl LambdaJulia {
+    // The λ with λ/2 − λ²/4 = Julia::DEFAULT_C, whose Julia set is the default one of z² + c moved
+    // and scaled.
+    pub const DEFAULT_LAMBDA: Complex = Complex::new(3.0548, -0.1518);
+
+    pub fn new(lambda: Complex) -> Self {
+        Lambda.julia(lambda)
+    }
+}
+
+// A plane of parameters and the Julia sets of its points, which the dual view shows side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Family {
+    #[default]
+    Mandelbrot, // z² + c
+    Lambda,     // λz(1 − z)
+}
+
+impl FromStr for Family {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "mandelbrot" => Ok(Family::Mandelbrot),
+            "lambda" => Ok(Family::Lambda),
+            _ => Err(Error::parse("fractal family", s, "mandelbrot or lambda")),
+        }
+    }
+}
+
+impl Family {
+    pub fn parameter_plane(self) -> Box<dyn Fractal> {
+        match self {
+            Family::Mandelbrot => Box::new(Mandelbrot),
+            Family::Lambda => Box::new(Lambda),
+        }
+    }
+
+    pub fn julia(self, parameter: Complex) -> Box<dyn Fractal> {
+        match self {
+            Family::Mandelbrot => Box::new(Mandelbrot.julia(parameter)),
+            Family::Lambda => Box::new(Lambda.julia(parameter)),
+        }
+    }
+
+    // The center and magnification of a view holding the Julia sets of most parameters.
+    pub fn julia_view(self) -> ((f32, f32), f32) {
+        match self {
+            Family::Mandelbrot => ((0.0, 0.0), 1.0),
+            Family::Lambda => ((0.5, 0.0), 2.0),
+        }
+    }
+}
+
+// Names of the AbsVariant fractals.
+pub const ABS_VARIANTS: [&str; 4] = ["celtic", "perpendicular-mandelbrot", "perpendicular-burning-ship", "buffalo"];
+
+// z² + c with absolute values taken of the parts of z before squaring or of the parts of z² after it,
+// the square being conjugated or not before c is added. The Burning Ship folds both parts of z; its
+// relatives take other combinations:
+//
+//     celtic                      |Re z²| + i Im z² + c
+//     perpendicular-mandelbrot    conj((|Re z| + i Im z)²) + c
+//     perpendicular-burning-ship  conj((Re z + i |Im z|)²) + c
+//     buffalo                     conj(|Re z²| + i |Im z²|) + c
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AbsVariant {
+    pub fold: (bool, bool),    // |Re z| and |Im z| before squaring
+    pub unfold: (bool, bool),  // |Re z²| and |Im z²| after it
+    pub conjugate: bool,       // Conjugate the result before adding c
+}
+
+impl AbsVariant {
+    pub fn by_name(name: &str) -> Result<Self, Error> {
+        let variant = |fold, unfold, conjugate| Ok(Self { fold, unfold, conjugate });
+        match name {
+            "celtic" => variant((false, false), (true, false), false),
+            "perpendicular-mandelbrot" => variant((true, false), (false, false), true),
+            "perpendicular-burning-ship" => variant((false, true), (false, false), true),
+            "buffalo" => variant((false, false), (true, true), true),
+            _ => Err(Error::parse("fractal", name, "celtic, perpendicular-mandelbrot, perpendicular-burning-ship or buffalo")),
+        }
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let abs = |value: f64, take: bool| if take { value.abs() } else { value };
+        let folded = Complex::new(abs(z.re, self.fold.0), abs(z.im, self.fold.1));
+        let square = folded * folded;
+        let square = Complex::new(abs(square.re, self.unfold.0), abs(square.im, self.unfold.1));
+        if self.conjugate { square.conj() + c } else { square + c }
+    }
+}
+
+impl Dynamics for AbsVariant {
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        escape(z0, max_iterations, bailout, observer, |z| self.step(z, c))
+    }
+
+    // Taking the absolute value of an imaginary part is what breaks the symmetry of the orbits.
+    fn commutes_with_conjugation(&self) -> bool {
+        !self.fold.1 && !self.unfold.1
+    }
+}
+
+// A hybrid of the quadratic formulas, the step of every iteration being chosen in turn by the
+// letters of a pattern repeated along the orbit: "MMB" makes two Mandelbrot steps, then one Burning
+// Ship step, then two Mandelbrot steps again. The letters are
+//
+//     M  z² + c                    T  conj(z)² + c
+//     B  (|Re z| + i|Im z|)² + c   C  |Re z²| + i Im z² + c
+//
+// It is deserialized from its steps, which must not be empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "HybridFields")]
+pub struct Hybrid {
+    steps: Vec<AbsVariant>,
+}
+
+#[derive(Deserialize)]
+struct HybridFields {
+    steps: Vec<AbsVariant>,
+}
+
+impl TryFrom<HybridFields> for Hybrid {
+    type Error = Error;
+
+    fn try_from(fields: HybridFields) -> Result<Self, Error> {
+        if fields.steps.is_empty() {
+            return Err(Error::InvalidParameter("A hybrid needs at least one step".to_string()));
+        }
+        Ok(Self { steps: fields.steps })
+    }
+}
+
+impl Hybrid {
+    pub const DEFAULT_PATTERN: &'static str = "MMB";
+
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        let steps = pattern
+            .chars()
+            .map(|letter| match letter.to_ascii_uppercase() {
+                'M' => Ok(AbsVariant::default()),
+                'B' => Ok(AbsVariant { fold: (true, true), ..AbsVariant::default() }),
+                'T' => Ok(AbsVariant { conjugate: true, ..AbsVariant::default() }),
+                'C' => AbsVariant::by_name("celtic"),
+                _ => Err(Error::parse("hybrid pattern", pattern, "letters among M, B, T and C")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err(Error::parse("hybrid pattern", pattern, "letters among M, B, T and C"));
+        }
+        Ok(Self { steps })
+    }
+}
+
+impl Dynamics for Hybrid {
+    // z coming back to an earlier value only repeats the orbit at the same place of the pattern, so
+    // cycles are not detected.
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        let mut steps = self.steps.iter().cycle();
+        escape_without_periodicity(z0, max_iterations, bailout, observer, |z| match steps.next() {
+            Some(step) => step.step(z, c),
+            None => z,
+        })
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        self.steps.iter().all(|step| step.commutes_with_conjugation())
+    }
+}
+
+// The orbit of a point up to its escape or the iteration limit, with the result of the iteration.
+pub fn orbit(fractal: &dyn Fractal, point: Complex, max_iterations: u32) -> (Vec<Complex>, EscapeResult) {
+    let mut recorder = OrbitRecorder::default();
+    let escape = fractal.iterate_with(point, max_iterations, DEFAULT_BAILOUT, &mut recorder);
+    (recorder.points, escape)
+}
+
+// Evaluate a function at every pixel of the bounds, the results being stored row by row.
+pub fn map_plane<T>(width: u32, height: u32, bounds: (f32, f32, f32, f32), mut f: impl FnMut(Complex) -> T) -> Vec<T> {
+    let (xmin, xmax, ymin, ymax) = bounds;
+    let scale_x = (xmax - xmin) as f64 / width as f64;
+    let scale_y = (ymax - ymin) as f64 / height as f64;
+
+    let mut values = Vec::with_capacity((width * height) as usize);
+    for py in 0..height {
+        for px in 0..width {
+            values.push(f(Complex::new(px as f64 * scale_x + xmin as f64, py as f64 * scale_y + ymin as f64)));
+        }
+    }
+    values
+}
+
+// The sum of the indices of the rows mirroring each other across the real axis, when the real axis
+// falls on a row or halfway between two rows. With other bounds no row has an exact mirror.
+fn mirror_row_sum(height: u32, bounds: (f32, f32, f32, f32)) -> Option<i64> {
+    let (_, _, ymin, ymax) = bounds;
+    let scale_y = (ymax - ymin) as f64 / height as f64;
+    let sum = -2.0 * ymin as f64 / scale_y;
+    // A thousandth of a pixel is well below what could be seen.
+    (sum.is_finite() && (sum - sum.round()).abs() < 1e-3).then(|| sum.round() as i64)
+}
+
+// Like map_plane for functions giving mirrored values at conjugate points: every row whose mirror
+// across the real axis was already computed is copied from it through `mirror`, the others are
+// evaluated. Views only partly overlapping the axis get the overlapping rows mirrored.
+pub fn map_plane_symmetric<T>(
+    width: u32,
+    height: u32,
+    bounds: (f32, f32, f32, f32),
+    mut f: impl FnMut(Complex) -> T,
+    mirror: impl Fn(&T) -> T,
+) -> Vec<T> {
+    let Some(row_sum) = mirror_row_sum(height, bounds) else {
+        return map_plane(width, height, bounds, f);
+    };
+    let (xmin, xmax, ymin, ymax) = bounds;
+    let scale_x = (xmax - xmin) as f64 / width as f64;
+    let scale_y = (ymax - ymin) as f64 / height as f64;
+
+    let mut values: Vec<T> = Vec::with_capacity((width * height) as usize);
+    for py in 0..height {
+        let mirror_row = row_sum - py as i64;
+        if (0..py as i64).contains(&mirror_row) {
+            let start = mirror_row as usize * width as usize;
+            for px in 0..width as usize {
+                let value = mirror(&values[start + px]);
+                values.push(value);
+            }
+        } else {
+            for px in 0..width {
+                values.push(f(Complex::new(px as f64 * scale_x + xmin as f64, py as f64 * scale_y + ymin as f64)));
+            }
+        }
+    }
+    values
+}
+
+// Iterate every pixel of the bounds, keeping only the iteration counts.
+pub fn compute_iterations(fractal: &dyn Fractal, width: u32, height: u32, max_iterations: u32, bounds: (f32, f32, f32, f32)) -> Vec<u32> {
+    let iterations = |point| fractal.iterate(point, max_iterations).iterations;
+    if fractal.conjugate_symmetric() {
+        map_plane_symmetric(width, height, bounds, iterations, |&i| i)
+    } else {
+        map_plane(width, height, bounds, iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the Mandelbrot iteration on a bounded cycle and on a point escaping after 0 → 1 → 2 → 5.
+    #[test]
+    fn test_mandelbrot_iterate() {
+        assert_eq!(Mandelbrot.iterate(Complex::new(-1.0, 0.0), 100).iterations, 100);
+        let escape = Mandelbrot.iterate(Complex::ONE, 100);
+        assert_eq!(escape.iterations, 3);
+        assert_eq!(escape.z, Complex::new(5.0, 0.0));
+        assert_eq!(escape.min_distance, 1.0);
+    }
+
+    /// Tests that the orbits caught in a cycle stop early but are still reported as reaching the limit.
+    #[test]
+    fn test_periodicity_checking() {
+        struct StepCounter(u32);
+        impl OrbitObserver for StepCounter {
+            fn observe(&mut self, _previous: Complex, _z: Complex) {
+                self.0 += 1;
+            }
+        }
+
+        // 0 → -1 → 0 is seen again after 4 steps, and 0 is a fixed point for c = 0.
+        for (c, steps) in [(Complex::new(-1.0, 0.0), 4), (Complex::ZERO, 1)] {
+            let mut counter = StepCounter(0);
+            assert_eq!(Mandelbrot.iterate_with(c, 1000, DEFAULT_BAILOUT, &mut counter).iterations, 1000);
+            assert_eq!(counter.0, steps);
+        }
+        let mut counter = StepCounter(0);
+        let escape = escape_without_periodicity(Complex::ZERO, 1000, DEFAULT_BAILOUT, &mut counter, |z| z * z - Complex::ONE);
+        assert_eq!((escape.iterations, counter.0), (1000, 1000));
+        // The cardioid point -0.5 converges slowly to its fixed point but is caught well before the limit.
+        let mut counter = StepCounter(0);
+        assert_eq!(Mandelbrot.iterate_with(Complex::new(-0.5, 0.0), 100_000, DEFAULT_BAILOUT, &mut counter).iterations, 100_000);
+        assert!(counter.0 < 1000);
+    }
+
+    /// Tests that mirroring the rows across the real axis gives the same counts as computing them, for a
+    /// view only partly below the axis and one where the axis falls between two rows.
+    #[test]
+    fn test_symmetric_plane() {
+        for bounds in [(-2.0, 1.0, -0.5, 1.5), (-2.0, 1.0, -1.0, 1.0)] {
+            let direct = map_plane(12, 8, bounds, |c| Mandelbrot.iterate(c, 50).iterations);
+            assert_eq!(compute_iterations(&Mandelbrot, 12, 8, 50, bounds), direct);
+        }
+        assert_eq!(mirror_row_sum(8, (-2.0, 1.0, -0.5, 1.5)), Some(4));
+        assert_eq!(mirror_row_sum(8, (-2.0, 1.0, -0.3, 1.5)), None);
+        assert!(!BurningShip.conjugate_symmetric() && !Julia::new(Julia::DEFAULT_C).conjugate_symmetric());
+    }
+
+    /// Tests that the recorded orbit starts at the first point and ends at the escape.
+    #[test]
+    fn test_orbit() {
+        let (points, escape) = orbit(&Mandelbrot, Complex::ONE, 100);
+        assert_eq!(points, vec![Complex::ZERO, Complex::ONE, Complex::new(2.0, 0.0), Complex::new(5.0, 0.0)]);
+        assert_eq!(escape.iterations, 3);
+        // A cycle is recorded until the periodicity check catches it.
+        let (points, escape) = orbit(&Mandelbrot, Complex::new(-1.0, 0.0), 100);
+        assert_eq!((points.len(), escape.iterations), (5, 100));
+    }
+
+    /// Tests that a larger bailout radius lets the orbit run longer before it escapes.
+    #[test]
+    fn test_bailout() {
+        let c = Complex::ONE; // 0 → 1 → 2 → 5 → 26 → 677
+        assert_eq!(Mandelbrot.iterate_with(c, 100, 2.0, &mut ()).iterations, 3);
+        let escape = Mandelbrot.iterate_with(c, 100, 100.0, &mut ());
+        assert_eq!((escape.iterations, escape.z), (5, Complex::new(677.0, 0.0)));
+        assert!(check_bailout(1e6).is_ok());
+        assert!(check_bailout(1.5).is_err() && check_bailout(f64::INFINITY).is_err());
+    }
+
+    /// Tests that the variants agree with the Mandelbrot set on the real axis, where folding and
+    /// conjugating change nothing, but not elsewhere.
+    #[test]
+    fn test_variants_on_real_axis() {
+        for re in [-1.9, -1.2, 0.2, 0.3] {
+            let c = Complex::new(re, 0.0);
+            assert_eq!(BurningShip.iterate(c, 50), Mandelbrot.iterate(c, 50));
+            assert_eq!(Tricorn.iterate(c, 50), Mandelbrot.iterate(c, 50));
+        }
+        let c = Complex::new(-0.5, 0.5);
+        assert_ne!(BurningShip.iterate(c, 50).z, Mandelbrot.iterate(c, 50).z);
+    }
+
+    /// Tests that the parameter of the Spider fractal moves with the orbit: from c = 0.2 it is
+    /// 0.2 → 0.34 → 0.5676 while z goes 0.2 → 0.24 → 0.3976, where a fixed c would give 0.2576.
+    #[test]
+    fn test_spider() {
+        let mut recorder = OrbitRecorder::default();
+        Spider.iterate_with(Complex::new(0.2, 0.0), 2, DEFAULT_BAILOUT, &mut recorder);
+        let values: Vec<f64> = recorder.points.iter().map(|z| z.re).collect();
+        assert!((values[1] - 0.24).abs() < 1e-12 && (values[2] - 0.3976).abs() < 1e-12, "{:?}", values);
+        assert!(Spider.iterate(Complex::new(0.5, 0.5), 100).iterations < 100);
+        assert_eq!(Spider.iterate(Complex::new(-0.1, 0.0), 100).iterations, 100);
+    }
+
+    /// Tests that the logistic map is conjugate to z² + c: λ and c = λ/2 − λ²/4 escape or not together.
+    #[test]
+    fn test_lambda() {
+        for lambda in [Complex::new(2.5, 0.3), Complex::new(3.9, 0.5), Complex::new(-1.5, 0.5), Complex::new(3.2, 0.0)] {
+            let c = lambda / 2.0 - lambda * lambda / 4.0;
+            assert_eq!(Lambda.iterate(lambda, 200).iterations < 200, Mandelbrot.iterate(c, 200).iterations < 200, "{:?}", lambda);
+        }
+        assert_eq!(Lambda.iterate(Complex::ONE, 200).iterations, 200);
+        assert!(Lambda.iterate(Complex::new(4.5, 0.0), 200).iterations < 200);
+        let julia = Family::Lambda.julia(Complex::new(2.0, 0.0));
+        assert_eq!(julia.iterate(Complex::new(0.5, 0.0), 100).iterations, 100);
+        assert!(julia.iterate(Complex::new(0.5, 1.5), 100).iterations < 100);
+        assert_eq!("lambda".parse::<Family>(), Ok(Family::Lambda));
+    }
+
+    /// Tests the variants of the Burning Ship family against their formulas, and their symmetry.
+    #[test]
+    fn test_abs_variants() {
+        let (z, c) = (Complex::new(-0.5, 0.75), Complex::new(0.1, -0.2));
+        let celtic = AbsVariant::by_name("celtic").unwrap();
+        // z² = -0.3125 - 0.75i
+        assert_eq!(celtic.step(z, c), Complex::new(0.3125 + 0.1, -0.75 - 0.2));
+        let perpendicular = AbsVariant::by_name("perpendicular-mandelbrot").unwrap();
+        assert_eq!(perpendicular.step(z, c), Complex::new(-0.3125 + 0.1, -0.75 - 0.2));
+        let buffalo = AbsVariant::by_name("buffalo").unwrap();
+        assert_eq!(buffalo.step(z, c), Complex::new(0.3125 + 0.1, -0.75 - 0.2));
+        // Without any absolute value the variant is the Mandelbrot set
+        let plain = AbsVariant::default();
+        assert_eq!(plain.iterate(Complex::new(-0.75, 0.1), 100), Mandelbrot.iterate(Complex::new(-0.75, 0.1), 100));
+        assert!(celtic.conjugate_symmetric() && perpendicular.conjugate_symmetric());
+        assert!(!buffalo.conjugate_symmetric());
+        assert!(!AbsVariant::by_name("perpendicular-burning-ship").unwrap().conjugate_symmetric());
+        assert!(AbsVariant::by_name("mandelbrot").is_err());
+    }
+
+    /// Tests that a hybrid follows its pattern step by step and that one letter gives the plain formula.
+    #[test]
+    fn test_hybrid() {
+        let (z, c) = (Complex::new(-0.5, 0.75), Complex::new(0.1, -0.2));
+        let mut recorder = OrbitRecorder::default();
+        Hybrid::parse("MB").unwrap().iterate_from(z, c, 3, 1e6, &mut recorder);
+        let ship = AbsVariant { fold: (true, true), ..AbsVariant::default() };
+        let first = z * z + c;
+        let second = ship.step(first, c);
+        assert_eq!(recorder.points[..4], [z, first, second, second * second + c]);
+        let tricorn = Hybrid::parse("t").unwrap();
+        assert_eq!(tricorn.iterate(Complex::new(-0.3, 0.6), 100).iterations, Tricorn.iterate(Complex::new(-0.3, 0.6), 100).iterations);
+        assert!(tricorn.conjugate_symmetric() && !Hybrid::parse("MMB").unwrap().conjugate_symmetric());
+        assert!(Hybrid::parse("").is_err() && Hybrid::parse("MX").is_err());
+    }
+
+    /// Tests that the Multibrot set of power 2 is the Mandelbrot set and that powers below 2 are refused.
+    #[test]
+    fn test_multibrot() {
+        let square = Multibrot::new(2).unwrap();
+        for c in [Complex::new(-0.75, 0.1), Complex::new(0.3, 0.5), Complex::ONE] {
+            assert_eq!(square.iterate(c, 100), Mandelbrot.iterate(c, 100));
+        }
+        // c = -1 is a cycle of z² − 1 but escapes from z³ − 1: 0 → −1 → −2 → −9.
+        assert_eq!(Multibrot::new(3).unwrap().iterate(Complex::new(-1.0, 0.0), 100).iterations, 3);
+        assert!(Multibrot::new(1).is_err());
+    }
+
+    /// Tests that the Julia sets of every formula iterate the same map from the pixels: the Multibrot
+    /// set of power 2 gives the Julia sets of z² + c, and with a real parameter the Burning Ship folding
+    /// changes nothing on the real axis.
+    #[test]
+    fn test_julia_sets_of_formulas() {
+        let c = Complex::new(-0.4, 0.6);
+        let square = Multibrot::new(2).unwrap().julia(c);
+        for z0 in [Complex::ZERO, Complex::new(0.3, -0.2), Complex::new(1.5, 0.5)] {
+            assert_eq!(square.iterate(z0, 100), Julia::new(c).iterate(z0, 100));
+        }
+        let ship = BurningShip.julia(Complex::new(-1.0, 0.0));
+        assert_eq!(ship.iterate(Complex::new(-0.5, 0.0), 50), Julia::new(Complex::new(-1.0, 0.0)).iterate(Complex::new(-0.5, 0.0), 50));
+        assert_eq!(ship.default_bounds(), (-2.0, 2.0, -1.5, 1.5));
+        assert!(Tricorn.julia(Complex::new(0.2, 0.0)).conjugate_symmetric() && !Tricorn.julia(c).conjugate_symmetric());
+        let boxed: Box<dyn Dynamics> = Box::new(Lambda);
+        assert_eq!(boxed.julia(Complex::new(2.0, 0.0)).iterate(Complex::new(0.5, 1.5), 100), Lambda.julia(Complex::new(2.0, 0.0)).iterate(Complex::new(0.5, 1.5), 100));
+    }
+
+    /// Tests that the pixels of the Julia set are starting points and that the results are stored row by row.
+    #[test]
+    fn test_compute_iterations_layout() {
+        let julia = Julia::new(Complex::ZERO);
+        // Columns at re = -1.5, -0.5, 0.5, 1.5 and rows at im = 0, 1: only |z| < 1 stays bounded under z².
+        let iterations = compute_iterations(&julia, 4, 2, 20, (-1.5, 2.5, 0.0, 2.0));
+        assert_eq!(&iterations[..4], &[1, 20, 20, 1]);
+        assert!(iterations[4..].iter().all(|&i| i < 20));
+    }
+
+    /// Tests that the fractals round-trip through serde, and that deserializing checks them like their
+    /// constructors do.
+    #[test]
+    fn test_serde_round_trip() {
+        let family: Family = serde_json::from_str(&serde_json::to_string(&Family::Lambda).unwrap()).unwrap();
+        assert_eq!(family, Family::Lambda);
+        let multibrot: Multibrot = serde_json::from_str(&serde_json::to_string(&Multibrot::new(5).unwrap()).unwrap()).unwrap();
+        assert_eq!(multibrot, Multibrot { power: 5 });
+        assert!(serde_json::from_str::<Multibrot>("{\"power\": 1}").is_err());
+        let buffalo = AbsVariant::by_name("buffalo").unwrap();
+        assert_eq!(serde_json::from_str::<AbsVariant>(&serde_json::to_string(&buffalo).unwrap()).unwrap(), buffalo);
+        let hybrid = Hybrid::parse("MMBC").unwrap();
+        assert_eq!(serde_json::from_str::<Hybrid>(&serde_json::to_string(&hybrid).unwrap()).unwrap(), hybrid);
+        assert!(serde_json::from_str::<Hybrid>("{\"steps\": []}").is_err());
+    }
+}
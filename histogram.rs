@@ -0,0 +1,91 @@
+// Histogram of the iteration counts of a render, written as CSV or JSON so that the iteration limit
+// and the palette can be chosen from the actual distribution: a large interior fraction with escaping
+// pixels piling up just below the limit asks for more iterations, a palette spread over counts that
+// barely occur wastes most of its colors.
+use crate::mandelbrot::EscapeInfo;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationHistogram {
+    pub max_iterations: u32,
+    pub counts: Vec<u64>, // Escaped pixels by iteration count, from 0 to max_iterations - 1
+    pub interior: u64,    // Pixels that reached the limit
+}
+
+impl IterationHistogram {
+    pub fn new(escapes: &[EscapeInfo], max_iterations: u32) -> Self {
+        let mut counts = vec![0; max_iterations as usize];
+        let mut interior = 0;
+        for escape in escapes {
+            match counts.get_mut(escape.iterations as usize) {
+                Some(count) if escape.escaped => *count += 1,
+                _ => interior += 1,
+            }
+        }
+        Self { max_iterations, counts, interior }
+    }
+
+    pub fn pixels(&self) -> u64 {
+        self.counts.iter().sum::<u64>() + self.interior
+    }
+
+    pub fn interior_fraction(&self) -> f64 {
+        self.interior as f64 / self.pixels().max(1) as f64
+    }
+
+    // The iteration counts reached by at least one pixel, with their number of pixels.
+    fn bins(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.counts.iter().copied().enumerate().filter(|&(_, count)| count > 0)
+    }
+
+    // One `iterations,count` line per count reached, the interior pixels on a last `interior` line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("iterations,count\n");
+        for (iterations, count) in self.bins() {
+            csv.push_str(&format!("{},{}\n", iterations, count));
+        }
+        csv.push_str(&format!("interior,{}\n", self.interior));
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let bins: Vec<String> = self.bins().map(|(iterations, count)| format!("{{\"iterations\": {}, \"count\": {}}}", iterations, count)).collect();
+        format!(
+            "{{\n  \"max_iterations\": {},\n  \"pixels\": {},\n  \"interior\": {},\n  \"interior_fraction\": {},\n  \"bins\": [{}]\n}}\n",
+            self.max_iterations,
+            self.pixels(),
+            self.interior,
+            self.interior_fraction(),
+            bins.join(", ")
+        )
+    }
+
+    // Write the histogram as JSON if the path ends with .json, as CSV otherwise.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = Path::new(path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let text = if json { self.to_json() } else { self.to_csv() };
+        fs::write(path, text).map_err(|e| format!("Could not write the histogram {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the counting of escaped and interior pixels and both output formats.
+    #[test]
+    fn test_histogram() {
+        let escapes: Vec<EscapeInfo> = [1, 1, 3, 10, 10].iter().map(|&iterations| EscapeInfo::from_iterations(iterations, 10)).collect();
+        let histogram = IterationHistogram::new(&escapes, 10);
+        assert_eq!(histogram.counts[1], 2);
+        assert_eq!(histogram.counts[3], 1);
+        assert_eq!((histogram.interior, histogram.pixels()), (2, 5));
+        assert_eq!(histogram.interior_fraction(), 0.4);
+
+        assert_eq!(histogram.to_csv(), "iterations,count\n1,2\n3,1\ninterior,2\n");
+        let json = histogram.to_json();
+        assert!(json.contains("\"interior_fraction\": 0.4"));
+        assert!(json.contains("[{\"iterations\": 1, \"count\": 2}, {\"iterations\": 3, \"count\": 1}]"));
+    }
+}
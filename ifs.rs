@@ -0,0 +1,225 @@
+// Iterated function systems: a few affine maps of the plane, each picked with its own probability.
+// Starting from any point and applying a map picked at random again and again (the chaos game), the
+// points visited soon stay on the attractor of the system, the one shape every map sends into itself,
+// and counting them shades the shape by how often it is visited.
+//
+// The Barnsley fern is made of four maps: the stem, the whole fern shrunk up into its top, and its two
+// lowest leaves. Other systems are read from a file in the format of config::load_document, one
+// table per map:
+//
+//     # sierpinski.ifs
+//     [[map]]
+//     a = 0.5
+//     d = 0.5
+//
+//     [[map]]
+//     a = 0.5
+//     d = 0.5
+//     e = 0.5
+//
+//     [[map]]
+//     a = 0.5
+//     d = 0.5
+//     e = 0.25
+//     f = 0.5
+//
+// The coefficients left out are 0. The maps are picked by their `probability`, in proportion to the
+// total, or when none is given in proportion to the area they keep, |ad − bc|. `bounds =
+// "xmin;xmax;ymin;ymax"` at the top of the file sets the view, which is otherwise found from a first
+// run of the chaos game.
+use crate::area::Random;
+use crate::config::{self, Document};
+use crate::density::{Density, Extent};
+use final_exercice::bounds;
+use std::collections::HashMap;
+
+// The first points only get closer to the attractor, they are not counted.
+const SKIPPED_POINTS: u32 = 20;
+
+pub const DEFAULT_POINTS: u64 = 1_000_000;
+// The points run to find the bounds of a system, and the space left around them.
+const BOUNDS_POINTS: u64 = 100_000;
+const BOUNDS_MARGIN: f64 = 0.05;
+// The smallest share of a degenerate map, which keeps no area but is still part of the attractor.
+const MIN_AREA_SHARE: f64 = 0.01;
+
+// (x, y) ↦ (a·x + b·y + e, c·x + d·y + f), picked with a probability.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AffineMap {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+    pub probability: f64,
+}
+
+impl AffineMap {
+    // Read a map from its table, and its probability if it is given.
+    fn from_options(options: &HashMap<String, String>) -> Result<(Self, Option<f64>), String> {
+        let parse = |name: &str| {
+            options.get(name).map_or(Ok(0.0), |v| v.parse::<f64>().ok().filter(|v| v.is_finite()).ok_or(format!("Invalid {} '{}'", name, v)))
+        };
+        let probability = options.get("probability").map(|_| parse("probability")).transpose()?;
+        if probability.is_some_and(|p| p < 0.0) {
+            return Err("The probability of a map cannot be negative".to_string());
+        }
+        let (a, b, c, d, e, f) = (parse("a")?, parse("b")?, parse("c")?, parse("d")?, parse("e")?, parse("f")?);
+        Ok((Self { a, b, c, d, e, f, probability: 0.0 }, probability))
+    }
+
+    // How much the map scales areas.
+    fn area(&self) -> f64 {
+        (self.a * self.d - self.b * self.c).abs()
+    }
+
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (self.a * x + self.b * y + self.e, self.c * x + self.d * y + self.f)
+    }
+}
+
+pub struct Ifs {
+    pub maps: Vec<AffineMap>,
+    pub bounds: Option<(f32, f32, f32, f32)>, // The view given with the system
+}
+
+impl Ifs {
+    pub fn barnsley_fern() -> Self {
+        let map = |a, b, c, d, e, f, probability| AffineMap { a, b, c, d, e, f, probability };
+        Self {
+            maps: vec![
+                map(0.0, 0.0, 0.0, 0.16, 0.0, 0.0, 0.01),
+                map(0.85, 0.04, -0.04, 0.85, 0.0, 1.6, 0.85),
+                map(0.2, -0.26, 0.23, 0.22, 0.0, 1.6, 0.07),
+                map(-0.15, 0.28, 0.26, 0.24, 0.0, 0.44, 0.07),
+            ],
+            bounds: Some(Self::FERN_BOUNDS),
+        }
+    }
+
+    // The bounds of the Barnsley fern.
+    pub const FERN_BOUNDS: (f32, f32, f32, f32) = (-2.2, 2.7, 0.0, 10.0);
+
+    // Build a system from a parsed file, its probabilities scaled to add up to 1.
+    pub fn from_document(document: &Document) -> Result<Self, String> {
+        let mut maps = Vec::new();
+        for table in &document.tables {
+            if table.name != "map" {
+                return Err(format!("Unexpected table [{}], the maps are [[map]] tables", table.name));
+            }
+            maps.push(AffineMap::from_options(&table.options)?);
+        }
+        if maps.is_empty() {
+            return Err("An iterated function system needs at least one [[map]] table".to_string());
+        }
+        let given = maps.iter().filter(|(_, probability)| probability.is_some()).count();
+        if given != 0 && given != maps.len() {
+            return Err("Either every map has a probability or none of them has".to_string());
+        }
+        let area_total: f64 = maps.iter().map(|(map, _)| map.area()).sum();
+        let mut maps: Vec<AffineMap> = maps
+            .into_iter()
+            .map(|(map, probability)| {
+                let share = if area_total > 0.0 { map.area() / area_total } else { 0.0 };
+                AffineMap { probability: probability.unwrap_or(share.max(MIN_AREA_SHARE)), ..map }
+            })
+            .collect();
+        let total: f64 = maps.iter().map(|map| map.probability).sum();
+        if !(total > 0.0 && total.is_finite()) {
+            return Err("The probabilities of the maps must not all be 0".to_string());
+        }
+        for map in &mut maps {
+            map.probability /= total;
+        }
+        let bounds = match document.options.get("bounds") {
+            Some(text) => Some(bounds::parse_checked(text).map_err(|e| e.to_string())?.bounds),
+            None => None,
+        };
+        Ok(Self { maps, bounds })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_document(&config::load_document(path)?)
+    }
+
+    // The bounds of the system: those it was given with, or those of the points of a first run of the
+    // chaos game, with a margin.
+    pub fn bounds(&self, random: &mut Random) -> Result<(f32, f32, f32, f32), String> {
+        if let Some(bounds) = self.bounds {
+            return Ok(bounds);
+        }
+        let mut extent = Extent::default();
+        self.play(BOUNDS_POINTS, random, |x, y| extent.add(x, y));
+        extent.bounds(BOUNDS_MARGIN).ok_or_else(|| "The points of the system go to infinity, one of its maps must be expanding".to_string())
+    }
+
+    // The map a number in [0, 1) picks, the probabilities being cumulated in order.
+    fn pick(&self, r: f64) -> &AffineMap {
+        let mut total = 0.0;
+        for map in &self.maps {
+            total += map.probability;
+            if r < total {
+                return map;
+            }
+        }
+        &self.maps[self.maps.len() - 1]
+    }
+
+    // Play the chaos game for a number of points, counting them in the density.
+    pub fn chaos_game(&self, points: u64, random: &mut Random, density: &mut Density) {
+        self.play(points, random, |x, y| density.add(x, y));
+    }
+
+    fn play(&self, points: u64, random: &mut Random, mut visit: impl FnMut(f64, f64)) {
+        let mut point = (0.0, 0.0);
+        for _ in 0..SKIPPED_POINTS {
+            point = self.pick(random.next_f64()).apply(point);
+        }
+        for _ in 0..points {
+            point = self.pick(random.next_f64()).apply(point);
+            visit(point.0, point.1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the maps are picked by their probabilities and that the fern stays in its bounds.
+    #[test]
+    fn test_barnsley_fern() {
+        let fern = Ifs::barnsley_fern();
+        assert_eq!(fern.pick(0.005), &fern.maps[0]);
+        assert_eq!(fern.pick(0.5), &fern.maps[1]);
+        assert_eq!(fern.pick(0.999), &fern.maps[3]);
+        let mut density = Density::new(49, 100, Ifs::FERN_BOUNDS);
+        fern.chaos_game(20_000, &mut Random::new(1), &mut density);
+        assert_eq!(density.counts.iter().map(|&count| count as u64).sum::<u64>(), 20_000);
+        // The stem at the bottom center is visited, the bottom corners are not
+        let count = |x: usize, y: usize| density.counts[y * 49 + x];
+        assert!((90..100).any(|y| count(22, y) > 0));
+        assert!((90..100).all(|y| count(0, y) == 0 && count(48, y) == 0));
+    }
+
+    /// Tests reading a system: the coefficients left out, the probabilities by area, and the bounds
+    /// found from its points.
+    #[test]
+    fn test_ifs_from_document() {
+        let text = "[[map]]\na = 0.5\nd = 0.5\n[[map]]\na = 0.5\nd = 0.5\ne = 0.5\n[[map]]\na = 0.5\nd = 0.5\ne = 0.25\nf = 0.5\n";
+        let sierpinski = Ifs::from_document(&config::parse_document(text).unwrap()).unwrap();
+        assert_eq!(sierpinski.maps.len(), 3);
+        assert!(sierpinski.maps.iter().all(|map| (map.probability - 1.0 / 3.0).abs() < 1e-12 && map.b == 0.0));
+        let (xmin, xmax, ymin, ymax) = sierpinski.bounds(&mut Random::new(2)).unwrap();
+        assert!(xmin < 0.0 && xmin > -0.1 && xmax > 1.0 && xmax < 1.1 && ymin < 0.0 && ymax > 1.0 && ymax < 1.1);
+        // Given probabilities are kept in proportion, and have to be given for every map
+        assert!(Ifs::from_document(&config::parse_document("[[map]]\na = 0.5\nprobability = 3\n[[map]]\nd = 0.5\n").unwrap()).is_err());
+        let weighted = Ifs::from_document(&config::parse_document("[[map]]\na = 0.5\nprobability = 3\n[[map]]\nd = 0.5\nprobability = 1\n").unwrap()).unwrap();
+        assert_eq!((weighted.maps[0].probability, weighted.maps[1].probability), (0.75, 0.25));
+        assert!(Ifs::from_document(&config::parse_document("bounds = \"0;1;0;1\"").unwrap()).is_err());
+        assert!(Ifs::from_document(&config::parse_document("[[map]]\na = x").unwrap()).is_err());
+        let expanding = Ifs::from_document(&config::parse_document("[[map]]\na = 2\nd = 2\ne = 1").unwrap()).unwrap();
+        assert!(expanding.bounds(&mut Random::new(1)).is_err());
+    }
+}
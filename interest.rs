@@ -0,0 +1,124 @@
+// Finding the interesting parts of a view: the view is scanned at a low resolution and split into a
+// grid of tiles, each scored by how varied its iteration counts are. A tile entirely inside the set or
+// far outside of it has a single count (or a smooth ramp of a few), while the tiles crossing filaments,
+// spirals and minibrots mix many counts and score high.
+use crate::mandelbrot::{self, DEFAULT_VIEW_WIDTH};
+use std::str::FromStr;
+
+// Resolution of the scan, and number of tiles per side of the view.
+pub const SCAN_SIZE: (u32, u32) = (160, 120);
+pub const DEFAULT_GRID: (u32, u32) = (4, 4);
+
+// How a tile is scored from the iteration counts of its pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Entropy,  // Shannon entropy of the counts in bits, the number of distinct counts and how evenly they appear
+    Variance, // Variance of the counts, favoring large jumps such as the boundary of the interior
+}
+
+impl FromStr for Score {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "entropy" => Ok(Score::Entropy),
+            "variance" => Ok(Score::Variance),
+            _ => Err(format!("Unknown score '{}', expected entropy or variance", s)),
+        }
+    }
+}
+
+impl Score {
+    pub fn of(&self, iterations: &[u32]) -> f64 {
+        match self {
+            Score::Entropy => entropy(iterations),
+            Score::Variance => variance(iterations),
+        }
+    }
+}
+
+pub fn entropy(iterations: &[u32]) -> f64 {
+    let mut sorted = iterations.to_vec();
+    sorted.sort_unstable();
+    let total = sorted.len() as f64;
+    sorted
+        .chunk_by(|a, b| a == b)
+        .map(|run| {
+            let p = run.len() as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+pub fn variance(iterations: &[u32]) -> f64 {
+    let n = iterations.len().max(1) as f64;
+    let mean = iterations.iter().map(|&i| i as f64).sum::<f64>() / n;
+    iterations.iter().map(|&i| (i as f64 - mean).powi(2)).sum::<f64>() / n
+}
+
+// A tile of a scanned view, as a view of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub center: (f32, f32),
+    pub zoom: f32,
+    pub score: f64,
+}
+
+// Scan the view of a center and zoom and score every tile of the grid, the best ones first.
+pub fn find_regions(center: (f32, f32), zoom: f32, max_iterations: u32, grid: (u32, u32), score: Score) -> Result<Vec<Region>, String> {
+    let (columns, rows) = grid;
+    let (width, height) = SCAN_SIZE;
+    if columns == 0 || rows == 0 || columns > width || rows > height {
+        return Err(format!("The grid {}x{} does not fit the {}x{} scan", columns, rows, width, height));
+    }
+    let bounds = mandelbrot::view_bounds(center, zoom, width, height);
+    let iterations = mandelbrot::compute_iterations(width, height, max_iterations, bounds);
+
+    let (xmin, xmax, ymin, ymax) = bounds;
+    let (tile_width, tile_height) = (width / columns, height / rows);
+    let mut regions = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let pixels: Vec<u32> = (row * tile_height..(row + 1) * tile_height)
+                .flat_map(|y| (column * tile_width..(column + 1) * tile_width).map(move |x| (x, y)))
+                .map(|(x, y)| iterations[(y * width + x) as usize])
+                .collect();
+            let re = xmin + (column as f32 + 0.5) * (xmax - xmin) / columns as f32;
+            let im = ymin + (row as f32 + 0.5) * (ymax - ymin) / rows as f32;
+            // The tiles keep the aspect ratio of their view when the grid is square
+            let tile_zoom = DEFAULT_VIEW_WIDTH / ((xmax - xmin) / columns as f32);
+            regions.push(Region { center: (re, im), zoom: tile_zoom, score: score.of(&pixels) });
+        }
+    }
+    regions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that uniform tiles score zero and mixed ones more.
+    #[test]
+    fn test_scores() {
+        assert_eq!(entropy(&[5; 16]), 0.0);
+        assert_eq!(entropy(&[1, 2, 3, 4]), 2.0);
+        assert_eq!(variance(&[5; 16]), 0.0);
+        assert_eq!(variance(&[1, 3]), 1.0);
+        assert_eq!("variance".parse(), Ok(Score::Variance));
+        assert!("beauty".parse::<Score>().is_err());
+    }
+
+    /// Tests that the tiles of the whole set far from the boundary are the least interesting, and that the
+    /// regions cover the view.
+    #[test]
+    fn test_find_regions() {
+        let regions = find_regions((-0.5, 0.0), 1.0, 100, (4, 4), Score::Entropy).unwrap();
+        assert_eq!(regions.len(), 16);
+        assert!(regions.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(regions.iter().all(|region| region.zoom == 4.0));
+        let best = regions[0].center;
+        assert!(best.0 > -2.0 && best.0 < 1.0, "{:?}", best);
+        assert!(find_regions((-0.5, 0.0), 1.0, 100, (0, 4), Score::Entropy).is_err());
+    }
+}
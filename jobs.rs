@@ -0,0 +1,186 @@
+// Render jobs: a large render split into independent shares, so that several machines can each render
+// theirs without talking to each other. split-job cuts the image into a grid of tiles and writes one
+// job file per share, holding the options of the render and the range of tiles to render:
+//
+//     job = "2"
+//     jobs = "4"
+//     width = "16000"
+//     height = "12000"
+//     tile_size = "512"
+//     first_tile = "188"
+//     end_tile = "376"
+//
+//     [[render]]
+//     center = "-0.75,0.1"
+//     zoom = "40"
+//
+// A machine renders its share with run-job into tile_{column}_{row}.png files next to the job file.
+// Once the tiles of every share are copied into one directory, merge-job writes their stitch manifest
+// and assembles the image.
+use crate::config::{self, Document, Table};
+use crate::pyramid::{Layout, Pyramid, Tile};
+use crate::stitch::{PlacedTile, TileManifest};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+pub const DEFAULT_JOB_TILE_SIZE: u32 = 512;
+const RENDER_TABLE: &str = "render";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobSpec {
+    pub job: u32, // From 1 to jobs
+    pub jobs: u32,
+    pub width: u32, // Size of the whole image
+    pub height: u32,
+    pub tile_size: u32,
+    pub tiles: Range<usize>, // Indexes of the tiles of this share, row by row
+    pub options: HashMap<String, String>, // Options of the render, as on the command line
+}
+
+// Split the render of a width × height image into `jobs` shares of about the same number of tiles.
+pub fn split(width: u32, height: u32, tile_size: u32, jobs: u32, options: HashMap<String, String>) -> Result<Vec<JobSpec>, String> {
+    let count = image_tiles(width, height, tile_size)?.len();
+    if jobs == 0 || jobs as usize > count {
+        return Err(format!("A render of {} tiles can be split into 1 to {} jobs, not {}", count, count, jobs));
+    }
+    let bound = |job: u32| count * job as usize / jobs as usize;
+    Ok((1..=jobs)
+        .map(|job| JobSpec { job, jobs, width, height, tile_size, tiles: bound(job - 1)..bound(job), options: options.clone() })
+        .collect())
+}
+
+// The tiles of the image row by row: the most detailed level of a pyramid without overlap.
+pub fn image_tiles(width: u32, height: u32, tile_size: u32) -> Result<Vec<Tile>, String> {
+    let grid = Pyramid::new(width, height, tile_size, 0, Layout::Dzi)?;
+    Ok(grid.level_tiles(grid.max_level()))
+}
+
+impl JobSpec {
+    // The tiles of this share.
+    pub fn tiles(&self) -> Result<Vec<Tile>, String> {
+        let tiles = image_tiles(self.width, self.height, self.tile_size)?;
+        tiles.get(self.tiles.clone()).map(<[Tile]>::to_vec).ok_or_else(|| format!("Job {} goes past the {} tiles of the image", self.job, tiles.len()))
+    }
+
+    pub fn tile_file(tile: &Tile) -> String {
+        format!("tile_{}_{}.png", tile.column, tile.row)
+    }
+
+    // The stitch manifest of every tile of the image, whichever job renders it.
+    pub fn manifest(&self) -> Result<TileManifest, String> {
+        image_manifest(self.width, self.height, self.tile_size)
+    }
+
+    // Whether two jobs split the same render.
+    pub fn same_render(&self, other: &JobSpec) -> bool {
+        (self.jobs, self.width, self.height, self.tile_size) == (other.jobs, other.width, other.height, other.tile_size) && self.options == other.options
+    }
+
+    pub fn to_document(&self) -> Document {
+        let mut options = HashMap::new();
+        for (name, value) in [("job", self.job), ("jobs", self.jobs), ("width", self.width), ("height", self.height), ("tile_size", self.tile_size)] {
+            options.insert(name.to_string(), value.to_string());
+        }
+        options.insert("first_tile".to_string(), self.tiles.start.to_string());
+        options.insert("end_tile".to_string(), self.tiles.end.to_string());
+        Document { options, tables: vec![Table { name: RENDER_TABLE.to_string(), options: self.options.clone() }] }
+    }
+
+    pub fn from_document(document: &Document) -> Result<Self, String> {
+        let number = |name: &str| {
+            let value = document.options.get(name).ok_or_else(|| format!("The job has no '{}'", name))?;
+            value.parse::<usize>().map_err(|_| format!("Invalid {} '{}' in the job", name, value))
+        };
+        let options = document.tables.iter().find(|table| table.name == RENDER_TABLE).map_or(HashMap::new(), |table| table.options.clone());
+        let spec = JobSpec {
+            job: number("job")? as u32,
+            jobs: number("jobs")? as u32,
+            width: number("width")? as u32,
+            height: number("height")? as u32,
+            tile_size: number("tile_size")? as u32,
+            tiles: number("first_tile")?..number("end_tile")?,
+            options,
+        };
+        if spec.job == 0 || spec.job > spec.jobs {
+            return Err(format!("Job {} is not one of the {} jobs", spec.job, spec.jobs));
+        }
+        Ok(spec)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, config::to_text(&self.to_document())).map_err(|e| format!("Could not write the job {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let document = config::load_document(&path.to_string_lossy())?;
+        Self::from_document(&document).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+// The stitch manifest of the tiles of an image, named by JobSpec::tile_file.
+pub fn image_manifest(width: u32, height: u32, tile_size: u32) -> Result<TileManifest, String> {
+    let tiles = image_tiles(width, height, tile_size)?;
+    let tiles = tiles.iter().map(|tile| PlacedTile {
+        file: JobSpec::tile_file(tile),
+        x: tile.x,
+        y: tile.y,
+        width: tile.width,
+        height: tile.height,
+        offset_x: 0,
+        offset_y: 0,
+    });
+    Ok(TileManifest { width, height, tiles: tiles.collect() })
+}
+
+// Name of the file of a job.
+pub fn job_file(job: u32) -> String {
+    format!("job_{:03}.conf", job)
+}
+
+// The jobs found in a directory, checked to split the same render and to all be there.
+pub fn load_jobs(directory: &Path) -> Result<Vec<JobSpec>, String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Could not read {}: {}", directory.display(), e))?;
+    let mut jobs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Could not read {}: {}", directory.display(), e))?.path();
+        let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        if name.starts_with("job_") && name.ends_with(".conf") {
+            jobs.push(JobSpec::load(&path)?);
+        }
+    }
+    jobs.sort_by_key(|job| job.job);
+    let first = jobs.first().ok_or_else(|| format!("No job_*.conf file in {}", directory.display()))?;
+    if let Some(other) = jobs.iter().find(|job| !job.same_render(first)) {
+        return Err(format!("Jobs {} and {} do not split the same render", first.job, other.job));
+    }
+    let missing: Vec<String> = (1..=first.jobs).filter(|job| !jobs.iter().any(|spec| spec.job == *job)).map(|job| job.to_string()).collect();
+    if !missing.is_empty() {
+        return Err(format!("The files of jobs {} of {} are missing", missing.join(", "), first.jobs));
+    }
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the jobs share out every tile once, and that a job file reads back the same job.
+    #[test]
+    fn test_split_jobs() {
+        let options = HashMap::from([("zoom".to_string(), "40".to_string())]);
+        let jobs = split(1000, 600, 256, 3, options).unwrap();
+        // 4 × 3 tiles
+        let ranges: Vec<Range<usize>> = jobs.iter().map(|job| job.tiles.clone()).collect();
+        assert_eq!(ranges, vec![0..4, 4..8, 8..12]);
+        let last = jobs[2].tiles().unwrap();
+        assert_eq!((last[3].column, last[3].row, last[3].width, last[3].height), (3, 2, 232, 88));
+        assert_eq!(JobSpec::tile_file(&last[3]), "tile_3_2.png");
+        assert_eq!(jobs[0].manifest().unwrap().layout_problems(), Vec::<String>::new());
+
+        let text = config::to_text(&jobs[1].to_document());
+        assert_eq!(JobSpec::from_document(&config::parse_document(&text).unwrap()), Ok(jobs[1].clone()));
+        assert!(split(1000, 600, 256, 13, HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,17 @@
+// The renderer shared by the program and the WebAssembly module: the escape-time fractals, the
+// coloring algorithms and the render options. It depends neither on a window nor on a terminal, so
+// it also compiles to wasm32-unknown-unknown with `--no-default-features --features wasm`.
+pub mod complex;
+pub mod fractal;
+pub mod mandelbrot;
+pub mod coloring;
+pub mod palette;
+pub mod dither;
+pub mod light;
+pub mod render;
+pub mod rays;
+pub mod bounds;
+pub mod error;
+pub mod plugins;
+#[cfg(feature = "wasm")]
+pub mod wasm;
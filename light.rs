@@ -0,0 +1,81 @@
+// Linear light: the channels of the 8-bit images are gamma encoded, so averaging them directly (to
+// antialias, or to blend two palettes) gives darker colors than mixing the light itself would. The
+// colors are decoded to linear RGB, mixed there, and encoded again only for the output.
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// The transfer function between the encoded channels and linear light.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Gamma {
+    #[default]
+    Srgb,       // The piecewise sRGB curve, what screens expect
+    Power(f64), // A pure power law, like 2.2
+}
+
+impl FromStr for Gamma {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s == "srgb" {
+            return Ok(Gamma::Srgb);
+        }
+        match s.parse::<f64>() {
+            Ok(gamma) if gamma.is_finite() && gamma > 0.0 => Ok(Gamma::Power(gamma)),
+            _ => Err(Error::parse("gamma", s, "srgb or a positive number like 2.2")),
+        }
+    }
+}
+
+impl Gamma {
+    // From an encoded channel in [0, 1] to linear light.
+    pub fn decode(&self, channel: f64) -> f64 {
+        match self {
+            Gamma::Srgb if channel <= 0.04045 => channel / 12.92,
+            Gamma::Srgb => ((channel + 0.055) / 1.055).powf(2.4),
+            Gamma::Power(gamma) => channel.max(0.0).powf(*gamma),
+        }
+    }
+
+    // From linear light in [0, 1] to an encoded channel.
+    pub fn encode(&self, light: f64) -> f64 {
+        match self {
+            Gamma::Srgb if light <= 0.003_130_8 => light * 12.92,
+            Gamma::Srgb => 1.055 * light.powf(1.0 / 2.4) - 0.055,
+            Gamma::Power(gamma) => light.max(0.0).powf(1.0 / gamma),
+        }
+    }
+
+    // The encoded color of the average light of several encoded colors, weighted.
+    pub fn mix(&self, colors: impl IntoIterator<Item = (f64, [f64; 3])>) -> [f64; 3] {
+        let mut light = [0.0; 3];
+        let mut total = 0.0;
+        for (weight, color) in colors {
+            for (sum, channel) in light.iter_mut().zip(color) {
+                *sum += weight * self.decode(channel);
+            }
+            total += weight;
+        }
+        light.map(|sum| self.encode(sum / total.max(f64::MIN_POSITIVE)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that encoding undoes decoding, and that half the light of white is brighter than 0.5.
+    #[test]
+    fn test_gamma() {
+        for gamma in [Gamma::Srgb, Gamma::Power(2.2)] {
+            for channel in [0.0, 0.02, 0.2, 0.5, 1.0] {
+                assert!((gamma.encode(gamma.decode(channel)) - channel).abs() < 1e-12);
+            }
+            let gray = gamma.mix([(1.0, [0.0; 3]), (1.0, [1.0; 3])]);
+            assert!(gray[0] > 0.7 && gray[0] < 0.74, "{:?}", gray);
+        }
+        assert_eq!("2.2".parse(), Ok(Gamma::Power(2.2)));
+        assert_eq!("srgb".parse(), Ok(Gamma::Srgb));
+        assert!("-1".parse::<Gamma>().is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// Logging of the commands through tracing: what a command did (files saved, frames and tiles
+// rendered) is logged on stderr, while the results asked for (estimates, lists, reports) are still
+// printed on stdout and can be piped. The renderer opens spans around its passes, which --verbose
+// reports with the time they took.
+//
+//   --quiet            only warnings and errors
+//   --verbose          debug logs as well, such as the scheduling of tiles, and the timing of passes
+//   --log-format json  one JSON object per line instead of text, for logs collected from the tile
+//                      server or batch renders
+use std::io;
+use std::str::FromStr;
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format '{}', expected text or json", s)),
+        }
+    }
+}
+
+// The most detailed level logged.
+pub fn level(verbose: bool, quiet: bool) -> Result<Level, String> {
+    match (verbose, quiet) {
+        (true, true) => Err("--verbose and --quiet cannot be used together".to_string()),
+        (true, false) => Ok(Level::DEBUG),
+        (false, true) => Ok(Level::WARN),
+        (false, false) => Ok(Level::INFO),
+    }
+}
+
+// Install the logger of the program, once.
+pub fn init(level: Level, format: LogFormat) {
+    let span_events = if level == Level::DEBUG { FmtSpan::CLOSE } else { FmtSpan::NONE };
+    let builder = tracing_subscriber::fmt().with_max_level(level).with_writer(io::stderr).with_target(false).with_span_events(span_events);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the levels chosen by the switches and the parsing of the formats.
+    #[test]
+    fn test_log_level() {
+        assert_eq!(level(false, false), Ok(Level::INFO));
+        assert_eq!(level(true, false), Ok(Level::DEBUG));
+        assert_eq!(level(false, true), Ok(Level::WARN));
+        assert!(level(true, true).is_err());
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
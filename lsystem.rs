@@ -0,0 +1,172 @@
+// L-systems: a string of symbols is rewritten a number of times, every symbol having a rule being
+// replaced by its replacement at once, and the result is drawn by a turtle reading it symbol by symbol:
+//
+//     F, G  move forward one step, drawing a line
+//     f     move forward without drawing
+//     +, -  turn left or right by the angle
+//     |     turn around
+//     [, ]  save the position and heading, and go back to the last one saved
+//
+// Other symbols draw nothing and only take part in the rewriting. The turtle starts heading up, and
+// the drawing is fitted into the image, so the length of a step does not matter. A grammar is read
+// from a file in the format of config::load_document, for example a plant:
+//
+//     # plant.lsys
+//     axiom = "X"
+//     angle = 25
+//     iterations = 6
+//
+//     [[rule]]
+//     symbol = "X"
+//     replacement = "F+[[X]-X]-F[-FX]+X"
+//
+//     [[rule]]
+//     symbol = "F"
+//     replacement = "FF"
+//
+// the Heighway dragon (axiom "FX", angle 90, X → "X+YF+", Y → "-FX-Y") or the Hilbert curve (axiom
+// "A", angle 90, A → "+BF-AFA-FB+", B → "-AF+BFB-FA+").
+use crate::config::{self, Document};
+use std::collections::HashMap;
+
+// Longer strings would take more memory than the drawings are worth.
+pub const MAX_SYMBOLS: usize = 10_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LSystem {
+    pub axiom: String,
+    pub rules: HashMap<char, String>,
+    pub angle: f64, // In degrees
+    pub iterations: u32,
+}
+
+impl LSystem {
+    pub fn from_document(document: &Document) -> Result<Self, String> {
+        let options = &document.options;
+        let axiom = options.get("axiom").ok_or("An L-system needs an axiom")?.clone();
+        let angle = match options.get("angle") {
+            Some(angle) => angle.parse::<f64>().ok().filter(|a| a.is_finite()).ok_or(format!("Invalid angle '{}'", angle))?,
+            None => 90.0,
+        };
+        let iterations = match options.get("iterations") {
+            Some(iterations) => iterations.parse().map_err(|_| format!("Invalid iterations '{}'", iterations))?,
+            None => 4,
+        };
+        let mut rules = HashMap::new();
+        for table in &document.tables {
+            if table.name != "rule" {
+                return Err(format!("Unexpected table [{}], the rules are [[rule]] tables", table.name));
+            }
+            let mut symbols = table.options.get("symbol").ok_or("Every rule needs a symbol")?.chars();
+            let (Some(symbol), None) = (symbols.next(), symbols.next()) else {
+                return Err("The symbol of a rule is one character".to_string());
+            };
+            let replacement = table.options.get("replacement").ok_or_else(|| format!("The rule of {} needs a replacement", symbol))?;
+            if rules.insert(symbol, replacement.clone()).is_some() {
+                return Err(format!("The symbol {} has two rules", symbol));
+            }
+        }
+        Ok(Self { axiom, rules, angle, iterations })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_document(&config::load_document(path)?)
+    }
+
+    // The string after the rewritings.
+    pub fn expand(&self) -> Result<String, String> {
+        let mut current = self.axiom.clone();
+        for iteration in 0..self.iterations {
+            let mut next = String::with_capacity(current.len() * 2);
+            for symbol in current.chars() {
+                match self.rules.get(&symbol) {
+                    Some(replacement) => next.push_str(replacement),
+                    None => next.push(symbol),
+                }
+                if next.len() > MAX_SYMBOLS {
+                    return Err(format!("The L-system grows beyond {} symbols at iteration {}, use fewer iterations", MAX_SYMBOLS, iteration + 1));
+                }
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+
+    // The lines the turtle draws, one polyline for every stretch drawn without lifting the pen.
+    pub fn draw(&self) -> Result<Vec<Vec<(f64, f64)>>, String> {
+        let angle = self.angle.to_radians();
+        let (mut position, mut heading) = ((0.0, 0.0), std::f64::consts::FRAC_PI_2);
+        let (mut stack, mut paths) = (Vec::new(), Vec::new());
+        let mut path = vec![position];
+        // A new polyline starts wherever the pen was lifted
+        let lift = |path: &mut Vec<(f64, f64)>, paths: &mut Vec<Vec<(f64, f64)>>, start| {
+            let finished = std::mem::replace(path, vec![start]);
+            if finished.len() > 1 {
+                paths.push(finished);
+            }
+        };
+        for symbol in self.expand()?.chars() {
+            match symbol {
+                'F' | 'G' | 'f' => {
+                    let (x, y): (f64, f64) = position;
+                    position = (x + heading.cos(), y + heading.sin());
+                    if symbol == 'f' {
+                        lift(&mut path, &mut paths, position);
+                    } else {
+                        path.push(position);
+                    }
+                }
+                '+' => heading += angle,
+                '-' => heading -= angle,
+                '|' => heading += std::f64::consts::PI,
+                '[' => stack.push((position, heading)),
+                ']' => {
+                    (position, heading) = stack.pop().ok_or("A ] closes no [ in the L-system")?;
+                    lift(&mut path, &mut paths, position);
+                }
+                _ => {}
+            }
+        }
+        lift(&mut path, &mut paths, position);
+        if paths.is_empty() {
+            return Err("The L-system draws nothing, it needs an F or a G".to_string());
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(text: &str) -> LSystem {
+        LSystem::from_document(&config::parse_document(text).unwrap()).unwrap()
+    }
+
+    /// Tests the rewriting of the algae system of Lindenmayer, whose lengths are the Fibonacci numbers.
+    #[test]
+    fn test_expand() {
+        let algae = system("axiom = A\niterations = 4\n[[rule]]\nsymbol = A\nreplacement = AB\n[[rule]]\nsymbol = B\nreplacement = A\n");
+        assert_eq!(algae.expand().unwrap(), "ABAABABA");
+        let growing = LSystem { iterations: 30, ..system("axiom = F\n[[rule]]\nsymbol = F\nreplacement = FF\n") };
+        assert!(growing.expand().is_err());
+        assert!(LSystem::from_document(&config::parse_document("angle = 90").unwrap()).is_err());
+        assert!(LSystem::from_document(&config::parse_document("axiom = F\n[[rule]]\nsymbol = FF\nreplacement = F").unwrap()).is_err());
+    }
+
+    /// Tests the turtle: a square from four turns, and the branches of a bracketed system as separate lines.
+    #[test]
+    fn test_draw() {
+        let square = system("axiom = F+F+F+F\niterations = 0\n").draw().unwrap();
+        assert_eq!(square.len(), 1);
+        let last = square[0][4];
+        assert!(last.0.abs() < 1e-12 && last.1.abs() < 1e-12, "{:?}", last);
+        let (x, y) = square[0][1];
+        assert!(x.abs() < 1e-12 && (y - 1.0).abs() < 1e-12);
+        let branches = system("axiom = F[+F]F\niterations = 0\nangle = 90").draw().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].len(), 3); // The stem up to the branch, and the branch
+        assert!(system("axiom = F]\niterations = 0").draw().is_err());
+        assert!(system("axiom = X\niterations = 0").draw().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+// Markus–Lyapunov fractals: the logistic map x ← r·x·(1 − x) is forced by a sequence such as "AB",
+// where r takes the value a on the A steps and b on the B steps. Each pixel is an (a, b) pair and is
+// colored by the Lyapunov exponent of the orbit: negative exponents mean a stable orbit, positive
+// ones mean chaos, and the two are drawn with separate gradients.
+use colorgrad::Gradient;
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+// Serialized with its sequence written in letters, and deserialized through Lyapunov::new.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "LyapunovFields", into = "LyapunovFields")]
+pub struct Lyapunov {
+    sequence: Vec<bool>, // false for A, true for B
+    pub warmup: u32,
+    pub iterations: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LyapunovFields {
+    sequence: String,
+    warmup: u32,
+    iterations: u32,
+}
+
+impl TryFrom<LyapunovFields> for Lyapunov {
+    type Error = String;
+
+    fn try_from(fields: LyapunovFields) -> Result<Self, String> {
+        Self::new(&fields.sequence, fields.warmup, fields.iterations)
+    }
+}
+
+impl From<Lyapunov> for LyapunovFields {
+    fn from(lyapunov: Lyapunov) -> Self {
+        let sequence = lyapunov.sequence.iter().map(|&b| if b { 'B' } else { 'A' }).collect();
+        Self { sequence, warmup: lyapunov.warmup, iterations: lyapunov.iterations }
+    }
+}
+
+impl Lyapunov {
+    // Build the generator from a sequence made of the letters A and B.
+    pub fn new(sequence: &str, warmup: u32, iterations: u32) -> Result<Self, String> {
+        let sequence = sequence
+            .chars()
+            .map(|letter| match letter.to_ascii_uppercase() {
+                'A' => Ok(false),
+                'B' => Ok(true),
+                other => Err(format!("Invalid letter '{}' in the sequence, only A and B are allowed", other)),
+            })
+            .collect::<Result<Vec<bool>, String>>()?;
+        if sequence.is_empty() {
+            return Err("The sequence must contain at least one letter".to_string());
+        }
+        if iterations == 0 {
+            return Err("The number of iterations must be positive".to_string());
+        }
+        Ok(Self { sequence, warmup, iterations })
+    }
+
+    // Compute the Lyapunov exponent for the pair (a, b), starting from x = 0.5.
+    pub fn exponent(&self, a: f64, b: f64) -> f64 {
+        let rate = |n: u32| if self.sequence[n as usize % self.sequence.len()] { b } else { a };
+        let mut x = 0.5;
+
+        // Let the orbit settle before measuring it.
+        for n in 0..self.warmup {
+            x = rate(n) * x * (1.0 - x);
+        }
+
+        let mut sum = 0.0;
+        for n in self.warmup..self.warmup + self.iterations {
+            let r = rate(n);
+            x = r * x * (1.0 - x);
+            // The derivative of the map is r·(1 − 2x), its log growth rate is the exponent.
+            sum += (r * (1.0 - 2.0 * x)).abs().ln();
+        }
+        sum / self.iterations as f64
+    }
+}
+
+// Colors stable (negative) and chaotic (positive) exponents with two different gradients.
+pub struct LyapunovColorMap {
+    stable: Gradient,
+    chaotic: Gradient,
+}
+
+impl LyapunovColorMap {
+    pub fn new() -> Self {
+        Self { stable: colorgrad::inferno(), chaotic: colorgrad::blues() }
+    }
+
+    pub fn color(&self, exponent: f64) -> Rgb<u8> {
+        let color = if exponent.is_nan() || exponent < 0.0 {
+            // Very stable orbits (down to −∞ for superstable ones) get the brightest color.
+            let t = if exponent.is_nan() { 1.0 } else { (-exponent / 2.0).min(1.0) };
+            self.stable.at(t).to_rgba8()
+        } else {
+            // Chaotic orbits of the logistic map have exponents of at most about ln 2 ≈ 0.69.
+            self.chaotic.at(1.0 - (exponent / std::f64::consts::LN_2).min(1.0)).to_rgba8()
+        };
+        Rgb([color[0], color[1], color[2]])
+    }
+}
+
+// Render the fractal with a on the horizontal axis and b on the vertical axis.
+pub fn generate_lyapunov_fractal(width: u32, height: u32, lyapunov: &Lyapunov, bounds: (f32, f32, f32, f32)) -> RgbImage {
+    let color_map = LyapunovColorMap::new();
+    let mut img = RgbImage::new(width, height);
+    let (amin, amax, bmin, bmax) = bounds;
+    let scale_a = (amax - amin) as f64 / width as f64;
+    let scale_b = (bmax - bmin) as f64 / height as f64;
+
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        let a = px as f64 * scale_a + amin as f64;
+        let b = py as f64 * scale_b + bmin as f64;
+        *pixel = color_map.color(lyapunov.exponent(a, b));
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the sign of the exponent in the stable and chaotic regimes of the logistic map.
+    #[test]
+    fn test_exponent_sign() {
+        let lyapunov = Lyapunov::new("A", 200, 1000).unwrap();
+        assert!(lyapunov.exponent(2.8, 2.8) < 0.0); // Stable fixed point
+        assert!(lyapunov.exponent(3.2, 3.2) < 0.0); // Stable 2-cycle
+        assert!(lyapunov.exponent(3.9, 3.9) > 0.0); // Chaos
+    }
+
+    /// Tests that only A/B sequences are accepted.
+    #[test]
+    fn test_invalid_sequence() {
+        assert!(Lyapunov::new("ABC", 100, 100).is_err());
+        assert!(Lyapunov::new("", 100, 100).is_err());
+        assert!(Lyapunov::new("abba", 100, 100).is_ok());
+    }
+
+    /// Tests that generators round-trip through serde, and that deserializing rejects what new does.
+    #[test]
+    fn test_serde_round_trip() {
+        let lyapunov = Lyapunov::new("AAB", 100, 500).unwrap();
+        let json = serde_json::to_string(&lyapunov).unwrap();
+        assert_eq!(serde_json::from_str::<Lyapunov>(&json).unwrap(), lyapunov);
+        assert!(serde_json::from_str::<Lyapunov>("{\"sequence\": \"\", \"warmup\": 100, \"iterations\": 100}").is_err());
+        assert!(serde_json::from_str::<Lyapunov>("{\"sequence\": \"AB\", \"warmup\": 100, \"iterations\": 0}").is_err());
+    }
+}
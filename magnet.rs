@@ -0,0 +1,84 @@
+// The Magnet fractals, from the renormalization of models of magnetism. Their formulas are rational
+// functions of z and c:
+//
+//     type 1    z ← ((z² + c − 1) / (2z + c − 2))²
+//     type 2    z ← ((z³ + 3(c − 1)z + (c − 1)(c − 2)) / (3z² + 3(c − 2)z + (c − 1)(c − 2) + 1))²
+//
+// iterated from z = 0, or from every pixel for their Julia sets. Besides escaping, orbits can converge
+// to the fixed point 1 shared by every c, which stops them like an escape (fractal::escape_or_converge),
+// so the set is what neither escapes nor reaches 1.
+use crate::complex::Complex;
+use crate::fractal::{self, Attractor, Dynamics, EscapeResult, OrbitObserver};
+
+// The orbits go well beyond |z| = 2 before coming back to 1, so they only count as escaped later.
+pub const MAGNET_BAILOUT: f64 = 100.0;
+// How close to 1 an orbit has to come to count as converged.
+pub const CONVERGENCE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Magnet {
+    TypeOne,
+    TypeTwo,
+}
+
+impl Magnet {
+    fn step(self, z: Complex, c: Complex) -> Complex {
+        let ratio = match self {
+            Magnet::TypeOne => (z * z + c - 1.0) / (z * 2.0 + c - 2.0),
+            Magnet::TypeTwo => {
+                let (a, b) = (c - 1.0, c - 2.0);
+                (z * z * z + a * z * 3.0 + a * b) / (z * z * 3.0 + b * z * 3.0 + a * b + 1.0)
+            }
+        };
+        ratio * ratio
+    }
+}
+
+impl Dynamics for Magnet {
+    // A pole of the formula gives an infinite z, which leaves the bailout circle like an escape.
+    fn iterate_from(&self, z0: Complex, c: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        let attractor = Attractor { point: Complex::ONE, tolerance: CONVERGENCE_TOLERANCE };
+        fractal::escape_or_converge(z0, max_iterations, bailout.max(MAGNET_BAILOUT), attractor, observer, |z| self.step(z, c))
+    }
+
+    // The coefficients of the formulas are real polynomials of c.
+    fn commutes_with_conjugation(&self) -> bool {
+        true
+    }
+
+    fn parameter_bounds(&self) -> (f32, f32, f32, f32) {
+        match self {
+            Magnet::TypeOne => (-4.0, 4.0, -3.0, 3.0),
+            Magnet::TypeTwo => (-1.5, 3.7, -1.95, 1.95),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::Fractal;
+
+    /// Tests that 1 is a fixed point of both formulas whatever c is.
+    #[test]
+    fn test_fixed_point() {
+        for magnet in [Magnet::TypeOne, Magnet::TypeTwo] {
+            for c in [Complex::new(0.5, 0.0), Complex::new(3.0, -1.0), Complex::new(-2.0, 2.5)] {
+                assert!((magnet.step(Complex::ONE, c) - Complex::ONE).norm() < 1e-12);
+            }
+        }
+    }
+
+    /// Tests that orbits stop either by converging to 1 or by escaping, and that others stay inside.
+    #[test]
+    fn test_convergence_and_escape() {
+        let converging = Magnet::TypeOne.iterate(Complex::new(1.5, 0.2), 500);
+        assert!(converging.converged && converging.iterations < 500);
+        assert!((converging.z - Complex::ONE).norm() < CONVERGENCE_TOLERANCE);
+        let escaping = Magnet::TypeOne.iterate(Complex::new(1.8, 0.0), 500);
+        assert!(!escaping.converged && escaping.iterations < 500);
+        assert!(escaping.z.norm() > MAGNET_BAILOUT);
+        let inside = Magnet::TypeOne.iterate(Complex::ZERO, 500);
+        assert!(!inside.converged && inside.iterations == 500);
+    }
+}
@@ -1,49 +1,126 @@
 /*
 Entry Point Explanation for the main function:
 
-Here's a breakdown of the key components and functionalities of the `main` function:
-1. User Input:
-   - The program prompts the user to choose between generating a chessboard or a Mandelbrot set by entering '1' or '2'.
-   - It captures the user's input as a string and processes it to decide the subsequent action.
-
-2. Match Statement:
-   - The match statement is used to handle different inputs:
-     - "1": Triggers the generation of a chessboard.
-     - "2": Leads to further prompts to decide between generating a colored or grayscale Mandelbrot set.
-
-3. Chessboard Generation:
-   - If "1" is chosen, the user is asked to specify the number of cells per side for the chessboard.
-   - The chessboard image is generated, saved to a file, and then displayed.
-
-4. Mandelbrot Set Generation:
-   - Choosing "2" initiates another loop asking for the type of Mandelbrot set to generate: colored or grayscale.
-   - Depending on the user's subsequent choice, the program either uses default bounds or prompts for custom bounds to generate the set.
-   - The image is then generated, saved, and displayed. If successful, the inner loop breaks.
-
-This setup ensures that the program remains responsive and interactive.
+1. Without arguments, the program shows a menu asking whether to generate a chessboard, a Mandelbrot set
+   or another test pattern by entering '1', '2' or '3', then asks for their parameters (pressing Enter keeps
+   the defaults). The image is saved and displayed; the viewer window of the Mandelbrot set explores it with
+   the mouse and the keyboard.
+
+2. With arguments, the menu is skipped and the command they name runs instead: one of COMMANDS below, run
+   by dispatch, or one of LINE_COMMANDS, which take more than their options.
+
+The commands, their options and the keys of the viewer are described in README.md.
 */
 
 
 // Import necessary modules and traits from local modules and external crates
 mod util;
-mod chessboard;
-mod mandelbrot;
+mod patterns;
+mod metadata;
+mod cli;
+mod cycling;
+mod video;
+mod reference_orbit;
+mod newton;
+mod formula;
+mod config;
+mod phoenix;
+mod magnet;
+mod collatz;
+mod nova;
+mod lyapunov;
+mod animation;
+mod apollonian;
+mod sweep;
+mod viewer;
+mod clipboard;
+mod text;
+mod axes;
+mod postprocess;
+mod bookmarks;
+mod palettes;
+mod session;
+mod batch;
+mod watch;
+mod pipe;
+mod report;
+mod bench;
+mod bifurcation;
+mod server;
+mod area;
+mod attractor;
+mod histogram;
+mod stats;
+mod interest;
+mod explore;
+mod compare;
+mod pyramid;
+mod stitch;
+mod jobs;
+mod checkpoint;
+mod logging;
+mod naming;
+mod buddhabrot;
+mod curves;
+mod density;
+mod ifs;
+mod lsystem;
+#[cfg(feature = "gui")]
+mod panel;
+#[cfg(feature = "scripting")]
+mod script;
 
+use final_exercice::{bounds, coloring, complex, error, fractal, mandelbrot, palette, rays, render};
 use image::RgbImage;
 use show_image::{create_window};
+use show_image::event::WindowEvent;
 use crate::util::to_showable_image;
 use crate::mandelbrot::{GrayscaleMap, ColoredColorMap, ColorMap};
+use crate::render::RenderOptions;
+use crate::cli::Args;
+use crate::fractal::{Dynamics, Fractal};
 use text_io::read;
+use std::collections::HashSet;
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use tracing::{debug, info, warn};
+
+// Entry point of the program. show_image keeps the main thread for the event loop of its windows and
+// runs the program in another thread, except for the control panel: eframe needs the main thread and
+// its own event loop. The panel offers the colorings of --plugin and the palettes of the user too.
+fn main() {
+    #[cfg(feature = "gui")]
+    if std::env::args().nth(1).as_deref() == Some("panel") {
+        let args = Args::parse(&std::env::args().skip(2).collect::<Vec<_>>());
+        if let Err(e) = load_extensions(&args, &mut HashSet::new()).and_then(|()| panel::run().map_err(Into::into)) {
+            report_error(&*e);
+            std::process::exit(exit_status(&*e));
+        }
+        return;
+    }
+    show_image::run_context(run_program)
+}
+
+// The program itself: a command when arguments are given, the menu otherwise.
+fn run_program() -> Result<(), Box<dyn Error>> {
+    // Run a command directly when arguments are given, instead of showing the menu
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = run_command(&args) {
+            report_error(&*e);
+            std::process::exit(exit_status(&*e));
+        }
+        return Ok(());
+    }
+    logging::init(tracing::Level::INFO, logging::LogFormat::Text);
 
-// Entry point of the program using show_image's macro for GUI applications
-#[show_image::main]
-fn main() -> Result<(), Box<dyn Error>> {
     // Infinite loop to keep asking for user input until valid input is given
     loop {
-        println!("Choose an option by inputing either: 1 or 2:");
+        println!("Choose an option by inputing either: 1, 2 or 3:");
         println!("1: Generate a chessboard");
         println!("2: Generate a Mandelbrot set");
+        println!("3: Generate a test pattern");
 
         let choice: String = read!();
         // Handle user input to determine the program's action
@@ -51,12 +128,13 @@ fn main() -> Result<(), Box<dyn Error>> {
             "1" => {
                 // Prompt for and read the number of cells for the chessboard
                 println!("Enter the number of cells:");
-                let cell_count: u32 = read!();
-                let image = chessboard::draw_square(cell_count);
+                let cell_count: u32 = read!("{}\n");
+                let options = read_pattern_options()?;
+                let image = patterns::draw_square(cell_count, &options)?;
                 let filename = format!("chessboard_{}x{}.png", cell_count, cell_count);
                 // Save and display the generated chessboard image
                 image.save(&filename)?;
-                println!("Chessboard saved as {}", filename);
+                info!("Chessboard saved as {}", filename);
                 display_image(image)?;
                 break; // Exit loop after displaying and saving the image
             },
@@ -67,25 +145,40 @@ fn main() -> Result<(), Box<dyn Error>> {
                     let color_choice: String = read!("{}\n");
 
                     if color_choice.trim() == "c" || color_choice.trim() == "gs" {
-                        let max_iterations = 100; // Or other appropriate value
                          // Determine the bounds for the Mandelbrot set based on user input
                         let bounds = if color_choice.trim() == "gs" {
-                            println!("Enter the space to display in the format xmin;xmax;ymin;ymax:");
-                            let input: String = read!("{}\n");
-                            parse_bounds(&input).unwrap_or((-2.0, 2.0, -1.5, 1.5))
+                            read_bounds((-2.0, 2.0, -1.5, 1.5))
                         } else {
                             (-2.0, 2.0, -1.5, 1.5) // Default bounds for colored
                         };
+                        let bounds = bounds::Framing::new(bounds, 800, 600, bounds::AspectPolicy::default()).bounds;
+                        let max_iterations = read_iterations(bounds);
 
-                        let image = generate_mandelbrot_set(color_choice.clone(), max_iterations, bounds);
-                        let filename = if color_choice.trim() == "c" {
-                            "colored_mandelbrot.png"
+                        let image = generate_mandelbrot_set(color_choice.clone(), max_iterations, bounds, (800, 600));
+                        let (default_name, palette) = if color_choice.trim() == "c" {
+                            ("colored_mandelbrot.png", "turbo")
                         } else {
-                            "grayscale_mandelbrot.png"
+                            ("grayscale_mandelbrot.png", mandelbrot::GRAYSCALE)
                         };
-                        image.save(filename)?;
-                        println!("Mandelbrot set saved as {}", filename);
-                        display_image(image)?;
+                        let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").region(bounds, mandelbrot::DEFAULT_VIEW_WIDTH);
+                        let tokens = tokens.with("iter", max_iterations).size(800, 600).with("palette", palette);
+                        let filename = read_file_name(default_name, &tokens);
+                        let filename = filename.as_str();
+                        // Store the parameters in the PNG so the image can be reproduced later
+                        let options = RenderOptions::builder().size(800, 600).bounds(bounds).iterations(max_iterations).palette(palette).build()?;
+                        metadata::save_with_metadata(&image, filename, &options)?;
+                        info!("Mandelbrot set saved as {}", filename);
+                        println!("Double-click a point in the window to center the view on it, or drag a box to zoom on it.");
+                        println!("Drag with the right button to pan, M shows a minimap of where the view is, A the axes.");
+                        println!("Arrow keys pan, +/- zoom, I/K change the iteration limit, H toggles the status line.");
+                        println!("Backspace or Z undoes the last move, Shift+Z redoes it, B saves a bookmark.");
+                        println!("C switches the box zoom between showing the whole box and filling the view with it.");
+                        println!("Space cycles the palette, [ and ] change the cycling speed.");
+                        println!("Tab picks a value to tune (iterations, palette offset and scale, Julia c), comma and period change it.");
+                        println!("F11 toggles fullscreen, P the presentation mode without cursor nor status line.");
+                        println!("S saves the view at 1920 pixels wide with antialiasing, Ctrl+C copies its coordinates.");
+                        let view = viewer::View::from_bounds(bounds, max_iterations, 800, 600);
+                        viewer::Viewer::new(view, palette.to_string(), image)?.run()?;
                         break; // Exit loop after displaying and saving the image
                     } else {
                         println!("Invalid color option. Please enter 'c' for colored or 'gs' for grayscale.");
@@ -93,39 +186,1572 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
                 break; // Exit loop after handling Mandelbrot set
             },
+            "3" => {
+                println!("Enter the pattern: {}", patterns::PATTERN_NAMES.join(", "));
+                let name: String = read!("{}\n");
+                let name = name.trim().to_string();
+                // Chessboards count cells, circles count rings, stars count spokes, the Sierpinski
+                // patterns their depth and the curves their order
+                let count: u32 = if patterns::Pattern::has_count(&name) {
+                    println!("Enter the number of cells, rings, spokes, recursion levels or the curve order:");
+                    read!("{}\n")
+                } else {
+                    0
+                };
+                let mut pattern = patterns::Pattern::from_name(&name, count)?;
+                if let Some(stroke) = pattern.stroke_mut() {
+                    println!("Enter the stroke width in pixels (press Enter for half the spacing of the lines):");
+                    let input: String = read!("{}\n");
+                    if !input.trim().is_empty() {
+                        *stroke = input.trim().parse().map_err(|_| format!("Invalid stroke width '{}'", input.trim()))?;
+                    }
+                }
+                let options = read_pattern_options()?;
+                let image = patterns::draw_pattern(pattern, &options)?;
+                let filename = format!("pattern_{}.png", name);
+                image.save(&filename)?;
+                info!("Pattern saved as {}", filename);
+                display_image(image)?;
+                break;
+            },
             _ => {
                 // Handle incorrect option entries
-                println!("Invalid option, please enter '1' or '2'.");
+                println!("Invalid option, please enter '1', '2' or '3'.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Print the error a command failed with, and under it how to get past it when the renderer knows.
+fn report_error(e: &(dyn Error + 'static)) {
+    eprintln!("Error: {}", describe_error(e));
+}
+
+// The message of an error, followed on another line by its hint when it has one.
+fn describe_error(e: &(dyn Error + 'static)) -> String {
+    match e.downcast_ref::<error::Error>().and_then(error::Error::hint) {
+        Some(hint) => format!("{}\nHint: {}", e, hint),
+        None => e.to_string(),
+    }
+}
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+// The exit status of a command that failed: EXIT_USAGE when its command line could not be read (an
+// unknown command, a malformed option or config file), EXIT_FAILURE when the command itself failed.
+fn exit_status(e: &(dyn Error + 'static)) -> i32 {
+    let usage = e.is::<cli::UsageError>() || matches!(e.downcast_ref::<error::Error>(), Some(error::Error::Parse { .. }));
+    if usage { EXIT_USAGE } else { EXIT_FAILURE }
+}
+
+// Load the plugin libraries of --plugin, separated like the directories of PATH, and register the palettes
+// of --palette-file, or else of the palettes file of the user when there is one. The files already in
+// `loaded` are left out, their colorings and palettes being registered already.
+fn load_extensions(args: &Args, loaded: &mut HashSet<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for path in args.value("plugin").map(std::env::split_paths).into_iter().flatten() {
+        if !loaded.contains(&path) {
+            load_plugin(&path)?;
+            loaded.insert(path);
+        }
+    }
+    let path = match args.value("palette-file") {
+        Some(path) => PathBuf::from(path),
+        None => match palettes::default_path() {
+            Ok(path) if path.exists() => path,
+            _ => return Ok(()),
+        },
+    };
+    if !loaded.contains(&path) {
+        let names = palettes::register_file(&path.to_string_lossy())?;
+        debug!("Registered the palettes {} from {}", names.join(", "), path.display());
+        loaded.insert(path);
+    }
+    Ok(())
+}
+
+// Register the colorings of a plugin library.
+#[cfg(feature = "plugins")]
+fn load_plugin(path: &Path) -> Result<(), Box<dyn Error>> {
+    let names = final_exercice::plugins::load_plugin(path)?;
+    info!("Loaded the colorings {} from {}", names.join(", "), path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugin(path: &Path) -> Result<(), Box<dyn Error>> {
+    Err(format!("Cannot load {}: the program was built without the plugins feature", path.display()).into())
+}
+
+// The options of a command line, those of its config file filling in whatever was not given
+fn command_args(raw_args: &[String]) -> Result<Args, Box<dyn Error>> {
+    let mut args = Args::parse(&raw_args[1..]);
+    if let Some(path) = args.value("config") {
+        let defaults = config::load_config(path).map_err(cli::UsageError)?;
+        args.merge_defaults(defaults);
+    }
+    Ok(args)
+}
+
+// Render again, saving the output and showing it in a window, whenever the config file changes, until the
+// window is closed. A render that fails is reported and the file is watched for a fix.
+fn watch_render(raw_args: &[String], args: &Args) -> Result<(), Box<dyn Error>> {
+    if raw_args[0] != "render" {
+        return Err(format!("--watch re-renders the render command, not {}", raw_args[0]).into());
+    }
+    let mut file = watch::FileWatch::new(args.value("config").ok_or("--watch renders again when the --config file changes, give one")?);
+    let window = create_window(format!("Watching {}", file.path()), Default::default())?;
+    let events = window.event_channel()?;
+    loop {
+        match command_args(raw_args).and_then(|args| render_image(&args)) {
+            Ok(image) => window.set_image("image-001", to_showable_image(&image))?,
+            Err(e) => report_error(&*e),
+        }
+        info!("Watching {} for changes", file.path());
+        loop {
+            match events.recv_timeout(watch::POLL) {
+                Ok(WindowEvent::CloseRequested(_) | WindowEvent::Destroyed(_)) => return Ok(()),
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
             }
+            if file.changed() {
+                std::thread::sleep(watch::SETTLE);
+                file.changed();
+                break;
+            }
+        }
+    }
+}
+
+// Dispatch a command given on the command line
+fn run_command(raw_args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = command_args(raw_args)?;
+    let level = logging::level(args.flag("verbose"), args.flag("quiet"))?;
+    logging::init(level, args.parse_value("log-format")?.unwrap_or_default());
+    let mut loaded = HashSet::new();
+    load_extensions(&args, &mut loaded)?;
+    match raw_args[0].as_str() {
+        "pipe" => run_pipe(loaded),
+        _ => execute(raw_args, &args),
+    }
+}
+
+// Run a command line once its options are read and the logging set up
+fn execute(raw_args: &[String], args: &Args) -> Result<(), Box<dyn Error>> {
+    if args.flag("watch") {
+        return watch_render(raw_args, args);
+    }
+    match raw_args[0].as_str() {
+        "save-session" => save_session(&raw_args[1..]),
+        "load-session" => load_session(args),
+        "batch" => batch(args),
+        #[cfg(not(feature = "gui"))]
+        "panel" => Err("Cannot open the control panel: the program was built without the gui feature".into()),
+        command => dispatch(command, args),
+    }
+}
+
+// Run the command lines read from stdin, each followed by its end marker. The plugins and palette files
+// of a job are loaded the first time one names them, the logging is set up once by the pipe command.
+fn run_pipe(mut loaded: HashSet<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let jobs = pipe::run(std::io::stdin().lock(), std::io::stdout(), |words| {
+        if words[0] == "pipe" {
+            return Err("A pipe cannot run another pipe".to_string());
+        }
+        let args = command_args(words).map_err(|e| describe_error(&*e))?;
+        if args.flag("watch") {
+            return Err("--watch never ends, it cannot run in a pipe".to_string());
+        }
+        if let Some(name) = ["verbose", "quiet", "log-format"].into_iter().find(|name| args.flag(name)) {
+            return Err(format!("--{} sets the logging of the whole pipe, give it to the pipe command", name));
+        }
+        load_extensions(&args, &mut loaded).and_then(|()| execute(words, &args)).map_err(|e| describe_error(&*e))
+    })?;
+    info!("Ran {} jobs from stdin", jobs);
+    Ok(())
+}
+
+// The rendering commands, run by dispatch.
+type Command = fn(&Args) -> Result<(), Box<dyn Error>>;
+const COMMANDS: &[(&str, Command)] = &[
+    ("reproduce", reproduce),
+    ("cycle-gif", cycle_gif),
+    ("zoom-video", zoom_video),
+    ("newton", newton_fractal),
+    ("formula", formula_fractal),
+    ("render", render_fractal),
+    ("animate", animate),
+    ("julia-sweep", julia_sweep),
+    ("bookmarks", open_bookmark),
+    ("serve", serve_tiles),
+    ("dual", dual_view),
+    ("area", estimate_area),
+    ("interesting", find_interesting),
+    ("explore", explore_gallery),
+    ("compare-palettes", compare_palettes),
+    ("palettes", palettes_command),
+    ("pyramid", export_pyramid),
+    ("stitch", stitch_tiles),
+    ("split-job", split_job),
+    ("run-job", run_job),
+    ("merge-job", merge_job),
+    ("buddhabrot", render_buddhabrot),
+    ("script", run_script),
+    ("koch", render_koch),
+    ("fern", render_fern),
+    ("ifs", render_ifs_file),
+    ("lsystem", render_lsystem),
+    ("attractor", render_attractor),
+    ("apollonian", render_apollonian),
+    ("dragon", render_dragon),
+    ("bench", run_bench),
+];
+
+// Other names of commands.
+const ALIASES: &[(&str, &str)] = &[("palette", "palettes")];
+
+// The commands run before dispatch, by execute, run_command and main.
+const LINE_COMMANDS: &[&str] = &["save-session", "load-session", "batch", "pipe", "panel"];
+
+// Run one of the rendering commands
+fn dispatch(command: &str, args: &Args) -> Result<(), Box<dyn Error>> {
+    let command = ALIASES.iter().find(|(alias, _)| *alias == command).map_or(command, |(_, name)| *name);
+    match COMMANDS.iter().find(|(name, _)| *name == command) {
+        Some((_, run)) => run(args),
+        None => {
+            let names: Vec<&str> = COMMANDS.iter().map(|(name, _)| *name).chain(LINE_COMMANDS.iter().copied()).collect();
+            Err(cli::UsageError(format!("Unknown command '{}'. Available commands: {}", command, names.join(", "))).into())
+        }
+    }
+}
+
+// Time the rendering of a view
+fn run_bench(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, bench::BENCH_SIZE)?;
+    Ok(bench::run(width, height, args.parse_value("runs")?.unwrap_or(bench::BENCH_RUNS))?)
+}
+
+// Render the Barnsley fern by the chaos game, shaded by how often its points are visited
+fn render_fern(args: &Args) -> Result<(), Box<dyn Error>> {
+    render_ifs(args, &ifs::Ifs::barnsley_fern(), "barnsley-fern", (500, 800))
+}
+
+// Render the iterated function system of the file given to the ifs command
+fn render_ifs_file(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: ifs <system.ifs> [--points] [--palette] [--size] [--bounds] [--seed] [--output]")?;
+    render_ifs(args, &ifs::Ifs::load(path)?, "ifs", (800, 800))
+}
+
+// Play the chaos game of a system and color the density of its points
+fn render_ifs(args: &Args, system: &ifs::Ifs, name: &'static str, default_size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, default_size)?;
+    let mut random = area::Random::new(seed_arg(args)?);
+    let bounds = match args.value("bounds") {
+        Some(_) => bounds_arg(args, (0.0, 1.0, 0.0, 1.0))?,
+        None => system.bounds(&mut random)?,
+    };
+    let bounds = bounds::Framing::new(bounds, width, height, bounds::AspectPolicy::Expand).bounds;
+    let points = args.parse_value("points")?.unwrap_or(ifs::DEFAULT_POINTS);
+    let palette_name = args.value("palette").unwrap_or("viridis");
+    let palette = palette::Palette::by_name(palette_name)?.with_transform(coloring_options_arg(args)?.palette_transform)?;
+    let mut density = density::Density::new(width, height, bounds);
+    system.chaos_game(points, &mut random, &mut density);
+    let tokens = naming::NameTokens::new().with("fractal", name).size(width, height).with("palette", palette_name);
+    let output = output_arg(args, &format!("{}.png", name.replace('-', "_")), &tokens)?;
+    let mut image = density.image(&palette);
+    post_process_arg(args, "axes")?.apply(&mut image, Some(bounds), &tokens)?;
+    image.save(&output)?;
+    info!("{} points of the {} saved as {}", points, name, output);
+    Ok(())
+}
+
+// Render a strange attractor, its parameters given as options
+fn render_attractor(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut attractor = attractor::Attractor::by_name(args.positional(0).unwrap_or("clifford"))?;
+    for (name, value) in attractor.parameters_mut() {
+        if let Some(given) = args.parse_value(name)? {
+            *value = given;
+        }
+    }
+    attractor.check()?;
+    let (width, height) = size_arg(args, (800, 800))?;
+    let bounds = match args.value("bounds") {
+        Some(_) => bounds_arg(args, (0.0, 1.0, 0.0, 1.0))?,
+        None => attractor.bounds()?,
+    };
+    let bounds = bounds::Framing::new(bounds, width, height, bounds::AspectPolicy::Expand).bounds;
+    let points = args.parse_value("points")?.unwrap_or(attractor::DEFAULT_POINTS);
+    let palette_name = args.value("palette").unwrap_or("inferno");
+    let palette = palette::Palette::by_name(palette_name)?.with_transform(coloring_options_arg(args)?.palette_transform)?;
+    let mut density = density::Density::new(width, height, bounds);
+    attractor.render(points, &mut density);
+    let tokens = naming::NameTokens::new().with("fractal", attractor.name()).size(width, height).with("palette", palette_name);
+    let output = output_arg(args, &format!("{}_attractor.png", attractor.name().replace('-', "_")), &tokens)?;
+    let mut image = density.image(&palette);
+    post_process_arg(args, "axes")?.apply(&mut image, Some(bounds), &tokens)?;
+    image.save(&output)?;
+    info!("{} points of the {} attractor saved as {}", points, attractor.name(), output);
+    Ok(())
+}
+
+// Draw the Apollonian gasket, its circles colored by generation
+fn render_apollonian(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 800))?;
+    let depth = args.parse_value("depth")?.unwrap_or(apollonian::DEFAULT_DEPTH);
+    let palette_name = args.value("palette").unwrap_or("viridis");
+    let palette = palette::Palette::by_name(palette_name)?.with_transform(coloring_options_arg(args)?.palette_transform)?;
+    let background = args.value("background").map_or(Ok(image::Rgb([0, 0, 0])), patterns::parse_hex_color)?;
+    let mut image = apollonian::render(width, height, depth, &palette, background)?;
+    let tokens = naming::NameTokens::new().with("fractal", "apollonian").with("iter", depth).size(width, height).with("palette", palette_name);
+    let output = output_arg(args, "apollonian.png", &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut image, None, &tokens)?;
+    image.save(&output)?;
+    info!("Apollonian gasket of depth {} saved as {}", depth, output);
+    Ok(())
+}
+
+// Draw the Heighway dragon, colored from its first segment to its last
+fn render_dragon(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 800))?;
+    let depth = args.parse_value("depth")?.unwrap_or(12);
+    let stroke = stroke_arg(args)?;
+    let points = curves::dragon(depth)?;
+    let tokens = naming::NameTokens::new().with("fractal", "dragon").with("iter", depth).size(width, height);
+    let output = output_arg(args, "dragon.png", &tokens)?;
+    save_curves(args, &[points], (width, height), &stroke, curve_palette_arg(args, Some("turbo"))?.as_ref(), &output, &tokens)?;
+    info!("Dragon curve of depth {} saved as {}", depth, output);
+    Ok(())
+}
+
+// Draw the Koch snowflake or curve, as an image or as an SVG file when the output ends in .svg
+fn render_koch(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 800))?;
+    let shape = args.parse_value("shape")?.unwrap_or(curves::KochShape::Snowflake);
+    let depth = args.parse_value("depth")?.unwrap_or(4);
+    let stroke = stroke_arg(args)?;
+    let points = curves::koch(shape, depth)?;
+    let (name, shape_name) = match shape {
+        curves::KochShape::Snowflake => ("koch-snowflake", "snowflake"),
+        curves::KochShape::Curve => ("koch-curve", "curve"),
+    };
+    let tokens = naming::NameTokens::new().with("fractal", name).with("iter", depth).size(width, height);
+    let output = output_arg(args, &format!("{}.png", name.replace('-', "_")), &tokens)?;
+    save_curves(args, &[points], (width, height), &stroke, curve_palette_arg(args, None)?.as_ref(), &output, &tokens)?;
+    info!("Koch {} of depth {} saved as {}", shape_name, depth, output);
+    Ok(())
+}
+
+// Draw the L-system of the file given to the lsystem command, as an image or an SVG file
+fn render_lsystem(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: lsystem <grammar.lsys> [--iterations] [--stroke] [--color] [--background] [--size] [--output]")?;
+    let mut system = lsystem::LSystem::load(path)?;
+    if let Some(iterations) = args.parse_value("iterations")? {
+        system.iterations = iterations;
+    }
+    let (width, height) = size_arg(args, (800, 800))?;
+    let stroke = stroke_arg(args)?;
+    let paths = system.draw()?;
+    let stem = std::path::Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("lsystem");
+    let tokens = naming::NameTokens::new().with("fractal", stem).with("iter", system.iterations).size(width, height);
+    let output = output_arg(args, &format!("{}.png", stem), &tokens)?;
+    save_curves(args, &paths, (width, height), &stroke, curve_palette_arg(args, None)?.as_ref(), &output, &tokens)?;
+    info!("L-system {} after {} iterations saved as {}", path, system.iterations, output);
+    Ok(())
+}
+
+// How the line fractals are drawn: `--stroke` pixels wide, in `--color` on `--background`
+fn stroke_arg(args: &Args) -> Result<curves::Stroke, Box<dyn Error>> {
+    let default = curves::Stroke::default();
+    let color = |name: &str, default| args.value(name).map_or(Ok(default), patterns::parse_hex_color);
+    let stroke = curves::Stroke {
+        width: args.parse_value("stroke")?.unwrap_or(default.width),
+        color: color("color", default.color)?,
+        background: color("background", default.background)?,
+    };
+    stroke.check()?;
+    Ok(stroke)
+}
+
+// Write curves as an SVG file or an image, by the extension of the output. Only the image is post-processed.
+fn save_curves(
+    args: &Args,
+    paths: &[Vec<(f64, f64)>],
+    (width, height): (u32, u32),
+    stroke: &curves::Stroke,
+    palette: Option<&palette::Palette>,
+    output: &str,
+    tokens: &naming::NameTokens,
+) -> Result<(), Box<dyn Error>> {
+    let post = post_process_arg(args, "axes")?;
+    if output.to_ascii_lowercase().ends_with(".svg") {
+        if !post.is_empty() {
+            return Err(cli::UsageError("--annotate, --watermark and --axes draw over images, not SVG files".to_string()).into());
+        }
+        std::fs::write(output, curves::svg(paths, width, height, stroke, palette)?)?;
+    } else {
+        let mut image = curves::render(paths, width, height, stroke, palette)?;
+        post.apply(&mut image, None, tokens)?;
+        image.save(output)?;
+    }
+    Ok(())
+}
+
+// The palette the line fractals are colored along with `--palette`, in place of the stroke color
+fn curve_palette_arg(args: &Args, default: Option<&str>) -> Result<Option<palette::Palette>, Box<dyn Error>> {
+    match args.value("palette").or(default) {
+        Some(name) => Ok(Some(palette::Palette::by_name(name)?.with_transform(coloring_options_arg(args)?.palette_transform)?)),
+        None => Ok(None),
+    }
+}
+
+// Render the density of the orbits of random points: a Buddhabrot, an Anti-Buddhabrot or a Nebulabrot
+fn render_buddhabrot(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mode = args.parse_value("mode")?.unwrap_or(buddhabrot::Mode::Buddhabrot);
+    let (width, height) = size_arg(args, (800, 600))?;
+    let framing = framing_arg(args, buddhabrot::BUDDHABROT_BOUNDS, width, height)?;
+    let default_iterations = match mode {
+        buddhabrot::Mode::Nebula => buddhabrot::NEBULA_ITERATIONS.to_vec(),
+        _ => vec![buddhabrot::DEFAULT_ITERATIONS],
+    };
+    let iterations = args.parse_list("iterations")?.unwrap_or(default_iterations);
+    let samples = args.parse_list("samples")?.unwrap_or(vec![buddhabrot::DEFAULT_SAMPLES]);
+    let channels = mode.channels(&iterations, &samples, args.parse_value("min-iterations")?.unwrap_or(0))?;
+    let default = buddhabrot::Exposure::default();
+    let exposure = buddhabrot::Exposure {
+        exposure: args.parse_value("exposure")?.unwrap_or(default.exposure),
+        gamma: args.parse_value("gamma")?.unwrap_or(default.gamma),
+    };
+    let seed = seed_arg(args)?;
+
+    let mut image = buddhabrot::render(&channels, width, height, framing.bounds, exposure, seed)?;
+    framing.apply(&mut image);
+    let iter = iterations.iter().map(u32::to_string).collect::<Vec<_>>().join("-");
+    let tokens = naming::NameTokens::new().with("fractal", mode.name()).region(framing.bounds, mandelbrot::DEFAULT_VIEW_WIDTH);
+    let tokens = tokens.with("iter", iter).size(width, height);
+    let output = output_arg(args, "{fractal}.png", &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut image, Some(framing.bounds), &tokens)?;
+    image.save(&output)?;
+    let orbits: u64 = channels.iter().map(|channel| channel.samples).sum();
+    info!("{} of {} sampled points saved as {}, seed {}", mode.name(), orbits, output, seed);
+    Ok(())
+}
+
+// Run a rhai script of renders, the positional arguments after it given to the script
+#[cfg(feature = "scripting")]
+fn run_script(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: script <file.rhai> [ARGS...]")?;
+    Ok(script::run_script(path, &args.positionals()[1..])?)
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_script(_: &Args) -> Result<(), Box<dyn Error>> {
+    Err("The program was built without the scripting feature, build it with --features scripting".into())
+}
+
+// Save a command with all its options into a session file instead of running it
+fn save_session(raw_args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "Usage: save-session <session.conf> <command> [arguments] [options]";
+    let path = raw_args.first().ok_or(usage)?;
+    let command = raw_args.get(1).ok_or(usage)?;
+    let mut args = Args::parse(&raw_args[2..]);
+    if let Some(config_path) = args.value("config") {
+        args.merge_defaults(config::load_config(config_path)?);
+    }
+    let document = session::capture(command, &args)?;
+    session::save(path, &document)?;
+    info!("Session of '{}' saved as {}", command, path);
+    Ok(())
+}
+
+// Run the command saved in a session file, options on the command line replacing the saved ones
+fn load_session(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: load-session <session.conf> [options]")?;
+    let session = session::load(path, args)?;
+    if session.command == "animate" {
+        // The keyframes are part of the session itself
+        let animation = animation::Animation::from_document(&session.document)?;
+        return run_animation(&animation, &session.args);
+    }
+    dispatch(&session.command, &session.args)
+}
+
+// Run every job of a batch file, on `--parallel` threads, then report those that failed
+fn batch(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: batch <jobs.conf> [--parallel N] [options]")?;
+    let jobs = batch::load(path, args)?;
+    let threads = args.parse_value::<usize>("parallel")?.unwrap_or(1);
+    if threads == 0 {
+        return Err("--parallel takes at least 1 thread".into());
+    }
+    let start = std::time::Instant::now();
+    let outcomes = batch::run(&jobs, threads, |session| dispatch(&session.command, &session.args).map_err(|e| e.to_string()));
+    let failed: Vec<&batch::Outcome> = outcomes.iter().filter(|outcome| outcome.result.is_err()).collect();
+    println!("{} of {} jobs done in {:.1}s", outcomes.len() - failed.len(), outcomes.len(), start.elapsed().as_secs_f64());
+    for outcome in &failed {
+        if let Err(e) = &outcome.result {
+            println!("  {} failed: {}", outcome.name, e);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("{} of {} jobs failed", failed.len(), outcomes.len()).into());
+    }
+    Ok(())
+}
+
+// Re-render a saved Mandelbrot image from its embedded parameters, possibly at another size or iteration count
+fn reproduce(args: &Args) -> Result<(), Box<dyn Error>> {
+    let source = args
+        .positional(0)
+        .ok_or("Usage: reproduce <image.png> [--size WIDTHxHEIGHT] [--iterations N] [--output FILE]")?;
+    let mut options = metadata::read_metadata(source)?;
+
+    // Keep the original size and iteration count unless new ones are requested
+    (options.width, options.height) = size_arg(args, (options.width, options.height))?;
+    if let Some(max_iterations) = args.parse_value::<u32>("iterations")? {
+        options.max_iterations = max_iterations;
+    }
+
+    let stem = std::path::Path::new(source).file_stem().and_then(|s| s.to_str()).unwrap_or("mandelbrot");
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").view(options.center, options.zoom);
+    let tokens = tokens.with("iter", options.max_iterations).size(options.width, options.height).with("palette", &options.palette);
+    // Braces in the name of the source are not tokens
+    let default = format!("{}_reproduced.png", stem).replace('{', "{{").replace('}', "}}");
+    let output = output_arg(args, &default, &tokens)?;
+
+    metadata::save_with_metadata(&options.render()?, &output, &options)?;
+    info!("Reproduced {} at {}x{} with {} iterations, saved as {}", source, options.width, options.height, options.max_iterations, output);
+    Ok(())
+}
+
+// Read the `--size` option, falling back to the given default
+fn size_arg(args: &Args, default: (u32, u32)) -> Result<(u32, u32), Box<dyn Error>> {
+    match args.value("size") {
+        Some(size) => Ok(cli::parse_size(size)?),
+        None => Ok(default),
+    }
+}
+
+// The seed of the random draws: --seed, for reproducible renders, or else one from the clock
+fn seed_arg(args: &Args) -> Result<u64, Box<dyn Error>> {
+    match args.parse_value("seed")? {
+        Some(seed) => Ok(seed),
+        None => Ok(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos() as u64),
+    }
+}
+
+// The view of a width × height image: `--center RE,IM` and `--zoom`, the magnification of the default
+// bounds, or else the `--bounds` fitted to the image by the `--aspect` policy, expand by default
+fn framing_arg(args: &Args, default: (f32, f32, f32, f32), width: u32, height: u32) -> Result<bounds::Framing, Box<dyn Error>> {
+    if args.value("center").is_some() || args.value("zoom").is_some() {
+        if args.value("bounds").is_some() {
+            return Err("Give the view either as --bounds or as --center and --zoom, not both".into());
+        }
+        let (xmin, xmax, ymin, ymax) = default;
+        let center = args.value("center").map_or(Ok(((xmin + xmax) / 2.0, (ymin + ymax) / 2.0)), cli::parse_point)?;
+        let zoom = args.parse_value("zoom")?.unwrap_or(1.0);
+        return Ok(bounds::Framing { bounds: bounds::centered(center, zoom, xmax - xmin, width, height)?, region: None });
+    }
+    let policy = args.parse_value("aspect")?.unwrap_or_default();
+    Ok(bounds::Framing::new(bounds_arg(args, default)?, width, height, policy))
+}
+
+// The file name of `--output`, or of the default, with the tokens of its template replaced
+fn output_arg(args: &Args, default: &str, tokens: &naming::NameTokens) -> Result<String, Box<dyn Error>> {
+    let output = naming::expand(args.value("output").unwrap_or(default), tokens)?;
+    if naming::has_frame(&output) {
+        return Err("{frame} only names the frames of animations".into());
+    }
+    Ok(output)
+}
+
+// The output of the frames of an animation, whose template may keep {frame} for them
+fn frames_output_arg(args: &Args, default: &str, tokens: &naming::NameTokens) -> Result<String, Box<dyn Error>> {
+    Ok(naming::expand(args.value("output").unwrap_or(default), tokens)?)
+}
+
+// Read the options of the colorings: the palette transform and the settings of the hsv coloring
+fn coloring_options_arg(args: &Args) -> Result<coloring::ColoringOptions, Box<dyn Error>> {
+    let default = coloring::HsvOptions::default();
+    let hsv = coloring::HsvOptions {
+        hue: args.parse_value("hue")?.unwrap_or(default.hue),
+        hue_period: args.parse_value("hue-period")?.unwrap_or(default.hue_period),
+        hue_offset: args.parse_value("hue-offset")?.unwrap_or(default.hue_offset),
+        saturation: args.value("saturation").map_or(Ok(default.saturation), str::parse)?,
+        value: args.value("value").map_or(Ok(default.value), str::parse)?,
+    };
+    hsv.check()?;
+    Ok(coloring::ColoringOptions { palette_transform: palette_transform_arg(args)?, hsv })
+}
+
+// Read the --palette-offset, --palette-scale, --palette-repeat, --palette-mirror and --palette-invert options
+fn palette_transform_arg(args: &Args) -> Result<palette::PaletteTransform, Box<dyn Error>> {
+    let default = palette::PaletteTransform::default();
+    let transform = palette::PaletteTransform {
+        offset: args.parse_value("palette-offset")?.unwrap_or(default.offset),
+        scale: args.parse_value("palette-scale")?.unwrap_or(default.scale),
+        repeat: args.parse_value("palette-repeat")?.unwrap_or(default.repeat),
+        mirror: args.flag("palette-mirror"),
+        invert: args.flag("palette-invert"),
+    };
+    transform.check()?;
+    Ok(transform)
+}
+
+// Read the `--bounds` option (xmin;xmax;ymin;ymax), falling back to the given default. Reversed ranges
+// are swapped with a warning, bounds selecting no region refused.
+fn bounds_arg(args: &Args, default: (f32, f32, f32, f32)) -> Result<(f32, f32, f32, f32), Box<dyn Error>> {
+    match args.value("bounds") {
+        Some(bounds) => Ok(checked_bounds(bounds::parse_checked(bounds)?)),
+        None => Ok(default),
+    }
+}
+
+// Write an animated GIF of the colored Mandelbrot set with a rotating palette
+fn cycle_gif(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 600))?;
+    let framing = framing_arg(args, (-2.0, 2.0, -1.5, 1.5), width, height)?;
+    let palette = args.value("palette").unwrap_or("turbo");
+    let options = cycling::CycleOptions {
+        width,
+        height,
+        max_iterations: args.parse_value("iterations")?.unwrap_or(100),
+        bounds: framing.bounds,
+        palette: palette::Palette::by_name(palette)?.with_transform(palette_transform_arg(args)?)?,
+        letterbox: framing.region,
+        frames: args.parse_value("frames")?.unwrap_or(32),
+        speed: args.parse_value("speed")?.unwrap_or(1.0),
+        delay_ms: args.parse_value("delay")?.unwrap_or(60),
+    };
+
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").region(framing.bounds, mandelbrot::DEFAULT_VIEW_WIDTH);
+    let tokens = tokens.with("iter", options.max_iterations).size(width, height).with("palette", palette);
+    let output = output_arg(args, "cycling_mandelbrot.gif", &tokens)?;
+    cycling::render_cycling_gif(&options, &output)?;
+    info!("Palette cycling animation ({} frames) saved as {}", options.frames, output);
+    Ok(())
+}
+
+// Encode a zoom animation directly into a video file
+fn zoom_video(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (1280, 720))?;
+    let center = match args.value("center") {
+        Some(center) => cli::parse_point(center)?,
+        None => (-0.743_643_9, 0.131_825_9), // A classic spot in the Seahorse Valley
+    };
+    let options = video::ZoomOptions {
+        width,
+        height,
+        max_iterations: args.parse_value("iterations")?.unwrap_or(500),
+        center,
+        zoom: args.parse_value("zoom")?.unwrap_or(1000.0),
+        frames: args.parse_value("frames")?.unwrap_or(300),
+        fps: args.parse_value("fps")?.unwrap_or(30),
+        bitrate: args.value("bitrate").unwrap_or("8M").to_string(),
+        // Adapting to the interior only makes sense on top of the automatic limit.
+        auto_iterations: args.flag("auto-iter") || args.flag("adapt-interior"),
+        adapt_to_interior: args.flag("adapt-interior"),
+        palette: args.value("palette").unwrap_or("turbo").to_string(),
+        coloring: args.value("coloring").unwrap_or("iterations").to_string(),
+        coloring_options: coloring_options_arg(args)?,
+    };
+    options.check()?;
+
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").view(center, options.zoom).with("iter", options.max_iterations);
+    let output = output_arg(args, "mandelbrot_zoom.mp4", &tokens.size(width, height).with("palette", &options.palette))?;
+    video::render_zoom_video(&options, &output)?;
+    info!("Zoom video ({} frames at {} fps) saved as {}", options.frames, options.fps, output);
+    Ok(())
+}
+
+// Serve tiles of the Mandelbrot set over HTTP for web maps
+fn serve_tiles(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut builder = RenderOptions::builder().palette(args.value("palette").unwrap_or("turbo"));
+    builder = builder.coloring(args.value("coloring").unwrap_or("iterations")).coloring_options(coloring_options_arg(args)?);
+    if let Some(bailout) = args.parse_value("bailout")? {
+        builder = builder.bailout(bailout);
+    }
+    let cache_tiles = args.parse_value("cache")?.unwrap_or(server::DEFAULT_CACHE_TILES);
+    let mut tile_server = server::TileServer::new(builder.build()?, args.parse_value("iterations")?, cache_tiles);
+    tile_server.run(args.parse_value("port")?.unwrap_or(8080))
+}
+
+// Render the Newton fractal of a polynomial
+fn newton_fractal(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 600))?;
+    let framing = framing_arg(args, (-2.0, 2.0, -1.5, 1.5), width, height)?;
+    let polynomial = newton::Polynomial::parse(args.value("poly").unwrap_or("1,0,0,-1"))?;
+    let max_iterations = args.parse_value("iterations")?.unwrap_or(50);
+    let fractal = newton::NewtonFractal::new(polynomial, max_iterations);
+
+    let mut image = newton::generate_newton_fractal(width, height, &fractal, framing.bounds);
+    framing.apply(&mut image);
+    let tokens = naming::NameTokens::new().with("fractal", "newton").region(framing.bounds, mandelbrot::DEFAULT_VIEW_WIDTH);
+    let tokens = tokens.with("iter", max_iterations).size(width, height);
+    let output = output_arg(args, "newton_fractal.png", &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut image, Some(framing.bounds), &tokens)?;
+    image.save(&output)?;
+    info!("Newton fractal with {} roots saved as {}", fractal.roots().len(), output);
+    Ok(())
+}
+
+// Render the escape-time fractal of a formula typed by the user
+fn formula_fractal(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 600))?;
+    let framing = framing_arg(args, (-2.0, 2.0, -1.5, 1.5), width, height)?;
+    let max_iterations = args.parse_value("iterations")?.unwrap_or(100);
+    let source = args.value("expr").ok_or("Usage: formula --expr \"z^2 + c\" [--bounds] [--size] [--iterations] [--output]")?;
+    // The error already shows where the formula is wrong, so print it as is
+    let formula = formula::Formula::parse(source).map_err(|e| format!("Invalid formula:\n{}", e))?;
+
+    let iterations = fractal::compute_iterations(&formula, width, height, max_iterations, framing.bounds);
+    let mut image = mandelbrot::colorize(width, height, &iterations, &ColoredColorMap::new(max_iterations));
+    framing.apply(&mut image);
+    let tokens = naming::NameTokens::new().with("fractal", "formula").region(framing.bounds, mandelbrot::DEFAULT_VIEW_WIDTH);
+    let tokens = tokens.with("iter", max_iterations).size(width, height).with("palette", "turbo");
+    let output = output_arg(args, "formula_fractal.png", &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut image, Some(framing.bounds), &tokens)?;
+    image.save(&output)?;
+    info!("Fractal of z = {} saved as {}", formula.source(), output);
+    Ok(())
+}
+
+// Escape-time fractals rendered by render --fractal through the Fractal trait, besides the Mandelbrot set
+const ESCAPE_TIME_FRACTALS: [&str; 16] = [
+    "julia",
+    "burning-ship",
+    "tricorn",
+    "phoenix",
+    "multibrot",
+    "celtic",
+    "perpendicular-mandelbrot",
+    "perpendicular-burning-ship",
+    "buffalo",
+    "magnet-1",
+    "magnet-2",
+    "collatz",
+    "spider",
+    "lambda",
+    "lambda-julia",
+    "hybrid",
+];
+
+// Build the escape-time fractal of a name with its parameters, or None if the name is not one of them.
+// The formulas with a parameter draw their Julia set of --julia RE,IM in place of their parameter plane.
+fn escape_time_fractal(name: &str, args: &Args) -> Result<Option<Box<dyn Fractal>>, Box<dyn Error>> {
+    if let Some(dynamics) = escape_time_dynamics(name, args)? {
+        return Ok(Some(match args.parse_value("julia")? {
+            Some(c) => Box::new(dynamics.julia(c)),
+            None => Box::new(dynamics),
+        }));
+    }
+    if args.value("julia").is_some() && ESCAPE_TIME_FRACTALS.contains(&name) {
+        return Err(format!("The {} fractal has no parameter plane to take a Julia set of", name).into());
+    }
+    Ok(Some(match name {
+        "julia" => Box::new(fractal::Julia::new(args.parse_value("c")?.unwrap_or(fractal::Julia::DEFAULT_C))),
+        "phoenix" => {
+            let p = args.parse_value("p")?.unwrap_or(phoenix::Phoenix::DEFAULT_P);
+            let q = args.parse_value("q")?.unwrap_or(phoenix::Phoenix::DEFAULT_Q);
+            Box::new(phoenix::Phoenix::new(q).julia(p))
+        }
+        "collatz" => Box::new(collatz::Collatz),
+        "spider" => Box::new(fractal::Spider),
+        "lambda-julia" => Box::new(fractal::LambdaJulia::new(args.parse_value("lambda")?.unwrap_or(fractal::LambdaJulia::DEFAULT_LAMBDA))),
+        _ => return Ok(None),
+    }))
+}
+
+// The escape-time fractals of a formula with a parameter, drawn on either plane
+fn escape_time_dynamics(name: &str, args: &Args) -> Result<Option<Box<dyn Dynamics>>, Box<dyn Error>> {
+    Ok(Some(match name {
+        "burning-ship" => Box::new(fractal::BurningShip),
+        "tricorn" => Box::new(fractal::Tricorn),
+        "multibrot" => Box::new(fractal::Multibrot::new(args.parse_value("power")?.unwrap_or(fractal::Multibrot::DEFAULT_POWER))?),
+        "magnet-1" => Box::new(magnet::Magnet::TypeOne),
+        "magnet-2" => Box::new(magnet::Magnet::TypeTwo),
+        "lambda" => Box::new(fractal::Lambda),
+        "hybrid" => Box::new(fractal::Hybrid::parse(args.value("pattern").unwrap_or(fractal::Hybrid::DEFAULT_PATTERN))?),
+        name if fractal::ABS_VARIANTS.contains(&name) => Box::new(fractal::AbsVariant::by_name(name)?),
+        _ => return Ok(None),
+    }))
+}
+
+// Estimate the area of a fractal by testing random points, with its confidence interval
+fn estimate_area(args: &Args) -> Result<(), Box<dyn Error>> {
+    let name = args.value("fractal").unwrap_or("mandelbrot");
+    let fractal: Box<dyn Fractal> = match name {
+        "mandelbrot" => Box::new(fractal::Mandelbrot),
+        _ => escape_time_fractal(name, args)?.ok_or_else(|| format!("No area estimation for the {} fractal", name))?,
+    };
+    let default_bounds = if name == "mandelbrot" { area::AREA_BOUNDS } else { fractal.default_bounds() };
+    let bounds = bounds_arg(args, default_bounds)?;
+    let samples = args.parse_value("samples")?.unwrap_or(area::DEFAULT_SAMPLES);
+    let max_iterations = args.parse_value("iterations")?.unwrap_or(area::DEFAULT_ITERATIONS);
+    let confidence = args.parse_value("confidence")?.unwrap_or(0.95);
+    let z = area::z_score(confidence)?;
+    let precision = args.parse_value("precision")?;
+    let seed = seed_arg(args)?;
+
+    let mut sampler = area::AreaSampler::new(&*fractal, bounds, max_iterations, seed)?;
+    let estimate = area::estimate_area(&mut sampler, samples, z, precision);
+    let margin = estimate.margin(z);
+    println!(
+        "Estimated area of the {} set: {:.6} ± {:.6} ({}% confidence interval {:.6} to {:.6})",
+        name,
+        estimate.area(),
+        margin,
+        confidence * 100.0,
+        estimate.area() - margin,
+        estimate.area() + margin
+    );
+    println!("{} of {} points inside after {} iterations, seed {}", estimate.inside, estimate.samples, max_iterations, seed);
+    Ok(())
+}
+
+// Report the most interesting tiles of a view, or keep zooming into the best one and render it
+fn find_interesting(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut center = args.value("center").map_or(Ok((-0.5, 0.0)), cli::parse_point)?;
+    let mut zoom: f32 = args.parse_value("zoom")?.unwrap_or(1.0);
+    let grid = args.value("grid").map_or(Ok(interest::DEFAULT_GRID), cli::parse_size)?;
+    let score = args.parse_value("score")?.unwrap_or(interest::Score::Entropy);
+    let fixed_iterations: Option<u32> = args.parse_value("iterations")?;
+    let iterations = |zoom| fixed_iterations.unwrap_or_else(|| mandelbrot::auto_iterations(zoom));
+
+    let Some(steps) = args.parse_value::<u32>("auto")? else {
+        let top = args.parse_value("top")?.unwrap_or(5);
+        let regions = interest::find_regions(center, zoom, iterations(zoom), grid, score)?;
+        for region in regions.iter().take(top) {
+            println!("score {:8.3}  --center {},{} --zoom {}", region.score, region.center.0, region.center.1, region.zoom);
         }
+        return Ok(());
+    };
+    for step in 1..=steps {
+        let best = interest::find_regions(center, zoom, iterations(zoom), grid, score)?[0];
+        (center, zoom) = (best.center, best.zoom);
+        info!("Step {}: --center {},{} --zoom {} (score {:.3})", step, center.0, center.1, zoom, best.score);
     }
+    let (width, height) = size_arg(args, (800, 600))?;
+    let palette = args.value("palette").unwrap_or("turbo");
+    let options = RenderOptions::builder().size(width, height).center(center.0, center.1).zoom(zoom).iterations(iterations(zoom)).palette(palette).build()?;
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").view(center, zoom).with("iter", options.max_iterations);
+    let tokens = tokens.size(width, height).with("palette", palette);
+    let output = output_arg(args, "interesting.png", &tokens)?;
+    let mut image = options.render()?;
+    post_process_arg(args, "axes")?.apply(&mut image, Some(options.bounds()), &tokens)?;
+    metadata::save_render(&image, &output, &options)?;
+    info!("Most interesting region saved as {}", output);
+    Ok(())
+}
 
+// Fill a directory with renders of random deep zooms
+fn explore_gallery(args: &Args) -> Result<(), Box<dyn Error>> {
+    if !args.flag("random") {
+        return Err("Usage: explore --random [--count N] [--seed N] [--size WIDTHxHEIGHT] [--palette NAME] [--output DIR]".into());
+    }
+    let (width, height) = size_arg(args, (800, 600))?;
+    let count = args.parse_value("count")?.unwrap_or(10);
+    let seed = seed_arg(args)?;
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").size(width, height);
+    let tokens = match args.value("palette") {
+        Some(palette) => tokens.with("palette", palette),
+        None => tokens,
+    };
+    let output = output_arg(args, "gallery", &tokens)?;
+    explore::explore_random(&output, count, seed, width, height, args.value("palette"), &post_process_arg(args, "axes")?)?;
+    info!("{} random views saved to {} (seed {})", count, output, seed);
+    Ok(())
+}
+
+// List the palettes or preview them
+fn palettes_command(args: &Args) -> Result<(), Box<dyn Error>> {
+    match args.positional(0).unwrap_or("list") {
+        "list" => list_palettes(args),
+        "preview" => preview_palettes(args),
+        action => Err(cli::UsageError(format!("Unknown action '{}', usage: palettes list | palettes preview [NAME]", action)).into()),
+    }
+}
+
+// List the built-in palettes and those of the user
+fn list_palettes(args: &Args) -> Result<(), Box<dyn Error>> {
+    let names = palette::palette_names();
+    let (built_in, user) = names.split_at(mandelbrot::PALETTE_NAMES.len() + 1); // Grayscale included
+    println!("Built-in palettes: {}", built_in.join(", "));
+    if user.is_empty() {
+        let path = args.value("palette-file").map(String::from).or_else(|| palettes::default_path().ok().map(|path| path.display().to_string()));
+        println!("No user palettes, define them in {}", path.as_deref().unwrap_or("palettes.conf"));
+    } else {
+        println!("User palettes: {}", user.join(", "));
+    }
     Ok(())
 }
 
-// Helper function to parse spatial bounds from user input
-fn parse_bounds(input: &str) -> Result<(f32, f32, f32, f32), &'static str> {
-    let parts: Vec<&str> = input.split(';').collect();
-    if parts.len() == 4 {
-        let xmin = parts[0].parse::<f32>().map_err(|_| "Error parsing xmin")?;
-        let xmax = parts[1].parse::<f32>().map_err(|_| "Error parsing xmax")?;
-        let ymin = parts[2].parse::<f32>().map_err(|_| "Error parsing ymin")?;
-        let ymax = parts[3].parse::<f32>().map_err(|_| "Error parsing ymax")?;
-        Ok((xmin, xmax, ymin, ymax))
+// Preview one palette, or all of them, as gradient strips in one image saved and displayed
+fn preview_palettes(args: &Args) -> Result<(), Box<dyn Error>> {
+    let names = match args.positional(1) {
+        Some(name) if name != "all" => vec![name.to_string()],
+        _ => palette::palette_names(),
+    };
+    let palettes: Vec<&str> = names.iter().map(String::as_str).collect();
+    let strip_size = size_arg(args, compare::STRIP_SIZE)?;
+    let thumbnail = if args.flag("thumbnail") {
+        let (width, height) = args.value("thumbnail").map_or(Ok(compare::THUMBNAIL_SIZE), cli::parse_size)?;
+        let options = mandelbrot_options_arg(args, width, height)?;
+        let escapes = options.compute()?;
+        Some((options, escapes))
     } else {
-        Err("Input must be in the format xmin;xmax;ymin;ymax")
+        None
+    };
+    let thumbnail = thumbnail.as_ref().map(|(options, escapes)| (options, escapes.as_slice()));
+    let mut image = compare::palette_strips(&palettes, strip_size, palette_transform_arg(args)?, thumbnail)?;
+    let (default, palette) = if palettes.len() == 1 { ("palette_{palette}.png", palettes[0]) } else { ("palettes.png", "all") };
+    let tokens = naming::NameTokens::new().with("palette", palette);
+    let output = output_arg(args, default, &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut image, None, &tokens)?;
+    image.save(&output)?;
+    info!("{} palettes previewed in {}", palettes.len(), output);
+    display_image(image)
+}
+
+// Color one render of the Mandelbrot set with several palettes, in a grid saved and displayed
+fn compare_palettes(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (320, 240))?;
+    let options = mandelbrot_options_arg(args, width, height)?;
+    let palettes: Vec<&str> = match args.value("palettes") {
+        Some(list) => list.split(',').map(str::trim).collect(),
+        None => mandelbrot::PALETTE_NAMES.to_vec(),
+    };
+    let escapes = options.compute()?;
+    let mut grid = compare::palette_grid(&options, &escapes, &palettes)?;
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").size(width, height);
+    let output = output_arg(args, "palettes_{size}.png", &tokens)?;
+    post_process_arg(args, "axes")?.apply(&mut grid, None, &tokens)?;
+    grid.save(&output)?;
+    info!("{} palettes compared in {}", palettes.len(), output);
+    display_image(grid)
+}
+
+// Draw the external rays of a comma separated list of angles (like "1/3,2/3") over a render of the bounds
+fn draw_rays(image: &mut RgbImage, angles: &str, bounds: (f32, f32, f32, f32), depth: u32) -> Result<(), Box<dyn Error>> {
+    let (width, height) = image.dimensions();
+    let (xmin, xmax, ymin, ymax) = bounds;
+    let to_pixel = |c: complex::Complex| {
+        let x = (c.re - xmin as f64) / (xmax - xmin) as f64 * width as f64;
+        let y = (c.im - ymin as f64) / (ymax - ymin) as f64 * height as f64;
+        (x as f32, y as f32)
+    };
+    for angle in angles.split(',') {
+        let ray = rays::trace_ray(angle.parse()?, depth);
+        for segment in ray.windows(2) {
+            viewer::draw_line(image, to_pixel(segment[0]), to_pixel(segment[1]), image::Rgb([255, 255, 255]));
+        }
+    }
+    Ok(())
+}
+
+// The iteration limit of a render at a zoom: --iterations, or with --auto-iter a limit growing with the zoom
+fn iterations_arg(args: &Args, default: u32, zoom: f32) -> Result<u32, Box<dyn Error>> {
+    if !args.flag("auto-iter") {
+        return Ok(args.parse_value("iterations")?.unwrap_or(default));
+    }
+    let max_iterations = mandelbrot::auto_iterations(zoom);
+    info!("Using {} iterations for a zoom of {}x", max_iterations, zoom);
+    Ok(max_iterations)
+}
+
+// Write the histogram of the iteration counts of a render to the file of --histogram, if given
+fn save_histogram(args: &Args, escapes: &[mandelbrot::EscapeInfo], max_iterations: u32) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = args.value("histogram") {
+        let histogram = histogram::IterationHistogram::new(escapes, max_iterations);
+        histogram.save(path)?;
+        info!("Iteration histogram saved as {} ({:.1}% of the pixels in the interior)", path, histogram.interior_fraction() * 100.0);
+    }
+    Ok(())
+}
+
+// The render options of a view of the Mandelbrot set given either as --bounds or as --center and --zoom,
+// with the palette, coloring and sampling options
+fn mandelbrot_options_arg(args: &Args, width: u32, height: u32) -> Result<RenderOptions, Box<dyn Error>> {
+    let mut builder = RenderOptions::builder().size(width, height);
+    let zoom = if args.value("bounds").is_some() {
+        let framing = framing_arg(args, (-2.0, 2.0, -1.5, 1.5), width, height)?;
+        builder = builder.framing(framing);
+        mandelbrot::DEFAULT_VIEW_WIDTH / (framing.bounds.1 - framing.bounds.0)
+    } else {
+        let (re, im) = args.value("center").map_or(Ok((-0.5, 0.0)), cli::parse_point)?;
+        let zoom = args.parse_value("zoom")?.unwrap_or(1.0);
+        builder = builder.center(re, im).zoom(zoom);
+        zoom
+    };
+    builder = builder.iterations(iterations_arg(args, 100, zoom)?);
+    builder = builder.palette(args.value("palette").unwrap_or("turbo")).coloring(args.value("coloring").unwrap_or("iterations"));
+    builder = builder.bailout(args.parse_value("bailout")?.unwrap_or(fractal::DEFAULT_BAILOUT));
+    builder = builder.coloring_options(coloring_options_arg(args)?).dither(args.parse_value("dither")?.unwrap_or_default());
+    builder = builder.antialias(args.parse_value("aa")?.unwrap_or(1)).gamma(args.parse_value("gamma")?.unwrap_or_default());
+    Ok(builder.build()?)
+}
+
+// Render the Mandelbrot set as a deep zoom tile pyramid
+fn export_pyramid(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (8192, 6144))?;
+    let options = mandelbrot_options_arg(args, width, height)?;
+    let layout = args.parse_value("layout")?.unwrap_or(pyramid::Layout::Dzi);
+    let tile_size = args.parse_value("tile-size")?.unwrap_or(match layout {
+        pyramid::Layout::Dzi => pyramid::DEFAULT_TILE_SIZE,
+        pyramid::Layout::Iiif => server::TILE_SIZE,
+    });
+    let overlap = args.parse_value("overlap")?.unwrap_or(pyramid::DEFAULT_OVERLAP);
+    let pyramid = pyramid::Pyramid::new(width, height, tile_size, overlap, layout)?;
+    let format = args.parse_value("format")?.unwrap_or(pyramid::TileFormat::Png);
+    let threads = match args.parse_value("threads")? {
+        Some(threads) => threads,
+        None => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+
+    let output = args.value("output").unwrap_or("pyramid");
+    let name = args.value("name").unwrap_or("mandelbrot");
+    let tiles = pyramid::export_pyramid(&pyramid, &options, output, name, format, args.value("base-url").unwrap_or(""), threads)?;
+    let (manifest, _) = pyramid.manifest(name, format, "");
+    info!("{} tiles over {} levels of a {}x{} image saved to {}, manifest {}", tiles, pyramid.max_level() + 1, width, height, output, manifest.display());
+    Ok(())
+}
+
+// Merge the tiles described by a manifest into a single image, after checking that they line up
+fn stitch_tiles(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: stitch <tiles.conf|NAME.dzi> [--output FILE] [--stream] [--allow-missing]")?;
+    let manifest = stitch::TileManifest::load(path)?;
+    stitch_manifest(&manifest, &stitch::manifest_directory(path), args, None)
+}
+
+// Check that the tiles of a manifest line up and write them as one image to --output, with the options of
+// the render when they are known
+fn stitch_manifest(manifest: &stitch::TileManifest, directory: &std::path::Path, args: &Args, options: Option<&RenderOptions>) -> Result<(), Box<dyn Error>> {
+    let mut problems = manifest.layout_problems();
+    let (missing, file_problems) = manifest.file_problems(directory);
+    problems.extend(file_problems);
+    for tile in &missing {
+        warn!("Missing tile {} at {},{}", tile.file, tile.x, tile.y);
+    }
+    if !missing.is_empty() && !args.flag("allow-missing") {
+        problems.push(format!("{} of the {} tiles are missing (--allow-missing leaves them black)", missing.len(), manifest.tiles.len()));
+    }
+    if !problems.is_empty() {
+        return Err(format!("The tiles do not line up:\n  {}", problems.join("\n  ")).into());
+    }
+
+    let output = args.value("output").unwrap_or("stitched.png");
+    if args.flag("stream") {
+        let text_chunks = options.map(metadata::to_text_chunks).transpose()?.unwrap_or_default();
+        stitch::stitch_streaming(manifest, directory, output, &text_chunks)?;
+    } else {
+        let image = stitch::stitch(manifest, directory)?;
+        match options {
+            Some(options) => metadata::save_render(&image, output, options)?,
+            None => image.save(output)?,
+        }
+    }
+    info!("{} tiles stitched into a {}x{} image saved as {}", manifest.tiles.len() - missing.len(), manifest.width, manifest.height, output);
+    Ok(())
+}
+
+// Split a large Mandelbrot render into job files to be rendered independently by run-job
+fn split_job(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (8192, 6144))?;
+    // Catch invalid options now rather than on every machine
+    let render = mandelbrot_options_arg(args, width, height)?;
+    let count = args.parse_value("jobs")?.unwrap_or(4);
+    let tile_size = args.parse_value("tile-size")?.unwrap_or(jobs::DEFAULT_JOB_TILE_SIZE);
+    let mut options = args.values();
+    options.retain(|name, _| !["config", "output", "jobs", "tile-size", "size"].contains(&name.as_str()));
+    // The limit chosen by --auto-iter, a switch which the job would not keep
+    options.insert("iterations".to_string(), render.max_iterations.to_string());
+
+    let directory = args.value("output").unwrap_or("jobs");
+    std::fs::create_dir_all(directory)?;
+    for job in jobs::split(width, height, tile_size, count, options.into_iter().collect())? {
+        let path = std::path::Path::new(directory).join(jobs::job_file(job.job));
+        job.save(&path)?;
+        info!("{}: tiles {} to {}", path.display(), job.tiles.start, job.tiles.end.saturating_sub(1));
+    }
+    Ok(())
+}
+
+// Render the tiles of one job next to its file, keeping the tiles already rendered
+fn run_job(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = std::path::Path::new(args.positional(0).ok_or("Usage: run-job <job.conf>")?);
+    let job = jobs::JobSpec::load(path)?;
+    let mut render_args = Args::parse(&[]);
+    render_args.merge_defaults(job.options.clone());
+    let options = mandelbrot_options_arg(&render_args, job.width, job.height)?;
+    let grid = pyramid::Pyramid::new(job.width, job.height, job.tile_size, 0, pyramid::Layout::Dzi)?;
+    let directory = stitch::manifest_directory(&path.to_string_lossy());
+
+    let tiles = job.tiles()?;
+    for (index, tile) in tiles.iter().enumerate() {
+        let tile_path = directory.join(jobs::JobSpec::tile_file(tile));
+        if tile_path.exists() {
+            continue;
+        }
+        let tile_options = grid.tile_options(&options, tile);
+        metadata::save_render(&tile_options.render()?, &tile_path, &tile_options)?;
+        debug!("Job {}/{}: tile {} of {} saved as {}", job.job, job.jobs, index + 1, tiles.len(), tile_path.display());
+    }
+    Ok(())
+}
+
+// Assemble the tiles rendered by every job of a directory into the final image
+fn merge_job(args: &Args) -> Result<(), Box<dyn Error>> {
+    let directory = std::path::Path::new(args.positional(0).ok_or("Usage: merge-job <directory> [--output FILE] [--stream] [--allow-missing]")?);
+    let jobs = jobs::load_jobs(directory)?;
+    let manifest = jobs[0].manifest()?;
+    let manifest_path = directory.join(stitch::MANIFEST_NAME);
+    manifest.save(&manifest_path.to_string_lossy())?;
+    info!("Manifest of the {} tiles of {} jobs saved as {}", manifest.tiles.len(), jobs.len(), manifest_path.display());
+    let mut render_args = Args::parse(&[]);
+    render_args.merge_defaults(jobs[0].options.clone());
+    let options = mandelbrot_options_arg(&render_args, jobs[0].width, jobs[0].height)?;
+    stitch_manifest(&manifest, directory, args, Some(&options))
+}
+
+// Resume the checkpoint of a render, telling how much of it is already done
+fn open_checkpoint(path: &str, description: &str, items: usize, unit: &str) -> Result<checkpoint::Checkpoint, Box<dyn Error>> {
+    let checkpoint = checkpoint::Checkpoint::open(std::path::Path::new(path), description)?;
+    if checkpoint.completed() > 0 {
+        info!("Resuming from {}: {} of the {} {} are done", path, checkpoint.completed(), items, unit);
+    }
+    Ok(checkpoint)
+}
+
+// Render the Mandelbrot set tile by tile into the directory PATH.tiles, recording the tiles done in the
+// checkpoint file PATH so that the render can resume, then stitch them. Both are deleted once stitched.
+fn render_checkpointed(options: &RenderOptions, path: &str) -> Result<RgbImage, Box<dyn Error>> {
+    let directory = std::path::PathBuf::from(format!("{}.tiles", path));
+    std::fs::create_dir_all(&directory)?;
+    let tiles = jobs::image_tiles(options.width, options.height, jobs::DEFAULT_JOB_TILE_SIZE)?;
+    let mut checkpoint = open_checkpoint(path, &format!("{:?}", options), tiles.len(), "tiles")?;
+    let grid = pyramid::Pyramid::new(options.width, options.height, jobs::DEFAULT_JOB_TILE_SIZE, 0, pyramid::Layout::Dzi)?;
+    for (index, tile) in tiles.iter().enumerate() {
+        if checkpoint.is_done(index as u32) {
+            continue;
+        }
+        let tile_options = grid.tile_options(options, tile);
+        metadata::save_render(&tile_options.render()?, directory.join(jobs::JobSpec::tile_file(tile)), &tile_options)?;
+        checkpoint.complete(index as u32)?;
+        debug!("Tile {} of {} rendered", index + 1, tiles.len());
+    }
+    checkpoint.save()?;
+
+    let manifest = jobs::image_manifest(options.width, options.height, jobs::DEFAULT_JOB_TILE_SIZE)?;
+    let image = stitch::stitch(&manifest, &directory)?;
+    checkpoint.remove()?;
+    std::fs::remove_dir_all(&directory)?;
+    Ok(image)
+}
+
+// Render one of the built-in fractal types selected with --fractal
+fn render_fractal(args: &Args) -> Result<(), Box<dyn Error>> {
+    render_image(args).map(|_| ())
+}
+
+// Render a fractal and save it, returning the image saved (or its 8-bit version for a 16-bit output)
+fn render_image(args: &Args) -> Result<RgbImage, Box<dyn Error>> {
+    let (width, height) = size_arg(args, (800, 600))?;
+    let fractal = args.value("fractal").unwrap_or("mandelbrot");
+    // Escape-time fractals share the coloring algorithms, convergent ones color themselves
+    let palette = args.value("palette").unwrap_or("turbo");
+    let coloring = args.value("coloring").unwrap_or("iterations");
+    let bailout = args.parse_value("bailout")?.unwrap_or(fractal::DEFAULT_BAILOUT);
+
+    let mut tokens = naming::NameTokens::new().with("fractal", fractal).size(width, height).with("palette", palette);
+    let start = std::time::Instant::now();
+    let mut plane_bounds = None; // The region of the complex plane shown, for --axes
+    let mut render_options = None; // The options of a Mandelbrot render, stored in its PNG
+    // Escape-time renders also return their escapes, iteration limit and samples per direction for the
+    // histogram, the statistics and the 16-bit output
+    let (mut image, escapes) = match fractal {
+        "mandelbrot" => {
+            let options = mandelbrot_options_arg(args, width, height)?;
+            tokens = tokens.view(options.center, options.zoom).with("iter", options.max_iterations);
+            // A checkpointed render only keeps the tiles of the image, not the escapes of its pixels
+            let (mut image, escapes) = match args.value("checkpoint") {
+                Some(path) => (render_checkpointed(&options, path)?, None),
+                None => {
+                    let escapes = options.compute()?;
+                    (options.colorize(&escapes)?, Some((escapes, options.max_iterations, options.antialias)))
+                }
+            };
+            if let Some(angles) = args.value("rays") {
+                draw_rays(&mut image, angles, options.bounds(), args.parse_value("ray-depth")?.unwrap_or(rays::RAY_DEPTH))?;
+            }
+            plane_bounds = Some(options.bounds());
+            render_options = Some(options);
+            (image, escapes)
+        }
+        "nova" => {
+            let framing = framing_arg(args, (-2.0, 2.0, -1.5, 1.5), width, height)?;
+            let exponent = args.parse_value("exponent")?.unwrap_or(3.0);
+            let relaxation = args.parse_value("relaxation")?.unwrap_or(complex::Complex::ONE);
+            let max_iterations = args.parse_value("iterations")?.unwrap_or(100);
+            let nova = nova::Nova::new(exponent, relaxation, max_iterations);
+            tokens = tokens.region(framing.bounds, 4.0).with("iter", max_iterations);
+            let mut image = nova::generate_nova_fractal(width, height, &nova, framing.bounds);
+            framing.apply(&mut image);
+            plane_bounds = Some(framing.bounds);
+            (image, None)
+        }
+        "lyapunov" => {
+            // Here the plane is the (a, b) pair of rates instead of the complex plane: its bounds are not
+            // fitted to the image, rates beyond 4 having no meaning
+            let bounds = bounds_arg(args, (2.0, 4.0, 2.0, 4.0))?;
+            let sequence = args.value("sequence").unwrap_or("AB");
+            let warmup = args.parse_value("warmup")?.unwrap_or(50);
+            let max_iterations = args.parse_value("iterations")?.unwrap_or(100);
+            let lyapunov = lyapunov::Lyapunov::new(sequence, warmup, max_iterations)?;
+            tokens = tokens.region(bounds, 2.0).with("iter", max_iterations);
+            (lyapunov::generate_lyapunov_fractal(width, height, &lyapunov, bounds), None)
+        }
+        "bifurcation" => {
+            // Like the Lyapunov fractal, the plane of the rates is not fitted to the image
+            let bounds = bounds_arg(args, bifurcation::BIFURCATION_BOUNDS)?;
+            let transient = args.parse_value("transient")?.unwrap_or(bifurcation::DEFAULT_TRANSIENT);
+            let samples = args.parse_value("samples")?.unwrap_or(bifurcation::DEFAULT_SAMPLES);
+            let diagram = bifurcation::Bifurcation::new(transient, samples)?;
+            let palette = palette::Palette::by_name(palette)?.with_transform(coloring_options_arg(args)?.palette_transform)?;
+            tokens = tokens.region(bounds, 1.5).with("iter", samples);
+            (bifurcation::generate_bifurcation_diagram(width, height, &diagram, bounds, &palette), None)
+        }
+        other => {
+            let escape_time = escape_time_fractal(other, args)?.ok_or_else(|| {
+                format!("Unknown fractal '{}'. Available fractals: mandelbrot, {}, nova, lyapunov, bifurcation", other, ESCAPE_TIME_FRACTALS.join(", "))
+            })?;
+            let default_bounds = escape_time.default_bounds();
+            let framing = framing_arg(args, default_bounds, width, height)?;
+            let bounds = framing.bounds;
+            let max_iterations = iterations_arg(args, 100, (default_bounds.1 - default_bounds.0) / (bounds.1 - bounds.0))?;
+            tokens = tokens.region(bounds, default_bounds.1 - default_bounds.0).with("iter", max_iterations);
+            let color_map = coloring::coloring_with_options(coloring, palette, max_iterations, &coloring_options_arg(args)?)?;
+            fractal::check_bailout(bailout)?;
+            let escapes = mandelbrot::compute_escape_info(&*escape_time, width, height, max_iterations, bounds, bailout, color_map.statistic());
+            let dither = args.parse_value("dither")?.unwrap_or_default();
+            let mut image = mandelbrot::colorize_escapes_dithered(width, height, &escapes, &*color_map, dither);
+            framing.apply(&mut image);
+            plane_bounds = Some(bounds);
+            (image, Some((escapes, max_iterations, 1)))
+        }
+    };
+
+    post_process_arg(args, "axes")?.apply(&mut image, plane_bounds, &tokens)?;
+
+    let mut stats = stats::RenderStats::new(width, height, start.elapsed());
+    if let Some((escapes, max_iterations, _)) = &escapes {
+        save_histogram(args, escapes, *max_iterations)?;
+        stats = stats.with_escapes(escapes, *max_iterations);
+    }
+    // With --json the results are printed once the image is saved, for its file name
+    if !args.flag("json") {
+        println!("{}", stats.summary());
+    }
+    if let Some(path) = args.value("stats") {
+        stats.save(path)?;
+    }
+
+    let output = output_arg(args, "{fractal}.png", &tokens)?;
+    let output = output.as_str();
+    match args.parse_value::<u32>("depth")? {
+        None | Some(8) => match &render_options {
+            Some(options) => metadata::save_render(&image, output, options)?,
+            None => image.save(output)?,
+        },
+        Some(16) => {
+            let Some((escapes, max_iterations, samples)) = &escapes else {
+                return Err(format!("The {} fractal has no iteration counts for a 16-bit output", fractal).into());
+            };
+            if palette != mandelbrot::GRAYSCALE {
+                return Err("The 16-bit output is grayscale, use it with --palette grayscale".into());
+            }
+            mandelbrot::grayscale16(width, height, *samples, escapes, *max_iterations).save(output)?;
+        }
+        Some(depth) => return Err(format!("Images are written with 8 or 16 bits per channel, not {}", depth).into()),
+    }
+    info!("{} fractal saved as {}", fractal, output);
+    if args.flag("json") {
+        let warnings = match args.value("bounds") {
+            Some(bounds) => bounds::parse_checked(bounds)?.warnings,
+            None => Vec::new(),
+        };
+        let options = args.values().into_iter().filter(|(name, _)| name != "config").collect();
+        println!("{}", report::RenderReport { output, tokens: &tokens, options, stats: &stats, warnings }.to_json());
+    }
+    Ok(image)
+}
+
+// Render a keyframe animation described in a file
+fn animate(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = args.positional(0).ok_or("Usage: animate <keyframes.conf> [--output DIR|FILE.mp4]")?;
+    let animation = animation::Animation::load(path)?;
+    run_animation(&animation, args)
+}
+
+fn run_animation(animation: &animation::Animation, args: &Args) -> Result<(), Box<dyn Error>> {
+    let tokens = naming::NameTokens::new().with("fractal", "mandelbrot").size(animation.width, animation.height);
+    let output = frames_output_arg(args, "frames", &tokens)?;
+    let output = output.as_str();
+    let checkpoint = match args.value("checkpoint") {
+        Some(path) => Some(open_checkpoint(path, &format!("{:?} {}", animation, output), animation.frame_count() as usize, "frames")?),
+        None => None,
+    };
+    animation::render_animation(animation, output, checkpoint)?;
+    info!("Animation of {} frames ({:.1}s) saved to {}", animation.frame_count(), animation.duration(), output);
+    Ok(())
+}
+
+// List the bookmarks, or open the viewer on the one named
+fn open_bookmark(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path = match args.value("file") {
+        Some(file) => std::path::PathBuf::from(file),
+        None => bookmarks::default_path()?,
+    };
+    let saved = bookmarks::load(&path)?;
+
+    let Some(name) = args.positional(0) else {
+        if saved.is_empty() {
+            println!("No bookmarks in {}, press B in the viewer to add one", path.display());
+        }
+        for bookmark in &saved {
+            println!(
+                "{}: center {},{} zoom {} iterations {} palette {}",
+                bookmark.name, bookmark.center.0, bookmark.center.1, bookmark.zoom, bookmark.iterations, bookmark.palette
+            );
+        }
+        return Ok(());
+    };
+    let bookmark = bookmarks::find(&saved, name)?;
+    let (width, height) = size_arg(args, (800, 600))?;
+    let viewer = viewer::Viewer::open(bookmark.view(width, height), bookmark.palette.clone())?.with_export(export_arg(args)?);
+    if args.flag("present") { viewer.presenting() } else { viewer }.run()
+}
+
+// Open the viewer in the dual view, the Julia set of --c being shown next to the Mandelbrot set, or next
+// to the λ plane of the logistic map with --fractal lambda
+fn dual_view(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (width, height) = size_arg(args, (640, 480))?;
+    let max_iterations = args.parse_value("iterations")?.unwrap_or(200);
+    let family = args.parse_value("fractal")?.unwrap_or_default();
+    let (bounds, default_c) = match family {
+        fractal::Family::Mandelbrot => ((-2.5, 1.5, -1.5, 1.5), fractal::Julia::DEFAULT_C),
+        fractal::Family::Lambda => (fractal::Lambda.default_bounds(), fractal::LambdaJulia::DEFAULT_LAMBDA),
+    };
+    let view = viewer::View::from_bounds(bounds, max_iterations, width, height);
+    let c = args.parse_value::<complex::Complex>("c")?.unwrap_or(default_c);
+    let palette = args.value("palette").unwrap_or("turbo").to_string();
+    let viewer = viewer::Viewer::open_family(view, palette, family)?.with_dual_view((c.re as f32, c.im as f32))?.with_export(export_arg(args)?);
+    if args.flag("present") { viewer.presenting() } else { viewer }.run()
+}
+
+// The quality of the views saved from the viewer with S
+fn export_arg(args: &Args) -> Result<viewer::Export, Box<dyn Error>> {
+    let default = viewer::Export::default();
+    Ok(viewer::Export {
+        width: args.parse_value("export-width")?.unwrap_or(default.width),
+        antialias: args.parse_value("export-aa")?.unwrap_or(default.antialias),
+        template: args.value("export-output").map_or(default.template, str::to_string),
+        post: post_process_arg(args, "export-axes")?,
+    })
+}
+
+// What is drawn over the images before they are saved: the axes with the flag given, the caption of
+// `--annotate` and the watermark of `--watermark`
+fn post_process_arg(args: &Args, axes_flag: &str) -> Result<postprocess::PostProcess, Box<dyn Error>> {
+    let watermark = match args.value("watermark") {
+        Some(path) => {
+            let corner = args.parse_value("watermark-corner")?.unwrap_or(text::Corner::BottomRight);
+            Some(postprocess::Watermark::load(path, corner, args.parse_value("watermark-opacity")?.unwrap_or(1.0))?)
+        }
+        None => None,
+    };
+    Ok(postprocess::PostProcess { axes: args.flag(axes_flag), caption: caption_arg(args)?, watermark })
+}
+
+// The caption of `--annotate`, in the corner of `--annotate-corner` with the font scaled by `--annotate-size`
+// in `--annotate-color`. Its text is still a template, whose tokens the caller knows.
+fn caption_arg(args: &Args) -> Result<Option<text::Caption>, Box<dyn Error>> {
+    // There is no font to choose, only the built-in one of text.rs
+    if args.value("annotate-font").is_some() {
+        return Err(cli::UsageError("--annotate writes in the built-in 5x7 font, the only one, which --annotate-size scales".to_string()).into());
+    }
+    let Some(text) = args.value("annotate") else {
+        return Ok(None);
+    };
+    let scale = args.parse_value("annotate-size")?.unwrap_or(2);
+    if scale == 0 {
+        return Err("--annotate-size scales the font by at least 1".into());
+    }
+    Ok(Some(text::Caption {
+        text: text.to_string(),
+        corner: args.parse_value("annotate-corner")?.unwrap_or_default(),
+        scale,
+        color: args.value("annotate-color").map_or(Ok(image::Rgb([255, 255, 255])), patterns::parse_hex_color)?,
+    }))
+}
+
+// Prompt for the appearance of a pattern, an empty answer keeps the default
+fn read_pattern_options() -> Result<patterns::PatternOptions, Box<dyn Error>> {
+    let mut options = patterns::PatternOptions::default();
+
+    println!("Enter the image size as WIDTHxHEIGHT or a single number (press Enter for {}x{}):", options.width, options.height);
+    let input: String = read!("{}\n");
+    match input.trim() {
+        "" => {}
+        size if size.contains('x') => (options.width, options.height) = cli::parse_size(size)?,
+        size => {
+            let size = size.parse().map_err(|_| format!("Invalid size '{}'", size))?;
+            (options.width, options.height) = (size, size);
+        }
+    }
+
+    println!("Enter the two colors, e.g. #ffffff #000000 (press Enter for white and black):");
+    let input: String = read!("{}\n");
+    let colors: Vec<&str> = input.split_whitespace().collect();
+    match colors.as_slice() {
+        [] => {}
+        [light, dark] => {
+            options.light = patterns::parse_hex_color(light)?;
+            options.dark = patterns::parse_hex_color(dark)?;
+        }
+        _ => return Err("Expected two colors separated by a space".into()),
+    }
+
+    println!("Enter a border color and width, e.g. #808080 10 (press Enter for no border):");
+    let input: String = read!("{}\n");
+    let border: Vec<&str> = input.split_whitespace().collect();
+    match border.as_slice() {
+        [] => {}
+        [color, width] => {
+            let width = width.parse().map_err(|_| format!("Invalid border width '{}'", width))?;
+            options.border = Some((patterns::parse_hex_color(color)?, width));
+        }
+        _ => return Err("Expected a border color and a width separated by a space".into()),
+    }
+    Ok(options)
+}
+
+// Render the Julia sets met along a path of the constant c
+fn julia_sweep(args: &Args) -> Result<(), Box<dyn Error>> {
+    let point = |name: &str, default: (f32, f32)| -> Result<(f32, f32), cli::UsageError> {
+        args.value(name).map_or(Ok(default), cli::parse_point)
+    };
+    let path = match args.value("path").unwrap_or("cardioid") {
+        "cardioid" => sweep::SweepPath::Cardioid { scale: args.parse_value("radius")?.unwrap_or(1.0) },
+        "circle" => sweep::SweepPath::Circle {
+            center: point("center", (0.0, 0.0))?,
+            radius: args.parse_value("radius")?.unwrap_or(0.7885),
+        },
+        "line" => sweep::SweepPath::Line { from: point("from", (-0.8, 0.156))?, to: point("to", (0.285, 0.01))? },
+        "waypoints" => sweep::SweepPath::Waypoints(sweep::parse_waypoints(args.value("points").ok_or("--path waypoints needs --points \"RE,IM;RE,IM;...\"")?)?),
+        other => return Err(format!("Unknown path '{}'. Available paths: cardioid, circle, line, waypoints", other).into()),
+    };
+
+    let (width, height) = size_arg(args, (800, 600))?;
+    let framing = framing_arg(args, (-1.6, 1.6, -1.2, 1.2), width, height)?;
+    let options = sweep::SweepOptions {
+        width,
+        height,
+        max_iterations: args.parse_value("iterations")?.unwrap_or(200),
+        bounds: framing.bounds,
+        letterbox: framing.region,
+        palette: args.value("palette").unwrap_or("turbo").to_string(),
+        frames: args.parse_value("frames")?.unwrap_or(120),
+        fps: args.parse_value("fps")?.unwrap_or(30),
+    };
+
+    let tokens = naming::NameTokens::new().with("fractal", "julia").size(width, height).with("iter", options.max_iterations);
+    let output = frames_output_arg(args, "julia_frames", &tokens.with("palette", &options.palette))?;
+    sweep::render_sweep(&path, &options, &output)?;
+    info!("Julia sweep of {} frames saved to {}", options.frames, output);
+    Ok(())
+}
+
+// Read bounds typed in the menu, asking again until they select a region. Enter keeps the default.
+fn read_bounds(default: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+    loop {
+        let (xmin, xmax, ymin, ymax) = default;
+        println!("Enter the space to display in the format xmin;xmax;ymin;ymax, or press Enter for {};{};{};{}:", xmin, xmax, ymin, ymax);
+        let input: String = read!("{}\n");
+        if input.trim().is_empty() {
+            return default;
+        }
+        match bounds::parse_checked(&input) {
+            Ok(checked) => return checked_bounds(checked),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// Read the name of the file to save typed in the menu, a template of naming like
+// {fractal}_{zoom}_{timestamp}.png, asking again until it is valid. Enter keeps the default.
+fn read_file_name(default: &str, tokens: &naming::NameTokens) -> String {
+    loop {
+        println!("Enter the file name, a template like {{fractal}}_{{zoom}}_{{timestamp}}.png, or press Enter for {}:", default);
+        let input: String = read!("{}\n");
+        if input.trim().is_empty() {
+            return default.to_string();
+        }
+        match naming::expand(input.trim(), tokens) {
+            Ok(name) if naming::has_frame(&name) => println!("{{frame}} only names the frames of animations"),
+            Ok(name) => return name,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// The bounds once checked, logging what was repaired or looks wrong about them.
+fn checked_bounds(checked: bounds::CheckedBounds) -> (f32, f32, f32, f32) {
+    for warning in &checked.warnings {
+        warn!("{}", warning);
+    }
+    checked.bounds
+}
+
+// Read the iteration limit typed in the menu, asking again until it is valid. Enter keeps 100.
+fn read_iterations(bounds: (f32, f32, f32, f32)) -> u32 {
+    loop {
+        println!("Enter the maximum number of iterations, 'auto' to scale it with the zoom, or press Enter for 100:");
+        let input: String = read!("{}\n");
+        match parse_iterations(&input, bounds) {
+            Ok(max_iterations) => return max_iterations,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// Helper function to parse the iteration limit typed in the menu, "auto" choosing it from the zoom of the bounds
+fn parse_iterations(input: &str, bounds: (f32, f32, f32, f32)) -> Result<u32, String> {
+    match input.trim() {
+        "" => Ok(100),
+        "auto" => {
+            let zoom = mandelbrot::DEFAULT_VIEW_WIDTH / (bounds.1 - bounds.0);
+            let max_iterations = mandelbrot::auto_iterations(zoom);
+            info!("Using {} iterations for a zoom of {}x", max_iterations, zoom);
+            Ok(max_iterations)
+        }
+        input => input
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Invalid number of iterations '{}', expected a positive whole number or 'auto'", input)),
     }
 }
 
-// Function to generate a Mandelbrot set image using specified color map and bounds
-fn generate_mandelbrot_set(color_choice: String, max_iterations: u32, bounds: (f32, f32, f32, f32)) -> RgbImage {
+// Function to generate a Mandelbrot set image using specified color map, bounds and image size
+fn generate_mandelbrot_set(color_choice: String, max_iterations: u32, bounds: (f32, f32, f32, f32), size: (u32, u32)) -> RgbImage {
     let color_map: Box<dyn ColorMap> = if color_choice.trim() == "c" {
         Box::new(ColoredColorMap::new(max_iterations))
     } else {
         Box::new(GrayscaleMap::new(max_iterations))
     };
 
-    mandelbrot::generate_mandelbrot_set(800, 600, &*color_map, bounds)
+    mandelbrot::generate_mandelbrot_set(size.0, size.1, &*color_map, bounds)
 }
 
 // Function to display an image in a window using show_image crate
@@ -1,11 +1,92 @@
 // Import necessary image handling and gradient functionalities from external crates.
-use image::{Rgb, RgbImage};
+use crate::coloring::{OrbitAverage, OrbitStatistic};
+use crate::complex::Complex;
+use crate::fractal::{self, EscapeResult, Fractal, Julia, Mandelbrot, DEFAULT_BAILOUT};
+use crate::dither::Dither;
+use crate::error::Error;
+use crate::light::Gamma;
+use crate::palette::{self, Palette};
+use image::{ImageBuffer, Luma, Rgb, RgbImage};
 use colorgrad::Gradient;
 
+// Everything known about the orbit of a pixel, so color maps are not limited to the iteration count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscapeInfo {
+    pub iterations: u32,
+    pub escaped: bool,
+    pub smooth: f64, // Continuous iteration count, removing the bands between whole counts
+    pub final_z: Complex,
+    pub min_trap_distance: f64, // Closest approach of the orbit to the origin
+    pub orbit_average: f64,     // Average of the statistic of the color map along the orbit, 0 without one
+}
+
+impl EscapeInfo {
+    // Describe the result of iterating a point up to max_iterations with a bailout radius (larger than 1).
+    pub fn new(escape: &EscapeResult, max_iterations: u32, bailout: f64) -> Self {
+        let escaped = escape.iterations < max_iterations;
+        // n + 1 - log2(ln |z| / ln R) makes the count continuous across the escape circle of radius R;
+        // orbits stopped at an attractor never reached the circle and keep their whole count.
+        let smooth = if escaped && !escape.converged && escape.z.norm() > 1.0 {
+            (escape.iterations as f64 + 1.0 - (escape.z.norm().ln() / bailout.ln()).log2()).max(0.0)
+        } else {
+            escape.iterations as f64
+        };
+        Self {
+            iterations: escape.iterations,
+            escaped,
+            smooth,
+            final_z: escape.z,
+            min_trap_distance: escape.min_distance,
+            orbit_average: 0.0,
+        }
+    }
+
+    // Add the average gathered along the orbit. The averages with and without the last step are
+    // blended with the fraction of the smooth count, which removes the bands between whole counts.
+    pub fn with_orbit_average(mut self, average: &OrbitAverage) -> Self {
+        let (previous, last) = average.averages();
+        self.orbit_average = previous + (last - previous) * self.smooth.fract();
+        self
+    }
+
+    // The information of the conjugate point, for fractals whose conjugate points have conjugate orbits.
+    pub fn mirrored(&self, statistic: Option<OrbitStatistic>) -> Self {
+        Self {
+            final_z: self.final_z.conj(),
+            orbit_average: statistic.map_or(self.orbit_average, |statistic| statistic.mirrored_average(self.orbit_average)),
+            ..*self
+        }
+    }
+
+    // Adapter for buffers holding only iteration counts: the other fields are derived from the count.
+    pub fn from_iterations(iterations: u32, max_iterations: u32) -> Self {
+        Self {
+            iterations,
+            escaped: iterations < max_iterations,
+            smooth: iterations as f64,
+            final_z: Complex::ZERO,
+            min_trap_distance: 0.0,
+            orbit_average: 0.0,
+        }
+    }
+}
+
 // Define a trait to specify behaviors for color mapping in different scenarios.
 pub trait ColorMap {
-    fn color(&self, i: u32) -> Rgb<u8>;
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8>;
+
+    // The color before its quantization to 8 bits, channels in [0, 1], for the dithered output.
+    // The maps computing their colors as floats return them here and quantize them in color.
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        self.color(escape).0.map(|channel| channel as f64 / 255.0)
+    }
+
     fn get_max_iterations(&self) -> u32;
+
+    // The statistic the compute pass has to average along each orbit for this map, if any.
+    fn statistic(&self) -> Option<OrbitStatistic> {
+        None
+    }
 }
 // A structure to handle grayscale mapping with a specific maximum iteration count.
 pub struct GrayscaleMap {
@@ -22,7 +103,8 @@ impl GrayscaleMap {
 
 // Implement the ColorMap trait for GrayscaleMap.
 impl ColorMap for GrayscaleMap {
-    fn color(&self, i: u32) -> Rgb<u8> {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        let i = escape.iterations;
         if i == self.max_iterations {
             Rgb([0, 0, 0]) // Points inside the set are black
         } else {
@@ -39,29 +121,82 @@ impl ColorMap for GrayscaleMap {
 // A structure to handle colored mapping using a gradient, supporting a specific max iteration count.
 pub struct ColoredColorMap {
     max_iterations: u32,
-    // Gradient to use for coloring outside the set.
-    gradient: Gradient,
+    // Palette to use for coloring outside the set.
+    palette: Palette,
+    // Fraction of the gradient it is rotated by, used to cycle the palette.
+    offset: f64,
 }
 // Implementation block for ColoredColorMap.
 impl ColoredColorMap {
     pub fn new(max_iterations: u32) -> Self {
         Self {
             max_iterations,
-            gradient: colorgrad::turbo(), // Utilizes the turbo gradient from colorgrad crate
+            palette: Palette::new(colorgrad::turbo()), // Utilizes the turbo gradient from colorgrad crate
+            offset: 0.0,
         }
     }
+
+    // Create a colored map using one of the named colorgrad gradients instead of turbo.
+    pub fn with_palette(max_iterations: u32, palette: &str) -> Result<Self, Error> {
+        Ok(Self::from_palette(max_iterations, Palette::by_name(palette)?))
+    }
+
+    // Create a colored map with a palette, possibly transformed.
+    pub fn from_palette(max_iterations: u32, palette: Palette) -> Self {
+        Self { max_iterations, palette, offset: 0.0 }
+    }
+
+    // Rotate the gradient, the colors leaving at its end coming back at its start.
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.offset = offset.rem_euclid(1.0);
+        self
+    }
+}
+
+// Names of the built-in gradients that can be selected as palettes, see palette::palette_names for all of them.
+pub const PALETTE_NAMES: [&str; 8] = ["turbo", "viridis", "inferno", "magma", "plasma", "cividis", "rainbow", "sinebow"];
+
+// Name of the grayscale color map, accepted wherever a palette name is.
+pub const GRAYSCALE: &str = "grayscale";
+
+// Build the color map of a palette name, the grayscale map or a colored map with that gradient.
+pub fn color_map_by_name(name: &str, max_iterations: u32) -> Result<Box<dyn ColorMap>, Error> {
+    if name == GRAYSCALE {
+        Ok(Box::new(GrayscaleMap::new(max_iterations)))
+    } else {
+        Ok(Box::new(ColoredColorMap::with_palette(max_iterations, name)?))
+    }
+}
+
+// Look up a colorgrad preset gradient by name, or else a gradient registered with palette::register_gradient.
+pub fn gradient_by_name(name: &str) -> Result<Gradient, Error> {
+    match name {
+        "turbo" => Ok(colorgrad::turbo()),
+        "viridis" => Ok(colorgrad::viridis()),
+        "inferno" => Ok(colorgrad::inferno()),
+        "magma" => Ok(colorgrad::magma()),
+        "plasma" => Ok(colorgrad::plasma()),
+        "cividis" => Ok(colorgrad::cividis()),
+        "rainbow" => Ok(colorgrad::rainbow()),
+        "sinebow" => Ok(colorgrad::sinebow()),
+        _ => palette::registered_gradient(name).unwrap_or_else(|| Err(Error::UnknownPalette(name.to_string()))),
+    }
 }
 
 // Implement the ColorMap trait for ColoredColorMap.
 impl ColorMap for ColoredColorMap {
     // Define how to color a pixel based on the iteration count for a colored image.
-    fn color(&self, i: u32) -> Rgb<u8> {
+    fn color(&self, escape: &EscapeInfo) -> Rgb<u8> {
+        quantize(self.color_float(escape))
+    }
+
+    fn color_float(&self, escape: &EscapeInfo) -> [f64; 3] {
+        let i = escape.iterations;
         if i >= self.max_iterations {
-            Rgb([0, 0, 0]) // Points inside the set are black
+            [0.0; 3] // Points inside the set are black
         } else {
-            let t = i as f64 / (self.max_iterations - 1) as f64; // Normalized iteration value
-            let color = self.gradient.at(t).to_rgba8();
-            Rgb([color[0], color[1], color[2]])
+            let t = i as f64 / (self.max_iterations - 1) as f64 + self.offset; // Normalized iteration value
+            self.palette.at_float(if t > 1.0 { t - 1.0 } else { t })
         }
     }
 
@@ -72,30 +207,206 @@ impl ColorMap for ColoredColorMap {
 
 // Function to generate a Mandelbrot set image based on the provided ColorMap and dimensions.
 pub fn generate_mandelbrot_set(width: u32, height: u32, color_map: &dyn ColorMap, bounds: (f32, f32, f32, f32)) -> RgbImage {
-    let mut img = RgbImage::new(width, height);
-    let (xmin, xmax, ymin, ymax) = bounds;
-    let scale_x = (xmax - xmin) / width as f32;
-    let scale_y = (ymax - ymin) / height as f32;
-
-    // Iterate over each pixel in the image.
-    for px in 0..width {
-        for py in 0..height {
-            let x0 = px as f32 * scale_x + xmin;
-            let y0 = py as f32 * scale_y + ymin;
-            let (mut x, mut y, mut iteration) = (0.0, 0.0, 0);
-
-            // Compute whether the point (x, y) escapes the Mandelbrot set within max_iterations.
-            while x * x + y * y <= 4.0 && iteration < color_map.get_max_iterations() {
-                let xtemp = x * x - y * y + x0;
-                y = 2.0 * x * y + y0;
-                x = xtemp;
-                iteration += 1;
-            }
-            // Set the pixel color based on the number of iterations and the colormap.
-            img.put_pixel(px, py, color_map.color(iteration));
+    let max_iterations = color_map.get_max_iterations();
+    let escapes = compute_escape_info(&Mandelbrot, width, height, max_iterations, bounds, DEFAULT_BAILOUT, color_map.statistic());
+    colorize_escapes(width, height, &escapes, color_map)
+}
+
+// Width of the complex plane shown at zoom 1, matching the default bounds x ⍷ [-2, 2].
+pub const DEFAULT_VIEW_WIDTH: f32 = 4.0;
+
+// Iteration limit chosen by auto_iterations at zoom 1, and added to it for every tenfold magnification.
+pub const AUTO_ITERATIONS_BASE: f32 = 100.0;
+pub const AUTO_ITERATIONS_PER_DECADE: f32 = 150.0;
+
+// An iteration limit growing with the log of the magnification: the deeper the view, the longer the
+// orbits of the points near the boundary, which would otherwise all look like the interior.
+pub fn auto_iterations(zoom: f32) -> u32 {
+    (AUTO_ITERATIONS_BASE + AUTO_ITERATIONS_PER_DECADE * zoom.log10().max(0.0)).round() as u32
+}
+
+// Convert a view given as a center and a magnification into bounds matching the image aspect ratio.
+pub fn view_bounds(center: (f32, f32), zoom: f32, width: u32, height: u32) -> (f32, f32, f32, f32) {
+    let half_width = DEFAULT_VIEW_WIDTH / zoom / 2.0;
+    let half_height = half_width * height as f32 / width as f32;
+    let (cx, cy) = center;
+    (cx - half_width, cx + half_width, cy - half_height, cy + half_height)
+}
+
+// Compute the escape iteration count of every pixel, stored row by row.
+// Keeping this separate from the coloring allows recoloring (e.g. palette cycling) without recomputing.
+pub fn compute_iterations(width: u32, height: u32, max_iterations: u32, bounds: (f32, f32, f32, f32)) -> Vec<u32> {
+    fractal::compute_iterations(&Mandelbrot, width, height, max_iterations, bounds)
+}
+
+// Compute the iteration counts of the Julia set of c: the pixel is the starting point of the orbit.
+pub fn compute_julia_iterations(width: u32, height: u32, max_iterations: u32, bounds: (f32, f32, f32, f32), c: (f32, f32)) -> Vec<u32> {
+    let julia = Julia::new(Complex::new(c.0 as f64, c.1 as f64));
+    fractal::compute_iterations(&julia, width, height, max_iterations, bounds)
+}
+
+// Compute the escape information of every pixel of any escape-time fractal, stored row by row,
+// averaging the statistic along the orbits when the coloring needs one.
+pub fn compute_escape_info(
+    fractal: &dyn Fractal,
+    width: u32,
+    height: u32,
+    max_iterations: u32,
+    bounds: (f32, f32, f32, f32),
+    bailout: f64,
+    statistic: Option<OrbitStatistic>,
+) -> Vec<EscapeInfo> {
+    let escape_info = |point| match statistic {
+        Some(statistic) => {
+            let mut average = OrbitAverage::new(statistic);
+            let escape = fractal.iterate_with(point, max_iterations, bailout, &mut average);
+            EscapeInfo::new(&escape, max_iterations, bailout).with_orbit_average(&average)
         }
+        None => EscapeInfo::new(&fractal.iterate_with(point, max_iterations, bailout, &mut ()), max_iterations, bailout),
+    };
+    if fractal.conjugate_symmetric() {
+        fractal::map_plane_symmetric(width, height, bounds, escape_info, |escape| escape.mirrored(statistic))
+    } else {
+        fractal::map_plane(width, height, bounds, escape_info)
+    }
+}
+
+// Turn a buffer of iteration counts (as returned by compute_iterations) into an image using the colormap.
+pub fn colorize(width: u32, height: u32, iterations: &[u32], color_map: &dyn ColorMap) -> RgbImage {
+    let max_iterations = color_map.get_max_iterations();
+    let mut img = RgbImage::new(width, height);
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        // Set the pixel color based on the number of iterations and the colormap.
+        *pixel = color_map.color(&EscapeInfo::from_iterations(iterations[(py * width + px) as usize], max_iterations));
     }
     // Return the completed image.
     img
 }
 
+// Turn a buffer of escape information (as returned by compute_escape_info) into an image using the colormap.
+pub fn colorize_escapes(width: u32, height: u32, escapes: &[EscapeInfo], color_map: &dyn ColorMap) -> RgbImage {
+    let mut img = RgbImage::new(width, height);
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        *pixel = color_map.color(&escapes[(py * width + px) as usize]);
+    }
+    img
+}
+
+// The same with the float colors of the colormap dithered into 8 bits, which removes the bands of
+// smooth gradients.
+pub fn colorize_escapes_dithered(width: u32, height: u32, escapes: &[EscapeInfo], color_map: &dyn ColorMap, dither: Dither) -> RgbImage {
+    if dither == Dither::None {
+        return colorize_escapes(width, height, escapes, color_map);
+    }
+    let mut img = RgbImage::new(width, height);
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        *pixel = Rgb(dither.quantize(color_map.color_float(&escapes[(py * width + px) as usize]), px, py));
+    }
+    img
+}
+
+// Turn the escape information of a render supersampled `factor` times in both directions into an image
+// of width x height, every pixel getting the average light of its factor² samples.
+pub fn colorize_supersampled(
+    width: u32,
+    height: u32,
+    factor: u32,
+    escapes: &[EscapeInfo],
+    color_map: &dyn ColorMap,
+    dither: Dither,
+    gamma: Gamma,
+) -> RgbImage {
+    if factor <= 1 {
+        return colorize_escapes_dithered(width, height, escapes, color_map, dither);
+    }
+    let samples_width = width * factor;
+    let mut img = RgbImage::new(width, height);
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        let samples = (0..factor).flat_map(|sy| (0..factor).map(move |sx| ((py * factor + sy) * samples_width + px * factor + sx) as usize));
+        let color = gamma.mix(samples.map(|index| (1.0, color_map.color_float(&escapes[index]))));
+        *pixel = Rgb(dither.quantize(color, px, py));
+    }
+    img
+}
+
+// A 16-bit single channel image of the smooth iteration counts, for scientific tools rather than for
+// viewing: a pixel is the count divided by the limit and scaled to 0..65535, points that did not escape
+// getting 65535, and `factor`² samples of a supersampled render are averaged.
+pub fn grayscale16(width: u32, height: u32, factor: u32, escapes: &[EscapeInfo], max_iterations: u32) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+    let factor = factor.max(1);
+    let samples_width = width * factor;
+    ImageBuffer::from_fn(width, height, |px, py| {
+        let samples = (0..factor).flat_map(|sy| (0..factor).map(move |sx| ((py * factor + sy) * samples_width + px * factor + sx) as usize));
+        let total: f64 = samples
+            .map(|index| {
+                let escape = &escapes[index];
+                if escape.escaped { (escape.smooth / max_iterations as f64).clamp(0.0, 1.0) } else { 1.0 }
+            })
+            .sum();
+        Luma([(total / (factor * factor) as f64 * u16::MAX as f64).round() as u16])
+    })
+}
+
+// Round a color with channels in [0, 1] to 8 bits.
+pub fn quantize(color: [f64; 3]) -> Rgb<u8> {
+    Rgb(Dither::None.quantize(color, 0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the smooth count of an escaped point is continuous rather than a whole count.
+    #[test]
+    fn test_escape_info_smooth() {
+        let max_iterations = 100;
+        // 0.5 + 0.5i escapes after 5 iterations with |z| ≈ 3.549, so 5 + 1 - log2(ln 3.549 / ln 2).
+        let info = EscapeInfo::new(&Mandelbrot.iterate(Complex::new(0.5, 0.5), max_iterations), max_iterations, DEFAULT_BAILOUT);
+        assert!(info.escaped);
+        assert_eq!(info.iterations, 5);
+        assert!((info.smooth - 5.1301).abs() < 1e-3);
+
+        let inside = EscapeInfo::new(&Mandelbrot.iterate(Complex::ZERO, max_iterations), max_iterations, DEFAULT_BAILOUT);
+        assert!(!inside.escaped);
+        assert_eq!(inside.smooth, max_iterations as f64);
+    }
+
+    /// Tests that the automatic iteration limit grows by a fixed amount for every tenfold zoom.
+    #[test]
+    fn test_auto_iterations() {
+        assert_eq!(auto_iterations(1.0), 100);
+        assert_eq!(auto_iterations(0.5), 100); // Zooming out never lowers the limit
+        assert_eq!(auto_iterations(1000.0), 550);
+        assert_eq!(auto_iterations(1e6), 1000);
+    }
+
+    /// Tests that the samples of a pixel are averaged in linear light.
+    #[test]
+    fn test_colorize_supersampled() {
+        // Every other sample is in the interior, the others are white
+        let escapes: Vec<EscapeInfo> = (0..16).map(|i| EscapeInfo::from_iterations(if (i + i / 4) % 2 == 0 { 10 } else { 9 }, 10)).collect();
+        let image = colorize_supersampled(2, 2, 2, &escapes, &GrayscaleMap::new(10), Dither::None, Gamma::Srgb);
+        // Half of the light of the gray 230 of 9 iterations, brighter than the 115 of the plain average
+        assert!(image.pixels().all(|pixel| pixel.0 == [169, 169, 169]), "{:?}", image);
+        let single = colorize_supersampled(4, 4, 1, &escapes, &GrayscaleMap::new(10), Dither::None, Gamma::Srgb);
+        assert_eq!(single, colorize_escapes(4, 4, &escapes, &GrayscaleMap::new(10)));
+    }
+
+    /// Tests that the 16-bit output is linear in the smooth count with the interior at the top.
+    #[test]
+    fn test_grayscale16() {
+        let escapes: Vec<EscapeInfo> = [0, 25, 100, 50].iter().map(|&i| EscapeInfo::from_iterations(i, 100)).collect();
+        let image = grayscale16(4, 1, 1, &escapes, 100);
+        assert_eq!(image.as_raw(), &vec![0, 16384, 65535, 32768]);
+        assert_eq!(grayscale16(2, 1, 2, &[escapes.clone(), escapes].concat(), 100).as_raw(), &vec![8192, 49151]);
+    }
+
+    /// Tests that the iteration maps color a plain iteration buffer through the adapter as before.
+    #[test]
+    fn test_colorize_iteration_buffer() {
+        let image = colorize(3, 1, &[0, 50, 100], &GrayscaleMap::new(100));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 0), &Rgb([128, 128, 128]));
+        assert_eq!(image.get_pixel(2, 0), &Rgb([0, 0, 0]));
+    }
+}
@@ -0,0 +1,165 @@
+// Embed the render parameters into the PNG text chunks so an image can be re-rendered later.
+use crate::mandelbrot;
+use crate::render::RenderOptions;
+use crate::{bounds, cli};
+use image::RgbImage;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// Keyword of the tEXt chunk holding the RenderOptions of the image as JSON. The Mandelbrot PNGs of the
+// menu, render, reproduce, interesting, explore, the S export of the viewer, the tiles of pyramid and
+// run-job and the image stitched by merge-job hold it.
+const KEY_RENDER_OPTIONS: &str = "RenderOptions";
+// Images saved before the whole options were stored hold these instead, and only the menu wrote them.
+const KEY_COLOR_MAP: &str = "ColorMap";
+const KEY_MAX_ITERATIONS: &str = "MaxIterations";
+const KEY_CENTER: &str = "Center";
+const KEY_ZOOM: &str = "Zoom";
+// And before the center and zoom were stored, their bounds.
+const KEY_BOUNDS: &str = "Bounds";
+
+// Convert the options of a render into (keyword, text) pairs for the PNG text chunks.
+pub fn to_text_chunks(options: &RenderOptions) -> Result<Vec<(String, String)>, String> {
+    let json = serde_json::to_string(options).map_err(|e| format!("Could not store the render options: {}", e))?;
+    Ok(vec![(KEY_RENDER_OPTIONS.to_string(), json)])
+}
+
+// Rebuild the options of a render from the text chunks of a PNG file of the given size. The view of the
+// options is kept as its center and zoom rather than as bounds, so that it can be rendered at another size
+// without being stretched.
+pub fn from_text_chunks(chunks: &[(String, String)], size: (u32, u32)) -> Result<RenderOptions, String> {
+    let find = |key: &str| {
+        chunks
+            .iter()
+            .find(|(keyword, _)| keyword == key)
+            .map(|(_, text)| text.as_str())
+            .ok_or_else(|| format!("Missing '{}' metadata", key))
+    };
+
+    if let Ok(json) = find(KEY_RENDER_OPTIONS) {
+        let options: RenderOptions = serde_json::from_str(json).map_err(|e| format!("Invalid 'RenderOptions' metadata: {}", e))?;
+        return Ok(RenderOptions { width: size.0, height: size.1, ..options });
+    }
+
+    let color_choice = find(KEY_COLOR_MAP).map_err(|_| format!("Missing '{}' metadata", KEY_RENDER_OPTIONS))?;
+    let palette = match color_choice {
+        "c" => "turbo",
+        "gs" => mandelbrot::GRAYSCALE,
+        other => return Err(format!("Invalid 'ColorMap' metadata '{}'", other)),
+    };
+    let max_iterations = find(KEY_MAX_ITERATIONS)?
+        .parse::<u32>()
+        .map_err(|_| "Invalid 'MaxIterations' metadata".to_string())?;
+    let builder = RenderOptions::builder().size(size.0, size.1).iterations(max_iterations).palette(palette);
+    let builder = if find(KEY_CENTER).is_err() {
+        builder.bounds(bounds::parse_bounds(find(KEY_BOUNDS)?)?)
+    } else {
+        let center = cli::parse_point(find(KEY_CENTER)?)?;
+        let zoom = find(KEY_ZOOM)?.parse::<f32>().ok().filter(|zoom| zoom.is_finite() && *zoom > 0.0);
+        let zoom = zoom.ok_or_else(|| "Invalid 'Zoom' metadata".to_string())?;
+        builder.center(center.0, center.1).zoom(zoom)
+    };
+
+    builder.build().map_err(String::from)
+}
+
+// Save an image as PNG with the options it was rendered with stored in tEXt chunks.
+pub fn save_with_metadata(image: &RgbImage, path: impl AsRef<Path>, options: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in to_text_chunks(options)? {
+        encoder.add_text_chunk(keyword, text)?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    Ok(())
+}
+
+// Save a Mandelbrot render, with its options when it is written as PNG and in whatever format the
+// extension asks for otherwise.
+pub fn save_render(image: &RgbImage, path: impl AsRef<Path>, options: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    if is_png(path) {
+        save_with_metadata(image, path, options)
+    } else {
+        Ok(image.save(path)?)
+    }
+}
+
+pub fn is_png(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+// Read the options a PNG saved with save_with_metadata was rendered with, at the size of the image.
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<RenderOptions, Box<dyn Error>> {
+    let reader = png::Decoder::new(File::open(path)?).read_info()?;
+    let info = reader.info();
+    let chunks: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+
+    Ok(from_text_chunks(&chunks, (info.width, info.height))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use final_exercice::dither::Dither;
+    use final_exercice::light::Gamma;
+
+    fn chunks(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(keyword, text)| (keyword.to_string(), text.to_string())).collect()
+    }
+
+    /// Tests that every option survives a round trip through the text chunks, the size being the image's.
+    #[test]
+    fn test_text_chunks_round_trip() {
+        let options = RenderOptions::builder()
+            .size(640, 480)
+            .center(-0.743643, 0.131825)
+            .zoom(12000.0)
+            .iterations(250)
+            .palette("viridis")
+            .coloring("smooth")
+            .bailout(1e6)
+            .dither(Dither::Bayer)
+            .antialias(3)
+            .gamma(Gamma::Power(2.2))
+            .build()
+            .unwrap();
+        let read = from_text_chunks(&to_text_chunks(&options).unwrap(), (640, 480)).unwrap();
+        assert_eq!(read, options);
+        assert_eq!(from_text_chunks(&to_text_chunks(&options).unwrap(), (320, 240)).unwrap().width, 320);
+    }
+
+    /// Tests that the center and zoom of the images saved before the whole options are read as a view.
+    #[test]
+    fn test_legacy_metadata() {
+        let read = from_text_chunks(&chunks(&[("ColorMap", "gs"), ("MaxIterations", "250"), ("Center", "-0.75,0.1"), ("Zoom", "40")]), (800, 600));
+        let options = read.unwrap();
+        assert_eq!((options.center, options.zoom, options.max_iterations), ((-0.75, 0.1), 40.0, 250));
+        assert_eq!(options.palette, mandelbrot::GRAYSCALE);
+    }
+
+    /// Tests that the bounds of the images saved before the center and zoom are read as a view.
+    #[test]
+    fn test_bounds_metadata() {
+        let options = from_text_chunks(&chunks(&[("ColorMap", "c"), ("MaxIterations", "100"), ("Bounds", "-2;2;-1.5;1.5")]), (800, 600)).unwrap();
+        assert_eq!((options.center, options.zoom, options.palette.as_str()), ((0.0, 0.0), 1.0, "turbo"));
+        assert_eq!(options.bounds(), (-2.0, 2.0, -1.5, 1.5));
+    }
+
+    /// Tests that a PNG without our chunks is reported instead of silently using defaults.
+    #[test]
+    fn test_missing_metadata() {
+        assert!(from_text_chunks(&chunks(&[("Software", "gimp")]), (800, 600)).is_err());
+        assert!(from_text_chunks(&chunks(&[("RenderOptions", "{\"width\": 800}")]), (800, 600)).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+// Templates of output file names, so that batch runs with different parameters write different files
+// instead of overwriting one another. `--output` takes tokens in braces, replaced by the parameters of
+// the render:
+//
+//     {fractal}    the fractal rendered, like mandelbrot or newton
+//     {center}     the center of the view, as RE_IM
+//     {zoom}       the magnification
+//     {iter}       the iteration limit
+//     {size}       the image size, as WIDTHxHEIGHT
+//     {palette}    the palette
+//     {timestamp}  when the command started, in UTC, as YYYYMMDD-HHMMSS
+//     {frame}      the number of the frame, in the outputs of animations
+//
+// A width like {frame:05} pads the value with zeros. Braces are written {{ and }}. A command fills the
+// tokens it knows; the others are refused, except {frame} which is kept for the frames to fill in.
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const TOKENS: [&str; 8] = ["fractal", "center", "zoom", "iter", "size", "palette", "timestamp", "frame"];
+const FRAME: &str = "frame";
+
+// The values of the tokens for one output.
+#[derive(Debug, Clone, Default)]
+pub struct NameTokens {
+    values: Vec<(&'static str, String)>,
+}
+
+impl NameTokens {
+    // The tokens known to every command: only the timestamp of now.
+    pub fn new() -> Self {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        Self::default().with("timestamp", timestamp(seconds))
+    }
+
+    pub fn with(mut self, token: &'static str, value: impl Display) -> Self {
+        self.values.retain(|(name, _)| *name != token);
+        self.values.push((token, value.to_string()));
+        self
+    }
+
+    pub fn view(self, center: (f32, f32), zoom: f32) -> Self {
+        self.with("center", format!("{}_{}", center.0, center.1)).with("zoom", zoom)
+    }
+
+    // The view of bounds, at the zoom making them `extent` times narrower than the default view is wide.
+    pub fn region(self, bounds: (f32, f32, f32, f32), extent: f32) -> Self {
+        let (xmin, xmax, ymin, ymax) = bounds;
+        self.view(((xmin + xmax) / 2.0, (ymin + ymax) / 2.0), extent / (xmax - xmin))
+    }
+
+    pub fn size(self, width: u32, height: u32) -> Self {
+        self.with("size", format!("{}x{}", width, height))
+    }
+
+    // The tokens given a value, in the order they were given.
+    pub fn values(&self) -> &[(&'static str, String)] {
+        &self.values
+    }
+
+    fn get(&self, token: &str) -> Option<&str> {
+        self.values.iter().find(|(name, _)| *name == token).map(|(_, value)| value.as_str())
+    }
+}
+
+// Replace the tokens of a template by their values.
+pub fn expand(template: &str, tokens: &NameTokens) -> Result<String, String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        name.push_str(&rest[..index]);
+        let brace = &rest[index..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            name.push_str(&brace[..1]);
+            rest = &brace[2..];
+            continue;
+        }
+        let end = brace.find('}').filter(|_| brace.starts_with('{')).ok_or_else(|| format!("Unmatched brace in the file name '{}'", template))?;
+        let (token, width) = match brace[1..end].split_once(':') {
+            Some((token, width)) => (token, Some(width)),
+            None => (&brace[1..end], None),
+        };
+        match tokens.get(token) {
+            Some(value) => name.push_str(&pad(value, width, template)?),
+            None if token == FRAME => name.push_str(&brace[..=end]),
+            None if TOKENS.contains(&token) => return Err(format!("The token {{{}}} has no value in this command", token)),
+            None => return Err(format!("Unknown token {{{}}} in the file name '{}'. Available tokens: {}", token, template, TOKENS.join(", "))),
+        }
+        rest = &brace[end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+// The name of a frame in a template holding {frame}.
+pub fn frame_name(template: &str, frame: u32) -> Result<String, String> {
+    expand(template, &NameTokens::default().with(FRAME, frame))
+}
+
+pub fn has_frame(template: &str) -> bool {
+    template.contains("{frame}") || template.contains("{frame:")
+}
+
+fn pad(value: &str, width: Option<&str>, template: &str) -> Result<String, String> {
+    match width {
+        None => Ok(value.to_string()),
+        Some(width) => match width.strip_prefix('0').and_then(|digits| digits.parse::<usize>().ok()) {
+            Some(width) => Ok(format!("{:0>width$}", value, width = width)),
+            None => Err(format!("Invalid width '{}' in the file name '{}', expected zeros like {{frame:05}}", width, template)),
+        },
+    }
+}
+
+// A time in seconds since 1970 as YYYYMMDD-HHMMSS, in UTC.
+fn timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // Date of a day since 1970, counting in eras of 400 years from March 1st, 0000
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the tokens are replaced, padded and escaped, and that unknown ones are refused.
+    #[test]
+    fn test_expand_template() {
+        let tokens = NameTokens::default().with("fractal", "mandelbrot").view((-0.75, 0.1), 40.0).with("iter", 500);
+        assert_eq!(expand("{fractal}_{center}_{zoom}_{iter}.png", &tokens), Ok("mandelbrot_-0.75_0.1_40_500.png".to_string()));
+        assert_eq!(expand("{{{iter:06}}}.png", &tokens), Ok("{000500}.png".to_string()));
+        assert_eq!(expand("shot_{frame:04}.png", &tokens), Ok("shot_{frame:04}.png".to_string()));
+        assert_eq!(frame_name("shot_{frame:04}.png", 7), Ok("shot_0007.png".to_string()));
+        assert!(expand("{palette}.png", &tokens).is_err());
+        assert!(expand("{date}.png", &tokens).is_err());
+        assert!(expand("{iter:4}.png", &tokens).is_err());
+        assert!(expand("{iter.png", &tokens).is_err());
+    }
+
+    /// Tests the calendar dates of the timestamps.
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "19700101-000000");
+        assert_eq!(timestamp(951782400 + 3661), "20000229-010101");
+        assert_eq!(timestamp(1791936000), "20261014-000000");
+    }
+}
@@ -0,0 +1,267 @@
+// Newton fractals: every pixel is a starting point for Newton's method on a polynomial, and it is
+// colored by the root the orbit converges to, shaded by how many steps the convergence took.
+//
+// Unlike the escape-time Mandelbrot loop, the orbits here converge instead of diverging, so this
+// module has its own convergence test and its own root-based coloring which other convergent
+// fractals can reuse.
+use crate::complex::Complex;
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+// Distance to a root under which an orbit is considered converged.
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+// A polynomial with complex coefficients, stored from the highest degree down to the constant term.
+// It is serialized as the list of its coefficients and deserialized through Polynomial::new.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Complex>", into = "Vec<Complex>")]
+pub struct Polynomial {
+    coefficients: Vec<Complex>,
+}
+
+impl TryFrom<Vec<Complex>> for Polynomial {
+    type Error = String;
+
+    fn try_from(coefficients: Vec<Complex>) -> Result<Self, String> {
+        Self::new(coefficients)
+    }
+}
+
+impl From<Polynomial> for Vec<Complex> {
+    fn from(polynomial: Polynomial) -> Self {
+        polynomial.coefficients
+    }
+}
+
+impl Polynomial {
+    pub fn new(coefficients: Vec<Complex>) -> Result<Self, String> {
+        // Leading zeros do not change the polynomial but would break the degree.
+        let first = coefficients.iter().position(|c| *c != Complex::ZERO).unwrap_or(coefficients.len());
+        let coefficients = coefficients[first..].to_vec();
+        if coefficients.len() < 2 {
+            return Err("The polynomial must have a degree of at least 1".to_string());
+        }
+        Ok(Self { coefficients })
+    }
+
+    // Build a polynomial from real coefficients, e.g. [1, 0, 0, -1] for z³ − 1.
+    pub fn from_real(coefficients: &[f64]) -> Result<Self, String> {
+        Self::new(coefficients.iter().map(|&c| Complex::from(c)).collect())
+    }
+
+    // Parse comma separated real coefficients, highest degree first, e.g. "1,0,0,-1".
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let coefficients = input
+            .split(',')
+            .map(|c| c.trim().parse::<f64>().map_err(|_| format!("Invalid coefficient '{}'", c.trim())))
+            .collect::<Result<Vec<f64>, String>>()?;
+        Self::from_real(&coefficients)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    // Evaluate the polynomial and its derivative at z in a single Horner pass.
+    pub fn eval_with_derivative(&self, z: Complex) -> (Complex, Complex) {
+        let mut value = Complex::ZERO;
+        let mut derivative = Complex::ZERO;
+        for &c in &self.coefficients {
+            derivative = derivative * z + value;
+            value = value * z + c;
+        }
+        (value, derivative)
+    }
+
+    pub fn eval(&self, z: Complex) -> Complex {
+        self.eval_with_derivative(z).0
+    }
+
+    // Find all the roots with the Durand–Kerner method.
+    pub fn roots(&self) -> Vec<Complex> {
+        let leading = self.coefficients[0];
+        let monic = |z: Complex| self.eval(z) / leading;
+        // Classic starting guesses: powers of a number that is neither real nor a root of unity.
+        let seed = Complex::new(0.4, 0.9);
+        let mut roots: Vec<Complex> = (0..self.degree()).map(|k| seed.powi(k as i32)).collect();
+
+        for _ in 0..500 {
+            let mut largest_step: f64 = 0.0;
+            for i in 0..roots.len() {
+                let mut denominator = Complex::ONE;
+                for j in 0..roots.len() {
+                    if i != j {
+                        denominator *= roots[i] - roots[j];
+                    }
+                }
+                let step = monic(roots[i]) / denominator;
+                roots[i] -= step;
+                largest_step = largest_step.max(step.norm());
+            }
+            if largest_step < 1e-14 {
+                break;
+            }
+        }
+        roots
+    }
+}
+
+// Outcome of iterating one starting point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Convergence {
+    pub root: Option<usize>, // Index of the root reached, None if the orbit did not settle
+    pub iterations: u32,
+}
+
+// Index of the root within `tolerance` of z, if there is one.
+pub fn nearest_root(roots: &[Complex], z: Complex, tolerance: f64) -> Option<usize> {
+    roots.iter().position(|root| (z - *root).norm_sqr() < tolerance * tolerance)
+}
+
+// Newton's method z ← z − p(z) / p'(z) applied to a polynomial.
+pub struct NewtonFractal {
+    polynomial: Polynomial,
+    roots: Vec<Complex>,
+    pub max_iterations: u32,
+    pub tolerance: f64,
+}
+
+impl NewtonFractal {
+    pub fn new(polynomial: Polynomial, max_iterations: u32) -> Self {
+        let roots = polynomial.roots();
+        Self { polynomial, roots, max_iterations, tolerance: DEFAULT_TOLERANCE }
+    }
+
+    pub fn roots(&self) -> &[Complex] {
+        &self.roots
+    }
+
+    // Iterate from z0 until the orbit lands on a root or max_iterations is reached.
+    pub fn iterate(&self, z0: Complex) -> Convergence {
+        let mut z = z0;
+        for iteration in 1..=self.max_iterations {
+            let (value, derivative) = self.polynomial.eval_with_derivative(z);
+            if derivative == Complex::ZERO {
+                break; // Critical point: Newton's step is undefined
+            }
+            z -= value / derivative;
+            if let Some(root) = nearest_root(&self.roots, z, self.tolerance) {
+                return Convergence { root: Some(root), iterations: iteration };
+            }
+        }
+        Convergence { root: None, iterations: self.max_iterations }
+    }
+}
+
+// Give every root its own color and darken the points that needed many steps to converge.
+pub struct RootColorMap {
+    colors: Vec<Rgb<u8>>,
+    max_iterations: u32,
+}
+
+impl RootColorMap {
+    pub fn new(root_count: usize, max_iterations: u32) -> Self {
+        // Spread the roots evenly over a cyclic gradient so neighbouring basins stay distinct.
+        let gradient = colorgrad::sinebow();
+        let colors = (0..root_count)
+            .map(|k| {
+                let color = gradient.at(k as f64 / root_count.max(1) as f64).to_rgba8();
+                Rgb([color[0], color[1], color[2]])
+            })
+            .collect();
+        Self { colors, max_iterations }
+    }
+
+    pub fn color(&self, convergence: &Convergence) -> Rgb<u8> {
+        match convergence.root {
+            Some(root) => {
+                // Fast convergence gives the full color, slow convergence fades towards black.
+                let speed = 1.0 - convergence.iterations as f32 / self.max_iterations.max(1) as f32;
+                let shade = speed.clamp(0.0, 1.0).sqrt();
+                let Rgb([r, g, b]) = self.colors[root % self.colors.len()];
+                Rgb([(r as f32 * shade) as u8, (g as f32 * shade) as u8, (b as f32 * shade) as u8])
+            }
+            None => Rgb([0, 0, 0]), // Points that never converged are black
+        }
+    }
+}
+
+// Render any convergent iteration over the bounds, coloring each pixel by the root it reaches.
+pub fn render_convergence(
+    width: u32,
+    height: u32,
+    bounds: (f32, f32, f32, f32),
+    color_map: &RootColorMap,
+    iterate: impl Fn(Complex) -> Convergence,
+) -> RgbImage {
+    let mut img = RgbImage::new(width, height);
+    let (xmin, xmax, ymin, ymax) = bounds;
+    let scale_x = (xmax - xmin) as f64 / width as f64;
+    let scale_y = (ymax - ymin) as f64 / height as f64;
+
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        let z0 = Complex::new(px as f64 * scale_x + xmin as f64, py as f64 * scale_y + ymin as f64);
+        *pixel = color_map.color(&iterate(z0));
+    }
+    img
+}
+
+// Render the Newton fractal of a polynomial.
+pub fn generate_newton_fractal(width: u32, height: u32, fractal: &NewtonFractal, bounds: (f32, f32, f32, f32)) -> RgbImage {
+    let color_map = RootColorMap::new(fractal.roots().len(), fractal.max_iterations);
+    render_convergence(width, height, bounds, &color_map, |z0| fractal.iterate(z0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests Horner's evaluation of p(z) = z³ − 1 and p'(z) = 3z².
+    #[test]
+    fn test_eval_with_derivative() {
+        let p = Polynomial::parse("1,0,0,-1").unwrap();
+        let (value, derivative) = p.eval_with_derivative(Complex::new(2.0, 0.0));
+        assert_eq!(value, Complex::new(7.0, 0.0));
+        assert_eq!(derivative, Complex::new(12.0, 0.0));
+    }
+
+    /// Tests that the roots of z³ − 1 are the three cube roots of unity.
+    #[test]
+    fn test_roots_of_unity() {
+        let p = Polynomial::parse("1,0,0,-1").unwrap();
+        let roots = p.roots();
+        assert_eq!(roots.len(), 3);
+        for k in 0..3 {
+            let angle = 2.0 * std::f64::consts::PI * k as f64 / 3.0;
+            let expected = Complex::new(angle.cos(), angle.sin());
+            assert!(nearest_root(&roots, expected, 1e-9).is_some(), "Missing root {:?}", expected);
+        }
+    }
+
+    /// Tests that a point close to a root converges to that root in a few steps.
+    #[test]
+    fn test_iterate_converges() {
+        let fractal = NewtonFractal::new(Polynomial::parse("1,0,0,-1").unwrap(), 50);
+        let convergence = fractal.iterate(Complex::new(1.1, 0.05));
+        let root = convergence.root.expect("The orbit should converge");
+        assert!((fractal.roots()[root] - Complex::ONE).norm() < 1e-6);
+        assert!(convergence.iterations < 10);
+    }
+
+    /// Tests that constant polynomials are rejected.
+    #[test]
+    fn test_invalid_polynomial() {
+        assert!(Polynomial::parse("0,0,3").is_err());
+        assert!(Polynomial::parse("1,x").is_err());
+    }
+
+    /// Tests that polynomials round-trip through serde, and that deserializing rejects what new does.
+    #[test]
+    fn test_serde_round_trip() {
+        let polynomial = Polynomial::parse("1,0,0,-1").unwrap();
+        let json = serde_json::to_string(&polynomial).unwrap();
+        assert_eq!(serde_json::from_str::<Polynomial>(&json).unwrap(), polynomial);
+        assert!(serde_json::from_str::<Polynomial>("[]").is_err());
+        assert!(serde_json::from_str::<Polynomial>("[{\"re\": 0.0, \"im\": 0.0}, {\"re\": 3.0, \"im\": 0.0}]").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+// The Nova fractal: a relaxed Newton iteration for zⁿ − 1 with the pixel c added at every step,
+// z ← z − R·(zⁿ − 1) / (n·zⁿ⁻¹) + c, started from the critical point z = 1.
+//
+// The orbits converge to fixed points that move with c, so instead of fixed roots the basins are
+// told apart by the direction of the point the orbit settles on, then colored with the Newton
+// renderer's RootColorMap.
+use crate::complex::Complex;
+use crate::newton::{self, Convergence, RootColorMap};
+use image::RgbImage;
+use std::f64::consts::TAU;
+
+pub struct Nova {
+    pub exponent: f64,
+    pub relaxation: Complex,
+    pub max_iterations: u32,
+    pub tolerance: f64,
+}
+
+impl Nova {
+    pub fn new(exponent: f64, relaxation: Complex, max_iterations: u32) -> Self {
+        Self { exponent, relaxation, max_iterations, tolerance: newton::DEFAULT_TOLERANCE }
+    }
+
+    // Number of distinct colors: one per sector of the plane, like the roots of zⁿ − 1.
+    pub fn sector_count(&self) -> usize {
+        self.exponent.round().max(1.0) as usize
+    }
+
+    fn power(&self, z: Complex, exponent: f64) -> Complex {
+        if exponent.fract() == 0.0 {
+            z.powi(exponent as i32)
+        } else {
+            z.powf(exponent)
+        }
+    }
+
+    // Iterate for the parameter c until two successive values are closer than the tolerance.
+    pub fn iterate(&self, c: Complex) -> Convergence {
+        let n = self.exponent;
+        let mut z = Complex::ONE;
+        for iteration in 1..=self.max_iterations {
+            let derivative = self.power(z, n - 1.0) * n;
+            if derivative == Complex::ZERO {
+                break;
+            }
+            let next = z - self.relaxation * (self.power(z, n) - 1.0) / derivative + c;
+            if !next.is_finite() {
+                break;
+            }
+            if (next - z).norm_sqr() < self.tolerance * self.tolerance {
+                return Convergence { root: Some(self.sector(next)), iterations: iteration };
+            }
+            z = next;
+        }
+        Convergence { root: None, iterations: self.max_iterations }
+    }
+
+    // Index of the angular sector containing z, centred on the directions of the n-th roots of unity.
+    fn sector(&self, z: Complex) -> usize {
+        let sectors = self.sector_count();
+        let turn = z.arg().rem_euclid(TAU) / TAU * sectors as f64;
+        turn.round() as usize % sectors
+    }
+}
+
+// Render the Nova fractal over the bounds, each pixel being the parameter c.
+pub fn generate_nova_fractal(width: u32, height: u32, nova: &Nova, bounds: (f32, f32, f32, f32)) -> RgbImage {
+    let color_map = RootColorMap::new(nova.sector_count(), nova.max_iterations);
+    newton::render_convergence(width, height, bounds, &color_map, |c| nova.iterate(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that with c = 0 and R = 1 the iteration is plain Newton and stays on the root 1.
+    #[test]
+    fn test_converges_without_c() {
+        let nova = Nova::new(3.0, Complex::ONE, 50);
+        let convergence = nova.iterate(Complex::ZERO);
+        assert_eq!(convergence.root, Some(0));
+        assert_eq!(convergence.iterations, 1);
+    }
+
+    /// Tests that fixed points are sorted into the sectors around the roots of unity.
+    #[test]
+    fn test_sector() {
+        let nova = Nova::new(3.0, Complex::ONE, 50);
+        assert_eq!(nova.sector(Complex::new(2.0, 0.1)), 0);
+        assert_eq!(nova.sector(Complex::from_polar(0.5, TAU / 3.0 + 0.2)), 1);
+        assert_eq!(nova.sector(Complex::from_polar(3.0, -TAU / 3.0)), 2);
+        assert_eq!(nova.sector(Complex::new(1.0, -0.1)), 0);
+    }
+}
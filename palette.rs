@@ -0,0 +1,295 @@
+// The palette layer between the color maps and colorgrad: a gradient with a transform of the value
+// looked up in it, so that one gradient gives many looks. The value t in [0, 1] handed by a color map
+// (its normalized iteration count, smooth count, angle...) becomes the index
+//
+//     u = (t·scale + offset) mod 1
+//
+// which the repeat then splits into that many copies of the gradient, every other copy running
+// backwards when mirrored so that the copies join without a seam; invert finally reverses it all.
+//
+// The gradient can also be built from several named ones, weighted by percentages that default to
+// equal shares: "70%inferno+30%viridis" runs through inferno over the first 70% of the index and
+// through viridis over the rest, while "70%inferno&30%viridis" mixes the colors of both at every index (in linear light).
+//
+// Besides the built-in gradients, a program registers its own under a name with register_gradient, like
+// the palettes users define in a file; they are then chosen and composed like the built-in ones.
+use crate::error::Error;
+use crate::light::Gamma;
+use crate::mandelbrot::{self, GRAYSCALE, PALETTE_NAMES};
+use colorgrad::{Color, CustomGradient, Gradient};
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PaletteTransform {
+    pub offset: f64,
+    pub scale: f64,
+    pub repeat: u32,
+    pub mirror: bool,
+    pub invert: bool,
+}
+
+impl Default for PaletteTransform {
+    fn default() -> Self {
+        Self { offset: 0.0, scale: 1.0, repeat: 1, mirror: false, invert: false }
+    }
+}
+
+impl PaletteTransform {
+    pub fn is_identity(&self) -> bool {
+        *self == PaletteTransform::default()
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        if !(self.offset.is_finite() && self.scale.is_finite()) {
+            return Err(Error::InvalidPalette("The palette offset and scale must be finite numbers".to_string()));
+        }
+        if self.repeat == 0 {
+            return Err(Error::InvalidPalette("The palette must be repeated at least once".to_string()));
+        }
+        Ok(())
+    }
+
+    // The index in the gradient of a value. Values already in [0, 1] are not wrapped, so that the
+    // end of the gradient stays the color of t = 1 without a transform.
+    pub fn apply(&self, t: f64) -> f64 {
+        let u = t * self.scale + self.offset;
+        let mut u = if (0.0..=1.0).contains(&u) { u } else { u.rem_euclid(1.0) };
+        if self.repeat > 1 {
+            let copies = u * self.repeat as f64;
+            let copy = copies.floor().min(self.repeat as f64 - 1.0);
+            u = copies - copy;
+            if self.mirror && copy as u32 % 2 == 1 {
+                u = 1.0 - u;
+            }
+        }
+        if self.invert {
+            1.0 - u
+        } else {
+            u
+        }
+    }
+}
+
+// Where the colors of a palette come from.
+enum Source {
+    Gradient(Gradient),
+    Concatenated(Vec<(f64, Gradient)>), // Each gradient with the end of its part of the index
+    Blended(Vec<(f64, Gradient)>),      // Each gradient with its weight, the weights adding up to 1
+}
+
+impl Source {
+    fn color(&self, u: f64) -> Color {
+        match self {
+            Source::Gradient(gradient) => gradient.at(u),
+            Source::Concatenated(parts) => {
+                let (index, local) = concatenated_part(parts.iter().map(|(end, _)| *end), u);
+                parts[index].1.at(local)
+            }
+            Source::Blended(parts) => {
+                // Mixed in linear light, so that two bright gradients do not blend into a darker one
+                let colors = parts.iter().map(|(weight, gradient)| {
+                    let color = gradient.at(u);
+                    (*weight, [color.r, color.g, color.b])
+                });
+                let [r, g, b] = Gamma::Srgb.mix(colors);
+                Color::new(r, g, b, 1.0)
+            }
+        }
+    }
+}
+
+// The part of a concatenation an index falls in, from the end of every part, and where it is in that part.
+fn concatenated_part(ends: impl Iterator<Item = f64>, u: f64) -> (usize, f64) {
+    let mut start = 0.0;
+    let mut last = (0, u);
+    for (index, end) in ends.enumerate() {
+        last = (index, ((u - start) / (end - start)).clamp(0.0, 1.0));
+        if u <= end {
+            break;
+        }
+        start = end;
+    }
+    last
+}
+
+// Split a composed palette into its gradient names and their weights, normalized to add up to 1.
+fn parse_parts(name: &str, separator: char) -> Result<Vec<(f64, Gradient)>, Error> {
+    let mut parts = Vec::new();
+    for part in name.split(separator) {
+        let (weight, gradient) = match part.trim().split_once('%') {
+            Some((weight, gradient)) => {
+                let weight: f64 = weight.trim().parse().map_err(|_| Error::InvalidPalette(format!("Invalid weight '{}' in the palette '{}'", weight, name)))?;
+                if !(weight.is_finite() && weight > 0.0) {
+                    return Err(Error::InvalidPalette(format!("The weights of the palette '{}' must be positive", name)));
+                }
+                (weight, gradient.trim())
+            }
+            None => (1.0, part.trim()),
+        };
+        parts.push((weight, mandelbrot::gradient_by_name(gradient)?));
+    }
+    let total: f64 = parts.iter().map(|(weight, _)| weight).sum();
+    Ok(parts.into_iter().map(|(weight, gradient)| (weight / total, gradient)).collect())
+}
+
+// The registered gradients by name, each kept as its colors evenly spaced along the gradient.
+fn registry() -> &'static RwLock<BTreeMap<String, Vec<Rgb<u8>>>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, Vec<Rgb<u8>>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+// Add a gradient through colors under a name, which must not be taken by a built-in palette or an earlier
+// registration, nor hold the characters of composed palettes.
+pub fn register_gradient(name: &str, colors: Vec<Rgb<u8>>) -> Result<(), Error> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || "+&%,".contains(c)) {
+        return Err(Error::InvalidPalette(format!("Invalid palette name '{}', expected a word", name)));
+    }
+    if colors.len() < 2 {
+        return Err(Error::InvalidPalette(format!("The palette '{}' needs at least two colors", name)));
+    }
+    let mut registry = registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if PALETTE_NAMES.contains(&name) || name == GRAYSCALE || registry.contains_key(name) {
+        return Err(Error::InvalidPalette(format!("The palette '{}' is already defined", name)));
+    }
+    registry.insert(name.to_string(), colors);
+    Ok(())
+}
+
+// The gradient of a registered palette.
+pub fn registered_gradient(name: &str) -> Option<Result<Gradient, Error>> {
+    let registry = registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let colors: Vec<Color> = registry.get(name)?.iter().map(|Rgb([r, g, b])| Color::from_rgba8(*r, *g, *b, 255)).collect();
+    Some(CustomGradient::new().colors(&colors).build().map_err(|e| Error::InvalidPalette(format!("The palette '{}' cannot be built: {}", name, e))))
+}
+
+// The names of every palette: the built-in ones, grayscale, then the registered ones in alphabetical order.
+pub fn palette_names() -> Vec<String> {
+    let registry = registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    PALETTE_NAMES.iter().chain([&GRAYSCALE]).map(|name| name.to_string()).chain(registry.keys().cloned()).collect()
+}
+
+pub struct Palette {
+    source: Source,
+    transform: PaletteTransform,
+}
+
+impl Palette {
+    pub fn new(gradient: Gradient) -> Self {
+        Self { source: Source::Gradient(gradient), transform: PaletteTransform::default() }
+    }
+
+    // One of the gradients of mandelbrot::PALETTE_NAMES, or a concatenation (joined by +) or blend
+    // (joined by &) of several of them.
+    pub fn by_name(name: &str) -> Result<Self, Error> {
+        let source = if name.contains('+') {
+            let mut end = 0.0;
+            let parts = parse_parts(name, '+')?.into_iter().map(|(weight, gradient)| {
+                end += weight;
+                (end, gradient)
+            });
+            Source::Concatenated(parts.collect())
+        } else if name.contains('&') {
+            Source::Blended(parse_parts(name, '&')?)
+        } else {
+            Source::Gradient(mandelbrot::gradient_by_name(name)?)
+        };
+        Ok(Self { source, transform: PaletteTransform::default() })
+    }
+
+    pub fn with_transform(mut self, transform: PaletteTransform) -> Result<Self, Error> {
+        transform.check()?;
+        self.transform = transform;
+        Ok(self)
+    }
+
+    pub fn at(&self, t: f64) -> Rgb<u8> {
+        let color = self.source.color(self.transform.apply(t)).to_rgba8();
+        Rgb([color[0], color[1], color[2]])
+    }
+
+    // The color before its quantization, channels in [0, 1].
+    pub fn at_float(&self, t: f64) -> [f64; 3] {
+        let color = self.source.color(self.transform.apply(t));
+        [color.r, color.g, color.b]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests each transform of the index, and that the identity keeps both ends of the gradient.
+    #[test]
+    fn test_palette_transform() {
+        let identity = PaletteTransform::default();
+        assert!(identity.is_identity());
+        assert_eq!((identity.apply(0.0), identity.apply(0.25), identity.apply(1.0)), (0.0, 0.25, 1.0));
+
+        let shifted = PaletteTransform { offset: 0.5, scale: 2.0, ..identity };
+        assert_eq!(shifted.apply(0.5), 0.5); // 1.5 mod 1
+        assert_eq!(shifted.apply(0.25), 1.0);
+
+        let repeated = PaletteTransform { repeat: 4, ..identity };
+        assert_eq!(repeated.apply(0.375), 0.5);
+        assert_eq!(repeated.apply(1.0), 1.0);
+        let mirrored = PaletteTransform { mirror: true, ..repeated };
+        assert_eq!(mirrored.apply(0.3125), 0.75); // Second copy, backwards
+        assert_eq!(mirrored.apply(0.5625), 0.25);
+
+        let inverted = PaletteTransform { invert: true, ..identity };
+        assert_eq!(inverted.apply(0.25), 0.75);
+        assert!(PaletteTransform { repeat: 0, ..identity }.check().is_err());
+        assert!(PaletteTransform { scale: f64::NAN, ..identity }.check().is_err());
+    }
+
+    /// Tests that an inverted palette swaps the colors of both ends of the gradient.
+    #[test]
+    fn test_palette_at() {
+        let palette = Palette::by_name("viridis").unwrap();
+        let inverted = Palette::by_name("viridis").unwrap().with_transform(PaletteTransform { invert: true, ..Default::default() }).unwrap();
+        assert_eq!(palette.at(0.0), inverted.at(1.0));
+        assert_eq!(palette.at(1.0), inverted.at(0.0));
+        assert!(Palette::by_name("nope").is_err());
+    }
+
+    /// Tests that registered gradients are chosen and composed by name, under names not taken.
+    #[test]
+    fn test_register_gradient() {
+        let colors = vec![Rgb([0, 0, 64]), Rgb([255, 200, 0])];
+        register_gradient("test-dusk", colors.clone()).unwrap();
+        assert!(Palette::by_name("test-dusk").is_ok());
+        assert!(Palette::by_name("50%test-dusk+50%inferno").is_ok());
+        assert!(palette_names().contains(&"test-dusk".to_string()));
+        assert!(register_gradient("test-dusk", colors.clone()).is_err());
+        assert!(register_gradient("inferno", colors.clone()).is_err());
+        assert!(register_gradient(GRAYSCALE, colors.clone()).is_err());
+        assert!(register_gradient("dusk+dawn", colors).is_err());
+        assert!(register_gradient("test-single", vec![Rgb([0, 0, 0])]).is_err());
+        assert!(registered_gradient("test-single").is_none());
+    }
+
+    /// Tests the parsing of composed palettes and where the index falls in a concatenation.
+    #[test]
+    fn test_composed_palettes() {
+        let Source::Concatenated(parts) = Palette::by_name("70%inferno + 30%viridis").unwrap().source else {
+            panic!("A concatenation was expected");
+        };
+        let ends: Vec<f64> = parts.iter().map(|(end, _)| *end).collect();
+        assert!((ends[0] - 0.7).abs() < 1e-12 && (ends[1] - 1.0).abs() < 1e-12);
+        assert_eq!(concatenated_part(ends.iter().copied(), 0.35), (0, 0.5));
+        let (index, local) = concatenated_part(ends.iter().copied(), 0.85);
+        assert!(index == 1 && (local - 0.5).abs() < 1e-9);
+        assert_eq!(concatenated_part(ends.iter().copied(), 1.0).0, 1);
+
+        let Source::Blended(parts) = Palette::by_name("magma&plasma&turbo").unwrap().source else {
+            panic!("A blend was expected");
+        };
+        assert!(parts.iter().all(|(weight, _)| (weight - 1.0 / 3.0).abs() < 1e-12));
+        assert!(Palette::by_name("inferno+nope").is_err());
+        assert!(Palette::by_name("-10%inferno+viridis").is_err());
+        assert!(Palette::by_name("x%inferno&viridis").is_err());
+    }
+}
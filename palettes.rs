@@ -0,0 +1,62 @@
+// Palettes of the user: `palettes.conf` in the configuration directory (for example
+// `~/.config/mandelbrot/palettes.conf`, or the file of `--palette-file`) names gradients by their colors,
+// evenly spaced from the start of the gradient to its end, one `name = "colors"` line each:
+//
+//     sunset = "#1a0533, #6b1d5c, #e0475b, #ffc05c"
+//     ocean = "#001020, #005f73, #0a9396, #e9d8a6"
+//
+// They are registered before a command runs, so that `--palette sunset` or `--palette 50%sunset+50%ocean`
+// work anywhere a built-in palette does.
+use crate::config;
+use crate::patterns;
+use final_exercice::palette;
+use image::Rgb;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// The name of a palette and its colors.
+pub type UserPalette = (String, Vec<Rgb<u8>>);
+
+// The palettes file in the configuration directory of the platform.
+pub fn default_path() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("palettes.conf"))
+}
+
+// The palettes of a parsed file, sorted by name.
+pub fn from_options(options: &HashMap<String, String>) -> Result<Vec<UserPalette>, String> {
+    let mut palettes = options
+        .iter()
+        .map(|(name, colors)| {
+            let colors = colors.split(',').map(patterns::parse_hex_color).collect::<Result<Vec<_>, _>>();
+            colors.map(|colors| (name.clone(), colors)).map_err(|e| format!("Palette '{}': {}", name, e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    palettes.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(palettes)
+}
+
+// Register the palettes of a file, returning their names.
+pub fn register_file(path: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    for (name, colors) in from_options(&config::load_config(path)?)? {
+        palette::register_gradient(&name, colors).map_err(|e| format!("{}: {}", path, e))?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the colors of every palette are read, and malformed ones reported with its name.
+    #[test]
+    fn test_from_options() {
+        let options = config::parse_config("sunset = \"#1a0533, #ffc05c\"\nmono = #000000,#ffffff,#000000\n").unwrap();
+        let palettes = from_options(&options).unwrap();
+        assert_eq!(palettes[0], ("mono".to_string(), vec![Rgb([0, 0, 0]), Rgb([255, 255, 255]), Rgb([0, 0, 0])]));
+        assert_eq!(palettes[1], ("sunset".to_string(), vec![Rgb([0x1a, 0x05, 0x33]), Rgb([0xff, 0xc0, 0x5c])]));
+        let error = from_options(&config::parse_config("dusk = #1a0533, blue\n").unwrap()).unwrap_err();
+        assert!(error.starts_with("Palette 'dusk'"));
+    }
+}
@@ -0,0 +1,135 @@
+// A control panel for exploring the Multibrot sets (the Mandelbrot set being the power 2) without
+// going back to the terminal: sliders for the iteration limit and the power, the palette and the
+// coloring, boxes for the center and the zoom, and the image next to them.
+//
+// With the live preview on, every change is rendered right away at PREVIEW_SIZE; the Render button
+// renders the view at RENDER_SIZE. Built with the `gui` feature, which adds eframe.
+use crate::coloring;
+use final_exercice::plugins;
+use crate::fractal::{Multibrot, DEFAULT_BAILOUT};
+use crate::mandelbrot;
+use crate::palette;
+use eframe::egui;
+use image::RgbImage;
+
+const RENDER_SIZE: (u32, u32) = (800, 600);
+const PREVIEW_SIZE: (u32, u32) = (200, 150);
+const MIN_ITERATIONS: u32 = 10;
+const MAX_ITERATIONS: u32 = 10_000;
+const MAX_POWER: i32 = 8;
+
+// Everything the image depends on.
+#[derive(Debug, Clone, PartialEq)]
+struct PanelSettings {
+    max_iterations: u32,
+    power: i32,
+    palette: String,
+    coloring: String,
+    center: (f32, f32),
+    zoom: f32,
+}
+
+impl PanelSettings {
+    fn render(&self, (width, height): (u32, u32)) -> Result<RgbImage, String> {
+        let fractal = Multibrot::new(self.power)?;
+        let color_map = coloring::coloring_by_name(&self.coloring, &self.palette, self.max_iterations)?;
+        let bounds = mandelbrot::view_bounds(self.center, self.zoom, width, height);
+        let escapes = mandelbrot::compute_escape_info(&fractal, width, height, self.max_iterations, bounds, DEFAULT_BAILOUT, color_map.statistic());
+        Ok(mandelbrot::colorize_escapes(width, height, &escapes, &*color_map))
+    }
+}
+
+struct ControlPanel {
+    settings: PanelSettings,
+    shown: Option<PanelSettings>, // Settings of the image on screen
+    live_preview: bool,
+    texture: Option<egui::TextureHandle>,
+    error: Option<String>,
+}
+
+impl ControlPanel {
+    fn new() -> Self {
+        let settings = PanelSettings {
+            max_iterations: 100,
+            power: 2,
+            palette: "turbo".to_string(),
+            coloring: "iterations".to_string(),
+            center: (-0.5, 0.0),
+            zoom: 1.0,
+        };
+        Self { settings, shown: None, live_preview: true, texture: None, error: None }
+    }
+
+    // Render the settings at a size and put the image on screen, or keep the previous one and show why.
+    fn show(&mut self, ctx: &egui::Context, size: (u32, u32)) {
+        match self.settings.render(size) {
+            Ok(image) => {
+                let pixels = egui::ColorImage::from_rgb([size.0 as usize, size.1 as usize], image.as_raw());
+                self.texture = Some(ctx.load_texture("fractal", pixels, egui::TextureOptions::default()));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+        self.shown = Some(self.settings.clone());
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) -> bool {
+        let settings = &mut self.settings;
+        ui.add(egui::Slider::new(&mut settings.max_iterations, MIN_ITERATIONS..=MAX_ITERATIONS).logarithmic(true).text("Iterations"));
+        ui.add(egui::Slider::new(&mut settings.power, 2..=MAX_POWER).text("Power"));
+
+        egui::ComboBox::from_label("Palette").selected_text(settings.palette.as_str()).show_ui(ui, |ui| {
+            for palette in palette::palette_names() {
+                ui.selectable_value(&mut settings.palette, palette.clone(), palette);
+            }
+        });
+        egui::ComboBox::from_label("Coloring").selected_text(settings.coloring.as_str()).show_ui(ui, |ui| {
+            for coloring in plugins::coloring_names() {
+                ui.selectable_value(&mut settings.coloring, coloring.clone(), coloring);
+            }
+        });
+
+        // Dragging moves the center by about a pixel of the rendered view; a value can also be typed.
+        let step = mandelbrot::DEFAULT_VIEW_WIDTH / settings.zoom / RENDER_SIZE.0 as f32;
+        ui.horizontal(|ui| {
+            ui.label("Center");
+            ui.add(egui::DragValue::new(&mut settings.center.0).speed(step).max_decimals(8));
+            ui.add(egui::DragValue::new(&mut settings.center.1).speed(step).max_decimals(8));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Zoom");
+            ui.add(egui::DragValue::new(&mut settings.zoom).speed(settings.zoom * 0.01).clamp_range(0.1..=1e6));
+        });
+
+        ui.checkbox(&mut self.live_preview, "Live preview");
+        let render = ui.button("Render").clicked();
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        render
+    }
+}
+
+impl eframe::App for ControlPanel {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let render = egui::SidePanel::left("controls").show(ctx, |ui| self.controls(ui)).inner;
+        if render {
+            self.show(ctx, RENDER_SIZE);
+        } else if self.live_preview && self.shown.as_ref() != Some(&self.settings) {
+            self.show(ctx, PREVIEW_SIZE);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(texture) = &self.texture {
+                ui.add(egui::Image::new(texture).shrink_to_fit());
+            }
+        });
+    }
+}
+
+// Open the control panel and return once its window is closed.
+pub fn run() -> Result<(), String> {
+    let options = eframe::NativeOptions::default();
+    eframe::run_native("Mandelbrot control panel", options, Box::new(|_| Box::new(ControlPanel::new())))
+        .map_err(|e| format!("Could not open the control panel: {}", e))
+}
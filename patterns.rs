@@ -0,0 +1,607 @@
+// Calibration and test patterns: the chessboard plus gradients, color bars, concentric circles, a
+// Siemens star and the Sierpinski triangle and carpet, all drawn at any resolution with two colors and
+// an optional border, and the Hilbert and Peano curves, whose line runs through a palette.
+use crate::curves;
+use final_exercice::palette::Palette;
+use image::{RgbImage, Rgb};
+use std::f32::consts::PI;
+
+// Appearance of a pattern: image size, the two colors it alternates between and an optional border.
+pub struct PatternOptions {
+    pub width: u32, // Size of the whole image in pixels, border included
+    pub height: u32,
+    pub light: Rgb<u8>,
+    pub dark: Rgb<u8>,
+    pub border: Option<(Rgb<u8>, u32)>, // Color and width of a frame drawn around the board
+}
+
+// Every pattern that can be drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    Chessboard { cells: u32 },
+    HorizontalGradient, // From the dark color on the left to the light color on the right
+    VerticalGradient,   // From the dark color at the top to the light color at the bottom
+    ColorBars,          // SMPTE-style bars, which keep their standard colors
+    ConcentricCircles { rings: u32 },
+    SiemensStar { spokes: u32 },
+    SierpinskiTriangle { depth: u32 },
+    SierpinskiCarpet { depth: u32 },
+    HilbertCurve { order: u32, stroke: u32 }, // A stroke of 0 pixels is half the spacing of the lines
+    PeanoCurve { order: u32, stroke: u32 },
+}
+
+// Names of the patterns accepted by Pattern::from_name.
+pub const PATTERN_NAMES: [&str; 10] = [
+    "chessboard",
+    "gradient-h",
+    "gradient-v",
+    "bars",
+    "circles",
+    "star",
+    "sierpinski-triangle",
+    "sierpinski-carpet",
+    "hilbert",
+    "peano",
+];
+
+impl Pattern {
+    // Build a pattern from its name, `count` being the cells, rings, spokes, recursion depth or curve order
+    // where the pattern has any. Curves get the stroke of half the spacing of their lines.
+    pub fn from_name(name: &str, count: u32) -> Result<Self, String> {
+        match name {
+            "chessboard" => Ok(Pattern::Chessboard { cells: count }),
+            "gradient-h" => Ok(Pattern::HorizontalGradient),
+            "gradient-v" => Ok(Pattern::VerticalGradient),
+            "bars" => Ok(Pattern::ColorBars),
+            "circles" => Ok(Pattern::ConcentricCircles { rings: count }),
+            "star" => Ok(Pattern::SiemensStar { spokes: count }),
+            "sierpinski-triangle" => Ok(Pattern::SierpinskiTriangle { depth: count }),
+            "sierpinski-carpet" => Ok(Pattern::SierpinskiCarpet { depth: count }),
+            "hilbert" => Ok(Pattern::HilbertCurve { order: count, stroke: 0 }),
+            "peano" => Ok(Pattern::PeanoCurve { order: count, stroke: 0 }),
+            other => Err(format!("Unknown pattern '{}'. Available patterns: {}", other, PATTERN_NAMES.join(", "))),
+        }
+    }
+
+    // Whether the pattern needs a count of cells, rings, spokes, a depth or an order.
+    pub fn has_count(name: &str) -> bool {
+        matches!(name, "chessboard" | "circles" | "star" | "sierpinski-triangle" | "sierpinski-carpet" | "hilbert" | "peano")
+    }
+
+    // The stroke width of the patterns drawn as a line, to be changed after from_name.
+    pub fn stroke_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            Pattern::HilbertCurve { stroke, .. } | Pattern::PeanoCurve { stroke, .. } => Some(stroke),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PatternOptions {
+    fn default() -> Self {
+        Self { width: 500, height: 500, light: Rgb([255, 255, 255]), dark: Rgb([0, 0, 0]), border: None }
+    }
+}
+
+/// Writes a color as a `#rrggbb` hex string.
+pub fn format_hex_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Parses a color written as a hex string, `#rrggbb` or `rrggbb`.
+pub fn parse_hex_color(input: &str) -> Result<Rgb<u8>, String> {
+    let hex = input.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid color '{}', expected #rrggbb", input.trim()));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok(Rgb([channel(0), channel(2), channel(4)]))
+}
+
+// Fill the image with the border color, then every pixel inside the border with `color(x, y, width, height)`,
+// the coordinates being relative to the board.
+fn fill_board(options: &PatternOptions, min_size: u32, color: impl Fn(u32, u32, u32, u32) -> Rgb<u8>) -> Result<RgbImage, String> {
+    let (border_color, border_width) = options.border.unwrap_or((options.dark, 0));
+    let board_width = options.width.saturating_sub(2 * border_width);
+    let board_height = options.height.saturating_sub(2 * border_width);
+    if board_width < min_size.max(1) || board_height < min_size.max(1) {
+        return Err(format!(
+            "The pattern does not fit in a {}x{}px board ({}x{}px image, {}px border)",
+            board_width, board_height, options.width, options.height, border_width
+        ));
+    }
+    let mut image = RgbImage::from_pixel(options.width, options.height, border_color);
+    for y in 0..board_height {
+        for x in 0..board_width {
+            image.put_pixel(border_width + x, border_width + y, color(x, y, board_width, board_height));
+        }
+    }
+    Ok(image)
+}
+
+fn lerp_color(from: Rgb<u8>, to: Rgb<u8>, t: f32) -> Rgb<u8> {
+    let channel = |i: usize| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t).round() as u8;
+    Rgb([channel(0), channel(1), channel(2)])
+}
+
+/// Draws a chessboard with a specified number of cells per side.
+///
+/// # Arguments
+/// * `cell_count` - The number of cells along one side of the chessboard.
+/// * `options` - The size, square colors and border of the image.
+///
+/// # Returns
+/// A `RgbImage` of the chessboard, or an error if the board does not fit in the image.
+pub fn draw_square(cell_count: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if cell_count == 0 {
+        return Err("The chessboard needs at least one cell per side".to_string());
+    }
+    // The cells do not always divide the board evenly, so each pixel is mapped to its cell
+    // proportionally: the remainder is spread over the cells, which differ by at most one pixel.
+    let cell = |offset: u32, length: u32| (offset as u64 * cell_count as u64 / length as u64) as u32;
+    fill_board(options, cell_count, |x, y, width, height| {
+        if (cell(x, width) + cell(y, height)).is_multiple_of(2) { options.light } else { options.dark }
+    })
+}
+
+/// Draws a linear gradient between the two colors of the options.
+///
+/// # Arguments
+/// * `horizontal` - Whether the gradient runs from left to right instead of top to bottom.
+/// * `options` - The size, colors and border of the image.
+pub fn draw_gradient(horizontal: bool, options: &PatternOptions) -> Result<RgbImage, String> {
+    fill_board(options, 1, |x, y, width, height| {
+        let (offset, length) = if horizontal { (x, width) } else { (y, height) };
+        lerp_color(options.dark, options.light, offset as f32 / (length.max(2) - 1) as f32)
+    })
+}
+
+/// Draws SMPTE-style color bars: seven 75% bars, a strip of reversed blue bars and a bottom row
+/// with the -I, white and +Q blocks followed by black and a PLUGE (three near-black strips).
+pub fn draw_color_bars(options: &PatternOptions) -> Result<RgbImage, String> {
+    const BARS: [[u8; 3]; 7] =
+        [[191, 191, 191], [191, 191, 0], [0, 191, 191], [0, 191, 0], [191, 0, 191], [191, 0, 0], [0, 0, 191]];
+    const REVERSED: [[u8; 3]; 7] =
+        [[0, 0, 191], [19, 19, 19], [191, 0, 191], [19, 19, 19], [0, 191, 191], [19, 19, 19], [191, 191, 191]];
+
+    fill_board(options, 7, |x, y, width, height| {
+        let fraction = x as f32 / width as f32;
+        let bar = ((fraction * 7.0) as usize).min(6);
+        let color = if y < height * 2 / 3 {
+            BARS[bar]
+        } else if y < height * 3 / 4 {
+            REVERSED[bar]
+        } else {
+            // The bottom row is split into blocks of 5/4 bar width, then the PLUGE under the fifth and sixth bars.
+            match (fraction * 28.0) as u32 {
+                0..=4 => [0, 33, 76],     // -I
+                5..=9 => [255, 255, 255], // White
+                10..=14 => [50, 0, 106],  // +Q
+                20 => [9, 9, 9],          // Below black
+                22 => [29, 29, 29],       // Above black
+                _ => [19, 19, 19],        // Black
+            }
+        };
+        Rgb(color)
+    })
+}
+
+/// Draws concentric rings alternating between the two colors, centered on the image.
+///
+/// # Arguments
+/// * `rings` - The number of rings between the center and the nearest edge.
+/// * `options` - The size, colors and border of the image.
+pub fn draw_concentric_circles(rings: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if rings == 0 {
+        return Err("The pattern needs at least one ring".to_string());
+    }
+    fill_board(options, 2 * rings, |x, y, width, height| {
+        let (dx, dy) = (x as f32 + 0.5 - width as f32 / 2.0, y as f32 + 0.5 - height as f32 / 2.0);
+        let ring_width = width.min(height) as f32 / 2.0 / rings as f32;
+        let ring = ((dx * dx + dy * dy).sqrt() / ring_width) as u32;
+        if ring.is_multiple_of(2) { options.light } else { options.dark }
+    })
+}
+
+/// Draws a Siemens star: alternating wedges meeting at the center, used to judge sharpness.
+///
+/// # Arguments
+/// * `spokes` - The number of dark wedges around the center.
+/// * `options` - The size, colors and border of the image; outside the star is the light color.
+pub fn draw_siemens_star(spokes: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if spokes < 2 {
+        return Err("The Siemens star needs at least two spokes".to_string());
+    }
+    fill_board(options, 1, |x, y, width, height| {
+        let (dx, dy) = (x as f32 + 0.5 - width as f32 / 2.0, y as f32 + 0.5 - height as f32 / 2.0);
+        if (dx * dx + dy * dy).sqrt() > width.min(height) as f32 / 2.0 {
+            return options.light;
+        }
+        let angle = dy.atan2(dx) + PI; // In [0, 2π]
+        let wedge = (angle / (PI / spokes as f32)) as u32;
+        if wedge.is_multiple_of(2) { options.dark } else { options.light }
+    })
+}
+
+// Deeper Sierpinski patterns would need more pixels than an image can have.
+pub const MAX_TRIANGLE_DEPTH: u32 = 16;
+pub const MAX_CARPET_DEPTH: u32 = 10;
+
+/// Draws the Sierpinski triangle in the dark color on the light one, its apex at the top of the board
+/// and its base along the bottom.
+///
+/// # Arguments
+/// * `depth` - The number of times the triangles are split, 0 being a plain triangle.
+/// * `options` - The size, colors and border of the image.
+///
+/// The rows of the triangle are the 2^depth first rows of Pascal's triangle, a cell being kept where
+/// its binomial coefficient is odd, which is the removal of the middle triangles done depth times.
+pub fn draw_sierpinski_triangle(depth: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if depth > MAX_TRIANGLE_DEPTH {
+        return Err(format!("The Sierpinski triangle goes at most {} levels deep, not {}", MAX_TRIANGLE_DEPTH, depth));
+    }
+    let rows = 1u32 << depth;
+    fill_board(options, rows, |x, y, width, height| {
+        let v = (y as f32 + 0.5) / height as f32; // The width of the triangle at this height
+        let u = (x as f32 + 0.5) / width as f32 - 0.5;
+        if u.abs() > v / 2.0 {
+            return options.light;
+        }
+        let row = ((v * rows as f32) as u32).min(rows - 1);
+        let cell = (((u + v / 2.0) / v * (row + 1) as f32) as u32).min(row);
+        if cell & (row - cell) == 0 { options.dark } else { options.light }
+    })
+}
+
+/// Draws the Sierpinski carpet in the dark color, the removed squares showing the light one.
+///
+/// # Arguments
+/// * `depth` - The number of times the squares are split in nine, their middle one being removed.
+/// * `options` - The size, colors and border of the image.
+pub fn draw_sierpinski_carpet(depth: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if depth > MAX_CARPET_DEPTH {
+        return Err(format!("The Sierpinski carpet goes at most {} levels deep, not {}", MAX_CARPET_DEPTH, depth));
+    }
+    let cells = 3u64.pow(depth);
+    fill_board(options, cells as u32, |x, y, width, height| {
+        // A square is removed at the levels where both base-3 digits of its cell are 1
+        let (mut column, mut row) = (x as u64 * cells / width as u64, y as u64 * cells / height as u64);
+        while column > 0 || row > 0 {
+            if column % 3 == 1 && row % 3 == 1 {
+                return options.light;
+            }
+            (column, row) = (column / 3, row / 3);
+        }
+        options.dark
+    })
+}
+
+// Deeper curves would have more turns than an image has pixels.
+pub const MAX_HILBERT_ORDER: u32 = 10;
+pub const MAX_PEANO_ORDER: u32 = 6;
+// The palette the space-filling curves run through, from their first step to their last.
+const CURVE_PALETTE: &str = "turbo";
+
+/// Returns the cell the Hilbert curve of a grid of 2^order cells per side visits at a step, (0, 0)
+/// being the bottom left cell where the curve starts, and the bottom right one where it ends.
+pub fn hilbert_cell(order: u32, step: u64) -> (u32, u32) {
+    let (mut x, mut y, mut t) = (0u64, 0u64, step);
+    let mut size = 1u64;
+    // From the smallest squares up, every pair of bits of the step picks a quadrant, the curve being
+    // turned in the lower two so that the quadrants join
+    while size < 1 << order {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                (x, y) = (size - 1 - x, size - 1 - y);
+            }
+            (x, y) = (y, x);
+        }
+        (x, y) = (x + size * rx, y + size * ry);
+        t /= 4;
+        size *= 2;
+    }
+    (x as u32, y as u32)
+}
+
+/// Returns the cell the Peano curve of a grid of 3^order cells per side visits at a step, from the
+/// bottom left cell to the top right one.
+///
+/// The base-3 digits of the step alternate between the column and the row, from the largest ninths
+/// down, each digit being reversed (d becoming 2 − d) when the digits of the other coordinate before it
+/// add up to an odd number, which makes the curve snake through every ninth.
+pub fn peano_cell(order: u32, step: u64) -> (u32, u32) {
+    let digits: Vec<u64> = (0..2 * order).rev().map(|i| step / 3u64.pow(i) % 3).collect();
+    let (mut x, mut y) = (0u32, 0u32);
+    let (mut x_sum, mut y_sum) = (0, 0); // The sums of the column and row digits so far
+    for pair in digits.chunks(2) {
+        let column = if y_sum % 2 == 0 { pair[0] } else { 2 - pair[0] };
+        x_sum += pair[0];
+        let row = if x_sum % 2 == 0 { pair[1] } else { 2 - pair[1] };
+        y_sum += pair[1];
+        (x, y) = (x * 3 + column as u32, y * 3 + row as u32);
+    }
+    (x, y)
+}
+
+// Draw the line through the centers of cells of a grid on the light color, in the order `cell` gives
+// them, along the curve palette.
+fn draw_curve(cells: u32, cell: impl Fn(u64) -> (u32, u32), stroke: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    let mut image = fill_board(options, cells, |_, _, _, _| options.light)?;
+    let border_width = options.border.map_or(0, |(_, width)| width);
+    let (board_width, board_height) = (options.width - 2 * border_width, options.height - 2 * border_width);
+    let (step_x, step_y) = (board_width as f64 / cells as f64, board_height as f64 / cells as f64);
+    let steps = cells as u64 * cells as u64;
+    let points: Vec<(f64, f64)> = (0..steps)
+        .map(|step| {
+            let (x, y) = cell(step);
+            (border_width as f64 + (x as f64 + 0.5) * step_x, border_width as f64 + (cells - y) as f64 * step_y - step_y / 2.0)
+        })
+        .collect();
+    let width = if stroke == 0 { (step_x.min(step_y) / 2.0).max(1.0) } else { stroke as f64 };
+    let palette = Palette::by_name(CURVE_PALETTE).map_err(|e| e.to_string())?;
+    let last = steps.saturating_sub(2).max(1) as f64;
+    curves::draw_polyline(&mut image, &points, width, |segment| palette.at(segment as f64 / last));
+    Ok(image)
+}
+
+/// Draws the Hilbert curve on the light color, its line running through a palette from its start at
+/// the bottom left of the board to its end at the bottom right.
+///
+/// # Arguments
+/// * `order` - The number of times the squares are split in four, the curve visiting 4^order cells.
+/// * `stroke` - The width of the line in pixels, or 0 for half the spacing of the lines.
+/// * `options` - The size, light color and border of the image.
+pub fn draw_hilbert_curve(order: u32, stroke: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if order == 0 || order > MAX_HILBERT_ORDER {
+        return Err(format!("The Hilbert curve has an order from 1 to {}, not {}", MAX_HILBERT_ORDER, order));
+    }
+    draw_curve(1 << order, |step| hilbert_cell(order, step), stroke, options)
+}
+
+/// Draws the Peano curve on the light color, its line running through a palette from the bottom left
+/// of the board to the top right.
+///
+/// # Arguments
+/// * `order` - The number of times the squares are split in nine, the curve visiting 9^order cells.
+/// * `stroke` - The width of the line in pixels, or 0 for half the spacing of the lines.
+/// * `options` - The size, light color and border of the image.
+pub fn draw_peano_curve(order: u32, stroke: u32, options: &PatternOptions) -> Result<RgbImage, String> {
+    if order == 0 || order > MAX_PEANO_ORDER {
+        return Err(format!("The Peano curve has an order from 1 to {}, not {}", MAX_PEANO_ORDER, order));
+    }
+    draw_curve(3u32.pow(order), |step| peano_cell(order, step), stroke, options)
+}
+
+/// Draws any pattern.
+pub fn draw_pattern(pattern: Pattern, options: &PatternOptions) -> Result<RgbImage, String> {
+    match pattern {
+        Pattern::Chessboard { cells } => draw_square(cells, options),
+        Pattern::HorizontalGradient => draw_gradient(true, options),
+        Pattern::VerticalGradient => draw_gradient(false, options),
+        Pattern::ColorBars => draw_color_bars(options),
+        Pattern::ConcentricCircles { rings } => draw_concentric_circles(rings, options),
+        Pattern::SiemensStar { spokes } => draw_siemens_star(spokes, options),
+        Pattern::SierpinskiTriangle { depth } => draw_sierpinski_triangle(depth, options),
+        Pattern::SierpinskiCarpet { depth } => draw_sierpinski_carpet(depth, options),
+        Pattern::HilbertCurve { order, stroke } => draw_hilbert_curve(order, stroke, options),
+        Pattern::PeanoCurve { order, stroke } => draw_peano_curve(order, stroke, options),
+    }
+}
+
+// Unit tests for the pattern drawing functions.
+// I used three tests to verify the image size, square size, and checkerboard pattern.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the generated image has the correct dimensions.
+    /// It checks that for a given number of cells, the chessboard image size is 500x500 pixels.
+    #[test]
+    fn test_image_size() {
+        let cell_count = 10; // Example value
+        let img = draw_square(cell_count, &PatternOptions::default()).unwrap();
+        assert_eq!(img.dimensions(), (500, 500));
+    }
+
+    /// Tests that each square in the chessboard has the correct size and color.
+    /// It verifies that each square is consistently sized based on the number of cells per side
+    /// and alternates colors correctly in a checkerboard pattern.
+    #[test]
+    fn test_square_size() {
+        let cell_count = 10;
+        let img = draw_square(cell_count, &PatternOptions::default()).unwrap();
+        let square_size = 500 / cell_count;
+
+        // Check each square for correct size and color
+        for x in 0..cell_count {
+            let start_x = x * square_size;
+            for y in 0..cell_count {
+                let start_y = y * square_size;
+                let expected_color = if (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) };
+
+                // Check all pixels in the current square
+                for i in start_x..start_x + square_size {
+                    for j in start_y..start_y + square_size {
+                        assert_eq!(*img.get_pixel(i, j), expected_color, "Mismatch at ({}, {})", i, j);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tests the checkerboard color pattern of the chessboard.
+    /// This test uses a minimal board size of 2x2 cells to ensure that the colors alternate correctly
+    /// across the chessboard, forming a valid checkerboard pattern.
+    #[test]
+    fn test_checkerboard_pattern() {
+        let cell_count = 2; // Minimal non-trivial board
+        let img = draw_square(cell_count, &PatternOptions::default()).unwrap();
+        let square_size = 500 / cell_count;
+
+        // Check that the checkerboard pattern alternates correctly
+        for i in 0..cell_count {
+            for j in 0..cell_count {
+                let expected_color = if (i + j) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) };
+                for x in 0..square_size {
+                    for y in 0..square_size {
+                        assert_eq!(*img.get_pixel((i * square_size + x) as u32, (j * square_size + y) as u32), expected_color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tests that custom colors, size and border are used for the board.
+    #[test]
+    fn test_custom_chessboard() {
+        let options = PatternOptions {
+            width: 120,
+            height: 120,
+            light: parse_hex_color("#ff0000").unwrap(),
+            dark: parse_hex_color("0000ff").unwrap(),
+            border: Some((Rgb([10, 20, 30]), 10)),
+        };
+        let img = draw_square(4, &options).unwrap();
+        assert_eq!(img.dimensions(), (120, 120));
+        assert_eq!(*img.get_pixel(5, 60), Rgb([10, 20, 30])); // Inside the border
+        assert_eq!(*img.get_pixel(10, 10), Rgb([255, 0, 0])); // First square
+        assert_eq!(*img.get_pixel(40, 10), Rgb([0, 0, 255])); // Second square
+        assert!(draw_square(4, &PatternOptions { width: 20, ..options }).is_err());
+    }
+
+    /// Tests parsing hex colors.
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#1a2B3c"), Ok(Rgb([0x1a, 0x2b, 0x3c])));
+        assert!(parse_hex_color("#12345").is_err());
+        assert!(parse_hex_color("zzzzzz").is_err());
+        assert_eq!(format_hex_color(Rgb([0x1a, 0x2b, 0x3c])), "#1a2b3c");
+    }
+
+    /// Tests that a cell count which does not divide the image still fills it completely,
+    /// with cells differing by at most one pixel.
+    #[test]
+    fn test_non_divisible_cell_count() {
+        let options = PatternOptions { width: 500, height: 300, ..PatternOptions::default() };
+        let img = draw_square(7, &options).unwrap();
+        assert_eq!(img.dimensions(), (500, 300));
+
+        // Measure the width of each cell along the first row by counting color changes.
+        let mut widths = vec![1];
+        for x in 1..500 {
+            if img.get_pixel(x, 0) == img.get_pixel(x - 1, 0) {
+                *widths.last_mut().unwrap() += 1;
+            } else {
+                widths.push(1);
+            }
+        }
+        assert_eq!(widths.len(), 7);
+        assert!(widths.iter().all(|&w| w == 71 || w == 72), "Uneven cells: {:?}", widths);
+        assert_eq!(*img.get_pixel(499, 299), Rgb([255, 255, 255])); // Cell (6, 6) in the corner
+    }
+
+    /// Tests that more cells than pixels are rejected instead of panicking.
+    #[test]
+    fn test_too_many_cells() {
+        assert!(draw_square(600, &PatternOptions::default()).is_err());
+        assert!(draw_square(0, &PatternOptions::default()).is_err());
+    }
+
+    /// Tests that a gradient goes from the dark color to the light color.
+    #[test]
+    fn test_gradient() {
+        let options = PatternOptions { width: 101, height: 10, ..PatternOptions::default() };
+        let img = draw_gradient(true, &options).unwrap();
+        assert_eq!(*img.get_pixel(0, 5), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(50, 5), Rgb([128, 128, 128]));
+        assert_eq!(*img.get_pixel(100, 5), Rgb([255, 255, 255]));
+        let img = draw_gradient(false, &options).unwrap();
+        assert_eq!(*img.get_pixel(0, 0), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(100, 9), Rgb([255, 255, 255]));
+    }
+
+    /// Tests the first and last color bars and the bottom row.
+    #[test]
+    fn test_color_bars() {
+        let options = PatternOptions { width: 700, height: 400, ..PatternOptions::default() };
+        let img = draw_pattern(Pattern::ColorBars, &options).unwrap();
+        assert_eq!(*img.get_pixel(10, 10), Rgb([191, 191, 191]));
+        assert_eq!(*img.get_pixel(690, 10), Rgb([0, 0, 191]));
+        assert_eq!(*img.get_pixel(10, 280), Rgb([0, 0, 191])); // Reversed strip
+        assert_eq!(*img.get_pixel(150, 390), Rgb([255, 255, 255]));
+    }
+
+    /// Tests the colors at the center and around the circles and the star.
+    #[test]
+    fn test_circles_and_star() {
+        let options = PatternOptions { width: 200, height: 200, ..PatternOptions::default() };
+        let circles = draw_pattern(Pattern::ConcentricCircles { rings: 4 }, &options).unwrap();
+        assert_eq!(*circles.get_pixel(100, 100), Rgb([255, 255, 255])); // Innermost ring
+        assert_eq!(*circles.get_pixel(100, 135), Rgb([0, 0, 0])); // Second ring, 25 to 50px out
+
+        let star = draw_pattern(Pattern::SiemensStar { spokes: 4 }, &options).unwrap();
+        // Opposite wedges share a color, neighbouring wedges alternate.
+        assert_eq!(star.get_pixel(150, 120), star.get_pixel(50, 80));
+        assert_ne!(star.get_pixel(150, 120), star.get_pixel(120, 150));
+        assert_eq!(*star.get_pixel(0, 0), Rgb([255, 255, 255])); // Outside the star
+    }
+
+    /// Tests the holes of the Sierpinski patterns: the middle square of the carpet and of its corner
+    /// squares, and the middle triangle of the bottom row of the triangle.
+    #[test]
+    fn test_sierpinski() {
+        let options = PatternOptions { width: 90, height: 90, ..PatternOptions::default() };
+        let carpet = draw_pattern(Pattern::SierpinskiCarpet { depth: 2 }, &options).unwrap();
+        assert_eq!(*carpet.get_pixel(45, 45), Rgb([255, 255, 255]));
+        assert_eq!(*carpet.get_pixel(15, 15), Rgb([255, 255, 255]));
+        assert_eq!(*carpet.get_pixel(5, 5), Rgb([0, 0, 0]));
+        assert_eq!(*carpet.get_pixel(35, 5), Rgb([0, 0, 0]));
+
+        let options = PatternOptions { width: 100, height: 100, ..PatternOptions::default() };
+        let triangle = draw_pattern(Pattern::SierpinskiTriangle { depth: 2 }, &options).unwrap();
+        assert_eq!(*triangle.get_pixel(50, 60), Rgb([255, 255, 255])); // Middle of row 2 of Pascal's triangle
+        assert_eq!(*triangle.get_pixel(35, 60), Rgb([0, 0, 0]));
+        assert_eq!(*triangle.get_pixel(50, 5), Rgb([0, 0, 0])); // Apex
+        assert_eq!(*triangle.get_pixel(2, 2), Rgb([255, 255, 255])); // Outside the triangle
+        assert!(draw_sierpinski_carpet(MAX_CARPET_DEPTH + 1, &options).is_err());
+        assert!(draw_sierpinski_carpet(5, &options).is_err()); // 243 squares in 100 pixels
+    }
+
+    /// Tests that the space-filling curves visit every cell once, each step moving to a neighbouring cell.
+    #[test]
+    fn test_space_filling_curves() {
+        let check = |cells: u32, cell: &dyn Fn(u64) -> (u32, u32), start: (u32, u32), end: (u32, u32)| {
+            let steps = cells as u64 * cells as u64;
+            let mut visited = vec![false; steps as usize];
+            for step in 0..steps {
+                let (x, y) = cell(step);
+                assert!(x < cells && y < cells && !visited[(y * cells + x) as usize]);
+                visited[(y * cells + x) as usize] = true;
+                if step > 0 {
+                    let (px, py) = cell(step - 1);
+                    assert_eq!(px.abs_diff(x) + py.abs_diff(y), 1, "step {}", step);
+                }
+            }
+            assert_eq!((cell(0), cell(steps - 1)), (start, end));
+        };
+        check(16, &|step| hilbert_cell(4, step), (0, 0), (15, 0));
+        check(27, &|step| peano_cell(3, step), (0, 0), (26, 26));
+
+        let options = PatternOptions { width: 64, height: 64, ..PatternOptions::default() };
+        let curve = draw_pattern(Pattern::HilbertCurve { order: 3, stroke: 2 }, &options).unwrap();
+        assert_eq!(curve.dimensions(), (64, 64));
+        assert_eq!(*curve.get_pixel(0, 0), Rgb([255, 255, 255])); // Between the lines
+        assert!(draw_hilbert_curve(MAX_HILBERT_ORDER + 1, 0, &options).is_err());
+        assert!(draw_peano_curve(4, 0, &options).is_err()); // 81 lines in 64 pixels
+    }
+
+    /// Tests selecting patterns by name.
+    #[test]
+    fn test_pattern_from_name() {
+        assert_eq!(Pattern::from_name("star", 12), Ok(Pattern::SiemensStar { spokes: 12 }));
+        assert_eq!(Pattern::from_name("bars", 0), Ok(Pattern::ColorBars));
+        assert!(Pattern::from_name("noise", 0).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+// The Phoenix fractal: z(n+1) = z(n)² + p + q·z(n-1).
+//
+// The next value depends on the two previous ones, which gives the characteristic feathered shapes.
+// p is the parameter of the formula: as in the classic renderings, the fractal is drawn as the Julia
+// set of a fixed p, every pixel being a starting point z(0), while q stays fixed in both planes.
+use crate::complex::Complex;
+use crate::fractal::{self, Dynamics, EscapeResult, OrbitObserver};
+
+pub struct Phoenix {
+    pub q: Complex,
+}
+
+impl Phoenix {
+    // The parameters of Ushiki's original Phoenix image.
+    pub const DEFAULT_P: Complex = Complex::new(0.56667, 0.0);
+    pub const DEFAULT_Q: Complex = Complex::new(-0.5, 0.0);
+
+    pub fn new(q: Complex) -> Self {
+        Self { q }
+    }
+}
+
+impl Dynamics for Phoenix {
+    // Iterate from z(0) with z(-1) = 0.
+    fn iterate_from(&self, z0: Complex, p: Complex, max_iterations: u32, bailout: f64, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        let mut previous = Complex::ZERO;
+        // Every value depends on the two previous ones, which a cycle check on z alone would miss.
+        fractal::escape_without_periodicity(z0, max_iterations, bailout, observer, |z| {
+            let next = z * z + p + self.q * previous;
+            previous = z;
+            next
+        })
+    }
+
+    fn commutes_with_conjugation(&self) -> bool {
+        self.q.im == 0.0
+    }
+
+    fn julia_bounds(&self) -> (f32, f32, f32, f32) {
+        (-1.6, 1.6, -1.2, 1.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::Fractal;
+
+    /// Tests that with q = 0 the Phoenix iteration is the plain Julia iteration z² + p.
+    #[test]
+    fn test_reduces_to_julia_without_q() {
+        let phoenix = Phoenix::new(Complex::ZERO).julia(Complex::new(-1.0, 0.0));
+        // 0 → -1 → 0 → -1 ... is a bounded cycle of z² − 1.
+        assert_eq!(phoenix.iterate(Complex::ZERO, 100).iterations, 100);
+        assert_eq!(phoenix.iterate(Complex::new(2.0, 2.0), 100).iterations, 0);
+    }
+
+    /// Tests that the previous value is used: 0.5 shrinks to 0 under z², but escapes once q·z(n-1) is added.
+    #[test]
+    fn test_uses_previous_value() {
+        let z0 = Complex::new(0.5, 0.0);
+        assert_eq!(Phoenix::new(Complex::ZERO).julia(Complex::ZERO).iterate(z0, 100).iterations, 100);
+        assert!(Phoenix::new(Complex::ONE).julia(Complex::ZERO).iterate(z0, 100).iterations < 10);
+    }
+}
@@ -0,0 +1,111 @@
+// Commands read from a pipe, for other programs driving the renderer: `pipe` reads standard input a line
+// at a time, each line being a command line like those given to the program (quoted like in a shell),
+// and runs it. Blank lines and lines starting with # are skipped, `quit` or the end of the input stops.
+// Once a job is over, a line starting with END_MARKER tells its number and how it went, so the reader
+// knows where the output of the job ends and whether to look at its files:
+//
+//     $ printf 'render --zoom 40 --output "a b.png"\nrender --size big\n' | final_exercice pipe
+//     ... (the report of the render)
+//     @end 1 ok
+//     @end 2 error Size 'big' must be in the format WIDTHxHEIGHT
+//
+// The markers are printed on stdout with the results of the commands, the logs staying on stderr. An error
+// is written on its marker line with its hint. The logging is set up once by the options of the pipe
+// command (`pipe --quiet`), the lines cannot change it.
+use std::io::{self, BufRead, Write};
+
+pub const END_MARKER: &str = "@end";
+const QUIT: &str = "quit";
+
+// The words of a command line: spaces separate them except inside quotes, a backslash escapes the
+// character after it outside single quotes.
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None; // None between words, so that "" is a word
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                let escaped = chars.next().ok_or("The line ends with a backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("Unclosed {} in the line", q));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// The marker ending a job, its error kept on the marker line.
+pub fn end_marker(job: usize, result: &Result<(), String>) -> String {
+    match result {
+        Ok(()) => format!("{} {} ok", END_MARKER, job),
+        Err(e) => format!("{} {} error {}", END_MARKER, job, e.split_whitespace().collect::<Vec<_>>().join(" ")),
+    }
+}
+
+// Run the command line of every line of the input, writing the marker of each job to the output once it
+// is over. Returns the number of jobs run.
+pub fn run(input: impl BufRead, mut output: impl Write, mut run_job: impl FnMut(&[String]) -> Result<(), String>) -> io::Result<usize> {
+    let mut jobs = 0;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == QUIT {
+            break;
+        }
+        jobs += 1;
+        let result = split_line(line).and_then(|words| if words.is_empty() { Err("No command".to_string()) } else { run_job(&words) });
+        writeln!(output, "{}", end_marker(jobs, &result))?;
+        // The reader waits for the marker, which must not stay in a buffer
+        output.flush()?;
+    }
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the splitting of words, quotes and escapes.
+    #[test]
+    fn test_split_line() {
+        assert_eq!(split_line("render  --zoom 40").unwrap(), vec!["render", "--zoom", "40"]);
+        assert_eq!(split_line(r#"render --output "a b.png" --annotate 'it''s {zoom}'"#).unwrap(), vec!["render", "--output", "a b.png", "--annotate", "its {zoom}"]);
+        assert_eq!(split_line(r#"a\ b "" "say \"hi\"""#).unwrap(), vec!["a b", "", "say \"hi\""]);
+        assert!(split_line("render --output \"a.png").is_err());
+        assert!(split_line("render \\").is_err());
+    }
+
+    /// Tests that every job ends with its marker, failing ones with their error, until quit.
+    #[test]
+    fn test_run() {
+        let input = "# jobs\nrender --zoom 2\n\nfail now\n\"unclosed\nquit\nrender --zoom 3\n";
+        let mut output = Vec::new();
+        let mut commands = Vec::new();
+        let jobs = run(input.as_bytes(), &mut output, |words| {
+            commands.push(words.join(" "));
+            if words[0] == "fail" { Err("Failed\nbadly".to_string()) } else { Ok(()) }
+        })
+        .unwrap();
+        assert_eq!(jobs, 3);
+        assert_eq!(commands, vec!["render --zoom 2", "fail now"]);
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(lines, vec!["@end 1 ok", "@end 2 error Failed badly", "@end 3 error Unclosed \" in the line"]);
+    }
+}
@@ -0,0 +1,140 @@
+// A registry of colorings defined outside of this crate. A program using the library registers its
+// own ColorMap implementations by name with register_coloring, and from then on they are chosen like
+// the built-in ones: the `--coloring` option, the menus and coloring_with_options find them here once
+// the names of COLORINGS did not match.
+//
+// With the `plugins` feature, the program also loads colorings from dynamic libraries given to
+// `--plugin`. Such a library is a cdylib depending on this crate and exporting
+//
+//     #[no_mangle]
+//     pub fn register_colorings(register: &mut dyn FnMut(&str, ColoringFactory)) { ... }
+//
+// It is handed a callback rather than calling register_coloring, since the library holds its own copy
+// of the registry. Rust has no stable ABI: the library must be built by the same compiler and against
+// the same version of this crate as the program, or loading it is undefined behavior.
+use crate::coloring::{ColoringOptions, COLORINGS};
+use crate::error::Error;
+use crate::mandelbrot::ColorMap;
+use crate::palette::Palette;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+// What a coloring is asked to be built with: the palette chosen by name, which it may ignore, the
+// iteration limit and the options of the command.
+pub struct ColoringRequest<'a> {
+    pub palette: &'a str,
+    pub max_iterations: u32,
+    pub options: &'a ColoringOptions,
+}
+
+impl ColoringRequest<'_> {
+    // The palette asked for with its transform, for the colorings drawing with a gradient.
+    pub fn palette(&self) -> Result<Palette, Error> {
+        Palette::by_name(self.palette)?.with_transform(self.options.palette_transform)
+    }
+}
+
+// Builds the color map of a registered coloring.
+pub type ColoringFactory = Arc<dyn Fn(&ColoringRequest) -> Result<Box<dyn ColorMap>, Error> + Send + Sync>;
+
+fn registry() -> &'static RwLock<BTreeMap<String, ColoringFactory>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, ColoringFactory>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+// Add a coloring under a name, which must not be taken by a built-in or an earlier registration.
+pub fn register_coloring<F>(name: &str, factory: F) -> Result<(), Error>
+where
+    F: Fn(&ColoringRequest) -> Result<Box<dyn ColorMap>, Error> + Send + Sync + 'static,
+{
+    register_factory(name, Arc::new(factory))
+}
+
+fn register_factory(name: &str, factory: ColoringFactory) -> Result<(), Error> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(Error::Plugin(format!("Invalid coloring name '{}', expected a word", name)));
+    }
+    let mut registry = registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if COLORINGS.contains(&name) || registry.contains_key(name) {
+        return Err(Error::Plugin(format!("The coloring '{}' is already defined", name)));
+    }
+    registry.insert(name.to_string(), factory);
+    Ok(())
+}
+
+// The factory of a registered coloring.
+pub fn registered_coloring(name: &str) -> Option<ColoringFactory> {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(name).cloned()
+}
+
+// The names of every coloring: the built-in ones, then the registered ones in alphabetical order.
+pub fn coloring_names() -> Vec<String> {
+    let registry = registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    COLORINGS.iter().map(|name| name.to_string()).chain(registry.keys().cloned()).collect()
+}
+
+// The function a plugin library exports as `register_colorings`.
+#[cfg(feature = "plugins")]
+pub type RegisterColorings = fn(&mut dyn FnMut(&str, ColoringFactory));
+
+// Load a plugin library and register its colorings, returning their names. The library stays loaded
+// until the program exits, the factories pointing into its code.
+#[cfg(feature = "plugins")]
+pub fn load_plugin(path: &std::path::Path) -> Result<Vec<String>, Error> {
+    let failed = |reason: String| Error::Plugin(format!("Cannot load the plugin {}: {}", path.display(), reason));
+    // Safety: see the header, the library must be built like the program to be called safely
+    let library = unsafe { libloading::Library::new(path) }.map_err(|e| failed(e.to_string()))?;
+    let register = unsafe { library.get::<RegisterColorings>(b"register_colorings") }.map_err(|e| failed(e.to_string()))?;
+    let (mut names, mut error) = (Vec::new(), None);
+    register(&mut |name, factory| match register_factory(name, factory) {
+        Ok(()) => names.push(name.to_string()),
+        Err(e) => {
+            error.get_or_insert(e);
+        }
+    });
+    std::mem::forget(library);
+    match error {
+        Some(e) => Err(e),
+        None => Ok(names),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring;
+    use crate::mandelbrot::EscapeInfo;
+    use image::Rgb;
+
+    struct ConstantColorMap {
+        max_iterations: u32,
+        color: Rgb<u8>,
+    }
+
+    impl ColorMap for ConstantColorMap {
+        fn color(&self, _: &EscapeInfo) -> Rgb<u8> {
+            self.color
+        }
+
+        fn get_max_iterations(&self) -> u32 {
+            self.max_iterations
+        }
+    }
+
+    /// Tests that a registered coloring is built by name like the built-in ones and cannot replace them.
+    #[test]
+    fn test_register_coloring() {
+        register_coloring("constant", |request| {
+            let color = request.palette()?.at_float(0.5).map(|c| (c * 255.0).round() as u8);
+            Ok(Box::new(ConstantColorMap { max_iterations: request.max_iterations, color: Rgb(color) }) as Box<dyn ColorMap>)
+        })
+        .unwrap();
+        let map = coloring::coloring_by_name("constant", "viridis", 300).unwrap();
+        assert_eq!(map.get_max_iterations(), 300);
+        assert!(coloring::coloring_by_name("constant", "sunset", 300).is_err());
+        assert!(coloring_names().contains(&"constant".to_string()));
+        assert!(register_coloring("constant", |_| Err(Error::InvalidColoring(String::new()))).is_err());
+        assert!(register_coloring("smooth", |_| Err(Error::InvalidColoring(String::new()))).is_err());
+        assert!(register_coloring("two words", |_| Err(Error::InvalidColoring(String::new()))).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+// What is drawn over an image once it is rendered, before it is saved: the grid and the axes of the
+// plane, a caption, then a watermark, a PNG composited through its alpha channel (a signature, a logo)
+// in a corner. Every command saving a still image reads the same options through post_process_arg in
+// main.rs, so captions and watermarks do not need an image editor afterwards; the axes need an image of
+// a plane, and the SVG files of the curves refuse them all. The animations, videos and tiles are left
+// as they are rendered.
+use crate::axes;
+use crate::naming::{self, NameTokens};
+use crate::text::{Caption, Corner};
+use image::{Rgb, RgbImage, RgbaImage};
+
+// The distance of the watermark to the edges of the image.
+const WATERMARK_MARGIN: u32 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    pub image: RgbaImage,
+    pub corner: Corner,
+    pub opacity: f32, // Multiplying the alpha of the PNG
+}
+
+impl Watermark {
+    pub fn load(path: &str, corner: Corner, opacity: f32) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(format!("The opacity of the watermark is between 0 and 1, not {}", opacity));
+        }
+        let image = image::open(path).map_err(|e| format!("Could not read the watermark {}: {}", path, e))?.to_rgba8();
+        Ok(Self { image, corner, opacity })
+    }
+
+    // Blend the watermark into its corner of the image, leaving out what does not fit.
+    pub fn draw(&self, image: &mut RgbImage) {
+        let (width, height) = image.dimensions();
+        let (mark_width, mark_height) = self.image.dimensions();
+        let left = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => WATERMARK_MARGIN as i64,
+            Corner::TopRight | Corner::BottomRight => width as i64 - WATERMARK_MARGIN as i64 - mark_width as i64,
+        };
+        let top = match self.corner {
+            Corner::TopLeft | Corner::TopRight => WATERMARK_MARGIN as i64,
+            Corner::BottomLeft | Corner::BottomRight => height as i64 - WATERMARK_MARGIN as i64 - mark_height as i64,
+        };
+        for (x, y, mark) in self.image.enumerate_pixels() {
+            let (px, py) = (left + x as i64, top + y as i64);
+            if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                continue;
+            }
+            let alpha = mark[3] as f32 / 255.0 * self.opacity;
+            let Rgb(below) = *image.get_pixel(px as u32, py as u32);
+            let blend = |i: usize| (below[i] as f32 + (mark[i] as f32 - below[i] as f32) * alpha).round() as u8;
+            image.put_pixel(px as u32, py as u32, Rgb([blend(0), blend(1), blend(2)]));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostProcess {
+    pub axes: bool,
+    pub caption: Option<Caption>, // Its text is a template of naming::expand
+    pub watermark: Option<Watermark>,
+}
+
+impl PostProcess {
+    // Whether nothing is drawn, for the outputs that cannot be drawn over.
+    pub fn is_empty(&self) -> bool {
+        !self.axes && self.caption.is_none() && self.watermark.is_none()
+    }
+
+    // Draw over an image of the bounds of the complex plane, or of something else than the plane (which
+    // has no axes) without bounds. The tokens fill in the caption.
+    pub fn apply(&self, image: &mut RgbImage, bounds: Option<(f32, f32, f32, f32)>, tokens: &NameTokens) -> Result<(), String> {
+        if self.axes {
+            axes::draw_axes(image, bounds.ok_or("The axes are those of the complex plane, which this image does not show")?);
+        }
+        if let Some(caption) = &self.caption {
+            Caption { text: naming::expand(&caption.text, tokens)?, ..caption.clone() }.draw(image);
+        }
+        if let Some(watermark) = &self.watermark {
+            watermark.draw(image);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Tests that the watermark is blended by its alpha and opacity into its corner.
+    #[test]
+    fn test_watermark() {
+        let mut mark = RgbaImage::from_pixel(4, 2, Rgba([255, 255, 255, 255]));
+        mark.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+        let watermark = Watermark { image: mark, corner: Corner::BottomRight, opacity: 0.5 };
+        let mut image = RgbImage::from_pixel(30, 20, Rgb([100, 100, 100]));
+        watermark.draw(&mut image);
+        let (left, top) = (30 - WATERMARK_MARGIN - 4, 20 - WATERMARK_MARGIN - 2);
+        assert_eq!(*image.get_pixel(left, top), Rgb([100, 100, 100])); // Transparent
+        assert_eq!(*image.get_pixel(left + 1, top), Rgb([178, 178, 178]));
+        assert_eq!(*image.get_pixel(left + 4, top), Rgb([100, 100, 100]));
+        // A watermark larger than the image is cut
+        let large = Watermark { image: RgbaImage::from_pixel(50, 50, Rgba([0, 0, 0, 255])), corner: Corner::TopLeft, opacity: 1.0 };
+        large.draw(&mut image);
+        assert_eq!(*image.get_pixel(29, 19), Rgb([0, 0, 0]));
+        assert!(Watermark::load("missing.png", Corner::TopLeft, 2.0).is_err());
+    }
+
+    /// Tests that the axes need bounds, and that captions get the tokens.
+    #[test]
+    fn test_post_process() {
+        let mut image = RgbImage::new(40, 30);
+        let axes = PostProcess { axes: true, ..PostProcess::default() };
+        assert!(axes.apply(&mut image, None, &NameTokens::new()).is_err());
+        assert!(axes.apply(&mut image, Some((-2.0, 2.0, -1.5, 1.5)), &NameTokens::new()).is_ok());
+        let caption = Caption { text: "{iter}".to_string(), corner: Corner::TopLeft, scale: 1, color: Rgb([255, 255, 255]) };
+        let captioned = PostProcess { caption: Some(caption), ..PostProcess::default() };
+        assert!(captioned.apply(&mut image, None, &NameTokens::new()).is_err()); // {iter} is unknown
+        assert!(captioned.apply(&mut image, None, &NameTokens::new().with("iter", 100)).is_ok());
+    }
+}
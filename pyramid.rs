@@ -0,0 +1,306 @@
+// Export of a render as a multi-resolution tile pyramid, to be explored in a deep zoom viewer such as
+// OpenSeadragon without ever holding the full image in memory.
+//
+// Level n of the pyramid is the image at 1/2^n of its full size (rounded up), down to a single pixel,
+// cut into square tiles of the same size. Every tile is rendered directly from the fractal at the
+// resolution of its level instead of being downsampled from the level below, so tiles are independent
+// and are rendered by several threads at once, the most detailed levels first.
+//
+// Two layouts are written:
+//   - dzi: NAME.dzi, the XML manifest, and NAME_files/{level}/{column}_{row}.{format}, level 0 being
+//     the single pixel. Tiles overlap their neighbours by `overlap` pixels so that viewers can blend
+//     their edges.
+//   - iiif: NAME/info.json, a level 0 IIIF Image API 3 service, and the tiles at the paths image
+//     viewers request them, NAME/{x},{y},{w},{h}/{width},{height}/0/default.{format}, the region
+//     being given in pixels of the full image. There is no overlap.
+use crate::mandelbrot::DEFAULT_VIEW_WIDTH;
+use crate::metadata;
+use crate::render::RenderOptions;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tracing::debug;
+
+// 254 pixels and an overlap of 1 make tiles of 256 pixels, apart from those of the edges.
+pub const DEFAULT_TILE_SIZE: u32 = 254;
+pub const DEFAULT_OVERLAP: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Dzi,
+    Iiif,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "dzi" => Ok(Layout::Dzi),
+            "iiif" => Ok(Layout::Iiif),
+            _ => Err(format!("Unknown pyramid layout '{}', expected dzi or iiif", s)),
+        }
+    }
+}
+
+// Format of the tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileFormat {
+    Png,
+    Jpeg,
+}
+
+impl FromStr for TileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "png" => Ok(TileFormat::Png),
+            "jpg" | "jpeg" => Ok(TileFormat::Jpeg),
+            _ => Err(format!("Unknown tile format '{}', expected png or jpg", s)),
+        }
+    }
+}
+
+impl TileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TileFormat::Png => "png",
+            TileFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+// One tile of the pyramid: its level, its place in the grid of the level and the part of the level
+// image it covers, overlap included, in pixels of that level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub level: u32,
+    pub column: u32,
+    pub row: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pyramid {
+    pub width: u32, // Size of the full image, the deepest level
+    pub height: u32,
+    pub tile_size: u32,
+    pub overlap: u32,
+    pub layout: Layout,
+}
+
+impl Pyramid {
+    pub fn new(width: u32, height: u32, tile_size: u32, overlap: u32, layout: Layout) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("The image size {}x{} must not be zero", width, height));
+        }
+        if tile_size == 0 {
+            return Err("The tiles must be at least one pixel wide".to_string());
+        }
+        // IIIF tiles are addressed by their region, which leaves no room for an overlap
+        let overlap = if layout == Layout::Iiif { 0 } else { overlap };
+        if overlap >= tile_size {
+            return Err(format!("The overlap of {} pixels must be smaller than the tiles of {}", overlap, tile_size));
+        }
+        Ok(Self { width, height, tile_size, overlap, layout })
+    }
+
+    // The level of the full image: the number of halvings down to a single pixel.
+    pub fn max_level(&self) -> u32 {
+        self.width.max(self.height).next_power_of_two().trailing_zeros()
+    }
+
+    // How many times smaller than the full image a level is.
+    pub fn scale(&self, level: u32) -> u32 {
+        1 << (self.max_level() - level)
+    }
+
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        let scale = self.scale(level);
+        (self.width.div_ceil(scale), self.height.div_ceil(scale))
+    }
+
+    // The tiles of every level, the most detailed levels first since they take the longest.
+    pub fn tiles(&self) -> Vec<Tile> {
+        (0..=self.max_level()).rev().flat_map(|level| self.level_tiles(level)).collect()
+    }
+
+    // The tiles of one level, row by row.
+    pub fn level_tiles(&self, level: u32) -> Vec<Tile> {
+        let (width, height) = self.level_size(level);
+        let mut tiles = Vec::new();
+        for row in 0..height.div_ceil(self.tile_size) {
+            for column in 0..width.div_ceil(self.tile_size) {
+                let (x, x_end) = self.span(column, width);
+                let (y, y_end) = self.span(row, height);
+                tiles.push(Tile { level, column, row, x, y, width: x_end - x, height: y_end - y });
+            }
+        }
+        tiles
+    }
+
+    // The pixels of the index-th tile along a side of the level, widened by the overlap.
+    fn span(&self, index: u32, length: u32) -> (u32, u32) {
+        let start = (index * self.tile_size).saturating_sub(self.overlap);
+        let end = ((index + 1) * self.tile_size + self.overlap).min(length);
+        (start, end)
+    }
+
+    // The render of a tile: the view of the full image shrunk to its level, restricted to the tile.
+    pub fn tile_options(&self, template: &RenderOptions, tile: &Tile) -> RenderOptions {
+        let (xmin, _, ymin, _) = template.bounds();
+        // Levels keep the pixels square, a pixel of the level covering `scale` pixels of the full image
+        let pixel = DEFAULT_VIEW_WIDTH / template.zoom / template.width as f32 * self.scale(tile.level) as f32;
+        RenderOptions {
+            width: tile.width,
+            height: tile.height,
+            center: (xmin + (tile.x as f32 + tile.width as f32 / 2.0) * pixel, ymin + (tile.y as f32 + tile.height as f32 / 2.0) * pixel),
+            zoom: DEFAULT_VIEW_WIDTH / (tile.width as f32 * pixel),
+            ..template.clone()
+        }
+    }
+
+    // Where a tile is written, relative to the directory of the pyramid.
+    pub fn tile_path(&self, name: &str, tile: &Tile, format: TileFormat) -> PathBuf {
+        match self.layout {
+            Layout::Dzi => PathBuf::from(format!("{}_files/{}/{}_{}.{}", name, tile.level, tile.column, tile.row, format.extension())),
+            Layout::Iiif => {
+                let (width, height) = self.level_size(tile.level);
+                // Viewers ask for the whole image when the level fits in a single tile
+                let region = if width < self.tile_size && height < self.tile_size {
+                    "full".to_string()
+                } else {
+                    let scale = self.scale(tile.level);
+                    let (x, y) = (tile.x * scale, tile.y * scale);
+                    let (w, h) = ((tile.width * scale).min(self.width - x), (tile.height * scale).min(self.height - y));
+                    format!("{},{},{},{}", x, y, w, h)
+                };
+                let size = if region == "full" && tile.level == self.max_level() { "max".to_string() } else { format!("{},{}", tile.width, tile.height) };
+                PathBuf::from(format!("{}/{}/{}/0/default.{}", name, region, size, format.extension()))
+            }
+        }
+    }
+
+    // The path and the text of the manifest describing the pyramid.
+    pub fn manifest(&self, name: &str, format: TileFormat, base_url: &str) -> (PathBuf, String) {
+        match self.layout {
+            Layout::Dzi => {
+                let xml = format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" TileSize=\"{}\" Overlap=\"{}\" Format=\"{}\">\n  <Size Width=\"{}\" Height=\"{}\"/>\n</Image>\n",
+                    self.tile_size,
+                    self.overlap,
+                    format.extension(),
+                    self.width,
+                    self.height
+                );
+                (PathBuf::from(format!("{}.dzi", name)), xml)
+            }
+            Layout::Iiif => {
+                let scale_factors: Vec<String> = (0..=self.max_level()).rev().map(|level| self.scale(level).to_string()).collect();
+                let id = format!("{}{}", base_url, name);
+                let json = format!(
+                    "{{\n  \"@context\": \"http://iiif.io/api/image/3/context.json\",\n  \"id\": \"{}\",\n  \"type\": \"ImageService3\",\n  \"protocol\": \"http://iiif.io/api/image\",\n  \"profile\": \"level0\",\n  \"width\": {},\n  \"height\": {},\n  \"tiles\": [{{\"width\": {}, \"scaleFactors\": [{}]}}],\n  \"extraFormats\": [\"{}\"]\n}}\n",
+                    id,
+                    self.width,
+                    self.height,
+                    self.tile_size,
+                    scale_factors.join(", "),
+                    format.extension()
+                );
+                (PathBuf::from(format!("{}/info.json", name)), json)
+            }
+        }
+    }
+}
+
+// Render every tile of a pyramid of the view of `template` (whose size is the full image) into
+// `directory`, on `threads` threads, then write its manifest. Returns the number of tiles.
+pub fn export_pyramid(pyramid: &Pyramid, template: &RenderOptions, directory: &str, name: &str, format: TileFormat, base_url: &str, threads: usize) -> Result<usize, String> {
+    let tiles = pyramid.tiles();
+    let next = AtomicUsize::new(0);
+    let failure = Mutex::new(None);
+    let render_tiles = || {
+        // Each thread takes the next tile of the schedule until none is left or one of them failed
+        while let Some(tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+            if let Err(e) = write_tile(pyramid, template, directory, name, format, tile) {
+                *failure.lock().unwrap() = Some(e);
+                next.store(tiles.len(), Ordering::Relaxed);
+            }
+        }
+    };
+    debug!("Rendering {} tiles over {} levels on {} threads", tiles.len(), pyramid.max_level() + 1, threads.max(1));
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(render_tiles);
+        }
+    });
+    if let Some(e) = failure.into_inner().unwrap() {
+        return Err(e);
+    }
+    let (path, manifest) = pyramid.manifest(name, format, base_url);
+    let path = Path::new(directory).join(path);
+    fs::write(&path, manifest).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    Ok(tiles.len())
+}
+
+fn write_tile(pyramid: &Pyramid, template: &RenderOptions, directory: &str, name: &str, format: TileFormat, tile: &Tile) -> Result<(), String> {
+    let path = Path::new(directory).join(pyramid.tile_path(name, tile, format));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Could not create {}: {}", parent.display(), e))?;
+    }
+    let options = pyramid.tile_options(template, tile);
+    metadata::save_render(&options.render()?, &path, &options).map_err(|e| format!("Could not save {}: {}", path.display(), e))?;
+    debug!("Tile {},{} of level {} saved as {}", tile.column, tile.row, tile.level, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the levels of a pyramid and the tiles of a level, overlap included.
+    #[test]
+    fn test_pyramid_levels() {
+        let pyramid = Pyramid::new(1000, 600, 254, 1, Layout::Dzi).unwrap();
+        assert_eq!(pyramid.max_level(), 10);
+        assert_eq!((pyramid.level_size(10), pyramid.level_size(9), pyramid.level_size(0)), ((1000, 600), (500, 300), (1, 1)));
+        assert_eq!(pyramid.level_size(1), (2, 2));
+
+        let tiles = pyramid.tiles();
+        assert_eq!(tiles[0], Tile { level: 10, column: 0, row: 0, x: 0, y: 0, width: 255, height: 255 });
+        assert_eq!(tiles[1], Tile { level: 10, column: 1, row: 0, x: 253, y: 0, width: 256, height: 255 });
+        assert_eq!(tiles[3], Tile { level: 10, column: 3, row: 0, x: 761, y: 0, width: 239, height: 255 });
+        assert_eq!(tiles.iter().filter(|tile| tile.level == 10).count(), 4 * 3);
+        assert_eq!(tiles.last(), Some(&Tile { level: 0, column: 0, row: 0, x: 0, y: 0, width: 1, height: 1 }));
+        assert_eq!(pyramid.tile_path("set", &tiles[1], TileFormat::Png), PathBuf::from("set_files/10/1_0.png"));
+        assert!(Pyramid::new(100, 100, 4, 4, Layout::Dzi).is_err());
+    }
+
+    /// Tests that the tiles of a level cover the view of the full image, and their IIIF paths.
+    #[test]
+    fn test_tile_views() {
+        let template = RenderOptions::builder().size(512, 256).bounds((-2.0, 2.0, -1.0, 1.0)).build().unwrap();
+        let pyramid = Pyramid::new(512, 256, 256, 0, Layout::Iiif).unwrap();
+        let tiles = pyramid.tiles();
+        assert_eq!(pyramid.tile_options(&template, &tiles[0]).bounds(), (-2.0, 0.0, -1.0, 1.0));
+        assert_eq!(pyramid.tile_options(&template, &tiles[1]).bounds(), (0.0, 2.0, -1.0, 1.0));
+        let half = tiles.iter().find(|tile| tile.level == 8).unwrap();
+        assert_eq!(pyramid.tile_options(&template, half).bounds(), (-2.0, 2.0, -1.0, 1.0));
+        let quarter = tiles.iter().find(|tile| tile.level == 7).unwrap();
+
+        assert_eq!(pyramid.tile_path("set", &tiles[1], TileFormat::Png), PathBuf::from("set/256,0,256,256/256,256/0/default.png"));
+        assert_eq!(pyramid.tile_path("set", half, TileFormat::Jpeg), PathBuf::from("set/0,0,512,256/256,128/0/default.jpg"));
+        assert_eq!(pyramid.tile_path("set", quarter, TileFormat::Png), PathBuf::from("set/full/128,64/0/default.png"));
+        let (path, manifest) = pyramid.manifest("set", TileFormat::Png, "http://localhost/");
+        assert_eq!(path, PathBuf::from("set/info.json"));
+        assert!(manifest.contains("\"id\": \"http://localhost/set\""));
+        assert!(manifest.contains("\"scaleFactors\": [1, 2, 4, 8, 16, 32, 64, 128, 256, 512]"));
+    }
+}
@@ -0,0 +1,133 @@
+// External rays of the Mandelbrot set: the curves of constant external angle, coming from infinity
+// and landing on the boundary. Rays of rational angles land at remarkable points, for example 1/3
+// and 2/3 at the root -3/4 of the period 2 bulb, which is why they are drawn to explain the set.
+//
+// A ray is traced inwards with Newton's method (as in Heiland-Allen's mandelbrot-numerics): at depth
+// j the point c where z_{j+1}(c) = r·e^{2πi·2^j·θ} is found for radii r going from the escape radius
+// down to its square root, the next depth starting again from the escape radius with the angle
+// doubled. The angle is kept as a fraction so that doubling it never loses precision.
+use crate::complex::Complex;
+use crate::error::Error;
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+pub const RAY_ESCAPE_RADIUS: f64 = 256.0;
+// Points of the ray computed per depth.
+pub const RAY_SHARPNESS: u32 = 8;
+// Depth reached unless another one is asked for, where the potential is below 1e-10. Rays landing
+// at the root of a bulb approach it slowly and still end a few hundredths away from it.
+pub const RAY_DEPTH: u32 = 40;
+// Newton steps tried for each point of the ray.
+const NEWTON_STEPS: u32 = 64;
+
+// An external angle in turns, the fraction numerator/denominator of a full turn, in [0, 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExternalAngle {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl ExternalAngle {
+    pub fn new(numerator: u64, denominator: u64) -> Result<Self, Error> {
+        if denominator == 0 || denominator > u64::MAX / 2 {
+            return Err(Error::InvalidParameter(format!("Invalid denominator {} for an external angle", denominator)));
+        }
+        Ok(Self { numerator: numerator % denominator, denominator })
+    }
+
+    pub fn turns(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    // The angle doubled modulo a full turn, what squaring z does to the angle of its orbit.
+    pub fn doubled(&self) -> Self {
+        Self { numerator: self.numerator * 2 % self.denominator, denominator: self.denominator }
+    }
+}
+
+impl FromStr for ExternalAngle {
+    type Err = Error;
+
+    // Read a fraction like "2/7", or a whole number of turns like "0".
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::parse("external angle", s, "a fraction like 1/3");
+        let (numerator, denominator) = s.trim().split_once('/').unwrap_or((s.trim(), "1"));
+        let numerator = numerator.trim().parse().map_err(|_| invalid())?;
+        let denominator = denominator.trim().parse().map_err(|_| invalid())?;
+        ExternalAngle::new(numerator, denominator)
+    }
+}
+
+// The points of the ray of an angle, from outside the escape radius to the given depth. The tracing
+// stops early if Newton's method fails, which happens when the ray gets too close to the set for f64.
+pub fn trace_ray(angle: ExternalAngle, depth: u32) -> Vec<Complex> {
+    let mut c = Complex::from_polar(RAY_ESCAPE_RADIUS, TAU * angle.turns());
+    let mut points = vec![c];
+    let mut angle_at_depth = angle;
+    for j in 0..depth {
+        for k in 0..RAY_SHARPNESS {
+            let radius = RAY_ESCAPE_RADIUS.powf(0.5f64.powf((k as f64 + 0.5) / RAY_SHARPNESS as f64));
+            let target = Complex::from_polar(radius, TAU * angle_at_depth.turns());
+            match newton_ray_point(c, target, j + 1) {
+                Some(next) => c = next,
+                None => return points,
+            }
+            points.push(c);
+        }
+        angle_at_depth = angle_at_depth.doubled();
+    }
+    points
+}
+
+// Solve z_n(c) = target with Newton's method, starting from c.
+fn newton_ray_point(mut c: Complex, target: Complex, iterations: u32) -> Option<Complex> {
+    for _ in 0..NEWTON_STEPS {
+        let (mut z, mut dc) = (Complex::ZERO, Complex::ZERO);
+        for _ in 0..iterations {
+            dc = z * dc * 2.0 + 1.0;
+            z = z * z + c;
+        }
+        let next = c - (z - target) / dc;
+        if !next.is_finite() {
+            return None;
+        }
+        let step = (next - c).norm();
+        c = next;
+        if step <= 1e-14 * c.norm().max(1e-3) {
+            break;
+        }
+    }
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the parsing of angles and that doubling stays exact.
+    #[test]
+    fn test_external_angle() {
+        let angle: ExternalAngle = "1/3".parse().unwrap();
+        assert_eq!(angle.doubled(), ExternalAngle::new(2, 3).unwrap());
+        assert_eq!(angle.doubled().doubled(), angle);
+        assert_eq!("0".parse::<ExternalAngle>().unwrap().turns(), 0.0);
+        assert_eq!("5/4".parse::<ExternalAngle>().unwrap().turns(), 0.25);
+        assert!("1/0".parse::<ExternalAngle>().is_err());
+        assert!("third".parse::<ExternalAngle>().is_err());
+    }
+
+    /// Tests that the rays 0 and 1/2 follow the real axis to the cusp 1/4 and the tip -2, and
+    /// that the ray 1/3 comes close to the root -3/4 of the period 2 bulb, where it lands.
+    #[test]
+    fn test_trace_ray_landing_points() {
+        let landings = [("0", Complex::new(0.25, 0.0), 0.05), ("1/2", Complex::new(-2.0, 0.0), 0.05), ("1/3", Complex::new(-0.75, 0.0), 0.1)];
+        for (angle, landing, distance) in landings {
+            let ray = trace_ray(angle.parse().unwrap(), RAY_DEPTH);
+            assert!(ray.len() > RAY_SHARPNESS as usize * 10, "The ray {} stopped early", angle);
+            let end = ray[ray.len() - 1];
+            assert!((end - landing).norm() < distance, "The ray {} ends at {:?}", angle, end);
+        }
+        let ray = trace_ray(ExternalAngle::new(0, 1).unwrap(), 10);
+        assert!(ray.iter().all(|c| c.im.abs() < 1e-9 && c.re > 0.25));
+    }
+}
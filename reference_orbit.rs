@@ -0,0 +1,216 @@
+// Reference orbits shared by the frames of a zoom. Every frame of `zoom-video` looks at the same center,
+// so the orbit of the center is iterated once, as deep as the iteration limit of the frames asks, and
+// only extended when a later frame raises the limit (with --auto-iter). The cache keeps the orbits by
+// their reference point, each knowing how deep it was iterated and whether it escaped.
+//
+// The pixels are iterated as small offsets from the orbit of the center (perturbation): with z = Z + δ,
+// z² + c becomes δ ← 2Zδ + δ² + δc, δc being the offset of the pixel from the center, computed from the
+// magnification rather than from the f32 bounds of the frame, which round every pixel to the same point
+// once the zoom is deep enough. When z gets smaller than δ, or the orbit of the center ends, δ takes the
+// whole of z and the orbit of the center is followed again from its start (rebasing), which keeps the
+// offsets small.
+//
+// Like the escape loop of fractal.rs, the cycles of the interior points are detected on z = Z + δ, and
+// a view centered on the real axis only iterates the rows above it, the others being their mirrors.
+use crate::coloring::{OrbitAverage, OrbitStatistic};
+use crate::complex::Complex;
+use crate::fractal::{EscapeResult, OrbitObserver, DEFAULT_BAILOUT, PERIODICITY_EPSILON};
+use crate::mandelbrot::{EscapeInfo, DEFAULT_VIEW_WIDTH};
+use std::collections::HashMap;
+
+const BAILOUT_SQR: f64 = DEFAULT_BAILOUT * DEFAULT_BAILOUT;
+
+// The orbit of z² + c from 0, for c the reference point.
+pub struct ReferenceOrbit {
+    point: Complex,
+    orbit: Vec<Complex>, // Z₀ = 0 to the last value iterated
+    escaped: bool,
+}
+
+impl ReferenceOrbit {
+    pub fn new(point: Complex) -> Self {
+        Self { point, orbit: vec![Complex::ZERO], escaped: false }
+    }
+
+    // The number of steps iterated so far.
+    pub fn depth(&self) -> u32 {
+        (self.orbit.len() - 1) as u32
+    }
+
+    // Iterate the orbit up to `depth` steps, or until it escapes, keeping the steps iterated before.
+    // Returns the number of new steps.
+    pub fn extend_to(&mut self, depth: u32) -> u32 {
+        let start = self.depth();
+        while !self.escaped && self.depth() < depth {
+            let z = self.orbit[self.orbit.len() - 1];
+            let next = z * z + self.point;
+            self.escaped = next.norm_sqr() > BAILOUT_SQR;
+            self.orbit.push(next);
+        }
+        self.depth() - start
+    }
+
+    // The escape of the point `offset` away from the reference point, like Mandelbrot::iterate_with with
+    // the default bailout, the observer seeing z = Z + δ at every step.
+    pub fn escape(&self, offset: Complex, max_iterations: u32, observer: &mut dyn OrbitObserver) -> EscapeResult {
+        let (mut delta, mut n, mut previous) = (Complex::ZERO, 0, Complex::ZERO);
+        let mut min_norm_sqr = f64::INFINITY;
+        // Compared with z at every power of two steps (Brent's method), as in fractal::escape
+        let (mut snapshot, mut next_snapshot) = (Complex::ZERO, 1);
+        for iteration in 1..=max_iterations {
+            delta = self.orbit[n] * delta * 2.0 + delta * delta + offset;
+            n += 1;
+            let z = self.orbit[n] + delta;
+            observer.observe(previous, z);
+            min_norm_sqr = min_norm_sqr.min(z.norm_sqr());
+            let result = |iterations| EscapeResult { iterations, z, min_distance: min_norm_sqr.sqrt(), converged: false };
+            if z.norm_sqr() > BAILOUT_SQR {
+                return result(iteration);
+            }
+            if (z - snapshot).norm_sqr() < PERIODICITY_EPSILON * PERIODICITY_EPSILON || iteration == max_iterations {
+                return result(max_iterations);
+            } else if iteration == next_snapshot {
+                (snapshot, next_snapshot) = (z, next_snapshot * 2);
+            }
+            if z.norm_sqr() < delta.norm_sqr() || n == self.orbit.len() - 1 {
+                (delta, n) = (z, 0);
+            }
+            previous = z;
+        }
+        EscapeResult { iterations: max_iterations, z: Complex::ZERO, min_distance: 0.0, converged: false }
+    }
+}
+
+// The reference orbits of the frames rendered so far, by reference point.
+#[derive(Default)]
+pub struct OrbitCache {
+    orbits: HashMap<(u64, u64), ReferenceOrbit>,
+    steps: u64, // Steps iterated over every orbit, for the logs
+}
+
+impl OrbitCache {
+    // The orbit of a point iterated at least `depth` steps (or until it escaped).
+    pub fn orbit(&mut self, point: Complex, depth: u32) -> &ReferenceOrbit {
+        let orbit = self.orbits.entry((point.re.to_bits(), point.im.to_bits())).or_insert_with(|| ReferenceOrbit::new(point));
+        self.steps += orbit.extend_to(depth) as u64;
+        orbit
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // The escape information of the view of `center` and `zoom` (like mandelbrot::view_bounds), stored row
+    // by row like mandelbrot::compute_escape_info, averaging the statistic along the orbits when the
+    // coloring needs one.
+    pub fn compute_escape_info(
+        &mut self,
+        center: (f64, f64),
+        zoom: f64,
+        width: u32,
+        height: u32,
+        max_iterations: u32,
+        statistic: Option<OrbitStatistic>,
+    ) -> Vec<EscapeInfo> {
+        let orbit = self.orbit(Complex::new(center.0, center.1), max_iterations);
+        let half_width = DEFAULT_VIEW_WIDTH as f64 / zoom / 2.0;
+        let half_height = half_width * height as f64 / width as f64;
+        let (scale_x, scale_y) = (2.0 * half_width / width as f64, 2.0 * half_height / height as f64);
+        let escape_info = |offset| match statistic {
+            Some(statistic) => {
+                let mut average = OrbitAverage::new(statistic);
+                let escape = orbit.escape(offset, max_iterations, &mut average);
+                EscapeInfo::new(&escape, max_iterations, DEFAULT_BAILOUT).with_orbit_average(&average)
+            }
+            None => EscapeInfo::new(&orbit.escape(offset, max_iterations, &mut ()), max_iterations, DEFAULT_BAILOUT),
+        };
+        let mut escapes: Vec<EscapeInfo> = Vec::with_capacity((width * height) as usize);
+        for py in 0..height {
+            // Row py is at (py - height / 2) rows from the center, so on the real axis row height - py
+            // is its mirror, as in fractal::map_plane_symmetric
+            let mirror_row = height - py;
+            if center.1 == 0.0 && mirror_row < py {
+                let start = (mirror_row * width) as usize;
+                for px in 0..width as usize {
+                    let escape = escapes[start + px].mirrored(statistic);
+                    escapes.push(escape);
+                }
+                continue;
+            }
+            for px in 0..width {
+                escapes.push(escape_info(Complex::new(px as f64 * scale_x - half_width, py as f64 * scale_y - half_height)));
+            }
+        }
+        escapes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{Fractal, Mandelbrot};
+
+    /// Tests that deeper frames extend the orbit of their center instead of iterating it again.
+    #[test]
+    fn test_orbit_cache() {
+        let mut cache = OrbitCache::default();
+        let center = Complex::new(-0.1, 0.1); // In the main cardioid
+        assert_eq!(cache.orbit(center, 100).depth(), 100);
+        assert_eq!(cache.orbit(center, 300).depth(), 300);
+        assert_eq!(cache.orbit(center, 200).depth(), 300);
+        assert_eq!(cache.steps(), 300);
+        // An escaping point stops where it escaped, however deep it is asked for
+        assert_eq!(cache.orbit(Complex::ONE, 100).depth(), 3);
+    }
+
+    /// Tests that the perturbed escapes match those of the escape loop at the same points, and that the rows
+    /// mirrored across the real axis match their own iteration. Only the points whose orbit lands on the
+    /// bailout circle, within rounding, may escape a step apart.
+    #[test]
+    fn test_compute_escape_info() {
+        let on_escape_boundary = |c: Complex, iterations: u32| {
+            let z = (0..iterations).fold(Complex::ZERO, |z, _| z * z + c);
+            (z.norm_sqr() - BAILOUT_SQR).abs() < 1e-9
+        };
+        let (width, height, max_iterations) = (40, 30, 200);
+        for (center, zoom) in [((-0.75, 0.1), 4.0), ((-0.5, 0.0), 1.0), ((0.3, 0.5), 60.0), ((-1.25, 0.0), 8.0)] {
+            // The pixels are DEFAULT_VIEW_WIDTH / zoom / width apart, the center being in the middle of the view
+            let step = DEFAULT_VIEW_WIDTH as f64 / zoom / width as f64;
+            let point = |i: usize| {
+                let (px, py) = ((i as u32 % width) as f64, (i as u32 / width) as f64);
+                Complex::new(center.0 + (px - width as f64 / 2.0) * step, center.1 + (py - height as f64 / 2.0) * step)
+            };
+            let perturbed = OrbitCache::default().compute_escape_info(center, zoom, width, height, max_iterations, None);
+            for (i, escape) in perturbed.iter().enumerate() {
+                let expected = EscapeInfo::new(&Mandelbrot.iterate(point(i), max_iterations), max_iterations, DEFAULT_BAILOUT);
+                let pixel = (i as u32 % width, i as u32 / width);
+                // The rounding of the two loops grows along the long orbits, a hundredth of a band is not visible
+                if expected.iterations == escape.iterations {
+                    assert!((expected.smooth - escape.smooth).abs() < 1e-2, "pixel {:?} around {:?}", pixel, center);
+                } else {
+                    assert!(on_escape_boundary(point(i), expected.iterations.min(escape.iterations)), "pixel {:?} around {:?}", pixel, center);
+                }
+            }
+        }
+    }
+
+    /// Tests that the statistic of the orbit colorings is averaged along the perturbed orbits like along the
+    /// orbits of the escape loop, the mirrored rows included.
+    #[test]
+    fn test_orbit_average() {
+        let statistic = OrbitStatistic::Stripe { density: 5.0 };
+        let (width, height, max_iterations) = (16, 12, 100);
+        let escapes = OrbitCache::default().compute_escape_info((-0.5, 0.0), 1.0, width, height, max_iterations, Some(statistic));
+        let step = DEFAULT_VIEW_WIDTH as f64 / width as f64;
+        for (i, escape) in escapes.iter().enumerate() {
+            let (px, py) = ((i as u32 % width) as f64, (i as u32 / width) as f64);
+            let point = Complex::new(-0.5 + (px - width as f64 / 2.0) * step, (py - height as f64 / 2.0) * step);
+            let mut average = OrbitAverage::new(statistic);
+            let expected = EscapeInfo::new(&Mandelbrot.iterate_with(point, max_iterations, DEFAULT_BAILOUT, &mut average), max_iterations, DEFAULT_BAILOUT);
+            let expected = expected.with_orbit_average(&average);
+            if expected.iterations == escape.iterations {
+                assert!((expected.orbit_average - escape.orbit_average).abs() < 1e-2, "pixel {:?}", (px, py));
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+// Options of a Mandelbrot render, built step by step instead of being passed as loose arguments:
+//
+//     let options = RenderOptions::builder().size(1920, 1080).center(-0.75, 0.0).zoom(250.0)
+//         .iterations(1000).palette("viridis").build()?;
+//     let image = options.render()?;
+//
+// The view is a center and a magnification, the bounds being derived from them with the aspect
+// ratio of the image, so the plane is never stretched and no argument order has to be remembered.
+use crate::bounds::Framing;
+use crate::coloring::{self, ColoringOptions};
+use crate::dither::Dither;
+use crate::error::Error;
+use crate::light::Gamma;
+use crate::fractal::{self, Fractal, Mandelbrot, DEFAULT_BAILOUT};
+use crate::mandelbrot::{self, ColorMap, EscapeInfo, DEFAULT_VIEW_WIDTH};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub center: (f32, f32),
+    pub zoom: f32, // Magnification relative to a view DEFAULT_VIEW_WIDTH wide
+    pub max_iterations: u32,
+    pub palette: String,  // A palette name or mandelbrot::GRAYSCALE
+    pub coloring: String, // One of coloring::COLORINGS, or a registered coloring
+    pub bailout: f64,     // Escape radius
+    #[serde(default)]
+    pub coloring_options: ColoringOptions, // Palette transform and hsv settings
+    #[serde(default)]
+    pub dither: Dither, // Of the quantization of the colors to 8 bits
+    #[serde(default = "no_antialiasing")]
+    pub antialias: u32, // Samples per pixel in each direction, averaged in linear light
+    #[serde(default)]
+    pub gamma: Gamma,
+    #[serde(default)]
+    pub letterbox: Option<(f32, f32, f32, f32)>, // Region outside of which the image is black, see bounds::Framing
+}
+
+// Most samples per pixel in each direction.
+pub const MAX_ANTIALIAS: u32 = 8;
+
+fn no_antialiasing() -> u32 {
+    1
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            center: (-0.5, 0.0),
+            zoom: 1.0,
+            max_iterations: 100,
+            palette: "turbo".to_string(),
+            coloring: "iterations".to_string(),
+            bailout: DEFAULT_BAILOUT,
+            coloring_options: ColoringOptions::default(),
+            dither: Dither::None,
+            antialias: no_antialiasing(),
+            gamma: Gamma::Srgb,
+            letterbox: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn builder() -> RenderOptionsBuilder {
+        RenderOptionsBuilder { options: RenderOptions::default() }
+    }
+
+    // The part of the plane covered by the image.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        mandelbrot::view_bounds(self.center, self.zoom, self.width, self.height)
+    }
+
+    pub fn render(&self) -> Result<RgbImage, Error> {
+        self.colorize(&self.compute()?)
+    }
+
+    // Render into an RGBA buffer, row by row and fully opaque, the layout expected by HTML canvases.
+    pub fn render_rgba(&self) -> Result<Vec<u8>, Error> {
+        let image = self.render()?;
+        Ok(image.pixels().flat_map(|pixel| {
+            let [r, g, b] = pixel.0;
+            [r, g, b, 255]
+        }).collect())
+    }
+
+    fn color_map(&self) -> Result<Box<dyn ColorMap>, Error> {
+        coloring::coloring_with_options(&self.coloring, &self.palette, self.max_iterations, &self.coloring_options)
+    }
+
+    // The compute pass of render: the escape information of every pixel, or of every sample when
+    // antialiased, which is the slow part.
+    pub fn compute(&self) -> Result<Vec<EscapeInfo>, Error> {
+        self.compute_with(&Mandelbrot)
+    }
+
+    // The compute pass with another fractal than the Mandelbrot set, for the viewers of other planes.
+    pub fn compute_with(&self, fractal: &dyn Fractal) -> Result<Vec<EscapeInfo>, Error> {
+        let _span = tracing::info_span!("compute", width = self.width, height = self.height, iterations = self.max_iterations).entered();
+        let statistic = self.color_map()?.statistic();
+        let (width, height, max_iterations) = (self.width * self.antialias, self.height * self.antialias, self.max_iterations);
+        Ok(mandelbrot::compute_escape_info(fractal, width, height, max_iterations, self.bounds(), self.bailout, statistic))
+    }
+
+    // The color pass of render, cheap enough to run again whenever only the colors change.
+    pub fn colorize(&self, escapes: &[EscapeInfo]) -> Result<RgbImage, Error> {
+        let _span = tracing::info_span!("colorize", width = self.width, height = self.height).entered();
+        let color_map = self.color_map()?;
+        let mut image = mandelbrot::colorize_supersampled(self.width, self.height, self.antialias, escapes, &*color_map, self.dither, self.gamma);
+        Framing { bounds: self.bounds(), region: self.letterbox }.apply(&mut image);
+        Ok(image)
+    }
+}
+
+// Builder of RenderOptions, every option left out keeping its default value.
+pub struct RenderOptionsBuilder {
+    options: RenderOptions,
+}
+
+impl RenderOptionsBuilder {
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        (self.options.width, self.options.height) = (width, height);
+        self
+    }
+
+    pub fn center(mut self, re: f32, im: f32) -> Self {
+        self.options.center = (re, im);
+        self
+    }
+
+    pub fn zoom(mut self, zoom: f32) -> Self {
+        self.options.zoom = zoom;
+        self
+    }
+
+    // Show the horizontal extent of the bounds, centered on them. The vertical extent follows
+    // from the aspect ratio of the image, so set the size first.
+    pub fn bounds(mut self, bounds: (f32, f32, f32, f32)) -> Self {
+        let (xmin, xmax, ymin, ymax) = bounds;
+        self.options.center = ((xmin + xmax) / 2.0, (ymin + ymax) / 2.0);
+        self.options.zoom = DEFAULT_VIEW_WIDTH / (xmax - xmin);
+        self
+    }
+
+    // The bounds of a framing with their letterbox, if any.
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.options.letterbox = framing.region;
+        self.bounds(framing.bounds)
+    }
+
+    pub fn iterations(mut self, max_iterations: u32) -> Self {
+        self.options.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn palette(mut self, palette: &str) -> Self {
+        self.options.palette = palette.to_string();
+        self
+    }
+
+    pub fn coloring(mut self, coloring: &str) -> Self {
+        self.options.coloring = coloring.to_string();
+        self
+    }
+
+    pub fn bailout(mut self, bailout: f64) -> Self {
+        self.options.bailout = bailout;
+        self
+    }
+
+    pub fn coloring_options(mut self, options: ColoringOptions) -> Self {
+        self.options.coloring_options = options;
+        self
+    }
+
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.options.dither = dither;
+        self
+    }
+
+    pub fn antialias(mut self, samples: u32) -> Self {
+        self.options.antialias = samples;
+        self
+    }
+
+    pub fn gamma(mut self, gamma: Gamma) -> Self {
+        self.options.gamma = gamma;
+        self
+    }
+
+    // Check the options and return them.
+    pub fn build(self) -> Result<RenderOptions, Error> {
+        let options = self.options;
+        if options.width == 0 || options.height == 0 {
+            return Err(Error::InvalidView(format!("The image size {}x{} must not be zero", options.width, options.height)));
+        }
+        if !(options.zoom.is_finite() && options.zoom > 0.0) {
+            return Err(Error::InvalidView(format!("The zoom must be a positive number, not {}", options.zoom)));
+        }
+        if !(options.center.0.is_finite() && options.center.1.is_finite()) {
+            return Err(Error::InvalidView("The center must be a finite point".to_string()));
+        }
+        if !(1..=MAX_ANTIALIAS).contains(&options.antialias) {
+            return Err(Error::InvalidParameter(format!("Antialiasing takes 1 to {} samples per direction, not {}", MAX_ANTIALIAS, options.antialias)));
+        }
+        if options.max_iterations == 0 {
+            return Err(Error::InvalidParameter("The number of iterations must be positive".to_string()));
+        }
+        fractal::check_bailout(options.bailout)?;
+        options.color_map()?;
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::PaletteTransform;
+
+    /// Tests that the builder sets every option and derives the bounds from the view.
+    #[test]
+    fn test_builder() {
+        let options = RenderOptions::builder().size(400, 200).center(-1.0, 0.5).zoom(2.0).iterations(50).palette("viridis").build().unwrap();
+        assert_eq!((options.width, options.height, options.max_iterations), (400, 200, 50));
+        assert_eq!(options.palette, "viridis");
+        assert_eq!(options.bounds(), (-2.0, 0.0, 0.0, 1.0));
+
+        let from_bounds = RenderOptions::builder().size(800, 600).bounds((-2.0, 2.0, -1.5, 1.5)).build().unwrap();
+        assert_eq!(from_bounds.bounds(), (-2.0, 2.0, -1.5, 1.5));
+    }
+
+    /// Tests that invalid options are reported by build.
+    #[test]
+    fn test_builder_validation() {
+        assert!(RenderOptions::builder().size(0, 10).build().is_err());
+        assert!(RenderOptions::builder().zoom(-1.0).build().is_err());
+        assert!(RenderOptions::builder().iterations(0).build().is_err());
+        assert!(RenderOptions::builder().palette("nope").build().is_err());
+        assert!(RenderOptions::builder().palette(mandelbrot::GRAYSCALE).build().is_ok());
+        assert!(RenderOptions::builder().coloring("smooth").build().is_ok());
+        assert!(RenderOptions::builder().bailout(1e6).build().is_ok());
+        assert!(RenderOptions::builder().antialias(0).build().is_err());
+        assert!(RenderOptions::builder().antialias(MAX_ANTIALIAS + 1).build().is_err());
+        assert!(RenderOptions::builder().bailout(1.0).build().is_err());
+        assert!(RenderOptions::builder().coloring("smooth").palette(mandelbrot::GRAYSCALE).build().is_err());
+        let inverted = ColoringOptions { palette_transform: PaletteTransform { invert: true, ..Default::default() }, ..Default::default() };
+        assert!(RenderOptions::builder().coloring_options(inverted).build().is_ok());
+        assert!(RenderOptions::builder().coloring_options(inverted).palette(mandelbrot::GRAYSCALE).build().is_err());
+    }
+
+    /// Tests that an antialiased render computes every sample and is brought back to the image size.
+    #[test]
+    fn test_antialiased_render() {
+        let options = RenderOptions::builder().size(8, 6).antialias(3).build().unwrap();
+        assert_eq!(options.compute().unwrap().len(), 24 * 18);
+        assert_eq!(options.render().unwrap().dimensions(), (8, 6));
+    }
+
+    /// Tests that the RGBA buffer holds the pixels of the image row by row with an opaque alpha.
+    #[test]
+    fn test_render_rgba() {
+        let options = RenderOptions::builder().size(4, 3).palette(mandelbrot::GRAYSCALE).build().unwrap();
+        let (image, rgba) = (options.render().unwrap(), options.render_rgba().unwrap());
+        assert_eq!(rgba.len(), 4 * 3 * 4);
+        let pixel = image.get_pixel(1, 2).0;
+        assert_eq!(&rgba[(2 * 4 + 1) * 4..][..4], &[pixel[0], pixel[1], pixel[2], 255]);
+        assert!(rgba.chunks(4).all(|rgba| rgba[3] == 255));
+    }
+}
@@ -0,0 +1,79 @@
+// The results of `render --json`: one JSON object on one line of stdout per render saved, instead of the
+// summary written for people, so that wrappers and CI pipelines can read what was rendered:
+//
+//     {"output": "mandelbrot.png", "parameters": {"fractal": "mandelbrot", "zoom": "40", ...},
+//      "options": {"zoom": "40"}, "stats": {"width": 800, "elapsed_seconds": 0.21, ...}, "warnings": []}
+//
+// The parameters are those the render was made with, defaults included, the options those given on
+// the command line or in the config file.
+use crate::naming::NameTokens;
+use crate::stats::RenderStats;
+use std::collections::BTreeMap;
+
+pub struct RenderReport<'a> {
+    pub output: &'a str,
+    pub tokens: &'a NameTokens,
+    pub options: BTreeMap<String, String>,
+    pub stats: &'a RenderStats,
+    pub warnings: Vec<String>,
+}
+
+impl RenderReport<'_> {
+    pub fn to_json(&self) -> String {
+        let parameters: Vec<(&str, &str)> = self.tokens.values().iter().map(|(name, value)| (*name, value.as_str())).collect();
+        let options: Vec<(&str, &str)> = self.options.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        let warnings: Vec<String> = self.warnings.iter().map(|warning| json_string(warning)).collect();
+        format!(
+            "{{\"output\": {}, \"parameters\": {}, \"options\": {}, \"stats\": {{{}}}, \"warnings\": [{}]}}",
+            json_string(self.output),
+            json_object(&parameters),
+            json_object(&options),
+            self.stats.json_fields().join(", "),
+            warnings.join(", ")
+        )
+    }
+}
+
+// A string quoted for JSON, its quotes, backslashes and control characters escaped.
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn json_object(pairs: &[(&str, &str)]) -> String {
+    let fields: Vec<String> = pairs.iter().map(|(name, value)| format!("{}: {}", json_string(name), json_string(value))).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Tests the escaping of strings and that the report is one line holding every part.
+    #[test]
+    fn test_report() {
+        assert_eq!(json_string("a \"b\" c\\d\n\u{1}"), "\"a \\\"b\\\" c\\\\d\\n\\u0001\"");
+        let tokens = NameTokens::default().with("fractal", "mandelbrot").size(4, 2);
+        let stats = RenderStats::new(4, 2, Duration::from_secs(1));
+        let options = BTreeMap::from([("zoom".to_string(), "40".to_string())]);
+        let warnings = vec!["Swapped \"xmin\" and xmax".to_string()];
+        let report = RenderReport { output: "out.png", tokens: &tokens, options, stats: &stats, warnings };
+        let json = report.to_json();
+        assert!(!json.contains('\n'));
+        assert!(json.starts_with("{\"output\": \"out.png\", \"parameters\": {\"fractal\": \"mandelbrot\", \"size\": \"4x2\"}, \"options\": {\"zoom\": \"40\"}"));
+        assert!(json.contains("\"stats\": {\"width\": 4, \"height\": 2, \"elapsed_seconds\": 1,"));
+        assert!(json.ends_with("\"warnings\": [\"Swapped \\\"xmin\\\" and xmax\"]}"));
+    }
+}
@@ -0,0 +1,240 @@
+// Scripts of renders in the rhai language, for the workflows neither the menu nor the commands can
+// express, like one Julia frame for every constant on a circle:
+//
+//     let frames = frames("julia_circle.mp4", 640, 480, 30);
+//     for step in 0..120 {
+//         let angle = 2.0 * PI() * step.to_float() / 120.0;
+//         frames.write(julia(0.7885 * cos(angle), 0.7885 * sin(angle), #{ width: 640, height: 480 }));
+//     }
+//     frames.finish();
+//
+// The functions given to the scripts:
+//
+//     render(view)                        the Mandelbrot set, as an Image
+//     julia(re, im, view)                 the Julia set of the constant re + im i
+//     image.save(path)                    write an Image, in the format of the extension of the path
+//     image.width, image.height           its size
+//     frames(output, width, height, fps)  the frames of an animation, written like the output of
+//                                         `animate`; frames.write(image) adds one, frames.finish() ends them
+//     file_name(template, frame)          a {frame} template of file names filled in
+//     palettes(), colorings()             the names the views accept
+//
+// A view is a map of the options of a render, any of them left out keeping its default:
+//
+//     #{ width: 800, height: 600, center: [-0.5, 0.0], zoom: 1.0, iterations: 100, palette: "turbo",
+//        coloring: "iterations" }
+//
+// with `bounds: [xmin, xmax, ymin, ymax]` in place of the center and zoom. Julia sets are colored by
+// iteration count, so their views take no coloring. The arguments given after the script are in the
+// array ARGS.
+use crate::animation::{self, FrameOutput, Keyframe};
+use crate::naming;
+use crate::render::RenderOptions;
+use final_exercice::{palette, plugins};
+use image::RgbImage;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, FLOAT, INT};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use tracing::info;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// The keys of a view.
+const VIEW_KEYS: [&str; 8] = ["width", "height", "center", "zoom", "bounds", "iterations", "palette", "coloring"];
+
+// A rendered image, shared by the variables of the script holding it.
+#[derive(Clone)]
+pub struct ScriptImage(Rc<RgbImage>);
+
+// The frames of an animation being written; None once finished.
+#[derive(Clone)]
+pub struct ScriptFrames(Rc<RefCell<Option<Frames>>>);
+
+struct Frames {
+    output: FrameOutput,
+    width: u32,
+    height: u32,
+    next: u32,
+}
+
+// Run a script, with the arguments given after it.
+pub fn run_script(path: &str, arguments: &[String]) -> Result<(), String> {
+    let engine = engine();
+    let mut scope = Scope::new();
+    let arguments: Array = arguments.iter().cloned().map(Dynamic::from).collect();
+    scope.push_constant("ARGS", arguments);
+    engine.run_file_with_scope(&mut scope, PathBuf::from(path)).map_err(|e| format!("Script {} failed: {}", path, e))
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.on_print(|text| info!("{}", text));
+    engine.register_type_with_name::<ScriptImage>("Image");
+    engine.register_type_with_name::<ScriptFrames>("Frames");
+    engine.register_fn("render", render);
+    engine.register_fn("julia", julia);
+    engine.register_fn("save", save);
+    engine.register_get("width", width);
+    engine.register_get("height", height);
+    engine.register_fn("frames", frames);
+    engine.register_fn("write", write_frame);
+    engine.register_fn("finish", finish);
+    engine.register_fn("file_name", file_name);
+    engine.register_fn("palettes", palettes);
+    engine.register_fn("colorings", colorings);
+    engine
+}
+
+fn render(view: Map) -> ScriptResult<ScriptImage> {
+    let image = view_options(&view)?.render().map_err(|e| e.to_string())?;
+    Ok(ScriptImage(Rc::new(image)))
+}
+
+fn julia(re: FLOAT, im: FLOAT, view: Map) -> ScriptResult<ScriptImage> {
+    if view.contains_key("coloring") {
+        return Err("Julia sets are colored by iteration count, their views take no coloring".into());
+    }
+    let options = view_options(&view)?;
+    let state = Keyframe {
+        time: 0.0,
+        center: options.center,
+        zoom: options.zoom,
+        iterations: options.max_iterations,
+        palette: options.palette,
+        julia: Some((re as f32, im as f32)),
+    };
+    Ok(ScriptImage(Rc::new(animation::render_frame(&state, options.width, options.height)?)))
+}
+
+fn save(image: &mut ScriptImage, path: &str) -> ScriptResult<()> {
+    image.0.save(path).map_err(|e| format!("Cannot save {}: {}", path, e))?;
+    info!("Image saved as {}", path);
+    Ok(())
+}
+
+fn width(image: &mut ScriptImage) -> INT {
+    image.0.width() as INT
+}
+
+fn height(image: &mut ScriptImage) -> INT {
+    image.0.height() as INT
+}
+
+fn frames(output: &str, width: INT, height: INT, fps: INT) -> ScriptResult<ScriptFrames> {
+    let (width, height, fps) = (positive(width, "width")?, positive(height, "height")?, positive(fps, "fps")?);
+    let output = FrameOutput::open(output, width, height, fps).map_err(|e| e.to_string())?;
+    Ok(ScriptFrames(Rc::new(RefCell::new(Some(Frames { output, width, height, next: 0 })))))
+}
+
+fn write_frame(frames: &mut ScriptFrames, image: ScriptImage) -> ScriptResult<()> {
+    let mut frames = frames.0.borrow_mut();
+    let frames = frames.as_mut().ok_or("The frames are already finished")?;
+    if image.0.dimensions() != (frames.width, frames.height) {
+        let (width, height) = image.0.dimensions();
+        return Err(format!("A frame of {}x{} cannot be added to frames of {}x{}", width, height, frames.width, frames.height).into());
+    }
+    frames.output.write(frames.next, &image.0).map_err(|e| e.to_string())?;
+    frames.next += 1;
+    Ok(())
+}
+
+fn finish(frames: &mut ScriptFrames) -> ScriptResult<()> {
+    let frames = frames.0.borrow_mut().take().ok_or("The frames are already finished")?;
+    frames.output.finish().map_err(|e| e.to_string())?;
+    info!("{} frames written", frames.next);
+    Ok(())
+}
+
+fn file_name(template: &str, frame: INT) -> ScriptResult<String> {
+    let frame = u32::try_from(frame).map_err(|_| format!("Invalid frame {}", frame))?;
+    Ok(naming::frame_name(template, frame)?)
+}
+
+fn palettes() -> Array {
+    palette::palette_names().into_iter().map(Dynamic::from).collect()
+}
+
+fn colorings() -> Array {
+    plugins::coloring_names().into_iter().map(Dynamic::from).collect()
+}
+
+fn positive(value: INT, name: &str) -> ScriptResult<u32> {
+    u32::try_from(value).ok().filter(|value| *value > 0).ok_or_else(|| format!("The {} must be a positive integer, not {}", name, value).into())
+}
+
+// The render options of a view.
+fn view_options(view: &Map) -> ScriptResult<RenderOptions> {
+    if let Some(key) = view.keys().find(|key| !VIEW_KEYS.contains(&key.as_str())) {
+        return Err(format!("Unknown key '{}' in the view. Available keys: {}", key, VIEW_KEYS.join(", ")).into());
+    }
+    let mut builder = RenderOptions::builder();
+    let default = RenderOptions::default();
+    let width = view.get("width").map(|value| integer(value, "width")).transpose()?.unwrap_or(default.width as INT);
+    let height = view.get("height").map(|value| integer(value, "height")).transpose()?.unwrap_or(default.height as INT);
+    builder = builder.size(positive(width, "width")?, positive(height, "height")?);
+    if let Some(bounds) = view.get("bounds") {
+        if view.contains_key("center") || view.contains_key("zoom") {
+            return Err("A view is given either by its bounds or by its center and zoom".into());
+        }
+        match numbers(bounds, "bounds")?[..] {
+            [xmin, xmax, ymin, ymax] => builder = builder.bounds((xmin, xmax, ymin, ymax)),
+            _ => return Err("The bounds of a view are four numbers [xmin, xmax, ymin, ymax]".into()),
+        }
+    }
+    if let Some(center) = view.get("center") {
+        match numbers(center, "center")?[..] {
+            [re, im] => builder = builder.center(re, im),
+            _ => return Err("The center of a view is two numbers [re, im]".into()),
+        }
+    }
+    if let Some(zoom) = view.get("zoom") {
+        builder = builder.zoom(number(zoom, "zoom")? as f32);
+    }
+    if let Some(iterations) = view.get("iterations") {
+        builder = builder.iterations(u32::try_from(integer(iterations, "iterations")?).map_err(|_| "The iterations cannot be negative")?);
+    }
+    if let Some(palette) = view.get("palette") {
+        builder = builder.palette(&text(palette, "palette")?);
+    }
+    if let Some(coloring) = view.get("coloring") {
+        builder = builder.coloring(&text(coloring, "coloring")?);
+    }
+    Ok(builder.build().map_err(|e| e.to_string())?)
+}
+
+fn number(value: &Dynamic, name: &str) -> ScriptResult<FLOAT> {
+    value.as_float().or_else(|_| value.as_int().map(|value| value as FLOAT)).map_err(|_| format!("The {} of a view must be a number", name).into())
+}
+
+fn integer(value: &Dynamic, name: &str) -> ScriptResult<INT> {
+    value.as_int().map_err(|_| format!("The {} of a view must be an integer", name).into())
+}
+
+fn numbers(value: &Dynamic, name: &str) -> ScriptResult<Vec<f32>> {
+    let array = value.clone().into_array().map_err(|_| format!("The {} of a view must be an array of numbers", name))?;
+    array.iter().map(|value| number(value, name).map(|value| value as f32)).collect()
+}
+
+fn text(value: &Dynamic, name: &str) -> ScriptResult<String> {
+    value.clone().into_string().map_err(|_| format!("The {} of a view must be a string", name).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a script renders and reads back the size of its images, and that bad views are refused.
+    #[test]
+    fn test_script_render() {
+        let engine = engine();
+        let size = engine.eval::<INT>("let image = render(#{ width: 40, height: 30, iterations: 50 }); image.width * image.height").unwrap();
+        assert_eq!(size, 1200);
+        assert!(engine.eval::<INT>("julia(-0.8, 0.156, #{ width: 20, height: 10 }).height").is_ok());
+        assert!(engine.run("render(#{ width: 40, height: 30, depth: 3 });").is_err());
+        assert!(engine.run("render(#{ center: [0.0], zoom: 2 });").is_err());
+        assert!(engine.run("julia(0.0, 0.0, #{ coloring: \"smooth\" });").is_err());
+        // Every name a view accepts, grayscale included
+        assert_eq!(engine.eval::<Array>("palettes()").unwrap().len(), palette::palette_names().len());
+    }
+}
@@ -0,0 +1,254 @@
+// A small HTTP server rendering tiles of the Mandelbrot set on demand, so that it can be browsed in a
+// web map like Leaflet or OpenLayers pointed at http://localhost:PORT/tiles/{z}/{x}/{y}.png.
+//
+// Tiles follow the slippy map convention: zoom level z splits the world, a square DEFAULT_VIEW_WIDTH
+// wide around WORLD_CENTER, into 2^z × 2^z tiles of TILE_SIZE pixels, x growing to the right and y
+// downwards. Requests are served one at a time and the most recently used tiles are kept in memory.
+use crate::mandelbrot::{self, DEFAULT_VIEW_WIDTH};
+use crate::render::RenderOptions;
+use image::ImageFormat;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const TILE_SIZE: u32 = 256;
+pub const WORLD_CENTER: (f32, f32) = (-0.5, 0.0);
+
+// Deeper tiles would be smaller than the precision of the f32 bounds.
+pub const MAX_TILE_ZOOM: u32 = 16;
+
+// Number of tiles kept in memory unless another one is asked for with --cache.
+pub const DEFAULT_CACHE_TILES: usize = 512;
+
+// How long a client may take to send its request before it is dropped, so that a client opening a
+// connection and sending nothing does not block the requests after it.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Position of a tile: zoom level, column and row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileKey {
+    // Magnification of the tiles of this level relative to the zoom 0 tile showing the whole world.
+    pub fn zoom(&self) -> f32 {
+        (1u32 << self.z) as f32
+    }
+
+    // Center of the tile on the complex plane.
+    pub fn center(&self) -> (f32, f32) {
+        let tile_width = DEFAULT_VIEW_WIDTH / self.zoom();
+        let (left, top) = (WORLD_CENTER.0 - DEFAULT_VIEW_WIDTH / 2.0, WORLD_CENTER.1 - DEFAULT_VIEW_WIDTH / 2.0);
+        (left + (self.x as f32 + 0.5) * tile_width, top + (self.y as f32 + 0.5) * tile_width)
+    }
+
+    // The part of the plane covered by the tile.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        mandelbrot::view_bounds(self.center(), self.zoom(), TILE_SIZE, TILE_SIZE)
+    }
+}
+
+// Parse the path of a tile request, /tiles/{z}/{x}/{y}.png.
+pub fn parse_tile_path(path: &str) -> Result<TileKey, String> {
+    let invalid = || format!("'{}' is not a tile path like /tiles/3/2/5.png", path);
+    let rest = path.strip_prefix("/tiles/").ok_or_else(invalid)?;
+    let rest = rest.strip_suffix(".png").ok_or_else(invalid)?;
+    let parts: Vec<u32> = rest.split('/').map(|part| part.parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+    let &[z, x, y] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    if z > MAX_TILE_ZOOM {
+        return Err(format!("Tiles go down to zoom level {}, not {}", MAX_TILE_ZOOM, z));
+    }
+    if x >= 1 << z || y >= 1 << z {
+        return Err(format!("Tile {}/{} is outside of the {} tiles per side of zoom level {}", x, y, 1u32 << z, z));
+    }
+    Ok(TileKey { z, x, y })
+}
+
+// The encoded PNG of the most recently used tiles, dropping the least recently used one when full.
+pub struct TileCache {
+    capacity: usize,
+    tiles: HashMap<TileKey, Vec<u8>>,
+    order: VecDeque<TileKey>, // Least recently used first
+}
+
+impl TileCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, tiles: HashMap::new(), order: VecDeque::new() }
+    }
+
+    pub fn get(&mut self, key: &TileKey) -> Option<&[u8]> {
+        if self.tiles.contains_key(key) {
+            self.touch(key);
+        }
+        self.tiles.get(key).map(Vec::as_slice)
+    }
+
+    pub fn insert(&mut self, key: TileKey, png: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.tiles.insert(key, png).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.tiles.remove(&oldest);
+            }
+        }
+    }
+
+    // Mark a cached tile as the most recently used.
+    fn touch(&mut self, key: &TileKey) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            self.order.remove(index);
+            self.order.push_back(*key);
+        }
+    }
+}
+
+pub struct TileServer {
+    // Palette, coloring and bailout of every tile; the size and the view come from the tile.
+    template: RenderOptions,
+    // Iteration limit of every tile, or None to raise it with the zoom level.
+    max_iterations: Option<u32>,
+    cache: TileCache,
+}
+
+impl TileServer {
+    pub fn new(template: RenderOptions, max_iterations: Option<u32>, cache_tiles: usize) -> Self {
+        Self { template, max_iterations, cache: TileCache::new(cache_tiles) }
+    }
+
+    // The PNG of a tile, rendered unless it is cached.
+    pub fn tile(&mut self, key: TileKey) -> Result<Vec<u8>, String> {
+        if let Some(png) = self.cache.get(&key) {
+            return Ok(png.to_vec());
+        }
+        let options = RenderOptions {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            center: key.center(),
+            zoom: key.zoom(),
+            max_iterations: self.max_iterations.unwrap_or_else(|| mandelbrot::auto_iterations(key.zoom())),
+            ..self.template.clone()
+        };
+        let mut png = Vec::new();
+        options.render()?.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(|e| format!("Could not encode the tile: {}", e))?;
+        info!("Rendered tile {}/{}/{} covering {:?}", key.z, key.x, key.y, key.bounds());
+        self.cache.insert(key, png.clone());
+        Ok(png)
+    }
+
+    // Answer one request: the PNG of a tile, or a plain text error.
+    fn handle(&mut self, stream: TcpStream) -> Result<(), Box<dyn Error>> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let request_line = read_request(BufReader::new(&stream))?;
+        let mut words = request_line.split_whitespace();
+        let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+
+        let response = if method != "GET" {
+            Err((405, "Method Not Allowed", format!("Only GET is supported, not {}", method)))
+        } else {
+            match parse_tile_path(path) {
+                Ok(key) => self.tile(key).map_err(|e| (500, "Internal Server Error", e)),
+                Err(e) => Err((404, "Not Found", e)),
+            }
+        };
+        let mut stream = stream;
+        match response {
+            Ok(png) => {
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\n", png.len())?;
+                // Let web maps on other origins load the tiles.
+                write!(stream, "Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n")?;
+                stream.write_all(&png)?;
+            }
+            Err((status, reason, message)) => {
+                write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n", status, reason, message.len())?;
+                write!(stream, "Connection: close\r\n\r\n{}", message)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Serve tiles until the process is stopped. A failed request is reported and the next one served.
+    pub fn run(&mut self, port: u16) -> Result<(), Box<dyn Error>> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        info!("Serving tiles at http://localhost:{}/tiles/{{z}}/{{x}}/{{y}}.png (Ctrl+C to stop)", port);
+        for stream in listener.incoming() {
+            if let Err(e) = stream.map_err(Box::from).and_then(|stream| self.handle(stream)) {
+                warn!("Request failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read the request line of an HTTP request and the headers after it, which are not used. Replying
+// before the client has sent them all could make it see the connection reset instead of the response.
+fn read_request(mut reader: impl BufRead) -> std::io::Result<String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+    Ok(request_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the tile at zoom 0 shows the whole world and that the four tiles of zoom 1 split it.
+    #[test]
+    fn test_tile_bounds() {
+        assert_eq!(TileKey { z: 0, x: 0, y: 0 }.bounds(), (-2.5, 1.5, -2.0, 2.0));
+        assert_eq!(TileKey { z: 1, x: 0, y: 0 }.bounds(), (-2.5, -0.5, -2.0, 0.0));
+        assert_eq!(TileKey { z: 1, x: 1, y: 1 }.bounds(), (-0.5, 1.5, 0.0, 2.0));
+    }
+
+    /// Tests the routing of tile paths and the refusal of tiles outside of their zoom level.
+    #[test]
+    fn test_parse_tile_path() {
+        assert_eq!(parse_tile_path("/tiles/3/2/5.png"), Ok(TileKey { z: 3, x: 2, y: 5 }));
+        assert!(parse_tile_path("/tiles/1/2/0.png").is_err());
+        assert!(parse_tile_path("/tiles/3/2.png").is_err());
+        assert!(parse_tile_path("/tiles/3/2/5.jpg").is_err());
+        assert!(parse_tile_path("/index.html").is_err());
+        assert!(parse_tile_path("/tiles/30/0/0.png").is_err());
+    }
+
+    /// Tests that the least recently used tile is the one dropped when the cache is full.
+    #[test]
+    fn test_tile_cache_eviction() {
+        let key = |x| TileKey { z: 2, x, y: 0 };
+        let mut cache = TileCache::new(2);
+        cache.insert(key(0), vec![0]);
+        cache.insert(key(1), vec![1]);
+        assert!(cache.get(&key(0)).is_some()); // Tile 1 is now the least recently used
+        cache.insert(key(2), vec![2]);
+        assert_eq!(cache.tiles.len(), 2);
+        assert!(cache.get(&key(1)).is_none());
+        assert_eq!(cache.get(&key(0)), Some(&[0u8][..]));
+    }
+
+    /// Tests that the headers of a request are read up to the blank line ending them, leaving the body.
+    #[test]
+    fn test_read_request() {
+        let mut request = Cursor::new("GET /tiles/0/0/0.png HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\nbody".as_bytes());
+        assert_eq!(read_request(&mut request).unwrap(), "GET /tiles/0/0/0.png HTTP/1.1\r\n");
+        assert_eq!(request.position(), 63);
+        // A client closing the connection before the blank line
+        assert_eq!(read_request(Cursor::new("GET / HTTP/1.0\r\n".as_bytes())).unwrap(), "GET / HTTP/1.0\r\n");
+    }
+}
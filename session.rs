@@ -0,0 +1,101 @@
+// Render sessions: everything needed to run a command again — the command, its arguments and all
+// its options (fractal type and parameters, view, palette, ...) and, for animations, the keyframes —
+// saved into a single file, so work can be resumed exactly where it was left.
+//
+// A session file uses the format of config::load_document: the command and options at the top,
+// then the `[[keyframe]]` tables of an animation, for example:
+//
+//     command = "render"
+//     fractal = "phoenix"
+//     iterations = "300"
+//     p = "0.56667"
+use crate::cli::Args;
+use crate::config::{self, Document};
+use std::collections::HashMap;
+use std::fs;
+
+// Keys of a session which are not options of the command.
+const COMMAND_KEY: &str = "command";
+const ARGUMENTS_KEY: &str = "arguments";
+
+pub struct Session {
+    pub command: String,
+    pub args: Args,
+    pub document: Document, // The whole file, read again by commands working on documents (animate)
+}
+
+// Build the session of a command. Animations keep a copy of their keyframe file instead of its path,
+// so the session still works when the file changes or disappears.
+pub fn capture(command: &str, args: &Args) -> Result<Document, String> {
+    let mut document = Document { options: HashMap::new(), tables: Vec::new() };
+    let mut positional = args.positionals().to_vec();
+    if command == "animate" {
+        let path = positional.first().ok_or("Usage: save-session <session.conf> animate <keyframes.conf> [options]")?;
+        let keyframes = config::load_document(path)?;
+        document.options.extend(keyframes.options);
+        document.tables = keyframes.tables;
+        positional.clear();
+    }
+
+    // The config file was already merged into the options, the session does not depend on it.
+    document.options.extend(args.values().into_iter().filter(|(name, _)| name != "config"));
+    document.options.insert(COMMAND_KEY.to_string(), command.to_string());
+    if !positional.is_empty() {
+        document.options.insert(ARGUMENTS_KEY.to_string(), positional.join(" "));
+    }
+    Ok(document)
+}
+
+// Turn a session file back into a command, its arguments and its options. The options of `overrides`
+// (given on the command line of load-session) win over the saved ones.
+pub fn restore(document: Document, overrides: &Args) -> Result<Session, String> {
+    let mut options = document.options.clone();
+    let command = options.remove(COMMAND_KEY).ok_or("The session does not name a command")?;
+    let positional = options.remove(ARGUMENTS_KEY).map_or(Vec::new(), |a| a.split_whitespace().map(String::from).collect());
+
+    let mut args = overrides.clone();
+    args.set_positional(positional);
+    args.merge_defaults(options);
+    Ok(Session { command, args, document })
+}
+
+pub fn save(path: &str, document: &Document) -> Result<(), String> {
+    let text = format!("# Render session, resume it with: load-session {}\n{}", path, config::to_text(document));
+    fs::write(path, text).map_err(|e| format!("Could not write '{}': {}", path, e))
+}
+
+pub fn load(path: &str, overrides: &Args) -> Result<Session, String> {
+    restore(config::load_document(path)?, overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Args {
+        Args::parse(&words.iter().map(|w| w.to_string()).collect::<Vec<_>>())
+    }
+
+    /// Tests that a command and its options survive a save and a restore.
+    #[test]
+    fn test_capture_and_restore() {
+        let document = capture("reproduce", &args(&["image.png", "--iterations", "500", "--config", "x.conf"])).unwrap();
+        assert!(!document.options.contains_key("config"));
+        let parsed = config::parse_document(&config::to_text(&document)).unwrap();
+
+        let session = restore(parsed, &args(&[])).unwrap();
+        assert_eq!(session.command, "reproduce");
+        assert_eq!(session.args.positional(0), Some("image.png"));
+        assert_eq!(session.args.value("iterations"), Some("500"));
+    }
+
+    /// Tests that options given when loading a session win over the saved ones.
+    #[test]
+    fn test_overrides() {
+        let document = config::parse_document("command = render\nfractal = nova\noutput = a.png\n").unwrap();
+        let session = restore(document, &args(&["--output", "b.png"])).unwrap();
+        assert_eq!(session.args.value("fractal"), Some("nova"));
+        assert_eq!(session.args.value("output"), Some("b.png"));
+        assert!(restore(config::parse_document("fractal = nova").unwrap(), &args(&[])).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+// Summary of a render printed once it is done, or written as JSON with --stats for scripts and the
+// benchmark: how long it took, the iteration counts it needed and how much memory its buffers used.
+use crate::mandelbrot::EscapeInfo;
+use std::fs;
+use std::mem;
+use std::time::Duration;
+
+// The iteration counts of an escape-time render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationStats {
+    pub max_iterations: u32, // The limit
+    pub min: u32,
+    pub max: u32,
+    pub mean: f64,
+    pub interior_fraction: f64,
+}
+
+impl IterationStats {
+    pub fn new(escapes: &[EscapeInfo], max_iterations: u32) -> Self {
+        let (min, max) = escapes.iter().fold((u32::MAX, 0), |(min, max), escape| (min.min(escape.iterations), max.max(escape.iterations)));
+        let total: u64 = escapes.iter().map(|escape| escape.iterations as u64).sum();
+        let interior = escapes.iter().filter(|escape| !escape.escaped).count();
+        let pixels = escapes.len().max(1) as f64;
+        Self {
+            max_iterations,
+            min: min.min(max),
+            max,
+            mean: total as f64 / pixels,
+            interior_fraction: interior as f64 / pixels,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderStats {
+    pub width: u32,
+    pub height: u32,
+    pub elapsed: Duration,
+    pub memory_bytes: usize, // Image and per-pixel buffers
+    pub iterations: Option<IterationStats>, // None for the fractals without iteration counts
+}
+
+impl RenderStats {
+    pub fn new(width: u32, height: u32, elapsed: Duration) -> Self {
+        let memory_bytes = width as usize * height as usize * 3;
+        Self { width, height, elapsed, memory_bytes, iterations: None }
+    }
+
+    pub fn with_escapes(mut self, escapes: &[EscapeInfo], max_iterations: u32) -> Self {
+        self.memory_bytes += mem::size_of_val(escapes);
+        self.iterations = Some(IterationStats::new(escapes, max_iterations));
+        self
+    }
+
+    pub fn pixels_per_second(&self) -> f64 {
+        (self.width as f64 * self.height as f64) / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Rendered {}x{} in {:.3}s ({:.0} pixels/s, {:.1} MB)",
+            self.width,
+            self.height,
+            self.elapsed.as_secs_f64(),
+            self.pixels_per_second(),
+            self.memory_bytes as f64 / 1e6
+        );
+        if let Some(iterations) = &self.iterations {
+            summary.push_str(&format!(
+                "\nIterations: min {}, max {}, mean {:.1} of {}; {:.1}% of the pixels in the interior",
+                iterations.min,
+                iterations.max,
+                iterations.mean,
+                iterations.max_iterations,
+                iterations.interior_fraction * 100.0
+            ));
+        }
+        summary
+    }
+
+    // The `"name": value` pairs of the JSON object, also written into the results of --json.
+    pub fn json_fields(&self) -> Vec<String> {
+        let mut fields = vec![
+            format!("\"width\": {}", self.width),
+            format!("\"height\": {}", self.height),
+            format!("\"elapsed_seconds\": {}", self.elapsed.as_secs_f64()),
+            format!("\"pixels_per_second\": {}", self.pixels_per_second()),
+            format!("\"memory_bytes\": {}", self.memory_bytes),
+        ];
+        if let Some(iterations) = &self.iterations {
+            fields.push(format!("\"max_iterations\": {}", iterations.max_iterations));
+            fields.push(format!("\"min_iterations_reached\": {}", iterations.min));
+            fields.push(format!("\"max_iterations_reached\": {}", iterations.max));
+            fields.push(format!("\"mean_iterations\": {}", iterations.mean));
+            fields.push(format!("\"interior_fraction\": {}", iterations.interior_fraction));
+        }
+        fields
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{{\n  {}\n}}\n", self.json_fields().join(",\n  "))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_json()).map_err(|e| format!("Could not write the statistics {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the iteration statistics and that they show up in both reports.
+    #[test]
+    fn test_render_stats() {
+        let escapes: Vec<EscapeInfo> = [2, 4, 6, 8].iter().map(|&iterations| EscapeInfo::from_iterations(iterations, 8)).collect();
+        let stats = RenderStats::new(2, 2, Duration::from_millis(500)).with_escapes(&escapes, 8);
+        let iterations = stats.iterations.unwrap();
+        assert_eq!((iterations.min, iterations.max, iterations.mean), (2, 8, 5.0));
+        assert_eq!(iterations.interior_fraction, 0.25);
+        assert_eq!(stats.pixels_per_second(), 8.0);
+        assert_eq!(stats.memory_bytes, 12 + 4 * mem::size_of::<EscapeInfo>());
+
+        assert!(stats.summary().contains("mean 5.0 of 8; 25.0% of the pixels in the interior"));
+        assert!(stats.to_json().contains("\"interior_fraction\": 0.25"));
+        assert!(!RenderStats::new(2, 2, Duration::from_millis(500)).to_json().contains("iterations"));
+    }
+}
@@ -0,0 +1,290 @@
+// Stitching rendered tiles back into a single image. The tiles of a directory are described by a
+// manifest in the format of config::load_document, the size of the image at the top and one table per
+// tile giving its file (relative to the manifest) and where it goes:
+//
+//     width = "4000"
+//     height = "3000"
+//
+//     [[tile]]
+//     file = "tile_0_0.png"
+//     x = "0"
+//     y = "0"
+//     width = "1000"
+//     height = "1000"
+//
+// A tile may also skip `offset_x` and `offset_y` pixels of its file, the overlap of its neighbours.
+// The manifest of a DZI pyramid (NAME.dzi) is read as well, stitching its most detailed level.
+//
+// Before anything is written the tiles are checked to line up: every tile inside the image, no two
+// tiles on the same pixel, every pixel covered, and every file present with the size of its tile.
+use crate::config::{self, Document, Table};
+use crate::pyramid::{Layout, Pyramid, TileFormat};
+use image::{imageops, RgbImage};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Name of the manifest written next to the tiles it describes.
+pub const MANIFEST_NAME: &str = "tiles.conf";
+const TILE_TABLE: &str = "tile";
+
+// Where the pixels of a tile file go in the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedTile {
+    pub file: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub offset_x: u32, // Pixels of the file left out on the left and at the top
+    pub offset_y: u32,
+}
+
+impl PlacedTile {
+    fn overlaps(&self, other: &PlacedTile) -> bool {
+        self.x < other.x + other.width && other.x < self.x + self.width && self.y < other.y + other.height && other.y < self.y + self.height
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileManifest {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<PlacedTile>,
+}
+
+// A number of the manifest, with the place it belongs to for the errors.
+fn number<T: FromStr>(options: &HashMap<String, String>, name: &str, context: &str) -> Result<T, String> {
+    let value = options.get(name).ok_or_else(|| format!("{}: missing '{}'", context, name))?;
+    value.parse().map_err(|_| format!("{}: invalid {} '{}'", context, name, value))
+}
+
+// The value of an attribute in a small XML document, wherever it appears.
+fn xml_attribute<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let start = xml.find(&pattern)? + pattern.len();
+    xml[start..].split('"').next()
+}
+
+impl TileManifest {
+    pub fn from_document(document: &Document) -> Result<Self, String> {
+        let width = number(&document.options, "width", "manifest")?;
+        let height = number(&document.options, "height", "manifest")?;
+        let mut tiles = Vec::new();
+        for (index, table) in document.tables.iter().filter(|table| table.name == TILE_TABLE).enumerate() {
+            let context = format!("tile {}", index + 1);
+            let options = &table.options;
+            let optional = |name| if options.contains_key(name) { number(options, name, &context) } else { Ok(0) };
+            tiles.push(PlacedTile {
+                file: options.get("file").ok_or_else(|| format!("{}: missing 'file'", context))?.clone(),
+                x: number(options, "x", &context)?,
+                y: number(options, "y", &context)?,
+                width: number(options, "width", &context)?,
+                height: number(options, "height", &context)?,
+                offset_x: optional("offset_x")?,
+                offset_y: optional("offset_y")?,
+            });
+        }
+        Ok(Self { width, height, tiles })
+    }
+
+    pub fn to_document(&self) -> Document {
+        let options = |pairs: &[(&str, String)]| pairs.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+        let tables = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                let mut pairs = vec![("file", tile.file.clone()), ("x", tile.x.to_string()), ("y", tile.y.to_string())];
+                pairs.extend([("width", tile.width.to_string()), ("height", tile.height.to_string())]);
+                if (tile.offset_x, tile.offset_y) != (0, 0) {
+                    pairs.extend([("offset_x", tile.offset_x.to_string()), ("offset_y", tile.offset_y.to_string())]);
+                }
+                Table { name: TILE_TABLE.to_string(), options: options(&pairs) }
+            })
+            .collect();
+        Document { options: options(&[("width", self.width.to_string()), ("height", self.height.to_string())]), tables }
+    }
+
+    // The most detailed level of a DZI pyramid, from the text of NAME.dzi.
+    pub fn from_dzi(xml: &str, name: &str) -> Result<Self, String> {
+        let attribute = |attribute| {
+            let value = xml_attribute(xml, attribute).ok_or_else(|| format!("The DZI manifest has no {} attribute", attribute))?;
+            value.parse::<u32>().map_err(|_| format!("Invalid {} '{}' in the DZI manifest", attribute, value))
+        };
+        let format: TileFormat = xml_attribute(xml, "Format").unwrap_or("png").parse()?;
+        let pyramid = Pyramid::new(attribute("Width")?, attribute("Height")?, attribute("TileSize")?, attribute("Overlap")?, Layout::Dzi)?;
+        let tiles = pyramid
+            .level_tiles(pyramid.max_level())
+            .into_iter()
+            .map(|tile| {
+                // The tile without the overlap, which the neighbours already hold
+                let (x, y) = (tile.column * pyramid.tile_size, tile.row * pyramid.tile_size);
+                let file = pyramid.tile_path(name, &tile, format).to_string_lossy().into_owned();
+                let (width, height) = (pyramid.tile_size.min(pyramid.width - x), pyramid.tile_size.min(pyramid.height - y));
+                PlacedTile { file, x, y, width, height, offset_x: x - tile.x, offset_y: y - tile.y }
+            })
+            .collect();
+        Ok(Self { width: pyramid.width, height: pyramid.height, tiles })
+    }
+
+    // Read a tile manifest, or a DZI manifest when its name ends with .dzi.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let path = Path::new(path);
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("dzi")) {
+            let xml = fs::read_to_string(path).map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+            let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+            return Self::from_dzi(&xml, &name);
+        }
+        Self::from_document(&config::load_document(&path.to_string_lossy())?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, config::to_text(&self.to_document())).map_err(|e| format!("Could not write the manifest {}: {}", path, e))
+    }
+
+    // Everything that keeps the tiles from lining up into the image, leaving the files aside.
+    pub fn layout_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for tile in &self.tiles {
+            if tile.x + tile.width > self.width || tile.y + tile.height > self.height {
+                problems.push(format!("{} at {},{} ({}x{}) goes past the {}x{} image", tile.file, tile.x, tile.y, tile.width, tile.height, self.width, self.height));
+            }
+        }
+        for (index, tile) in self.tiles.iter().enumerate() {
+            for other in &self.tiles[index + 1..] {
+                if tile.overlaps(other) {
+                    problems.push(format!("{} and {} cover the same pixels", tile.file, other.file));
+                }
+            }
+        }
+        // Without overlaps the tiles inside the image cover it exactly when their areas add up to it
+        if problems.is_empty() {
+            let covered: u64 = self.tiles.iter().map(|tile| tile.width as u64 * tile.height as u64).sum();
+            let missing = self.width as u64 * self.height as u64 - covered;
+            if missing > 0 {
+                problems.push(format!("{} pixels of the image are covered by no tile", missing));
+            }
+        }
+        problems
+    }
+
+    // The tiles whose file is missing, and the problems with the files that are there.
+    pub fn file_problems(&self, directory: &Path) -> (Vec<&PlacedTile>, Vec<String>) {
+        let (mut missing, mut problems) = (Vec::new(), Vec::new());
+        for tile in &self.tiles {
+            let path = directory.join(&tile.file);
+            if !path.exists() {
+                missing.push(tile);
+                continue;
+            }
+            match image::image_dimensions(&path) {
+                Ok((width, height)) if width < tile.offset_x + tile.width || height < tile.offset_y + tile.height => problems.push(format!(
+                    "{} is {}x{}, too small for a {}x{} tile offset by {},{}",
+                    tile.file, width, height, tile.width, tile.height, tile.offset_x, tile.offset_y
+                )),
+                Ok(_) => {}
+                Err(e) => problems.push(format!("Could not read {}: {}", tile.file, e)),
+            }
+        }
+        (missing, problems)
+    }
+}
+
+// The pixels of a tile, cut out of its file.
+fn load_tile(directory: &Path, tile: &PlacedTile) -> Result<RgbImage, String> {
+    let path = directory.join(&tile.file);
+    let image = image::open(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?.to_rgb8();
+    Ok(RgbImage::from_fn(tile.width, tile.height, |x, y| *image.get_pixel(tile.offset_x + x, tile.offset_y + y)))
+}
+
+// Assemble the image in memory, the missing tiles left black.
+pub fn stitch(manifest: &TileManifest, directory: &Path) -> Result<RgbImage, String> {
+    let mut image = RgbImage::new(manifest.width, manifest.height);
+    for tile in manifest.tiles.iter().filter(|tile| directory.join(&tile.file).exists()) {
+        imageops::replace(&mut image, &load_tile(directory, tile)?, tile.x as i64, tile.y as i64);
+    }
+    Ok(image)
+}
+
+// Write the image as a PNG row by row, holding only the tiles crossing the current row in memory, so
+// that images larger than the memory can be stitched. The missing tiles are left black. The text chunks
+// given, like those of metadata::to_text_chunks, are written before the pixels.
+pub fn stitch_streaming(manifest: &TileManifest, directory: &Path, output: &str, text_chunks: &[(String, String)]) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("Could not create {}: {}", output, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), manifest.width, manifest.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let encoding_error = |e: png::EncodingError| format!("Could not write {}: {}", output, e);
+    for (keyword, text) in text_chunks {
+        encoder.add_text_chunk(keyword.clone(), text.clone()).map_err(encoding_error)?;
+    }
+    let mut writer = encoder.write_header().map_err(encoding_error)?.into_stream_writer().map_err(encoding_error)?;
+
+    let mut pending: Vec<&PlacedTile> = manifest.tiles.iter().filter(|tile| directory.join(&tile.file).exists()).collect();
+    pending.sort_by_key(|tile| std::cmp::Reverse(tile.y)); // The next tile to load last
+    let mut loaded: Vec<(&PlacedTile, RgbImage)> = Vec::new();
+    let mut row = vec![0; manifest.width as usize * 3];
+    for y in 0..manifest.height {
+        while let Some(&tile) = pending.last().filter(|tile| tile.y == y) {
+            pending.pop();
+            loaded.push((tile, load_tile(directory, tile)?));
+        }
+        loaded.retain(|(tile, _)| y < tile.y + tile.height);
+        row.fill(0);
+        for (tile, pixels) in &loaded {
+            let start = tile.x as usize * 3;
+            let source = &pixels.as_raw()[((y - tile.y) * tile.width) as usize * 3..][..tile.width as usize * 3];
+            row[start..start + source.len()].copy_from_slice(source);
+        }
+        writer.write_all(&row).map_err(|e| format!("Could not write {}: {}", output, e))?;
+    }
+    writer.finish().map_err(encoding_error)
+}
+
+// The directory the files of a manifest are relative to.
+pub fn manifest_directory(path: &str) -> PathBuf {
+    Path::new(path).parent().map_or_else(PathBuf::new, Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(file: &str, x: u32, y: u32, width: u32, height: u32) -> PlacedTile {
+        PlacedTile { file: file.to_string(), x, y, width, height, offset_x: 0, offset_y: 0 }
+    }
+
+    /// Tests that misplaced, overlapping and missing tiles are all reported.
+    #[test]
+    fn test_layout_problems() {
+        let mut manifest = TileManifest { width: 20, height: 10, tiles: vec![tile("a.png", 0, 0, 10, 10), tile("b.png", 10, 0, 10, 10)] };
+        assert!(manifest.layout_problems().is_empty());
+
+        manifest.tiles[1].width = 5;
+        assert_eq!(manifest.layout_problems(), vec!["50 pixels of the image are covered by no tile".to_string()]);
+        manifest.tiles[1].x = 8;
+        assert_eq!(manifest.layout_problems(), vec!["a.png and b.png cover the same pixels".to_string()]);
+        manifest.tiles[1] = tile("b.png", 15, 0, 10, 10);
+        assert_eq!(manifest.layout_problems().len(), 1);
+        assert!(manifest.layout_problems()[0].contains("goes past"));
+    }
+
+    /// Tests that a manifest reads back what was written, and the tiles of a DZI pyramid without their overlap.
+    #[test]
+    fn test_manifests() {
+        let mut manifest = TileManifest { width: 20, height: 10, tiles: vec![tile("a.png", 0, 0, 10, 10), tile("b.png", 10, 0, 10, 10)] };
+        manifest.tiles[1].offset_x = 1;
+        let text = config::to_text(&manifest.to_document());
+        assert_eq!(TileManifest::from_document(&config::parse_document(&text).unwrap()), Ok(manifest));
+        assert!(TileManifest::from_document(&config::parse_document("width = 4\n[[tile]]\nfile = \"a.png\"").unwrap()).is_err());
+
+        let xml = "<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" TileSize=\"254\" Overlap=\"1\" Format=\"jpg\">\n  <Size Width=\"600\" Height=\"300\"/>\n</Image>";
+        let dzi = TileManifest::from_dzi(xml, "set").unwrap();
+        assert_eq!((dzi.width, dzi.height, dzi.tiles.len()), (600, 300, 6));
+        assert_eq!(dzi.tiles[1], PlacedTile { file: "set_files/10/1_0.jpg".to_string(), x: 254, y: 0, width: 254, height: 254, offset_x: 1, offset_y: 0 });
+        assert_eq!((dzi.tiles[5].x, dzi.tiles[5].y, dzi.tiles[5].width, dzi.tiles[5].height), (508, 254, 92, 46));
+        assert!(dzi.layout_problems().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+// Julia parameter sweeps: the view stays fixed while the Julia constant c moves along a path,
+// one frame per step, showing how the Julia set morphs with its parameter.
+use crate::animation::FrameOutput;
+use crate::bounds::Framing;
+use crate::mandelbrot::{self, ColoredColorMap};
+use std::error::Error;
+use std::f32::consts::TAU;
+use tracing::info;
+
+// The path followed by the constant c, parameterized by t from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SweepPath {
+    // The boundary of the main cardioid, scaled around the origin (1.0 is exactly on the boundary).
+    Cardioid { scale: f32 },
+    Circle { center: (f32, f32), radius: f32 },
+    Line { from: (f32, f32), to: (f32, f32) },
+    // A polyline through the given points, travelled at constant speed.
+    Waypoints(Vec<(f32, f32)>),
+}
+
+impl SweepPath {
+    pub fn point_at(&self, t: f32) -> (f32, f32) {
+        match self {
+            SweepPath::Cardioid { scale } => {
+                // c(θ) = e^(iθ)/2 − e^(2iθ)/4 traces the main cardioid.
+                let theta = TAU * t;
+                let re = theta.cos() / 2.0 - (2.0 * theta).cos() / 4.0;
+                let im = theta.sin() / 2.0 - (2.0 * theta).sin() / 4.0;
+                (re * scale, im * scale)
+            }
+            SweepPath::Circle { center, radius } => {
+                let theta = TAU * t;
+                (center.0 + radius * theta.cos(), center.1 + radius * theta.sin())
+            }
+            SweepPath::Line { from, to } => (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t),
+            SweepPath::Waypoints(points) => waypoint_at(points, t),
+        }
+    }
+}
+
+// Position at the fraction t of the total length of the polyline.
+fn waypoint_at(points: &[(f32, f32)], t: f32) -> (f32, f32) {
+    let segment_length = |a: (f32, f32), b: (f32, f32)| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+    let total: f32 = points.windows(2).map(|w| segment_length(w[0], w[1])).sum();
+    let mut remaining = t.clamp(0.0, 1.0) * total;
+
+    for pair in points.windows(2) {
+        let length = segment_length(pair[0], pair[1]);
+        if remaining <= length && length > 0.0 {
+            let s = remaining / length;
+            return (pair[0].0 + (pair[1].0 - pair[0].0) * s, pair[0].1 + (pair[1].1 - pair[0].1) * s);
+        }
+        remaining -= length;
+    }
+    points[points.len() - 1]
+}
+
+// Parse waypoints given as `RE,IM;RE,IM;...`.
+pub fn parse_waypoints(input: &str) -> Result<Vec<(f32, f32)>, String> {
+    let points = input.split(';').map(crate::cli::parse_point).collect::<Result<Vec<_>, _>>()?;
+    if points.len() < 2 {
+        return Err("A waypoint path needs at least two points".to_string());
+    }
+    Ok(points)
+}
+
+// Parameters of a sweep.
+pub struct SweepOptions {
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u32,
+    pub bounds: (f32, f32, f32, f32),
+    pub letterbox: Option<(f32, f32, f32, f32)>, // Region outside of which the frames are black
+    pub palette: String,
+    pub frames: u32,
+    pub fps: u32,
+}
+
+// Render one Julia set per step along the path.
+pub fn render_sweep(path: &SweepPath, options: &SweepOptions, output: &str) -> Result<(), Box<dyn Error>> {
+    let color_map = ColoredColorMap::with_palette(options.max_iterations, &options.palette)?;
+    let mut frame_output = FrameOutput::open(output, options.width, options.height, options.fps)?;
+    // Closed paths would show their first frame twice when looping, so they stop one step short.
+    let closed = matches!(path, SweepPath::Cardioid { .. } | SweepPath::Circle { .. });
+    let steps = if closed { options.frames } else { options.frames.saturating_sub(1).max(1) };
+
+    let framing = Framing { bounds: options.bounds, region: options.letterbox };
+    for frame in 0..options.frames {
+        let c = path.point_at(frame as f32 / steps as f32);
+        let iterations = mandelbrot::compute_julia_iterations(options.width, options.height, options.max_iterations, options.bounds, c);
+        let mut image = mandelbrot::colorize(options.width, options.height, &iterations, &color_map);
+        framing.apply(&mut image);
+        frame_output.write(frame, &image)?;
+        info!("Frame {}/{} rendered (c = {:.4} {:+.4}i)", frame + 1, options.frames, c.0, c.1);
+    }
+    frame_output.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the cardioid path starts at the cusp 1/4 and passes through −3/4 halfway.
+    #[test]
+    fn test_cardioid_points() {
+        let path = SweepPath::Cardioid { scale: 1.0 };
+        let start = path.point_at(0.0);
+        assert!((start.0 - 0.25).abs() < 1e-6 && start.1.abs() < 1e-6);
+        let middle = path.point_at(0.5);
+        assert!((middle.0 + 0.75).abs() < 1e-6 && middle.1.abs() < 1e-6);
+    }
+
+    /// Tests that waypoints are travelled at constant speed over segments of different lengths.
+    #[test]
+    fn test_waypoints_constant_speed() {
+        let path = SweepPath::Waypoints(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 3.0)]);
+        assert_eq!(path.point_at(0.0), (0.0, 0.0));
+        assert_eq!(path.point_at(0.25), (1.0, 0.0));
+        assert_eq!(path.point_at(0.5), (1.0, 1.0));
+        assert_eq!(path.point_at(1.0), (1.0, 3.0));
+    }
+
+    /// Tests parsing and validation of a waypoint list.
+    #[test]
+    fn test_parse_waypoints() {
+        assert_eq!(parse_waypoints("0,0;-0.5,0.5").unwrap(), vec![(0.0, 0.0), (-0.5, 0.5)]);
+        assert!(parse_waypoints("0,0").is_err());
+        assert!(parse_waypoints("0,0;x").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Text drawn directly into images with a built-in 5x7 pixel font, so labels need neither a font
+// file nor a font rendering dependency. Lowercase letters are drawn as uppercase.
+use image::{Rgb, RgbImage};
+use std::str::FromStr;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+// Horizontal distance between two characters, including one column of spacing.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+// Rows of a glyph from top to bottom, the lowest 5 bits of each row being the pixels from left to right.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        ';' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '*' => [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        // Anything else is shown as a question mark
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+// Size in pixels of a line of text drawn at the given scale.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let count = text.chars().count() as u32;
+    ((count * ADVANCE).saturating_sub(1) * scale, GLYPH_HEIGHT * scale)
+}
+
+// Draw a line of text with its top left corner at (x, y), every font pixel becoming a
+// scale x scale square. Parts falling outside the image are clipped.
+pub fn draw_text(image: &mut RgbImage, text: &str, position: (i32, i32), scale: u32, color: Rgb<u8>) {
+    let (width, height) = image.dimensions();
+    for (index, c) in text.chars().enumerate() {
+        let left = position.0 + (index as u32 * ADVANCE * scale) as i32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = left + (column * scale + dx) as i32;
+                        let y = position.1 + (row as u32 * scale + dy) as i32;
+                        if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                            image.put_pixel(x as u32, y as u32, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Darken a rectangle towards black, keeping `keep` of the original brightness, to put text over any image.
+pub fn darken_rect(image: &mut RgbImage, top_left: (u32, u32), size: (u32, u32), keep: f32) {
+    let (width, height) = image.dimensions();
+    for y in top_left.1..(top_left.1 + size.1).min(height) {
+        for x in top_left.0..(top_left.0 + size.0).min(width) {
+            let Rgb([r, g, b]) = *image.get_pixel(x, y);
+            let dim = |v: u8| (v as f32 * keep) as u8;
+            image.put_pixel(x, y, Rgb([dim(r), dim(g), dim(b)]));
+        }
+    }
+}
+
+// The corners of an image a caption can be drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    #[default]
+    BottomLeft,
+    BottomRight,
+}
+
+impl FromStr for Corner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "top-left" => Ok(Corner::TopLeft),
+            "top-right" => Ok(Corner::TopRight),
+            "bottom-left" => Ok(Corner::BottomLeft),
+            "bottom-right" => Ok(Corner::BottomRight),
+            _ => Err(format!("Unknown corner '{}', expected top-left, top-right, bottom-left or bottom-right", s)),
+        }
+    }
+}
+
+// Text written over an image, like a title or the coordinates of the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    pub text: String, // Lines separated by newlines
+    pub corner: Corner,
+    pub scale: u32,
+    pub color: Rgb<u8>,
+}
+
+impl Caption {
+    // Draw the lines of the caption on a darkened box in its corner, aligned on the side of the corner.
+    pub fn draw(&self, image: &mut RgbImage) {
+        let padding = 2 * self.scale;
+        let line_height = GLYPH_HEIGHT * self.scale + padding;
+        let lines: Vec<&str> = self.text.lines().collect();
+        let text_width = lines.iter().map(|line| text_size(line, self.scale).0).max().unwrap_or(0);
+        let size = (text_width + 2 * padding, lines.len() as u32 * line_height + padding);
+        let (width, height) = image.dimensions();
+        let on_right = matches!(self.corner, Corner::TopRight | Corner::BottomRight);
+        let left = if on_right { width.saturating_sub(size.0) } else { 0 };
+        let top = if matches!(self.corner, Corner::BottomLeft | Corner::BottomRight) { height.saturating_sub(size.1) } else { 0 };
+        darken_rect(image, (left, top), size, 0.35);
+        for (index, line) in lines.iter().enumerate() {
+            let x = if on_right { left + size.0 - padding - text_size(line, self.scale).0 } else { left + padding };
+            let y = top + padding + index as u32 * line_height;
+            draw_text(image, line, (x as i32, y as i32), self.scale, self.color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the size of a scaled line of text.
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_size("AB", 1), (11, 7));
+        assert_eq!(text_size("AB", 2), (22, 14));
+        assert_eq!(text_size("", 1), (0, 7));
+    }
+
+    /// Tests that glyph pixels are drawn at their place, scaled and clipped at the image edge.
+    #[test]
+    fn test_draw_text() {
+        let white = Rgb([255, 255, 255]);
+        let mut image = RgbImage::new(20, 20);
+        draw_text(&mut image, "1", (2, 3), 2, white);
+        // The top row of '1' has a single pixel in the middle column, drawn as a 2x2 square.
+        assert_eq!(*image.get_pixel(2 + 4, 3), white);
+        assert_eq!(*image.get_pixel(2 + 5, 4), white);
+        assert_eq!(*image.get_pixel(2, 3), Rgb([0, 0, 0]));
+        draw_text(&mut image, "WIDE TEXT", (-4, 15), 2, white); // Must not panic
+    }
+
+    /// Tests that captions are drawn in their corner, their lines aligned on its side.
+    #[test]
+    fn test_caption() {
+        let white = Rgb([255, 255, 255]);
+        let caption = Caption { text: "1\n11".to_string(), corner: Corner::BottomRight, scale: 1, color: white };
+        let mut image = RgbImage::from_pixel(40, 30, Rgb([100, 100, 100]));
+        caption.draw(&mut image);
+        // A box of 11 + 2 × 2 by 2 × 9 + 2 pixels, the top row of the first '1' ending with the box
+        assert_eq!(*image.get_pixel(40 - 16, 30 - 20), Rgb([100, 100, 100]));
+        assert_eq!(*image.get_pixel(40 - 15, 30 - 20), Rgb([35, 35, 35]));
+        assert_eq!(*image.get_pixel(40 - 2 - 3, 30 - 20 + 2), white);
+        assert_eq!(*image.get_pixel(2, 2), Rgb([100, 100, 100]));
+        assert_eq!("top-right".parse::<Corner>(), Ok(Corner::TopRight));
+        assert!("middle".parse::<Corner>().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// Zoom animations encoded straight to an .mp4/.webm video through an ffmpeg subprocess.
+//
+// The frames are never written to disk: each rendered frame is piped as raw RGB into ffmpeg's stdin.
+// They are iterated from the orbit of the center, computed once for the whole zoom (see reference_orbit.rs).
+use crate::cli::UsageError;
+use crate::coloring::{self, ColoringOptions};
+use crate::mandelbrot::{self, EscapeInfo};
+use crate::reference_orbit::OrbitCache;
+use image::RgbImage;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use tracing::{debug, info};
+
+// Parameters of a zoom animation.
+pub struct ZoomOptions {
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u32,
+    pub center: (f32, f32),
+    // Magnification reached on the last frame, relative to the first one.
+    pub zoom: f32,
+    pub frames: u32,
+    pub fps: u32,
+    // Target bitrate passed to ffmpeg, e.g. "8M".
+    pub bitrate: String,
+    // Raise the iteration limit with the magnification of each frame (see mandelbrot::auto_iterations).
+    pub auto_iterations: bool,
+    // Also raise it when too much of the previous frame was left in the interior.
+    pub adapt_to_interior: bool,
+    // The frames are colored like the renders, see coloring::coloring_with_options.
+    pub palette: String,
+    pub coloring: String,
+    pub coloring_options: ColoringOptions,
+}
+
+impl ZoomOptions {
+    // Reject the options no video can be made of, before ffmpeg is started.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        if !(self.zoom.is_finite() && self.zoom > 0.0) {
+            return Err(UsageError(format!("--zoom must be a positive number, not {}", self.zoom)).into());
+        }
+        if self.frames == 0 || self.fps == 0 {
+            return Err(UsageError("--frames and --fps must be positive".to_string()).into());
+        }
+        self.color_map(self.max_iterations)?;
+        Ok(())
+    }
+
+    // The color map of a frame iterated up to `max_iterations`.
+    fn color_map(&self, max_iterations: u32) -> Result<Box<dyn mandelbrot::ColorMap>, Box<dyn Error>> {
+        Ok(coloring::coloring_with_options(&self.coloring, &self.palette, max_iterations, &self.coloring_options)?)
+    }
+}
+
+// Share of interior pixels in a frame above which the next frames get more iterations, and the
+// factor applied each time, up to a maximum so that zooming into the set itself stays affordable.
+pub const INTERIOR_FRACTION_LIMIT: f64 = 0.5;
+pub const INTERIOR_BOOST: f32 = 1.25;
+pub const MAX_INTERIOR_BOOST: f32 = 4.0;
+
+// The iteration limit of a frame: the given one, or with auto_iterations the one matching the
+// magnification of the frame if it is higher, multiplied by the boost earned by interior-heavy frames.
+pub fn frame_iterations(options: &ZoomOptions, frame: u32, boost: f32) -> u32 {
+    if !options.auto_iterations {
+        return options.max_iterations;
+    }
+    let limit = options.max_iterations.max(mandelbrot::auto_iterations(frame_zoom(options, frame)));
+    (limit as f32 * boost).round() as u32
+}
+
+// Magnification of a frame relative to the first one; it grows exponentially so the motion looks steady.
+// The zoom is positive, see ZoomOptions::check.
+fn frame_zoom(options: &ZoomOptions, frame: u32) -> f32 {
+    let progress = if options.frames > 1 { frame as f32 / (options.frames - 1) as f32 } else { 0.0 };
+    options.zoom.powf(progress)
+}
+
+// Share of the pixels of a frame that reached the iteration limit.
+fn interior_fraction(escapes: &[EscapeInfo]) -> f64 {
+    escapes.iter().filter(|escape| !escape.escaped).count() as f64 / escapes.len().max(1) as f64
+}
+
+// Pick the encoder matching the container requested by the output file extension.
+fn codec_for(path: &Path) -> Result<&'static str, Box<dyn Error>> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("mp4") => Ok("libx264"),
+        Some("webm") => Ok("libvpx-vp9"),
+        _ => Err("The video output must end with .mp4 or .webm".into()),
+    }
+}
+
+// True if the path asks for one of the supported video containers.
+pub fn is_video_path(path: &Path) -> bool {
+    codec_for(path).is_ok()
+}
+
+// A running ffmpeg process receiving frames of a fixed size.
+pub struct VideoEncoder {
+    ffmpeg: Child,
+    width: u32,
+    height: u32,
+}
+
+impl VideoEncoder {
+    // Start ffmpeg, writing to `path` with a codec chosen from its extension.
+    pub fn start(path: &Path, width: u32, height: u32, fps: u32, bitrate: &str) -> Result<Self, Box<dyn Error>> {
+        let codec = codec_for(path)?;
+        if !width.is_multiple_of(2) || !height.is_multiple_of(2) {
+            return Err("Video width and height must be even numbers".into());
+        }
+
+        let ffmpeg = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &fps.to_string(), "-i", "-"])
+            .args(["-c:v", codec, "-b:v", bitrate, "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start ffmpeg (is it installed and in PATH?): {}", e))?;
+        Ok(Self { ffmpeg, width, height })
+    }
+
+    // Send one frame to ffmpeg.
+    pub fn write_frame(&mut self, image: &RgbImage) -> Result<(), Box<dyn Error>> {
+        if image.dimensions() != (self.width, self.height) {
+            return Err("All the frames of a video must have the same size".into());
+        }
+        let stdin = self.ffmpeg.stdin.as_mut().ok_or("Could not open ffmpeg's input")?;
+        stdin.write_all(image.as_raw())?;
+        Ok(())
+    }
+
+    // Close ffmpeg's input and wait for the video to be written.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        // Closing stdin tells ffmpeg that the last frame was sent.
+        drop(self.ffmpeg.stdin.take());
+        let status = self.ffmpeg.wait()?;
+        if !status.success() {
+            return Err(format!("ffmpeg failed with {}", status).into());
+        }
+        Ok(())
+    }
+}
+
+// Render every frame of the zoom and pipe it into ffmpeg.
+pub fn render_zoom_video(options: &ZoomOptions, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    options.check()?;
+    let mut encoder = VideoEncoder::start(path.as_ref(), options.width, options.height, options.fps, &options.bitrate)?;
+    let mut boost = 1.0;
+    let mut orbits = OrbitCache::default();
+    let center = (options.center.0 as f64, options.center.1 as f64);
+    for frame in 0..options.frames {
+        let max_iterations = frame_iterations(options, frame, boost);
+        let zoom = frame_zoom(options, frame) as f64;
+        let color_map = options.color_map(max_iterations)?;
+        let escapes = orbits.compute_escape_info(center, zoom, options.width, options.height, max_iterations, color_map.statistic());
+        let image = mandelbrot::colorize_escapes(options.width, options.height, &escapes, &*color_map);
+        encoder.write_frame(&image)?;
+        if options.adapt_to_interior && interior_fraction(&escapes) > INTERIOR_FRACTION_LIMIT {
+            boost = (boost * INTERIOR_BOOST).min(MAX_INTERIOR_BOOST);
+        }
+        if options.auto_iterations {
+            info!("Frame {}/{} encoded with {} iterations", frame + 1, options.frames, max_iterations);
+        } else {
+            info!("Frame {}/{} encoded", frame + 1, options.frames);
+        }
+    }
+    debug!("{} steps of the orbit of the center iterated for {} frames", orbits.steps(), options.frames);
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ZoomOptions {
+        ZoomOptions {
+            width: 800,
+            height: 600,
+            max_iterations: 100,
+            center: (-0.5, 0.0),
+            zoom: 100.0,
+            frames: 11,
+            fps: 30,
+            bitrate: "8M".to_string(),
+            auto_iterations: false,
+            adapt_to_interior: false,
+            palette: "turbo".to_string(),
+            coloring: "iterations".to_string(),
+            coloring_options: ColoringOptions::default(),
+        }
+    }
+
+    /// Tests that the first frame shows the default view and the last one the full magnification.
+    #[test]
+    fn test_frame_zoom_endpoints() {
+        let options = options();
+        assert_eq!(frame_zoom(&options, 0), 1.0);
+        assert!((frame_zoom(&options, 5) - 10.0).abs() < 1e-4);
+        assert!((frame_zoom(&options, 10) - 100.0).abs() < 1e-3);
+    }
+
+    /// Tests that only .mp4 and .webm outputs are accepted.
+    #[test]
+    fn test_codec_for() {
+        assert_eq!(codec_for(Path::new("zoom.mp4")).unwrap(), "libx264");
+        assert_eq!(codec_for(Path::new("zoom.webm")).unwrap(), "libvpx-vp9");
+        assert!(codec_for(Path::new("zoom.avi")).is_err());
+    }
+
+    /// Tests that the automatic limit follows the magnification of each frame without going below the given one.
+    #[test]
+    fn test_frame_iterations() {
+        let mut options = options();
+        assert_eq!(frame_iterations(&options, 10, 1.0), 100);
+
+        options.auto_iterations = true;
+        options.max_iterations = 200;
+        assert_eq!(frame_iterations(&options, 0, 1.0), 200);
+        assert_eq!(frame_iterations(&options, 10, 1.0), 400); // 100 + 150 per decade for a zoom of 100
+        assert_eq!(frame_iterations(&options, 10, 1.5), 600);
+        let escapes = [3, 200, 200, 10].map(|iterations| EscapeInfo::from_iterations(iterations, 200));
+        assert_eq!(interior_fraction(&escapes), 0.5);
+    }
+
+    /// Tests that the options no video can be made of are rejected, along with unknown colorings.
+    #[test]
+    fn test_check() {
+        assert!(options().check().is_ok());
+        let changes: [fn(&mut ZoomOptions); 5] =
+            [|o| o.zoom = 0.0, |o| o.zoom = -10.0, |o| o.frames = 0, |o| o.fps = 0, |o| o.coloring = "none".to_string()];
+        for change in changes {
+            let mut options = options();
+            change(&mut options);
+            assert!(options.check().is_err());
+        }
+    }
+}
@@ -0,0 +1,1676 @@
+// Interactive viewer: shows a rendered Mandelbrot set in a window and re-renders it when the user
+// navigates, keeping track of the view (center, zoom, iterations) between renders. It can show the
+// parameter plane of another fractal::Family instead, the Julia sets being those of that family.
+//
+// Window events are given in window pixels, while the image is scaled to fit the window with its
+// aspect ratio preserved, so positions are first mapped back to image pixels, then to the plane.
+use crate::axes;
+use crate::bookmarks::{self, Bookmark};
+use crate::bounds::AspectPolicy;
+use crate::clipboard;
+use crate::coloring::ColoringOptions;
+use crate::complex::Complex;
+use crate::fractal::{self, Family};
+use crate::mandelbrot::{self, ColoredColorMap, EscapeInfo, DEFAULT_VIEW_WIDTH};
+use crate::metadata;
+use crate::naming::{self, NameTokens};
+use crate::palette::{Palette, PaletteTransform};
+use crate::render::RenderOptions;
+use crate::postprocess::PostProcess;
+use crate::text;
+use crate::util::to_showable_image;
+use image::{Rgb, RgbImage};
+use show_image::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+use show_image::{create_window, WindowOptions, WindowProxy};
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use text_io::read;
+use tracing::{info, warn};
+
+// Two clicks closer than this in time and distance form a double-click.
+const DOUBLE_CLICK_DELAY: Duration = Duration::from_millis(400);
+const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+// A press and release further apart than this is a drag selecting a box, not a click.
+const DRAG_DISTANCE: f32 = 5.0;
+// Fraction of the view moved by one press of an arrow key.
+const PAN_STEP: f32 = 0.1;
+const MIN_ITERATIONS: u32 = 10;
+// Number of previous views kept for undo.
+const HISTORY_SIZE: usize = 100;
+// Palette cycling: initial speed in gradient lengths per second, factor applied by [ and ], and
+// delay between two recolored frames.
+const CYCLE_SPEED: f64 = 0.2;
+const CYCLE_SPEED_STEP: f64 = 1.5;
+const CYCLE_FRAME: Duration = Duration::from_millis(33);
+// Julia preview: size of the inset, its iteration limit at most, its distance to the corner of the
+// image, and how often a preview being rendered is checked for.
+const PREVIEW_SIZE: (u32, u32) = (200, 150);
+const PREVIEW_MAX_ITERATIONS: u32 = 300;
+const PREVIEW_MARGIN: u32 = 8;
+const PREVIEW_POLL: Duration = Duration::from_millis(15);
+// Minimap: its size, its iteration limit, the color of the box of the view drawn on it, and the box
+// below which the view is marked with a cross instead.
+const MINIMAP_SIZE: (u32, u32) = (160, 120);
+const MINIMAP_ITERATIONS: u32 = 100;
+const MINIMAP_BOX_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const MINIMAP_MIN_BOX: f32 = 4.0;
+// Color of the orbit drawn over the image.
+const ORBIT_COLOR: Rgb<u8> = Rgb([255, 255, 0]);
+// Tuning: the steps of the values changed with comma and period (a factor for the iteration limit and
+// the palette scale, a fraction of the gradient for the offset and of the view width for c), and how
+// long the keys must be left alone before the changes needing a render are rendered.
+const TUNING_ITERATION_STEP: f32 = 1.25;
+const TUNING_OFFSET_STEP: f64 = 0.02;
+const TUNING_SCALE_STEP: f64 = 1.1;
+const TUNING_C_STEP: f32 = 0.01;
+const TUNING_DELAY: Duration = Duration::from_millis(300);
+// Resizing: how long the window must keep its size before the view is rendered at it, and the
+// smallest view rendered.
+const RESIZE_DELAY: Duration = Duration::from_millis(200);
+const MIN_VIEW_SIZE: u32 = 16;
+// Export: the width of the images saved with S by default (their height following the shape of the
+// view), their samples per pixel in each direction and the template of their names.
+pub const EXPORT_WIDTH: u32 = 1920;
+pub const EXPORT_ANTIALIAS: u32 = 3;
+pub const EXPORT_TEMPLATE: &str = "{fractal}_{center}_{zoom}_{timestamp}.png";
+
+// A change of the view triggered by the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Pan(f32, f32), // Fractions of the view width and height, positive towards the right and the bottom
+    Zoom(f32),     // Factor applied to the magnification
+    ScaleIterations(f32),
+}
+
+// The key bindings of the viewer.
+pub fn action_for_key(key: VirtualKeyCode) -> Option<Action> {
+    match key {
+        VirtualKeyCode::Left => Some(Action::Pan(-PAN_STEP, 0.0)),
+        VirtualKeyCode::Right => Some(Action::Pan(PAN_STEP, 0.0)),
+        VirtualKeyCode::Up => Some(Action::Pan(0.0, -PAN_STEP)),
+        VirtualKeyCode::Down => Some(Action::Pan(0.0, PAN_STEP)),
+        // '+' shares its key with '=' on most layouts
+        VirtualKeyCode::Plus | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => Some(Action::Zoom(2.0)),
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => Some(Action::Zoom(0.5)),
+        VirtualKeyCode::I => Some(Action::ScaleIterations(2.0)),
+        VirtualKeyCode::K => Some(Action::ScaleIterations(0.5)),
+        _ => None,
+    }
+}
+
+// The part of the plane being displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub center: (f32, f32),
+    pub zoom: f32, // Magnification relative to a view DEFAULT_VIEW_WIDTH wide
+    pub max_iterations: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl View {
+    // The view showing the horizontal extent of the bounds, centered on them.
+    pub fn from_bounds(bounds: (f32, f32, f32, f32), max_iterations: u32, width: u32, height: u32) -> Self {
+        let (xmin, xmax, ymin, ymax) = bounds;
+        let center = ((xmin + xmax) / 2.0, (ymin + ymax) / 2.0);
+        Self { center, zoom: DEFAULT_VIEW_WIDTH / (xmax - xmin), max_iterations, width, height }
+    }
+
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        mandelbrot::view_bounds(self.center, self.zoom, self.width, self.height)
+    }
+
+    // The options rendering this view with a palette.
+    pub fn render_options(&self, palette: &str) -> Result<RenderOptions, String> {
+        RenderOptions::builder()
+            .size(self.width, self.height)
+            .center(self.center.0, self.center.1)
+            .zoom(self.zoom)
+            .iterations(self.max_iterations)
+            .palette(palette)
+            .build()
+            .map_err(String::from)
+    }
+
+    // The point of the plane at an image pixel, using the same mapping as the renderer.
+    pub fn pixel_to_complex(&self, pixel: (f32, f32)) -> (f32, f32) {
+        let (xmin, xmax, ymin, ymax) = self.bounds();
+        let scale_x = (xmax - xmin) / self.width as f32;
+        let scale_y = (ymax - ymin) / self.height as f32;
+        (pixel.0 * scale_x + xmin, pixel.1 * scale_y + ymin)
+    }
+
+    // The view of a rectangle of its pixels, at the same scale, rendering the same points there.
+    pub fn region(&self, (x, y): (u32, u32), (width, height): (u32, u32)) -> View {
+        let (left, top) = self.pixel_to_complex((x as f32, y as f32));
+        let (right, bottom) = self.pixel_to_complex(((x + width) as f32, (y + height) as f32));
+        let center = ((left + right) / 2.0, (top + bottom) / 2.0);
+        View { center, zoom: self.zoom * self.width as f32 / width as f32, width, height, ..*self }
+    }
+
+    // The image pixel of a point of the plane, the inverse of pixel_to_complex.
+    pub fn complex_to_pixel(&self, point: (f32, f32)) -> (f32, f32) {
+        let (xmin, xmax, ymin, ymax) = self.bounds();
+        ((point.0 - xmin) / (xmax - xmin) * self.width as f32, (point.1 - ymin) / (ymax - ymin) * self.height as f32)
+    }
+
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::Pan(dx, dy) => {
+                let (xmin, xmax, ymin, ymax) = self.bounds();
+                self.center = (self.center.0 + dx * (xmax - xmin), self.center.1 + dy * (ymax - ymin));
+            }
+            Action::Zoom(factor) => self.zoom *= factor,
+            Action::ScaleIterations(factor) => {
+                self.max_iterations = ((self.max_iterations as f32 * factor).round() as u32).max(MIN_ITERATIONS);
+            }
+        }
+    }
+
+    // Zoom on the box between two image pixels, fitted to the aspect ratio of the image by the policy:
+    // enlarged along one side so that everything selected stays visible, or cut along the other to
+    // fill the view with it. The image is never stretched.
+    pub fn zoom_to_box(&mut self, corner: (f32, f32), opposite: (f32, f32), aspect: AspectPolicy) {
+        let box_width = (corner.0 - opposite.0).abs().max(1.0);
+        let box_height = (corner.1 - opposite.1).abs().max(1.0);
+        let center = ((corner.0 + opposite.0) / 2.0, (corner.1 + opposite.1) / 2.0);
+        self.center = self.pixel_to_complex(center);
+        self.zoom *= aspect.box_zoom(self.width as f32 / box_width, self.height as f32 / box_height);
+    }
+}
+
+// Shift the escapes of an image by whole pixels, the pixel (x, y) taking the escape of (x − dx, y − dy).
+// The pixels uncovered keep their old escapes, to be computed again.
+pub fn shift_escapes(escapes: &[EscapeInfo], (width, height): (u32, u32), (dx, dy): (i32, i32)) -> Vec<EscapeInfo> {
+    let mut shifted = escapes.to_vec();
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let (from_x, from_y) = (x - dx, y - dy);
+            if (0..width as i32).contains(&from_x) && (0..height as i32).contains(&from_y) {
+                shifted[(y * width as i32 + x) as usize] = escapes[(from_y * width as i32 + from_x) as usize];
+            }
+        }
+    }
+    shifted
+}
+
+// The rectangles (left, top, width, height) uncovered by shifting an image: a band of rows along the
+// top or the bottom, and a band of columns along a side for the other rows.
+pub fn exposed_strips((width, height): (u32, u32), (dx, dy): (i32, i32)) -> Vec<(u32, u32, u32, u32)> {
+    let (moved_x, moved_y) = (dx.unsigned_abs().min(width), dy.unsigned_abs().min(height));
+    let rows = if dy > 0 { (0, 0, width, moved_y) } else { (0, height - moved_y, width, moved_y) };
+    let other_rows = if dy > 0 { (moved_y, height - moved_y) } else { (0, height - moved_y) };
+    let columns_left = if dx > 0 { 0 } else { width - moved_x };
+    let columns = (columns_left, other_rows.0, moved_x, other_rows.1);
+    [rows, columns].into_iter().filter(|&(_, _, width, height)| width > 0 && height > 0).collect()
+}
+
+// Map a position in the window to a pixel of the image shown in it, which is outside the image
+// when the position is on the bars. The image is scaled to fit and centered, leaving bars on two
+// sides when the aspect ratios differ.
+pub fn window_to_image(position: (f32, f32), window: (u32, u32), image: (u32, u32)) -> (f32, f32) {
+    let scale = (window.0 as f32 / image.0 as f32).min(window.1 as f32 / image.1 as f32);
+    let offset_x = (window.0 as f32 - image.0 as f32 * scale) / 2.0;
+    let offset_y = (window.1 as f32 - image.1 as f32 * scale) / 2.0;
+    ((position.0 - offset_x) / scale, (position.1 - offset_y) / scale)
+}
+
+fn is_inside(pixel: (f32, f32), image: (u32, u32)) -> bool {
+    (0.0..image.0 as f32).contains(&pixel.0) && (0.0..image.1 as f32).contains(&pixel.1)
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+// Copy of the frame with the outline of the selected box drawn over it. Each outline pixel takes
+// the inverse of the color below so the box stays visible on any palette.
+pub fn draw_selection(frame: &RgbImage, corner: (f32, f32), opposite: (f32, f32)) -> RgbImage {
+    let mut image = frame.clone();
+    let (width, height) = image.dimensions();
+    let clamp = |v: f32, max: u32| (v.max(0.0) as u32).min(max - 1);
+    let (x0, x1) = (clamp(corner.0.min(opposite.0), width), clamp(corner.0.max(opposite.0), width));
+    let (y0, y1) = (clamp(corner.1.min(opposite.1), height), clamp(corner.1.max(opposite.1), height));
+    let mut invert = |x: u32, y: u32| {
+        let Rgb([r, g, b]) = *image.get_pixel(x, y);
+        image.put_pixel(x, y, Rgb([255 - r, 255 - g, 255 - b]));
+    };
+    for x in x0..=x1 {
+        invert(x, y0);
+        if y1 != y0 {
+            invert(x, y1);
+        }
+    }
+    for y in y0 + 1..y1 {
+        invert(x0, y);
+        if x1 != x0 {
+            invert(x1, y);
+        }
+    }
+    image
+}
+
+// Magnification written compactly, switching to scientific notation for deep zooms.
+fn format_zoom(zoom: f32) -> String {
+    if zoom < 1e4 { format!("{:.2}x", zoom) } else { format!("{:.3e}x", zoom) }
+}
+
+fn format_point(point: (f32, f32)) -> String {
+    format!("{:+.6}{:+.6}i", point.0, point.1)
+}
+
+// The text of the status line: the point under the cursor (when it is over the image), then the view.
+pub fn status_line(view: &View, cursor: Option<(f32, f32)>) -> String {
+    let cursor = cursor.map_or("-".to_string(), |pixel| format_point(view.pixel_to_complex(pixel)));
+    format!(
+        "Cursor {}  Center {}  Zoom {}  Iterations {}",
+        cursor,
+        format_point(view.center),
+        format_zoom(view.zoom),
+        view.max_iterations
+    )
+}
+
+// Draw a status line on a darkened strip along the bottom of the image, at double size when it fits.
+pub fn draw_hud(image: &mut RgbImage, line: &str) {
+    draw_strip(image, line, false);
+}
+
+// Draw a line of text on a darkened strip along the top or the bottom of the image.
+fn draw_strip(image: &mut RgbImage, line: &str, at_top: bool) {
+    const PADDING: u32 = 4;
+    let (width, height) = image.dimensions();
+    let scale = if text::text_size(line, 2).0 + 2 * PADDING <= width { 2 } else { 1 };
+    let strip_height = (text::text_size(line, scale).1 + 2 * PADDING).min(height);
+    let top = if at_top { 0 } else { height - strip_height };
+    text::darken_rect(image, (0, top), (width, strip_height), 0.35);
+    text::draw_text(image, line, (PADDING as i32, (top + PADDING) as i32), scale, Rgb([255, 255, 255]));
+}
+
+// The quality of the views saved with S, apart from the preview quality of the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    pub width: u32,
+    pub antialias: u32,
+    pub template: String, // A template of naming::expand
+    pub post: PostProcess, // The grid, caption and watermark drawn over the image
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self { width: EXPORT_WIDTH, antialias: EXPORT_ANTIALIAS, template: EXPORT_TEMPLATE.to_string(), post: PostProcess::default() }
+    }
+}
+
+impl Export {
+    // The size of the export of a view: the width of the export with the shape of the view.
+    pub fn size(&self, view: &View) -> (u32, u32) {
+        let height = (self.width as f64 * view.height as f64 / view.width as f64).round() as u32;
+        (self.width, height.max(1))
+    }
+
+    // The options rendering a view at the export quality.
+    pub fn render_options(&self, view: &View, palette: &str, transform: PaletteTransform) -> Result<RenderOptions, String> {
+        let (width, height) = self.size(view);
+        RenderOptions::builder()
+            .size(width, height)
+            .center(view.center.0, view.center.1)
+            .zoom(view.zoom)
+            .iterations(view.max_iterations)
+            .palette(palette)
+            .coloring_options(ColoringOptions { palette_transform: transform, ..Default::default() })
+            .antialias(self.antialias)
+            .build()
+            .map_err(String::from)
+    }
+}
+
+// The values tuned from the keyboard, Tab going from one to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameter {
+    Iterations,
+    PaletteOffset,
+    PaletteScale,
+    JuliaRe, // The real part of the c of the Julia set of the dual view
+    JuliaIm,
+}
+
+impl Parameter {
+    const ALL: [Parameter; 5] = [Parameter::Iterations, Parameter::PaletteOffset, Parameter::PaletteScale, Parameter::JuliaRe, Parameter::JuliaIm];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&parameter| parameter == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// The tuning line: the parameter picked, and the iteration limit and c tuned but not rendered yet,
+// which wait until the keys have been left alone until `due`.
+pub struct Tuning {
+    pub parameter: Parameter,
+    pub max_iterations: Option<u32>,
+    pub c: Option<(f32, f32)>,
+    pub due: Option<Instant>,
+}
+
+// The text of the tuning line, the parameter being tuned between brackets. The values waiting to be
+// rendered are shown in place of those of the view and of the Julia set of c.
+pub fn tuning_line(tuning: &Tuning, view: &View, c: Option<(f32, f32)>, transform: &PaletteTransform) -> String {
+    let c = tuning.c.or(c);
+    let part = |value: Option<f32>| value.map_or("-".to_string(), |value| format!("{:+.6}", value));
+    let values = [
+        (Parameter::Iterations, format!("Iterations {}", tuning.max_iterations.unwrap_or(view.max_iterations))),
+        (Parameter::PaletteOffset, format!("Offset {:.2}", transform.offset)),
+        (Parameter::PaletteScale, format!("Scale {:.2}", transform.scale)),
+        (Parameter::JuliaRe, format!("Re c {}", part(c.map(|c| c.0)))),
+        (Parameter::JuliaIm, format!("Im c {}", part(c.map(|c| c.1)))),
+    ];
+    let values: Vec<String> =
+        values.into_iter().map(|(parameter, text)| if parameter == tuning.parameter { format!("[{}]", text) } else { text }).collect();
+    values.join("  ")
+}
+
+// Recognize double-clicks from the stream of single clicks.
+#[derive(Default)]
+struct ClickTracker {
+    last: Option<(Instant, (f32, f32))>,
+}
+
+impl ClickTracker {
+    // Register a click, returning true when it completes a double-click.
+    fn click(&mut self, time: Instant, position: (f32, f32)) -> bool {
+        let double = self.last.is_some_and(|(last_time, last_position)| {
+            time.duration_since(last_time) <= DOUBLE_CLICK_DELAY && distance(position, last_position) <= DOUBLE_CLICK_DISTANCE
+        });
+        // A double-click is consumed so a third click starts over.
+        self.last = if double { None } else { Some((time, position)) };
+        double
+    }
+}
+
+// Views visited before (for undo) and after (for redo) the current one.
+pub struct History {
+    past: Vec<View>,
+    future: Vec<View>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self { past: Vec::new(), future: Vec::new(), capacity }
+    }
+
+    // Remember the view being left. Navigating somewhere new drops the views that could be redone.
+    pub fn record(&mut self, view: View) {
+        if self.past.len() == self.capacity {
+            self.past.remove(0); // The oldest view is forgotten first
+        }
+        self.past.push(view);
+        self.future.clear();
+    }
+
+    // The previous view, the current one becoming available for redo.
+    pub fn undo(&mut self, current: View) -> Option<View> {
+        let previous = self.past.pop()?;
+        self.future.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: View) -> Option<View> {
+        let next = self.future.pop()?;
+        self.past.push(current);
+        Some(next)
+    }
+}
+
+// The rotation of the palette animated while the image is displayed.
+pub struct PaletteCycle {
+    pub running: bool,
+    pub speed: f64,  // Gradient lengths per second
+    pub offset: f64, // Current rotation, a fraction of the gradient
+}
+
+impl PaletteCycle {
+    pub fn new() -> Self {
+        Self { running: false, speed: CYCLE_SPEED, offset: 0.0 }
+    }
+
+    // Rotate the palette by the distance covered in the elapsed time.
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.offset = (self.offset + self.speed * elapsed.as_secs_f64()).rem_euclid(1.0);
+    }
+}
+
+// Render the whole Julia set of the parameter c of a family, shared by the preview inset and the dual view.
+pub fn render_julia(family: Family, c: (f32, f32), max_iterations: u32, palette: &str, (width, height): (u32, u32)) -> Result<RgbImage, String> {
+    let (center, zoom) = family.julia_view();
+    let bounds = mandelbrot::view_bounds(center, zoom, width, height);
+    let julia = family.julia(Complex::new(c.0 as f64, c.1 as f64));
+    let iterations = fractal::compute_iterations(&*julia, width, height, max_iterations, bounds);
+    Ok(mandelbrot::colorize(width, height, &iterations, &*mandelbrot::color_map_by_name(palette, max_iterations)?))
+}
+
+// Render the Julia set of c at the size of the preview inset.
+pub fn render_julia_preview(family: Family, c: (f32, f32), max_iterations: u32, palette: &str) -> Result<RgbImage, String> {
+    render_julia(family, c, max_iterations.min(PREVIEW_MAX_ITERATIONS), palette, PREVIEW_SIZE)
+}
+
+// Put two images of the same height next to each other.
+pub fn side_by_side(left: &RgbImage, right: &RgbImage) -> RgbImage {
+    let mut image = RgbImage::new(left.width() + right.width(), left.height().max(right.height()));
+    for (x, y, pixel) in left.enumerate_pixels() {
+        image.put_pixel(x, y, *pixel);
+    }
+    for (x, y, pixel) in right.enumerate_pixels() {
+        image.put_pixel(left.width() + x, y, *pixel);
+    }
+    image
+}
+
+// Draw the segment between two pixels (Bresenham's algorithm), leaving out the part outside the image.
+pub fn draw_line(image: &mut RgbImage, from: (f32, f32), to: (f32, f32), color: Rgb<u8>) {
+    let (width, height) = image.dimensions();
+    // Only the part over the pixels is walked, escaping orbits getting far outside quickly. Points that
+    // are not finite have no line to draw.
+    if ![from.0, from.1, to.0, to.1].iter().all(|v| v.is_finite()) {
+        return;
+    }
+    let edges = (-0.5, width as f64 - 0.5, -0.5, height as f64 - 0.5);
+    let Some((from, to)) = clip_segment((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64), edges) else {
+        return;
+    };
+    let (mut x, mut y, x1, y1) = (from.0.round() as i64, from.1.round() as i64, to.0.round() as i64, to.1.round() as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+    let mut error = dx + dy;
+    loop {
+        if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+        if x == x1 && y == y1 {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+// The part of a segment inside the rectangle (xmin, xmax, ymin, ymax) (Liang–Barsky), None when the
+// segment misses it.
+fn clip_segment(from: (f64, f64), to: (f64, f64), (xmin, xmax, ymin, ymax): (f64, f64, f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut start, mut end) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, from.0 - xmin), (dx, xmax - from.0), (-dy, from.1 - ymin), (dy, ymax - from.1)] {
+        if p == 0.0 {
+            // Parallel to the edge: inside it all along or nowhere
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            start = start.max(q / p);
+        } else {
+            end = end.min(q / p);
+        }
+    }
+    let at = |t: f64| (from.0 + t * dx, from.1 + t * dy);
+    (start <= end).then(|| (at(start), at(end)))
+}
+
+// Mark a pixel with a small cross, inverting the colors below like the selection box.
+pub fn draw_marker(image: &mut RgbImage, pixel: (f32, f32)) {
+    const ARM: i64 = 5;
+    let (width, height) = image.dimensions();
+    let (cx, cy) = (pixel.0.round() as i64, pixel.1.round() as i64);
+    // The center belongs to the horizontal arm only, so that it is inverted once.
+    let vertical = (-ARM..=ARM).filter(|&d| d != 0).map(|d| (cx, cy + d));
+    for (x, y) in (-ARM..=ARM).map(|d| (cx + d, cy)).chain(vertical) {
+        if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+            let Rgb([r, g, b]) = *image.get_pixel(x as u32, y as u32);
+            image.put_pixel(x as u32, y as u32, Rgb([255 - r, 255 - g, 255 - b]));
+        }
+    }
+}
+
+// Copy the inset into the top right corner of the image with a white border, unless it does not fit.
+pub fn draw_inset(image: &mut RgbImage, inset: &RgbImage) {
+    draw_framed(image, inset, false);
+}
+
+// Copy an inset into the top left or right corner of the image with a white border, unless it does not fit.
+fn draw_framed(image: &mut RgbImage, inset: &RgbImage, on_left: bool) {
+    let (width, height) = image.dimensions();
+    let (inset_width, inset_height) = inset.dimensions();
+    if inset_width + 2 * PREVIEW_MARGIN > width || inset_height + 2 * PREVIEW_MARGIN > height {
+        return;
+    }
+    let (left, top) = (if on_left { PREVIEW_MARGIN } else { width - PREVIEW_MARGIN - inset_width }, PREVIEW_MARGIN);
+    for y in top - 1..=top + inset_height {
+        for x in left - 1..=left + inset_width {
+            let inside = (left..left + inset_width).contains(&x) && (top..top + inset_height).contains(&y);
+            let pixel = if inside { *inset.get_pixel(x - left, y - top) } else { Rgb([255, 255, 255]) };
+            image.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+// The overview of the default bounds of a family, and its view.
+pub fn render_minimap(family: Family, palette: &str) -> Result<(View, RgbImage), String> {
+    let plane = family.parameter_plane();
+    let view = View::from_bounds(plane.default_bounds(), MINIMAP_ITERATIONS, MINIMAP_SIZE.0, MINIMAP_SIZE.1);
+    let options = view.render_options(palette)?;
+    let image = options.colorize(&options.compute_with(&*plane)?)?;
+    Ok((view, image))
+}
+
+// Copy the minimap into the top left corner of the image with the box of the view drawn on it, or a
+// cross where it is once it gets too small to be seen.
+pub fn draw_minimap(image: &mut RgbImage, (minimap_view, minimap): (&View, &RgbImage), view: &View) {
+    let mut map = minimap.clone();
+    let (xmin, xmax, ymin, ymax) = view.bounds();
+    let (left, top) = minimap_view.complex_to_pixel((xmin, ymin));
+    let (right, bottom) = minimap_view.complex_to_pixel((xmax, ymax));
+    if right - left < MINIMAP_MIN_BOX && bottom - top < MINIMAP_MIN_BOX {
+        draw_marker(&mut map, ((left + right) / 2.0, (top + bottom) / 2.0));
+    } else {
+        let corners = [(left, top), (right, top), (right, bottom), (left, bottom), (left, top)];
+        for side in corners.windows(2) {
+            draw_line(&mut map, side[0], side[1], MINIMAP_BOX_COLOR);
+        }
+    }
+    draw_framed(image, &map, true);
+}
+
+// Julia set of the point under the cursor, rendered on a background thread so that moving the mouse
+// stays smooth. Only the latest position is rendered when several arrive during a render.
+pub struct JuliaPreview {
+    requests: Sender<((f32, f32), u32)>,
+    results: Receiver<((f32, f32), Result<RgbImage, String>)>,
+    requested: Option<(f32, f32)>, // Last c asked for
+    shown: Option<(f32, f32)>,     // c of the inset
+    inset: Option<RgbImage>,
+}
+
+impl JuliaPreview {
+    pub fn start(palette: String, family: Family) -> Self {
+        let (requests, pending) = mpsc::channel::<((f32, f32), u32)>();
+        let (finished, results) = mpsc::channel();
+        // The thread stops once the preview, and so the sending end of the requests, is dropped.
+        thread::spawn(move || {
+            while let Ok(mut request) = pending.recv() {
+                while let Ok(newer) = pending.try_recv() {
+                    request = newer;
+                }
+                let (c, max_iterations) = request;
+                if finished.send((c, render_julia_preview(family, c, max_iterations, &palette))).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results, requested: None, shown: None, inset: None }
+    }
+
+    // Ask for the Julia set of c.
+    pub fn request(&mut self, c: (f32, f32), max_iterations: u32) {
+        if self.requested != Some(c) && self.requests.send((c, max_iterations)).is_ok() {
+            self.requested = Some(c);
+        }
+    }
+
+    // True while the latest request has not been rendered yet.
+    pub fn waiting(&self) -> bool {
+        self.requested.is_some() && self.requested != self.shown
+    }
+
+    // Take the previews rendered since the last call, returning true when the inset changed.
+    pub fn receive(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        while let Ok((c, image)) = self.results.try_recv() {
+            self.inset = Some(image?);
+            self.shown = Some(c);
+            changed = true;
+        }
+        Ok(changed)
+    }
+}
+
+// The right half of the dual view: the Julia set of a point c chosen on the Mandelbrot set.
+struct DualPane {
+    c: (f32, f32),
+    frame: RgbImage,
+}
+
+pub struct Viewer {
+    view: View,
+    palette: String, // A palette name or mandelbrot::GRAYSCALE
+    escapes: Vec<EscapeInfo>, // Result of the compute pass, empty until needed
+    frame: RgbImage, // The colored image, without selection or HUD
+    cycle: PaletteCycle,
+    hud_visible: bool,
+    cursor: Option<(f32, f32)>, // Image pixel under the mouse
+    selection: Option<((f32, f32), (f32, f32))>, // Corners of the box being dragged
+    history: History,
+    julia: Option<JuliaPreview>, // The inset, while it is turned on
+    dual: Option<DualPane>,      // The Julia set next to the view, while the dual view is on
+    orbit: Vec<(f32, f32)>,      // Orbit drawn over the view, empty when there is none
+    aspect: AspectPolicy,        // How the boxes dragged are fitted to the view, C switching expand and crop
+    family: Family,              // The plane shown and the Julia sets of its points
+    transform: PaletteTransform, // The offset and scale of the palette, tuned from the keyboard
+    tuning: Option<Tuning>,      // The tuning line, while it is shown
+    presenting: bool,            // Fullscreen without cursor, status or tuning lines
+    export: Export,              // How S saves the view
+    minimap: Option<(View, RgbImage)>, // The overview of the plane, while it is shown
+    axes_visible: bool,          // The grid and the axes over the view
+}
+
+impl Viewer {
+    // Start from an image already rendered for the view.
+    pub fn new(view: View, palette: String, frame: RgbImage) -> Result<Self, String> {
+        view.render_options(&palette)?;
+        Ok(Self {
+            view,
+            palette,
+            escapes: Vec::new(),
+            frame,
+            cycle: PaletteCycle::new(),
+            hud_visible: true,
+            cursor: None,
+            selection: None,
+            history: History::new(HISTORY_SIZE),
+            julia: None,
+            dual: None,
+            orbit: Vec::new(),
+            aspect: AspectPolicy::default(),
+            family: Family::default(),
+            transform: PaletteTransform::default(),
+            tuning: None,
+            presenting: false,
+            export: Export::default(),
+            minimap: None,
+            axes_visible: false,
+        })
+    }
+
+
+    // Start by rendering the view.
+    pub fn open(view: View, palette: String) -> Result<Self, String> {
+        Self::open_family(view, palette, Family::default())
+    }
+
+    // Start by rendering the view of the parameter plane of a family, like the λ plane of the logistic map.
+    pub fn open_family(view: View, palette: String, family: Family) -> Result<Self, String> {
+        let mut viewer = Self { family, ..Self::new(view, palette, RgbImage::new(view.width, view.height))? };
+        viewer.render()?;
+        Ok(viewer)
+    }
+
+    // Start in the presentation mode, for a projector or an ambient display.
+    pub fn presenting(mut self) -> Self {
+        self.presenting = true;
+        self
+    }
+
+    pub fn with_export(mut self, export: Export) -> Self {
+        self.export = export;
+        self
+    }
+
+    fn render(&mut self) -> Result<(), String> {
+        self.escapes.clear();
+        self.orbit.clear(); // It was drawn for the previous view
+        self.colorize()
+    }
+
+    // Color the escapes of the view with the palette rotated by the cycle, computing them first if
+    // they are not known yet. Only this pass runs for each frame of the palette cycling.
+    fn colorize(&mut self) -> Result<(), String> {
+        let options = self.view.render_options(&self.palette)?;
+        if self.escapes.is_empty() {
+            self.escapes = options.compute_with(&*self.family.parameter_plane())?;
+        }
+        self.frame = if self.palette == mandelbrot::GRAYSCALE {
+            options.colorize(&self.escapes)? // The grayscale map has no palette to rotate
+        } else {
+            let palette = Palette::by_name(&self.palette)?.with_transform(self.transform)?;
+            let color_map = ColoredColorMap::from_palette(self.view.max_iterations, palette).with_offset(self.cycle.offset);
+            mandelbrot::colorize_escapes(self.view.width, self.view.height, &self.escapes, &color_map)
+        };
+        Ok(())
+    }
+
+    // Change the view, keeping the current one in the history, and render the new one.
+    fn navigate(&mut self, change: impl FnOnce(&mut View)) -> Result<(), String> {
+        let previous = self.view;
+        change(&mut self.view);
+        if self.view != previous {
+            self.history.record(previous);
+            self.render()?;
+        }
+        Ok(())
+    }
+
+    // Compute the orbit of c to draw it over the view, and log how it ends.
+    fn show_orbit(&mut self, c: (f32, f32)) {
+        let (points, escape) = fractal::orbit(&*self.family.parameter_plane(), Complex::new(c.0 as f64, c.1 as f64), self.view.max_iterations);
+        self.orbit = points.iter().map(|z| (z.re as f32, z.im as f32)).collect();
+        if escape.iterations < self.view.max_iterations {
+            info!("The orbit of {} escapes after {} iterations", format_point(c), escape.iterations);
+        } else {
+            info!("The orbit of {} stays bounded for {} iterations", format_point(c), escape.iterations);
+        }
+    }
+
+    // Show the Julia set of c next to the view, turning the dual view on.
+    fn select_julia(&mut self, c: (f32, f32)) -> Result<(), String> {
+        let frame = render_julia(self.family, c, self.view.max_iterations, &self.palette, (self.view.width, self.view.height))?;
+        self.dual = Some(DualPane { c, frame });
+        Ok(())
+    }
+
+    // Start in the dual view with the Julia set of c.
+    pub fn with_dual_view(mut self, c: (f32, f32)) -> Result<Self, String> {
+        self.select_julia(c)?;
+        Ok(self)
+    }
+
+    // Size of the displayed image, twice as wide as the view in the dual view.
+    fn display_size(&self) -> (u32, u32) {
+        let panes = if self.dual.is_some() { 2 } else { 1 };
+        (self.view.width * panes, self.view.height)
+    }
+
+    // Show the tuning line, or pick its next parameter.
+    fn next_parameter(&mut self) {
+        self.tuning = Some(match self.tuning.take() {
+            Some(tuning) => Tuning { parameter: tuning.parameter.next(), ..tuning },
+            None => Tuning { parameter: Parameter::Iterations, max_iterations: None, c: None, due: None },
+        });
+    }
+
+    // Lower (direction -1) or raise (+1) the parameter of the tuning line. The palette is recolored
+    // right away, while the iteration limit and c wait for the keys to be left alone.
+    fn tune(&mut self, direction: f32, now: Instant) -> Result<(), String> {
+        let Some(tuning) = &mut self.tuning else {
+            return Ok(());
+        };
+        let (xmin, xmax, _, _) = self.view.bounds();
+        let c_step = direction * TUNING_C_STEP * (xmax - xmin);
+        // c starts from the Julia set of the dual view, or from the center of the view
+        let c = tuning.c.or(self.dual.as_ref().map(|dual| dual.c)).unwrap_or(self.view.center);
+        match tuning.parameter {
+            Parameter::Iterations => {
+                let factor = TUNING_ITERATION_STEP.powf(direction);
+                let max_iterations = tuning.max_iterations.unwrap_or(self.view.max_iterations);
+                tuning.max_iterations = Some(((max_iterations as f32 * factor).round() as u32).max(MIN_ITERATIONS));
+            }
+            Parameter::PaletteOffset => self.transform.offset = (self.transform.offset + direction as f64 * TUNING_OFFSET_STEP).rem_euclid(1.0),
+            Parameter::PaletteScale => self.transform.scale *= TUNING_SCALE_STEP.powf(direction as f64),
+            Parameter::JuliaRe => tuning.c = Some((c.0 + c_step, c.1)),
+            Parameter::JuliaIm => tuning.c = Some((c.0, c.1 + c_step)),
+        }
+        match tuning.parameter {
+            Parameter::PaletteOffset | Parameter::PaletteScale => self.colorize(),
+            _ => {
+                tuning.due = Some(now + TUNING_DELAY);
+                Ok(())
+            }
+        }
+    }
+
+    // Render the iteration limit and c of the tuning line once they are due, returning true when the
+    // image changed.
+    fn apply_tuning(&mut self, now: Instant) -> Result<bool, String> {
+        let Some(tuning) = &mut self.tuning else {
+            return Ok(false);
+        };
+        if tuning.due.is_none_or(|due| due > now) {
+            return Ok(false);
+        }
+        tuning.due = None;
+        let (max_iterations, c) = (tuning.max_iterations.take(), tuning.c.take());
+        if let Some(max_iterations) = max_iterations {
+            self.navigate(|view| view.max_iterations = max_iterations)?;
+        }
+        if let Some(c) = c {
+            self.select_julia(c)?;
+        }
+        Ok(true)
+    }
+
+    // Move the view by whole pixels, the image following the mouse: the escapes still in the view are
+    // shifted, and only the strips uncovered along its edges are computed.
+    fn pan_by(&mut self, (dx, dy): (i32, i32)) -> Result<(), String> {
+        let (width, height) = (self.view.width, self.view.height);
+        let (xmin, xmax, _, _) = self.view.bounds();
+        let scale = (xmax - xmin) / width as f32; // The same along both axes
+        self.view.center = (self.view.center.0 - dx as f32 * scale, self.view.center.1 - dy as f32 * scale);
+        if dx.unsigned_abs() >= width || dy.unsigned_abs() >= height {
+            self.escapes.clear(); // Nothing is left to keep
+        }
+        if self.escapes.is_empty() {
+            return self.colorize();
+        }
+        let mut escapes = shift_escapes(&self.escapes, (width, height), (dx, dy));
+        let fractal = self.family.parameter_plane();
+        for (left, top, strip_width, strip_height) in exposed_strips((width, height), (dx, dy)) {
+            let strip = self.view.region((left, top), (strip_width, strip_height));
+            let computed = strip.render_options(&self.palette)?.compute_with(&*fractal)?;
+            for (index, escape) in computed.into_iter().enumerate() {
+                let (x, y) = (left + index as u32 % strip_width, top + index as u32 / strip_width);
+                escapes[(y * width + x) as usize] = escape;
+            }
+        }
+        self.escapes = escapes;
+        self.colorize()
+    }
+
+    // Render the view at the size of the window, the panes of the dual view sharing its width, keeping
+    // the center and the zoom: the plane is cut to the new shape rather than stretched. Returns false
+    // when the size did not change.
+    fn resize(&mut self, (window_width, window_height): (u32, u32)) -> Result<bool, String> {
+        let panes = if self.dual.is_some() { 2 } else { 1 };
+        let (width, height) = ((window_width / panes).max(MIN_VIEW_SIZE), window_height.max(MIN_VIEW_SIZE));
+        if (width, height) == (self.view.width, self.view.height) {
+            return Ok(false);
+        }
+        (self.view.width, self.view.height) = (width, height);
+        self.cursor = None;
+        self.render()?;
+        if let Some(c) = self.dual.as_ref().map(|dual| dual.c) {
+            self.select_julia(c)?;
+        }
+        Ok(true)
+    }
+
+    // Go back (or forward with redo) in the history, returning false when there is nowhere to go.
+    // The views come back at the current size of the window.
+    fn travel(&mut self, redo: bool) -> Result<bool, String> {
+        let view = if redo { self.history.redo(self.view) } else { self.history.undo(self.view) };
+        match view {
+            Some(view) => {
+                self.view = View { width: self.view.width, height: self.view.height, ..view };
+                self.render()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Ask for a name in the terminal and store the current view in the bookmarks file.
+    fn save_bookmark(&self) -> Result<(), Box<dyn Error>> {
+        let path = bookmarks::default_path()?;
+        let mut saved = bookmarks::load(&path)?;
+        println!("Enter a name for the bookmark (press Enter for an automatic name):");
+        let input: String = read!("{}\n");
+        let name = match input.trim() {
+            "" => bookmarks::unused_name(&saved),
+            name => name.to_string(),
+        };
+        let bookmark = Bookmark::from_view(name.clone(), &self.view, &self.palette);
+        bookmarks::insert(&mut saved, bookmark);
+        bookmarks::save(&path, &saved)?;
+        info!("Bookmark '{}' saved in {}", name, path.display());
+        Ok(())
+    }
+
+    // The palette transform giving the colors of the window, the cycling included: it shifts the
+    // gradient before it is scaled. Grayscale takes none.
+    fn window_transform(&self) -> PaletteTransform {
+        if self.palette == mandelbrot::GRAYSCALE {
+            return PaletteTransform::default();
+        }
+        PaletteTransform { offset: (self.transform.offset + self.cycle.offset * self.transform.scale).rem_euclid(1.0), ..self.transform }
+    }
+
+    // Render the view at the export quality, in the colors of the window.
+    fn render_export(&self) -> Result<RgbImage, String> {
+        let options = self.export.render_options(&self.view, &self.palette, self.window_transform())?;
+        let escapes = options.compute_with(&*self.family.parameter_plane())?;
+        Ok(options.colorize(&escapes)?)
+    }
+
+    // Save the view at the export quality under the name of the export template.
+    fn save_export(&self) -> Result<(), Box<dyn Error>> {
+        let (width, height) = self.export.size(&self.view);
+        let fractal = match self.family {
+            Family::Mandelbrot => "mandelbrot",
+            Family::Lambda => "lambda",
+        };
+        let tokens = NameTokens::new()
+            .with("fractal", fractal)
+            .view(self.view.center, self.view.zoom)
+            .with("iter", self.view.max_iterations)
+            .with("palette", &self.palette)
+            .size(width, height);
+        let path = naming::expand(&self.export.template, &tokens)?;
+        info!("Exporting {}x{} with {}x{} samples per pixel...", width, height, self.export.antialias, self.export.antialias);
+        let mut image = self.render_export()?;
+        self.export.post.apply(&mut image, Some(View { width, height, ..self.view }.bounds()), &tokens)?;
+        match self.family {
+            // Only a Mandelbrot render can be reproduced from its options
+            Family::Mandelbrot => metadata::save_render(&image, &path, &self.export.render_options(&self.view, &self.palette, self.window_transform())?)?,
+            Family::Lambda => image.save(&path)?,
+        }
+        info!("View exported to {}", path);
+        Ok(())
+    }
+
+    // The view as text to share: its center, zoom and iteration limit, then the command rendering it
+    // again in the colors of the window.
+    fn location_text(&self) -> String {
+        let (re, im) = self.view.center;
+        let mut text = format!("center {},{} zoom {} iterations {}", re, im, self.view.zoom, self.view.max_iterations);
+        if self.family != Family::Mandelbrot {
+            return text; // The render command only takes a view of the Mandelbrot set
+        }
+        text += &format!(
+            "\nfinal_exercice render --fractal mandelbrot --center {},{} --zoom {} --iterations {} --palette {} --size {}x{}",
+            re, im, self.view.zoom, self.view.max_iterations, self.palette, self.view.width, self.view.height
+        );
+        let transform = self.window_transform();
+        if transform.offset != 0.0 {
+            text += &format!(" --palette-offset {}", transform.offset);
+        }
+        if transform.scale != 1.0 {
+            text += &format!(" --palette-scale {}", transform.scale);
+        }
+        text
+    }
+
+    // Copy the location of the view to the clipboard, and log it in case there is no clipboard.
+    fn copy_location(&self) {
+        let text = self.location_text();
+        info!("{}", text);
+        match clipboard::copy(&text) {
+            Ok(()) => info!("View copied to the clipboard"),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    // The frame with the selection box and the HUD drawn over it, the HUD and the tuning line being
+    // left out while presenting.
+    fn compose(&self) -> RgbImage {
+        let hud_visible = self.hud_visible && !self.presenting;
+        let mut image = match self.selection {
+            Some((corner, opposite)) => draw_selection(&self.frame, corner, opposite),
+            None => self.frame.clone(),
+        };
+        if self.axes_visible && !self.presenting {
+            axes::draw_axes(&mut image, self.view.bounds());
+        }
+        for segment in self.orbit.windows(2) {
+            draw_line(&mut image, self.view.complex_to_pixel(segment[0]), self.view.complex_to_pixel(segment[1]), ORBIT_COLOR);
+        }
+        if let Some(inset) = self.julia.as_ref().and_then(|julia| julia.inset.as_ref()) {
+            draw_inset(&mut image, inset);
+        }
+        if let Some((minimap_view, minimap)) = self.minimap.as_ref().filter(|_| !self.presenting) {
+            draw_minimap(&mut image, (minimap_view, minimap), &self.view);
+        }
+        if hud_visible {
+            draw_hud(&mut image, &status_line(&self.view, self.cursor));
+        }
+        if let Some(tuning) = self.tuning.as_ref().filter(|_| !self.presenting) {
+            draw_strip(&mut image, &tuning_line(tuning, &self.view, self.dual.as_ref().map(|dual| dual.c), &self.transform), true);
+        }
+        let Some(dual) = &self.dual else {
+            return image;
+        };
+        draw_marker(&mut image, self.view.complex_to_pixel(dual.c));
+        let mut julia = dual.frame.clone();
+        if hud_visible {
+            draw_hud(&mut julia, &format!("Julia set of c = {}", format_point(dual.c)));
+        }
+        side_by_side(&image, &julia)
+    }
+
+    fn show(&self, window: &WindowProxy) -> Result<(), Box<dyn Error>> {
+        window.set_image("image-001", to_showable_image(&self.compose()))?;
+        Ok(())
+    }
+
+    // Enter or leave the presentation mode, the window going fullscreen and the cursor being hidden.
+    fn set_presenting(&mut self, window: &WindowProxy, presenting: bool) -> Result<(), Box<dyn Error>> {
+        self.presenting = presenting;
+        window.run_function_wait(move |mut handle| {
+            handle.set_fullscreen(presenting);
+            handle.window().set_cursor_visible(!presenting);
+        })?;
+        Ok(())
+    }
+
+    // Show the image and handle events until the window is closed.
+    // Double-clicking a point recenters the view on it and renders again at the same zoom, while
+    // dragging a box with the left button zooms on that box and the keys of action_for_key pan,
+    // zoom and change the iteration limit. H shows or hides the HUD, Backspace or Z goes back to the
+    // previous view and Shift+Z forward again. B saves the view as a bookmark. Space starts or stops
+    // the palette cycling, [ and ] slow it down and speed it up. J shows or hides the Julia set of the
+    // point under the cursor in the top right corner. D turns the dual view on or off: the Julia set
+    // of the point last clicked is shown on the right of the view. Clicking with O held draws the
+    // orbit of the point over the view. Tab shows the tuning line and picks its next value (the iteration
+    // limit, the offset and scale of the palette, the real and imaginary parts of the Julia c of the dual
+    // view), comma and period lower and raise it, and Escape hides the line. Once the window has been
+    // resized, the view is rendered again at its new size. F11 switches the window to fullscreen and
+    // back, P enters or leaves the presentation mode (fullscreen without cursor, status nor tuning
+    // lines), which Escape also leaves. S saves the view at the export quality. Ctrl+C copies the
+    // center, the zoom and a command rendering the view again to the clipboard. Dragging with the right
+    // button pans the view, computing only the pixels uncovered until the button is released, when the
+    // view is rendered again in full. M shows or hides the minimap, the whole plane with the box of the
+    // view, in the top left corner. A draws the grid and the axes of the plane over the view.
+    pub fn run(mut self) -> Result<(), Box<dyn Error>> {
+        // The built-in controls of show_image pan and zoom the displayed image itself, which would
+        // break the mapping from window positions to the plane.
+        let (display_width, display_height) = self.display_size();
+        let options = WindowOptions::new().set_size([display_width, display_height]).set_default_controls(false);
+        let window = create_window("Mandelbrot Viewer", options)?;
+        if self.presenting {
+            self.set_presenting(&window, true)?;
+        }
+        self.show(&window)?;
+        let events = window.event_channel()?;
+        let size = window.run_function_wait(|handle| handle.inner_size())?;
+        let mut window_size = (size.x, size.y);
+        let mut clicks = ClickTracker::default();
+        let mut drag_start: Option<(f32, f32)> = None; // Image pixel where the left button went down
+        let mut pan: Option<((f32, f32), View)> = None; // Pixel the view was last panned to and the view before
+        let mut orbit_key = false; // O is held down
+        let mut last_frame = Instant::now(); // When the palette was last rotated
+        let mut resize_due: Option<Instant> = None; // When the window will have kept its new size long enough
+
+        loop {
+            // While the palette cycles or a Julia preview is rendered, events are only awaited until
+            // the next frame is due or until it is time to check for the preview.
+            let mut timeout = self.cycle.running.then(|| CYCLE_FRAME.saturating_sub(last_frame.elapsed()));
+            if self.julia.as_ref().is_some_and(JuliaPreview::waiting) {
+                timeout = Some(timeout.map_or(PREVIEW_POLL, |timeout| timeout.min(PREVIEW_POLL)));
+            }
+            if let Some(due) = self.tuning.as_ref().and_then(|tuning| tuning.due).into_iter().chain(resize_due).min() {
+                let wait = due.saturating_duration_since(Instant::now());
+                timeout = Some(timeout.map_or(wait, |timeout| timeout.min(wait)));
+            }
+            let event = match timeout {
+                Some(timeout) => match events.recv_timeout(timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match events.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                },
+            };
+            if self.cycle.running && last_frame.elapsed() >= CYCLE_FRAME {
+                self.cycle.advance(last_frame.elapsed());
+                last_frame = Instant::now();
+                self.colorize()?;
+                self.show(&window)?;
+            }
+            if let Some(julia) = &mut self.julia {
+                if julia.receive()? {
+                    self.show(&window)?;
+                }
+            }
+            if self.apply_tuning(Instant::now())? {
+                self.show(&window)?;
+            }
+            if resize_due.is_some_and(|due| due <= Instant::now()) {
+                resize_due = None;
+                if self.resize(window_size)? {
+                    self.show(&window)?;
+                }
+            }
+            let Some(event) = event else { continue };
+
+            // Positions are mapped to the whole displayed image, of which only the view takes clicks.
+            let (image_size, display_size) = ((self.view.width, self.view.height), self.display_size());
+            match event {
+                WindowEvent::Resized(event) => {
+                    window_size = (event.size.x, event.size.y);
+                    resize_due = Some(Instant::now() + RESIZE_DELAY);
+                }
+                WindowEvent::MouseButton(event) if event.button == MouseButton::Left => {
+                    let pixel = window_to_image((event.position.x, event.position.y), window_size, display_size);
+                    match (event.state, drag_start.take()) {
+                        (ElementState::Pressed, _) if is_inside(pixel, image_size) => drag_start = Some(pixel),
+                        (ElementState::Released, Some(start)) if distance(start, pixel) > DRAG_DISTANCE => {
+                            let aspect = self.aspect;
+                            self.navigate(|view| view.zoom_to_box(start, pixel, aspect))?;
+                        }
+                        (ElementState::Released, Some(start)) if orbit_key => self.show_orbit(self.view.pixel_to_complex(start)),
+                        (ElementState::Released, Some(start)) if clicks.click(Instant::now(), start) => {
+                            self.navigate(|view| view.center = view.pixel_to_complex(start))?;
+                        }
+                        (ElementState::Released, Some(start)) if self.dual.is_some() => {
+                            self.select_julia(self.view.pixel_to_complex(start))?;
+                        }
+                        _ => {}
+                    }
+                    self.selection = None;
+                    self.show(&window)?;
+                }
+                WindowEvent::MouseButton(event) if event.button == MouseButton::Right => {
+                    let pixel = window_to_image((event.position.x, event.position.y), window_size, display_size);
+                    match (event.state, pan.take()) {
+                        (ElementState::Pressed, _) if is_inside(pixel, image_size) => pan = Some((pixel, self.view)),
+                        (ElementState::Released, Some((_, before))) if before != self.view => {
+                            // The strips were computed at the precision of their own bounds
+                            self.history.record(before);
+                            self.render()?;
+                            self.show(&window)?;
+                        }
+                        _ => {}
+                    }
+                }
+                WindowEvent::MouseMove(event) => {
+                    let pixel = window_to_image((event.position.x, event.position.y), window_size, display_size);
+                    self.cursor = is_inside(pixel, image_size).then_some(pixel);
+                    if let Some((last, _)) = &mut pan {
+                        let (dx, dy) = ((pixel.0 - last.0).round(), (pixel.1 - last.1).round());
+                        if dx != 0.0 || dy != 0.0 {
+                            *last = (last.0 + dx, last.1 + dy);
+                            self.pan_by((dx as i32, dy as i32))?;
+                            self.show(&window)?;
+                            continue;
+                        }
+                    }
+                    if let (Some(julia), Some(cursor)) = (&mut self.julia, self.cursor) {
+                        julia.request(self.view.pixel_to_complex(cursor), self.view.max_iterations);
+                    }
+                    self.selection = drag_start.filter(|&start| distance(start, pixel) > DRAG_DISTANCE).map(|start| (start, pixel));
+                    // Without HUD or selection the displayed image does not change
+                    if (self.hud_visible && !self.presenting) || drag_start.is_some() {
+                        self.show(&window)?;
+                    }
+                }
+                WindowEvent::KeyboardInput(event) if event.input.key_code == Some(VirtualKeyCode::O) => {
+                    orbit_key = event.input.state == ElementState::Pressed;
+                }
+                WindowEvent::KeyboardInput(event) if event.input.state == ElementState::Pressed => {
+                    let shift = event.input.modifiers.shift();
+                    match event.input.key_code {
+                        Some(VirtualKeyCode::H) => self.hud_visible = !self.hud_visible,
+                        Some(VirtualKeyCode::A) => self.axes_visible = !self.axes_visible,
+                        Some(VirtualKeyCode::M) => {
+                            self.minimap = match self.minimap {
+                                Some(_) => None,
+                                None => Some(render_minimap(self.family, &self.palette)?),
+                            };
+                        }
+                        Some(VirtualKeyCode::Tab) => self.next_parameter(),
+                        Some(VirtualKeyCode::Escape) if self.presenting => self.set_presenting(&window, false)?,
+                        Some(VirtualKeyCode::Escape) if self.tuning.is_some() => self.tuning = None,
+                        Some(VirtualKeyCode::P) => self.set_presenting(&window, !self.presenting)?,
+                        Some(VirtualKeyCode::F11) => {
+                            window.run_function_wait(|mut handle| {
+                                let fullscreen = !handle.is_fullscreen();
+                                handle.set_fullscreen(fullscreen);
+                            })?;
+                            continue;
+                        }
+                        Some(VirtualKeyCode::Comma) => self.tune(-1.0, Instant::now())?,
+                        Some(VirtualKeyCode::Period) => self.tune(1.0, Instant::now())?,
+                        Some(VirtualKeyCode::D) => match self.dual {
+                            Some(_) => self.dual = None,
+                            None => self.select_julia(self.cursor.map_or(self.view.center, |cursor| self.view.pixel_to_complex(cursor)))?,
+                        },
+                        Some(VirtualKeyCode::J) => {
+                            self.julia = match self.julia.take() {
+                                Some(_) => None,
+                                None => {
+                                    let mut julia = JuliaPreview::start(self.palette.clone(), self.family);
+                                    if let Some(cursor) = self.cursor {
+                                        julia.request(self.view.pixel_to_complex(cursor), self.view.max_iterations);
+                                    }
+                                    Some(julia)
+                                }
+                            };
+                        }
+                        Some(VirtualKeyCode::Space) => {
+                            if self.palette == mandelbrot::GRAYSCALE {
+                                warn!("Palette cycling needs a colored palette");
+                            } else {
+                                self.cycle.running = !self.cycle.running;
+                                last_frame = Instant::now();
+                            }
+                            continue;
+                        }
+                        Some(VirtualKeyCode::LBracket) => {
+                            self.cycle.speed /= CYCLE_SPEED_STEP;
+                            continue;
+                        }
+                        Some(VirtualKeyCode::RBracket) => {
+                            self.cycle.speed *= CYCLE_SPEED_STEP;
+                            continue;
+                        }
+                        Some(VirtualKeyCode::B) => {
+                            if self.family == Family::Mandelbrot {
+                                self.save_bookmark()?;
+                            } else {
+                                warn!("Bookmarks only keep views of the Mandelbrot set");
+                            }
+                            continue;
+                        }
+                        Some(VirtualKeyCode::S) => {
+                            // A failed export (an unknown token in the template, a missing directory)
+                            // leaves the viewer open
+                            if let Err(e) = self.save_export() {
+                                warn!("The view could not be exported: {}", e);
+                            }
+                            continue;
+                        }
+                        Some(VirtualKeyCode::C) if event.input.modifiers.ctrl() => {
+                            self.copy_location();
+                            continue;
+                        }
+                        Some(VirtualKeyCode::C) => {
+                            self.aspect = if self.aspect == AspectPolicy::Crop { AspectPolicy::Expand } else { AspectPolicy::Crop };
+                            info!("Boxes now zoom with {:?}", self.aspect);
+                            continue;
+                        }
+                        Some(VirtualKeyCode::Z) if shift => {
+                            if !self.travel(true)? {
+                                continue;
+                            }
+                        }
+                        Some(VirtualKeyCode::Back | VirtualKeyCode::Z) => {
+                            if !self.travel(false)? {
+                                continue;
+                            }
+                        }
+                        Some(key) => match action_for_key(key) {
+                            Some(action) => self.navigate(|view| view.apply(action))?,
+                            None => continue,
+                        },
+                        None => continue,
+                    }
+                    self.show(&window)?;
+                }
+                WindowEvent::CloseRequested(_) | WindowEvent::Destroyed(_) => break,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that window positions account for the scaling and the bars around the image.
+    #[test]
+    fn test_window_to_image() {
+        // An 800x600 image in a 1600x600 window is shown 1:1 with 400px bars on the sides.
+        assert_eq!(window_to_image((400.0, 0.0), (1600, 600), (800, 600)), (0.0, 0.0));
+        assert!(!is_inside(window_to_image((100.0, 300.0), (1600, 600), (800, 600)), (800, 600)));
+        // The same image in a 400x300 window is shown at half scale.
+        assert_eq!(window_to_image((200.0, 150.0), (400, 300), (800, 600)), (400.0, 300.0));
+    }
+
+    /// Tests that the center pixel maps to the center of the view and the corner to the bounds.
+    #[test]
+    fn test_pixel_to_complex() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        assert_eq!(view.pixel_to_complex((400.0, 300.0)), (0.0, 0.0));
+        assert_eq!(view.pixel_to_complex((0.0, 0.0)), (-2.0, -1.5));
+    }
+
+    /// Tests that only two close and quick clicks make a double-click.
+    #[test]
+    fn test_double_click() {
+        let start = Instant::now();
+        let mut clicks = ClickTracker::default();
+        assert!(!clicks.click(start, (10.0, 10.0)));
+        assert!(clicks.click(start + Duration::from_millis(200), (12.0, 11.0)));
+        assert!(!clicks.click(start + Duration::from_millis(300), (12.0, 11.0))); // Starts a new pair
+        assert!(!clicks.click(start + Duration::from_millis(900), (12.0, 11.0))); // Too slow
+        assert!(!clicks.click(start + Duration::from_millis(1000), (80.0, 11.0))); // Too far
+    }
+
+    /// Tests that a box zoom keeps the whole box visible along its longer side.
+    #[test]
+    fn test_zoom_to_box() {
+        let mut view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        // A tall 100x300 box: its height limits the zoom to 2x.
+        view.zoom_to_box((400.0, 300.0), (500.0, 0.0), AspectPolicy::Expand);
+        assert_eq!(view.center, (0.25, -0.75));
+        assert_eq!(view.zoom, 2.0);
+        let (_, _, ymin, ymax) = view.bounds();
+        assert_eq!((ymin, ymax), (-1.5, 0.0));
+        // Cropped, its width fills the view instead.
+        let mut view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        view.zoom_to_box((400.0, 300.0), (500.0, 0.0), AspectPolicy::Crop);
+        assert_eq!(view.zoom, 8.0);
+    }
+
+    /// Tests that the selection outline inverts the pixels on the border of the box only.
+    #[test]
+    fn test_draw_selection() {
+        let frame = RgbImage::from_pixel(20, 10, Rgb([0, 100, 255]));
+        let selection = draw_selection(&frame, (15.0, 8.0), (2.0, 2.0));
+        assert_eq!(*selection.get_pixel(2, 5), Rgb([255, 155, 0]));
+        assert_eq!(*selection.get_pixel(15, 8), Rgb([255, 155, 0]));
+        assert_eq!(*selection.get_pixel(8, 5), Rgb([0, 100, 255])); // Inside the box
+        assert_eq!(*selection.get_pixel(1, 5), Rgb([0, 100, 255])); // Outside the box
+    }
+
+    /// Tests that the keyboard actions pan by a tenth of the view, zoom by 2x and scale the iterations.
+    #[test]
+    fn test_keyboard_actions() {
+        let mut view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        view.apply(action_for_key(VirtualKeyCode::Right).unwrap());
+        view.apply(action_for_key(VirtualKeyCode::Up).unwrap());
+        assert!((view.center.0 - 0.4).abs() < 1e-6 && (view.center.1 + 0.3).abs() < 1e-6);
+
+        view.apply(action_for_key(VirtualKeyCode::Equals).unwrap());
+        assert_eq!(view.zoom, 2.0);
+        view.apply(action_for_key(VirtualKeyCode::Left).unwrap());
+        assert!((view.center.0 - 0.2).abs() < 1e-6); // The step shrinks with the view
+
+        view.apply(action_for_key(VirtualKeyCode::I).unwrap());
+        assert_eq!(view.max_iterations, 200);
+        for _ in 0..10 {
+            view.apply(action_for_key(VirtualKeyCode::K).unwrap());
+        }
+        assert_eq!(view.max_iterations, MIN_ITERATIONS);
+        assert_eq!(action_for_key(VirtualKeyCode::Q), None);
+    }
+
+    /// Tests the content of the status line with and without a cursor over the image.
+    #[test]
+    fn test_status_line() {
+        let mut view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        assert_eq!(
+            status_line(&view, Some((0.0, 0.0))),
+            "Cursor -2.000000-1.500000i  Center +0.000000+0.000000i  Zoom 1.00x  Iterations 100"
+        );
+        view.zoom = 250_000.0;
+        assert!(status_line(&view, None).starts_with("Cursor -  "));
+        assert!(status_line(&view, None).contains("Zoom 2.500e5x"));
+    }
+
+    /// Tests that the HUD darkens the bottom strip and leaves the rest of the image untouched.
+    #[test]
+    fn test_draw_hud() {
+        let mut image = RgbImage::from_pixel(400, 100, Rgb([200, 200, 200]));
+        draw_hud(&mut image, "ZOOM 1.00X");
+        assert_eq!(*image.get_pixel(200, 10), Rgb([200, 200, 200]));
+        assert_eq!(*image.get_pixel(399, 99), Rgb([70, 70, 70]));
+    }
+
+    /// Tests that the inset is copied into the top right corner inside a white border.
+    #[test]
+    fn test_draw_inset() {
+        let mut image = RgbImage::from_pixel(40, 30, Rgb([0, 0, 0]));
+        draw_inset(&mut image, &RgbImage::from_pixel(10, 5, Rgb([9, 9, 9])));
+        assert_eq!(*image.get_pixel(40 - PREVIEW_MARGIN - 10, PREVIEW_MARGIN), Rgb([9, 9, 9]));
+        assert_eq!(*image.get_pixel(40 - PREVIEW_MARGIN - 1, PREVIEW_MARGIN + 4), Rgb([9, 9, 9]));
+        assert_eq!(*image.get_pixel(40 - PREVIEW_MARGIN, PREVIEW_MARGIN), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(40 - PREVIEW_MARGIN - 11, PREVIEW_MARGIN - 1), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(5, 20), Rgb([0, 0, 0]));
+
+        // An inset larger than the image is left out.
+        let mut small = RgbImage::new(12, 12);
+        draw_inset(&mut small, &RgbImage::from_pixel(10, 5, Rgb([9, 9, 9])));
+        assert!(small.pixels().all(|pixel| *pixel == Rgb([0, 0, 0])));
+    }
+
+    /// Tests that the dual view puts the Julia set on the right and that the marker crosses the chosen pixel.
+    #[test]
+    fn test_dual_view_images() {
+        let joined = side_by_side(&RgbImage::from_pixel(4, 3, Rgb([1, 1, 1])), &RgbImage::from_pixel(2, 3, Rgb([2, 2, 2])));
+        assert_eq!(joined.dimensions(), (6, 3));
+        assert_eq!((*joined.get_pixel(3, 2), *joined.get_pixel(4, 0)), (Rgb([1, 1, 1]), Rgb([2, 2, 2])));
+
+        let mut image = RgbImage::new(20, 20);
+        draw_marker(&mut image, (10.0, 10.0));
+        assert_eq!(*image.get_pixel(10, 10), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(15, 10), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(10, 5), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(12, 12), Rgb([0, 0, 0]));
+
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        assert_eq!(view.complex_to_pixel(view.pixel_to_complex((200.0, 450.0))), (200.0, 450.0));
+    }
+
+    /// Tests that a line covers both ends and the pixels between them, and is clipped to the image.
+    #[test]
+    fn test_draw_line() {
+        let mut image = RgbImage::new(10, 10);
+        draw_line(&mut image, (1.0, 1.0), (7.0, 4.0), Rgb([255, 0, 0]));
+        assert_eq!(*image.get_pixel(1, 1), Rgb([255, 0, 0]));
+        assert_eq!(*image.get_pixel(7, 4), Rgb([255, 0, 0]));
+        assert_eq!(image.pixels().filter(|pixel| pixel.0 == [255, 0, 0]).count(), 7); // One pixel per column
+
+        let mut image = RgbImage::new(10, 10);
+        draw_line(&mut image, (-5.0, 5.0), (1e9, 5.0), Rgb([255, 0, 0]));
+        assert!((0..10).all(|x| *image.get_pixel(x, 5) == Rgb([255, 0, 0])));
+
+        let mut image = RgbImage::new(10, 10);
+        draw_line(&mut image, (f32::NAN, 5.0), (5.0, 5.0), Rgb([255, 0, 0]));
+        assert!(image.pixels().all(|pixel| pixel.0 == [0, 0, 0]));
+    }
+
+    /// Tests that a line to a point far outside keeps its slope over the image.
+    #[test]
+    fn test_draw_line_far_end() {
+        let mut image = RgbImage::new(10, 10);
+        draw_line(&mut image, (1.0, 1.0), (2e6 + 1.0, 1e6 + 1.0), Rgb([255, 0, 0]));
+        let lit: Vec<(u32, u32)> = image.enumerate_pixels().filter(|(_, _, pixel)| pixel.0 == [255, 0, 0]).map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(lit.len(), 9); // One pixel per column from x = 1
+        assert!(lit.iter().all(|&(x, y)| (y as f64 - (1.0 + (x as f64 - 1.0) / 2.0)).abs() <= 0.5));
+    }
+
+    /// Tests that the background thread renders the preview of the latest point asked for.
+    #[test]
+    fn test_julia_preview() {
+        let mut julia = JuliaPreview::start("turbo".to_string(), Family::Mandelbrot);
+        assert!(!julia.waiting());
+        julia.request((0.3, 0.5), 50);
+        julia.request((-0.8, 0.156), 50);
+        assert!(julia.waiting());
+        let start = Instant::now();
+        while julia.waiting() && start.elapsed() < Duration::from_secs(10) {
+            julia.receive().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(julia.shown, Some((-0.8, 0.156)));
+        assert_eq!(julia.inset.as_ref().unwrap().dimensions(), PREVIEW_SIZE);
+    }
+
+    /// Tests that the palette rotation follows the elapsed time and wraps around the gradient.
+    #[test]
+    fn test_palette_cycle_advance() {
+        let mut cycle = PaletteCycle::new();
+        cycle.speed = 0.5;
+        cycle.advance(Duration::from_millis(500));
+        assert_eq!(cycle.offset, 0.25);
+        cycle.advance(Duration::from_secs(2));
+        assert_eq!(cycle.offset, 0.25);
+        cycle.advance(Duration::from_millis(1500));
+        assert_eq!(cycle.offset, 0.0);
+    }
+
+    /// Tests the tuning line: the palette changes right away, the iteration limit and c once they are due.
+    #[test]
+    fn test_tuning() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 40, 30);
+        let mut viewer = Viewer::new(view, "turbo".to_string(), RgbImage::new(40, 30)).unwrap();
+        let start = Instant::now();
+        viewer.next_parameter();
+        viewer.tune(1.0, start).unwrap();
+        assert_eq!(viewer.tuning.as_ref().unwrap().max_iterations, Some(125));
+        assert_eq!(viewer.view.max_iterations, 100);
+        assert!(!viewer.apply_tuning(start).unwrap());
+        assert!(viewer.apply_tuning(start + TUNING_DELAY).unwrap());
+        assert_eq!(viewer.view.max_iterations, 125);
+        assert!(viewer.dual.is_none());
+
+        viewer.next_parameter();
+        viewer.tune(-1.0, start).unwrap();
+        assert!((viewer.transform.offset - 0.98).abs() < 1e-9);
+        assert_eq!(viewer.tuning.as_ref().unwrap().due, None);
+        for _ in 0..3 {
+            viewer.next_parameter();
+        }
+        viewer.tune(1.0, start).unwrap(); // The imaginary part of c, by a hundredth of the view width
+        assert!(viewer.apply_tuning(start + TUNING_DELAY).unwrap());
+        assert!((viewer.dual.as_ref().unwrap().c.1 - 0.04).abs() < 1e-6);
+        let c = viewer.dual.as_ref().map(|dual| dual.c);
+        let line = tuning_line(viewer.tuning.as_ref().unwrap(), &viewer.view, c, &viewer.transform);
+        assert_eq!(line, "Iterations 125  Offset 0.98  Scale 1.00  Re c +0.000000  [Im c +0.040000]");
+        assert_eq!(Parameter::JuliaIm.next(), Parameter::Iterations);
+    }
+
+    /// Tests that a resized window renders the same center and zoom at its size, shared by the dual view.
+    #[test]
+    fn test_resize() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 50, 40, 30);
+        let mut viewer = Viewer::new(view, "turbo".to_string(), RgbImage::new(40, 30)).unwrap();
+        assert!(!viewer.resize((40, 30)).unwrap());
+        assert!(viewer.resize((80, 30)).unwrap());
+        assert_eq!((viewer.view.center, viewer.view.zoom), (view.center, view.zoom));
+        assert_eq!(viewer.frame.dimensions(), (80, 30));
+        let (_, _, ymin, ymax) = viewer.view.bounds();
+        assert!((ymin + 0.75).abs() < 1e-6 && (ymax - 0.75).abs() < 1e-6); // Cut to the wider shape
+        viewer.select_julia((0.0, 0.0)).unwrap();
+        assert!(viewer.resize((100, 40)).unwrap());
+        assert_eq!(viewer.display_size(), (100, 40));
+        assert_eq!(viewer.dual.as_ref().unwrap().frame.dimensions(), (50, 40));
+        assert!(viewer.resize((1, 1)).unwrap());
+        assert_eq!((viewer.view.width, viewer.view.height), (MIN_VIEW_SIZE, MIN_VIEW_SIZE));
+    }
+
+    /// Tests that presenting shows the frames alone, without the status and tuning lines.
+    #[test]
+    fn test_presentation() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 50, 40, 30);
+        let mut viewer = Viewer::new(view, "turbo".to_string(), RgbImage::new(40, 30)).unwrap().presenting();
+        viewer.next_parameter();
+        assert_eq!(viewer.compose(), viewer.frame);
+        viewer.select_julia((0.0, 0.0)).unwrap();
+        let composed = viewer.compose();
+        assert_eq!(composed.get_pixel(79, 29), viewer.dual.as_ref().unwrap().frame.get_pixel(39, 29));
+        viewer.presenting = false;
+        assert_ne!(viewer.compose(), composed);
+    }
+
+    /// Tests that exports keep the shape of the view at their own size and quality, in its colors.
+    #[test]
+    fn test_export() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 50, 40, 30);
+        let mut viewer = Viewer::open(view, "grayscale".to_string()).unwrap();
+        viewer = viewer.with_export(Export { width: 80, antialias: 2, template: "{fractal}_{size}.png".to_string(), ..Export::default() });
+        assert_eq!(viewer.export.size(&viewer.view), (80, 60));
+        let options = viewer.export.render_options(&viewer.view, "turbo", PaletteTransform::default()).unwrap();
+        assert_eq!((options.width, options.height, options.antialias), (80, 60, 2));
+        assert_eq!(options.bounds(), viewer.view.bounds());
+        let exported = viewer.render_export().unwrap();
+        assert_eq!(exported.dimensions(), (80, 60));
+        // The same grayscale as the window away from the edges of the set, where the samples agree
+        assert_eq!(exported.get_pixel(0, 0), viewer.frame.get_pixel(0, 0));
+        assert!(Export { antialias: 0, ..Export::default() }.render_options(&view, "turbo", PaletteTransform::default()).is_err());
+    }
+
+    /// Tests the text copied for a view, its command keeping the colors of the window.
+    #[test]
+    fn test_location_text() {
+        let view = View { center: (-0.75, 0.125), zoom: 4.0, max_iterations: 300, width: 40, height: 30 };
+        let mut viewer = Viewer::new(view, "turbo".to_string(), RgbImage::new(40, 30)).unwrap();
+        assert_eq!(
+            viewer.location_text(),
+            "center -0.75,0.125 zoom 4 iterations 300\n\
+             final_exercice render --fractal mandelbrot --center -0.75,0.125 --zoom 4 --iterations 300 --palette turbo --size 40x30"
+        );
+        viewer.transform.scale = 2.0;
+        viewer.cycle.offset = 0.125;
+        assert!(viewer.location_text().ends_with(" --palette-offset 0.25 --palette-scale 2"));
+        viewer.family = Family::Lambda;
+        assert_eq!(viewer.location_text(), "center -0.75,0.125 zoom 4 iterations 300");
+    }
+
+    /// Tests that panning by pixels computes the same escapes as rendering the moved view.
+    #[test]
+    fn test_pan_by() {
+        assert_eq!(exposed_strips((10, 8), (3, 2)), vec![(0, 0, 10, 2), (0, 2, 3, 6)]);
+        assert_eq!(exposed_strips((10, 8), (-3, 0)), vec![(7, 0, 3, 8)]);
+        assert_eq!(exposed_strips((10, 8), (0, -2)), vec![(0, 6, 10, 2)]);
+        let view = View { center: (-0.5, 0.0), zoom: 1.0, max_iterations: 60, width: 64, height: 48 };
+        let (xmin, xmax, ymin, ymax) = view.region((16, 12), (32, 24)).bounds();
+        assert_eq!(((xmin, ymin), (xmax, ymax)), (view.pixel_to_complex((16.0, 12.0)), view.pixel_to_complex((48.0, 36.0))));
+        let mut viewer = Viewer::open(view, "turbo".to_string()).unwrap();
+        for offset in [(5, 3), (-7, 0), (0, -4)] {
+            viewer.pan_by(offset).unwrap();
+        }
+        let full = viewer.view.render_options("turbo").unwrap().compute().unwrap();
+        let same = full.iter().zip(&viewer.escapes).filter(|(a, b)| a.iterations == b.iterations).count();
+        assert!(same as f32 >= 0.99 * full.len() as f32, "{} of {}", same, full.len());
+        // Moved by 2 pixels to the right and 1 down in all
+        let scale = DEFAULT_VIEW_WIDTH / 64.0;
+        let (re, im) = viewer.view.center;
+        assert!((re - (-0.5 + 2.0 * scale)).abs() < 1e-6 && (im - scale).abs() < 1e-6, "{:?}", (re, im));
+    }
+
+    /// Tests that the minimap shows the box of the view, and a cross once it is too small.
+    #[test]
+    fn test_minimap() {
+        let (minimap_view, minimap) = render_minimap(Family::Mandelbrot, "turbo").unwrap();
+        assert_eq!(minimap.dimensions(), MINIMAP_SIZE);
+        assert_eq!(minimap_view.bounds(), (-2.0, 2.0, -1.5, 1.5));
+        let black = RgbImage::new(160, 120);
+        let mut image = RgbImage::new(300, 200);
+        // The view of the right half of the plane, its box from the middle of the minimap to its edge
+        let view = View::from_bounds((0.0, 2.0, -1.5, 1.5), 50, 80, 120);
+        draw_minimap(&mut image, (&minimap_view, &black), &view);
+        let (left, top) = (PREVIEW_MARGIN, PREVIEW_MARGIN);
+        assert_eq!(*image.get_pixel(left + 80, top + 60), MINIMAP_BOX_COLOR);
+        assert_eq!(*image.get_pixel(left + 40, top + 60), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(left - 1, top + 60), Rgb([255, 255, 255])); // The frame
+        // A deep view is a cross on its center
+        let mut image = RgbImage::new(300, 200);
+        let deep = View { center: (-1.0, 0.0), zoom: 1e4, ..view };
+        draw_minimap(&mut image, (&minimap_view, &black), &deep);
+        let (cx, cy) = minimap_view.complex_to_pixel((-1.0, 0.0));
+        assert_eq!(*image.get_pixel(left + cx as u32 + 3, top + cy as u32), Rgb([255, 255, 255]));
+    }
+
+    /// Tests undo and redo through the history, and that a new view drops the redo stack.
+    #[test]
+    fn test_history() {
+        let first = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        let second = View { zoom: 2.0, ..first };
+        let third = View { zoom: 4.0, ..first };
+        let mut history = History::new(10);
+        history.record(first);
+        history.record(second);
+
+        assert_eq!(history.undo(third), Some(second));
+        assert_eq!(history.undo(second), Some(first));
+        assert_eq!(history.undo(first), None);
+        assert_eq!(history.redo(first), Some(second));
+
+        history.record(second);
+        assert_eq!(history.redo(third), None);
+    }
+
+    /// Tests that the oldest views are dropped once the history is full.
+    #[test]
+    fn test_history_capacity() {
+        let view = View::from_bounds((-2.0, 2.0, -1.5, 1.5), 100, 800, 600);
+        let mut history = History::new(2);
+        for zoom in [1.0, 2.0, 3.0] {
+            history.record(View { zoom, ..view });
+        }
+        assert_eq!(history.undo(view).map(|v| v.zoom), Some(3.0));
+        assert_eq!(history.undo(view).map(|v| v.zoom), Some(2.0));
+        assert_eq!(history.undo(view), None);
+    }
+}
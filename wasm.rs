@@ -0,0 +1,34 @@
+// wasm-bindgen exports of the renderer, for a web page drawing the set on a canvas:
+//
+//     import init, { render_rgba } from "./pkg/final_exercice.js";
+//     await init();
+//     const pixels = render_rgba(800, 600, -0.5, 0, 1, 200, "turbo", "smooth");
+//     context.putImageData(new ImageData(new Uint8ClampedArray(pixels), 800, 600), 0, 0);
+use crate::render::RenderOptions;
+use wasm_bindgen::prelude::*;
+
+// Render a view of the Mandelbrot set into an RGBA buffer, row by row, the layout of ImageData.
+// Invalid options are thrown as a JavaScript error holding the message.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)] // JavaScript callers pass plain values, not a RenderOptions
+pub fn render_rgba(
+    width: u32,
+    height: u32,
+    center_re: f32,
+    center_im: f32,
+    zoom: f32,
+    max_iterations: u32,
+    palette: &str,
+    coloring: &str,
+) -> Result<Vec<u8>, JsValue> {
+    let options = RenderOptions::builder()
+        .size(width, height)
+        .center(center_re, center_im)
+        .zoom(zoom)
+        .iterations(max_iterations)
+        .palette(palette)
+        .coloring(coloring)
+        .build()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    options.render_rgba().map_err(|e| JsValue::from_str(&e.to_string()))
+}
@@ -0,0 +1,59 @@
+// Watching a file for changes, for `render --watch` re-rendering whenever its config file is saved. The
+// file is polled: its modification time and length are compared with those seen last, which works with
+// every editor, whether it writes the file in place or replaces it.
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+// How often the file is looked at, and how long it must stay the same after a change before it is
+// read, editors writing some files in several steps.
+pub const POLL: Duration = Duration::from_millis(250);
+pub const SETTLE: Duration = Duration::from_millis(100);
+
+pub struct FileWatch {
+    path: String,
+    stamp: Option<(SystemTime, u64)>, // None while the file is missing
+}
+
+impl FileWatch {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string(), stamp: stamp(path) }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // Whether the file was modified, created or removed since it was last looked at.
+    pub fn changed(&mut self) -> bool {
+        let stamp = stamp(&self.path);
+        let changed = stamp != self.stamp;
+        self.stamp = stamp;
+        changed
+    }
+}
+
+fn stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that writing, then removing the file are changes, looking again without one is not.
+    #[test]
+    fn test_changed() {
+        let path = std::env::temp_dir().join(format!("watch_test_{}.conf", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "zoom = 2\n").unwrap();
+        let mut watch = FileWatch::new(path);
+        assert!(!watch.changed());
+        fs::write(path, "zoom = 40\n").unwrap();
+        assert!(watch.changed());
+        assert!(!watch.changed());
+        fs::remove_file(path).unwrap();
+        assert!(watch.changed());
+        assert!(!watch.changed());
+    }
+}